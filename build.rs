@@ -9,5 +9,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .file_descriptor_set_path(out_dir.join("user_descriptor.bin"))
         .compile_protos(&["proto/user/user.proto"], &["proto"])?;
 
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("error_descriptor.bin"))
+        .compile_protos(&["proto/error/error.proto"], &["proto"])?;
+
+    // sqlx::migrate! embeds the migrations, so rebuild when they change
+    println!("cargo:rerun-if-changed=migrations");
+
     Ok(())
 }
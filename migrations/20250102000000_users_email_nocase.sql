@@ -0,0 +1,3 @@
+-- Emails are unique regardless of case, so "Ada@Example.com" and
+-- "ada@example.com" can't both register.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email_nocase ON users (email COLLATE NOCASE);
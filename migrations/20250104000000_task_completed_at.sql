@@ -0,0 +1,7 @@
+-- When each task was last marked completed; NULL while it is pending.
+-- Tasks already completed take their last update as the best estimate.
+ALTER TABLE tasks ADD COLUMN completed_at TEXT;
+
+UPDATE tasks SET completed_at = updated_at WHERE completed = 1;
+
+CREATE INDEX IF NOT EXISTS idx_tasks_completed_at ON tasks (completed_at);
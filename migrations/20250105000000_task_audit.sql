@@ -0,0 +1,80 @@
+-- Change history for tasks, one row per insert, update or delete. Triggers
+-- write it, so every write path records its changes in its own
+-- transaction. Rows outlive their task, so there is no foreign key.
+CREATE TABLE IF NOT EXISTS task_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id INTEGER NOT NULL,
+    op TEXT NOT NULL,
+    old_json TEXT,
+    new_json TEXT,
+    at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_audit_task_id ON task_audit (task_id, id);
+
+CREATE TRIGGER IF NOT EXISTS trg_task_audit_insert AFTER INSERT ON tasks
+BEGIN
+    INSERT INTO task_audit (task_id, op, new_json)
+    VALUES (
+        NEW.id,
+        'create',
+        json_object(
+            'id', NEW.id,
+            'title', NEW.title,
+            'description', NEW.description,
+            'completed', json(CASE WHEN NEW.completed THEN 'true' ELSE 'false' END),
+            'assigned_user_id', NEW.assigned_user_id,
+            'created_at', NEW.created_at,
+            'updated_at', NEW.updated_at,
+            'completed_at', NEW.completed_at
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_task_audit_update AFTER UPDATE ON tasks
+BEGIN
+    INSERT INTO task_audit (task_id, op, old_json, new_json)
+    VALUES (
+        NEW.id,
+        'update',
+        json_object(
+            'id', OLD.id,
+            'title', OLD.title,
+            'description', OLD.description,
+            'completed', json(CASE WHEN OLD.completed THEN 'true' ELSE 'false' END),
+            'assigned_user_id', OLD.assigned_user_id,
+            'created_at', OLD.created_at,
+            'updated_at', OLD.updated_at,
+            'completed_at', OLD.completed_at
+        ),
+        json_object(
+            'id', NEW.id,
+            'title', NEW.title,
+            'description', NEW.description,
+            'completed', json(CASE WHEN NEW.completed THEN 'true' ELSE 'false' END),
+            'assigned_user_id', NEW.assigned_user_id,
+            'created_at', NEW.created_at,
+            'updated_at', NEW.updated_at,
+            'completed_at', NEW.completed_at
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_task_audit_delete AFTER DELETE ON tasks
+BEGIN
+    INSERT INTO task_audit (task_id, op, old_json)
+    VALUES (
+        OLD.id,
+        'delete',
+        json_object(
+            'id', OLD.id,
+            'title', OLD.title,
+            'description', OLD.description,
+            'completed', json(CASE WHEN OLD.completed THEN 'true' ELSE 'false' END),
+            'assigned_user_id', OLD.assigned_user_id,
+            'created_at', OLD.created_at,
+            'updated_at', OLD.updated_at,
+            'completed_at', OLD.completed_at
+        )
+    );
+END;
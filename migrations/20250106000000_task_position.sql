@@ -0,0 +1,4 @@
+-- Manual ordering for tasks. NULL until a task is first moved; until then
+-- it sorts by `-id`, so tasks nobody has moved keep the newest-first order
+-- they had before this column existed.
+ALTER TABLE tasks ADD COLUMN position REAL;
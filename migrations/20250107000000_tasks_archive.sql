@@ -0,0 +1,19 @@
+-- Completed tasks moved out of `tasks` to keep it small. Rows keep the id,
+-- timestamps and position they had, and their tags as a JSON array since
+-- `task_tags` rows go with the task. Ids can repeat once `tasks` has been
+-- emptied and renumbered, so `id` is not a key here.
+CREATE TABLE IF NOT EXISTS tasks_archive (
+    id INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    completed BOOLEAN NOT NULL,
+    assigned_user_id INTEGER,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    completed_at TEXT,
+    position REAL,
+    tags TEXT NOT NULL DEFAULT '[]',
+    archived_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_tasks_archive_archived_at ON tasks_archive (archived_at, id);
@@ -0,0 +1,87 @@
+-- Where each task stands: todo, in_progress, done or cancelled. `completed`
+-- stays, written alongside as `status = 'done'`, for older clients.
+ALTER TABLE tasks ADD COLUMN status TEXT NOT NULL DEFAULT 'todo';
+
+CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks (status);
+
+-- Only completed tasks are archived
+ALTER TABLE tasks_archive ADD COLUMN status TEXT NOT NULL DEFAULT 'done';
+
+-- History entries carry the status from here on
+DROP TRIGGER IF EXISTS trg_task_audit_insert;
+DROP TRIGGER IF EXISTS trg_task_audit_update;
+DROP TRIGGER IF EXISTS trg_task_audit_delete;
+
+CREATE TRIGGER IF NOT EXISTS trg_task_audit_insert AFTER INSERT ON tasks
+BEGIN
+    INSERT INTO task_audit (task_id, op, new_json)
+    VALUES (
+        NEW.id,
+        'create',
+        json_object(
+            'id', NEW.id,
+            'title', NEW.title,
+            'description', NEW.description,
+            'completed', json(CASE WHEN NEW.completed THEN 'true' ELSE 'false' END),
+            'status', NEW.status,
+            'assigned_user_id', NEW.assigned_user_id,
+            'created_at', NEW.created_at,
+            'updated_at', NEW.updated_at,
+            'completed_at', NEW.completed_at
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_task_audit_update AFTER UPDATE ON tasks
+BEGIN
+    INSERT INTO task_audit (task_id, op, old_json, new_json)
+    VALUES (
+        NEW.id,
+        'update',
+        json_object(
+            'id', OLD.id,
+            'title', OLD.title,
+            'description', OLD.description,
+            'completed', json(CASE WHEN OLD.completed THEN 'true' ELSE 'false' END),
+            'status', OLD.status,
+            'assigned_user_id', OLD.assigned_user_id,
+            'created_at', OLD.created_at,
+            'updated_at', OLD.updated_at,
+            'completed_at', OLD.completed_at
+        ),
+        json_object(
+            'id', NEW.id,
+            'title', NEW.title,
+            'description', NEW.description,
+            'completed', json(CASE WHEN NEW.completed THEN 'true' ELSE 'false' END),
+            'status', NEW.status,
+            'assigned_user_id', NEW.assigned_user_id,
+            'created_at', NEW.created_at,
+            'updated_at', NEW.updated_at,
+            'completed_at', NEW.completed_at
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_task_audit_delete AFTER DELETE ON tasks
+BEGIN
+    INSERT INTO task_audit (task_id, op, old_json)
+    VALUES (
+        OLD.id,
+        'delete',
+        json_object(
+            'id', OLD.id,
+            'title', OLD.title,
+            'description', OLD.description,
+            'completed', json(CASE WHEN OLD.completed THEN 'true' ELSE 'false' END),
+            'status', OLD.status,
+            'assigned_user_id', OLD.assigned_user_id,
+            'created_at', OLD.created_at,
+            'updated_at', OLD.updated_at,
+            'completed_at', OLD.completed_at
+        )
+    );
+END;
+
+-- After the triggers, so synced clients see completed tasks become done
+UPDATE tasks SET status = 'done' WHERE completed = 1;
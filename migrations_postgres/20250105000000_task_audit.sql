@@ -0,0 +1,34 @@
+-- Change history for tasks, one row per insert, update or delete, written
+-- by a trigger in the same transaction as the change. Mirrors the SQLite
+-- migration of the same name.
+CREATE TABLE IF NOT EXISTS task_audit (
+    id BIGSERIAL PRIMARY KEY,
+    task_id BIGINT NOT NULL,
+    op TEXT NOT NULL,
+    old_json TEXT,
+    new_json TEXT,
+    at TEXT NOT NULL DEFAULT to_char(clock_timestamp() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"')
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_audit_task_id ON task_audit (task_id, id);
+
+CREATE OR REPLACE FUNCTION record_task_audit() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO task_audit (task_id, op, new_json)
+        VALUES (NEW.id, 'create', row_to_json(NEW)::text);
+    ELSIF TG_OP = 'UPDATE' THEN
+        INSERT INTO task_audit (task_id, op, old_json, new_json)
+        VALUES (NEW.id, 'update', row_to_json(OLD)::text, row_to_json(NEW)::text);
+    ELSE
+        INSERT INTO task_audit (task_id, op, old_json)
+        VALUES (OLD.id, 'delete', row_to_json(OLD)::text);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_task_audit ON tasks;
+CREATE TRIGGER trg_task_audit
+    AFTER INSERT OR UPDATE OR DELETE ON tasks
+    FOR EACH ROW EXECUTE FUNCTION record_task_audit();
@@ -0,0 +1,3 @@
+-- Manual ordering for tasks. Mirrors the SQLite migration of the same name:
+-- NULL until a task is first moved, sorting by `-id` until then.
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS position DOUBLE PRECISION;
@@ -0,0 +1,17 @@
+-- Completed tasks moved out of `tasks`. Mirrors the SQLite migration of the
+-- same name, with tags as JSONB.
+CREATE TABLE IF NOT EXISTS tasks_archive (
+    id BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    completed BOOLEAN NOT NULL,
+    assigned_user_id BIGINT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    completed_at TEXT,
+    position DOUBLE PRECISION,
+    tags JSONB NOT NULL DEFAULT '[]',
+    archived_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"')
+);
+
+CREATE INDEX IF NOT EXISTS idx_tasks_archive_archived_at ON tasks_archive (archived_at, id);
@@ -0,0 +1,9 @@
+-- Where each task stands. Mirrors the SQLite migration of the same name;
+-- the audit trigger already records whole rows.
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'todo';
+
+CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks (status);
+
+ALTER TABLE tasks_archive ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'done';
+
+UPDATE tasks SET status = 'done' WHERE completed;
@@ -1,16 +1,184 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use sqlx::{
-    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqliteSynchronous},
     SqlitePool,
 };
+use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
+
+const DEFAULT_DATABASE_URL: &str = "sqlite://tasks.db";
+
+/// Schema migrations in `migrations/`, embedded at compile time. Tests run
+/// the same migrator so they never drift from the real schema.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+const DEFAULT_DB_TIMEOUT: Duration = Duration::from_secs(5);
+
+const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+
+// Primary SQLite result codes behind the errors `init_db_with` explains
+const SQLITE_PERM: i32 = 3;
+const SQLITE_READONLY: i32 = 8;
+const SQLITE_CANTOPEN: i32 = 14;
+
+/// Prefix prepended to every table name (`TABLE_PREFIX`), letting several
+/// tenants share one database file. Restricted to `[a-zA-Z0-9_]`, not
+/// starting with a digit, because it is spliced into SQL as an identifier.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TablePrefix(String);
+
+impl TablePrefix {
+    pub fn new(prefix: &str) -> Result<Self> {
+        if !prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            bail!(
+                "Invalid table prefix {:?}: only [a-zA-Z0-9_] is allowed",
+                prefix
+            );
+        }
+        if prefix.starts_with(|c: char| c.is_ascii_digit()) {
+            bail!(
+                "Invalid table prefix {:?}: must not start with a digit",
+                prefix
+            );
+        }
+
+        Ok(Self(prefix.to_string()))
+    }
+
+    /// Reads `TABLE_PREFIX`; unset means no prefix.
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("TABLE_PREFIX") {
+            Ok(prefix) => Self::new(&prefix),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// `name` with the prefix applied, e.g. `tenantA_tasks`.
+    pub fn table(&self, name: &str) -> String {
+        format!("{}{}", self.0, name)
+    }
+}
+
+/// Where a task stands, stored as its snake_case name.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    #[default]
+    Todo,
+    InProgress,
+    Done,
+    Cancelled,
+}
+
+impl TaskStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaskStatus::Todo => "todo",
+            TaskStatus::InProgress => "in_progress",
+            TaskStatus::Done => "done",
+            TaskStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// The status a client setting only the old `completed` flag means.
+    pub fn from_completed(completed: bool) -> Self {
+        if completed {
+            TaskStatus::Done
+        } else {
+            TaskStatus::Todo
+        }
+    }
+
+    /// The derived `completed` flag.
+    pub fn is_done(self) -> bool {
+        self == TaskStatus::Done
+    }
+}
+
+impl std::fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TaskStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "todo" => Ok(TaskStatus::Todo),
+            "in_progress" => Ok(TaskStatus::InProgress),
+            "done" => Ok(TaskStatus::Done),
+            "cancelled" => Ok(TaskStatus::Cancelled),
+            other => Err(anyhow!("Unknown task status: {}", other)),
+        }
+    }
+}
+
+impl TryFrom<String> for TaskStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
 
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct TaskModel {
     pub id: i64,
     pub title: String,
     pub description: String,
+    /// Kept equal to `status == Done` by every write.
     pub completed: bool,
+    #[sqlx(try_from = "String")]
+    pub status: TaskStatus,
+    pub assigned_user_id: Option<i64>,
+    /// ISO-8601 UTC timestamps maintained by the repository.
+    pub created_at: String,
+    pub updated_at: String,
+    /// Set when the task becomes done, cleared when it leaves that status.
+    pub completed_at: Option<String>,
+    /// Sorted tags from `task_tags`, selected as a JSON array.
+    #[sqlx(json)]
+    pub tags: Vec<String>,
+}
+
+/// One change to a task, recorded by the `task_audit` triggers.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TaskAuditModel {
+    pub id: i64,
+    pub task_id: i64,
+    /// `create`, `update` or `delete`.
+    pub op: String,
+    /// The task row as a JSON object before the change; unset for `create`.
+    pub old_json: Option<String>,
+    /// The task row as a JSON object after the change; unset for `delete`.
+    pub new_json: Option<String>,
+    /// ISO-8601 UTC timestamp of the change.
+    pub at: String,
 }
 
 #[derive(Debug, Clone, sqlx::FromRow)]
@@ -20,40 +188,865 @@ pub struct UserModel {
     pub email: String,
 }
 
+/// How long to wait for a pooled connection, and for SQLite to release a
+/// lock, before failing the query. SQLite has no per-statement timeout, so
+/// the busy timeout is the closest bound on a stuck write. Configured with
+/// `DB_TIMEOUT_SECS`.
+pub fn db_timeout() -> Duration {
+    std::env::var("DB_TIMEOUT_SECS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_DB_TIMEOUT)
+}
+
+/// Durability/throughput pragmas applied to every connection. Unset fields
+/// keep sqlx's defaults (`synchronous = FULL`, SQLite's own cache size).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SqlitePragmas {
+    /// `SQLITE_SYNCHRONOUS`: `OFF`, `NORMAL` or `FULL`. Under WAL, `NORMAL`
+    /// is still crash-safe and much faster for writes.
+    pub synchronous: Option<SqliteSynchronous>,
+    /// `SQLITE_CACHE_SIZE`: pages when positive, KiB when negative.
+    pub cache_size: Option<i64>,
+}
+
+impl SqlitePragmas {
+    pub fn from_env() -> Result<Self> {
+        Self::parse(
+            std::env::var("SQLITE_SYNCHRONOUS").ok().as_deref(),
+            std::env::var("SQLITE_CACHE_SIZE").ok().as_deref(),
+        )
+    }
+
+    /// [`SqlitePragmas::from_env`] with the variables already read.
+    pub fn parse(synchronous: Option<&str>, cache_size: Option<&str>) -> Result<Self> {
+        let synchronous = match synchronous.map(str::trim) {
+            None | Some("") => None,
+            Some(value) => Some(match value.to_ascii_uppercase().as_str() {
+                "OFF" => SqliteSynchronous::Off,
+                "NORMAL" => SqliteSynchronous::Normal,
+                "FULL" => SqliteSynchronous::Full,
+                _ => bail!(
+                    "Invalid SQLITE_SYNCHRONOUS {:?}: expected OFF, NORMAL or FULL",
+                    value
+                ),
+            }),
+        };
+
+        let cache_size = match cache_size.map(str::trim) {
+            None | Some("") => None,
+            Some(value) => match value.parse::<i64>() {
+                Ok(size) => Some(size),
+                Err(_) => bail!("Invalid SQLITE_CACHE_SIZE {:?}: expected an integer", value),
+            },
+        };
+
+        Ok(Self {
+            synchronous,
+            cache_size,
+        })
+    }
+
+    pub fn apply(&self, mut options: SqliteConnectOptions) -> SqliteConnectOptions {
+        if let Some(synchronous) = self.synchronous {
+            options = options.synchronous(synchronous);
+        }
+        if let Some(cache_size) = self.cache_size {
+            options = options.pragma("cache_size", cache_size.to_string());
+        }
+        options
+    }
+}
+
+/// How long pooled connections may live. Recycling them stops a
+/// long-lived connection from pinning the WAL. Unset fields keep sqlx's
+/// defaults (10 minutes idle, 30 minutes total).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolLifetimes {
+    /// `DB_IDLE_TIMEOUT_SECS`: close connections idle for this long.
+    pub idle_timeout: Option<Duration>,
+    /// `DB_MAX_LIFETIME_SECS`: close connections this long after opening.
+    pub max_lifetime: Option<Duration>,
+}
+
+impl PoolLifetimes {
+    pub fn from_env() -> Result<Self> {
+        Self::parse(
+            std::env::var("DB_IDLE_TIMEOUT_SECS").ok().as_deref(),
+            std::env::var("DB_MAX_LIFETIME_SECS").ok().as_deref(),
+        )
+    }
+
+    /// [`PoolLifetimes::from_env`] with the variables already read.
+    pub fn parse(idle_timeout: Option<&str>, max_lifetime: Option<&str>) -> Result<Self> {
+        fn seconds(name: &str, value: Option<&str>) -> Result<Option<Duration>> {
+            match value.map(str::trim) {
+                None | Some("") => Ok(None),
+                Some(value) => match value.parse::<u64>() {
+                    Ok(secs) if secs > 0 => Ok(Some(Duration::from_secs(secs))),
+                    _ => bail!(
+                        "Invalid {} {:?}: expected a positive number of seconds",
+                        name,
+                        value
+                    ),
+                },
+            }
+        }
+
+        Ok(Self {
+            idle_timeout: seconds("DB_IDLE_TIMEOUT_SECS", idle_timeout)?,
+            max_lifetime: seconds("DB_MAX_LIFETIME_SECS", max_lifetime)?,
+        })
+    }
+
+    pub fn apply(&self, mut options: SqlitePoolOptions) -> SqlitePoolOptions {
+        if let Some(idle_timeout) = self.idle_timeout {
+            options = options.idle_timeout(idle_timeout);
+        }
+        if let Some(max_lifetime) = self.max_lifetime {
+            options = options.max_lifetime(max_lifetime);
+        }
+        options
+    }
+}
+
+/// `DATABASE_URL`, defaulting to `tasks.db` in the working directory.
+pub fn database_url() -> String {
+    std::env::var("DATABASE_URL").unwrap_or_else(|_| DEFAULT_DATABASE_URL.to_string())
+}
+
+/// True for `postgres://` and `postgresql://` URLs, which select the
+/// Postgres repositories instead of SQLite.
+pub fn is_postgres(url: &str) -> bool {
+    url.starts_with("postgres://") || url.starts_with("postgresql://")
+}
+
+/// True for `sqlite::memory:` and `mode=memory` URLs, whose data only lives
+/// as long as a connection to it stays open.
+pub fn is_in_memory(url: &str) -> bool {
+    url.contains(":memory:") || url.contains("mode=memory")
+}
+
+/// Pool size for `url`: in-memory databases must share a single connection.
+pub fn max_connections(url: &str) -> u32 {
+    if is_in_memory(url) {
+        1
+    } else {
+        DEFAULT_MAX_CONNECTIONS
+    }
+}
+
 pub async fn init_db() -> Result<SqlitePool> {
-    let options = SqliteConnectOptions::from_str("sqlite://tasks.db")?.create_if_missing(true);
+    init_db_with(&database_url(), &TablePrefix::from_env()?).await
+}
+
+/// Opens the pool for `url` and creates or upgrades the unprefixed schema.
+pub async fn init_db_with_url(url: &str) -> Result<SqlitePool> {
+    init_db_with(url, &TablePrefix::default()).await
+}
 
-    let pool = SqlitePoolOptions::new()
-        .max_connections(5)
+/// Opens the pool for `url` and creates or upgrades the schema, using
+/// `prefix`ed table names when one is set.
+pub async fn init_db_with(url: &str, prefix: &TablePrefix) -> Result<SqlitePool> {
+    let timeout = db_timeout();
+    let options = SqlitePragmas::from_env()?.apply(
+        SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .busy_timeout(timeout),
+    );
+    let path = options.get_filename().to_path_buf();
+
+    let pool_options = if is_in_memory(url) {
+        // Every connection to `:memory:` is a separate empty database, so the
+        // pool keeps exactly one connection open for the process lifetime.
+        SqlitePoolOptions::new()
+            .max_connections(max_connections(url))
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+    } else {
+        prepare_database_file(&path)?;
+        PoolLifetimes::from_env()?
+            .apply(SqlitePoolOptions::new().max_connections(max_connections(url)))
+    };
+
+    // Ping connections on checkout so one broken while idle is replaced
+    // instead of failing the request that drew it.
+    let pool = pool_options
+        .test_before_acquire(true)
+        .acquire_timeout(timeout)
         .connect_with(options)
+        .await
+        .map_err(|e| open_error(&path, e))?;
+
+    if prefix.is_empty() {
+        upgrade_legacy_schema(&pool).await?;
+        MIGRATOR.run(&pool).await?;
+    } else {
+        migrate_prefixed(&pool, prefix).await?;
+    }
+
+    Ok(pool)
+}
+
+/// Creates the database file's directory when it's missing, and refuses a
+/// read-only file up front: SQLite would open it anyway and only fail at
+/// the first write.
+fn prepare_database_file(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            anyhow!(
+                "Can't create the database directory {}: {}; create it or point DATABASE_URL elsewhere",
+                dir.display(),
+                e
+            )
+        })?;
+    }
+
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.permissions().readonly() => bail!(
+            "Database file {} is read-only; make it writable or point DATABASE_URL elsewhere",
+            path.display()
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// `error` from opening `path`, explained when SQLite couldn't open or
+/// write the file.
+fn open_error(path: &Path, error: sqlx::Error) -> anyhow::Error {
+    let code = match &error {
+        sqlx::Error::Database(e) => e
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            .map(|code| code & 0xff),
+        _ => None,
+    };
+    match code {
+        Some(SQLITE_CANTOPEN) => anyhow!(
+            "Can't open database file {}: check that this process can read and write it and its directory ({})",
+            path.display(),
+            error
+        ),
+        Some(SQLITE_PERM | SQLITE_READONLY) => anyhow!(
+            "Database file {} is not writable by this process ({})",
+            path.display(),
+            error
+        ),
+        _ => error.into(),
+    }
+}
+
+/// Applies `MIGRATOR`'s migrations to a prefixed copy of the schema.
+///
+/// sqlx tracks migrations in a single `_sqlx_migrations` table, so each
+/// prefix records its applied versions in its own `{prefix}schema_migrations`
+/// table instead. Migrations must refer to tables only by the names in
+/// [`TABLES`] and name indexes `idx_*` and triggers `trg_*` for the rewrite
+/// to find them.
+pub async fn migrate_prefixed(pool: &SqlitePool, prefix: &TablePrefix) -> Result<()> {
+    let applied_table = prefix.table("schema_migrations");
+    sqlx::query(&format!(
+        "CREATE TABLE IF NOT EXISTS {} (version INTEGER PRIMARY KEY)",
+        applied_table
+    ))
+    .execute(pool)
+    .await?;
+
+    for migration in MIGRATOR.iter() {
+        let (applied,): (bool,) = sqlx::query_as(&format!(
+            "SELECT EXISTS (SELECT 1 FROM {} WHERE version = ?)",
+            applied_table
+        ))
+        .bind(migration.version)
+        .fetch_one(pool)
+        .await?;
+
+        if applied {
+            continue;
+        }
+
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(&prefix_identifiers(&migration.sql, prefix))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!(
+            "INSERT INTO {} (version) VALUES (?)",
+            applied_table
+        ))
+        .bind(migration.version)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+    }
+
+    Ok(())
+}
+
+/// Tables created by the migrations, renamed per tenant.
+const TABLES: &[&str] = &["tasks", "users", "task_tags", "task_audit", "tasks_archive"];
+
+/// Prefixes the table names in [`TABLES`], `idx_*` index names and `trg_*`
+/// trigger names in `sql`.
+fn prefix_identifiers(sql: &str, prefix: &TablePrefix) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut word = String::new();
+
+    let flush = |word: &mut String, out: &mut String| {
+        if TABLES.contains(&word.as_str()) || word.starts_with("idx_") || word.starts_with("trg_") {
+            out.push_str(&prefix.table(word));
+        } else {
+            out.push_str(word);
+        }
+        word.clear();
+    };
+
+    for c in sql.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut word, &mut out);
+
+    out
+}
+
+const SEED_USERS: &[(&str, &str)] = &[
+    ("Ada Lovelace", "ada@example.com"),
+    ("Grace Hopper", "grace@example.com"),
+];
+
+const SEED_TASKS: &[(&str, &str, bool)] = &[
+    ("Write README", "Document how to run the servers", true),
+    (
+        "Add pagination",
+        "Support limit/offset on list endpoints",
+        false,
+    ),
+    ("Try grpcurl", "List services via reflection", false),
+];
+
+/// Inserts sample users and tasks for local development. Each table is only
+/// seeded while it's empty, so calling this on every startup is safe.
+pub async fn seed(pool: &SqlitePool) -> Result<()> {
+    seed_with_prefix(pool, &TablePrefix::default()).await
+}
+
+pub async fn seed_with_prefix(pool: &SqlitePool, prefix: &TablePrefix) -> Result<()> {
+    let users_table = prefix.table("users");
+    let tasks_table = prefix.table("tasks");
+    let mut tx = pool.begin().await?;
+
+    let (users,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", users_table))
+        .fetch_one(&mut *tx)
         .await?;
+    if users == 0 {
+        for (name, email) in SEED_USERS {
+            sqlx::query(&format!(
+                "INSERT INTO {} (name, email) VALUES (?, ?)",
+                users_table
+            ))
+            .bind(name)
+            .bind(email)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
 
-    // Create the tasks table if it doesn't exist
-    sqlx::query(
+    let (tasks,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", tasks_table))
+        .fetch_one(&mut *tx)
+        .await?;
+    if tasks == 0 {
+        for (title, description, completed) in SEED_TASKS {
+            sqlx::query(&format!(
+                r#"
+                INSERT INTO {} (title, description, completed, status, completed_at)
+                VALUES (?, ?, ?, ?, CASE WHEN ? THEN strftime('%Y-%m-%dT%H:%M:%fZ', 'now') END)
+                "#,
+                tasks_table
+            ))
+            .bind(title)
+            .bind(description)
+            .bind(completed)
+            .bind(TaskStatus::from_completed(*completed).as_str())
+            .bind(completed)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Brings a database created before migrations existed up to the initial
+/// migration's schema, so that migration (and everything after it) applies
+/// cleanly. No-op for new databases and for ones already under migration.
+async fn upgrade_legacy_schema(pool: &SqlitePool) -> Result<()> {
+    let (legacy,): (bool,) = sqlx::query_as(
         r#"
-        CREATE TABLE IF NOT EXISTS tasks (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            title TEXT NOT NULL,
-            description TEXT NOT NULL,
-            completed BOOLEAN NOT NULL DEFAULT 0
-        )
+        SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'tasks')
+           AND NOT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')
         "#,
     )
-    .execute(&pool)
+    .fetch_one(pool)
     .await?;
 
-    // Create the users table if it doesn't exist
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT NOT NULL,
-            email TEXT NOT NULL UNIQUE
-        )
-        "#,
+    if !legacy {
+        return Ok(());
+    }
+
+    add_column_if_missing(
+        pool,
+        "tasks",
+        "assigned_user_id",
+        "INTEGER REFERENCES users(id)",
     )
-    .execute(&pool)
     .await?;
 
+    // SQLite can't add a column with a non-constant default, so rows that
+    // predate the timestamps get the epoch
+    for column in ["created_at", "updated_at"] {
+        add_column_if_missing(
+            pool,
+            "tasks",
+            column,
+            "TEXT NOT NULL DEFAULT '1970-01-01T00:00:00.000Z'",
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let (exists,): (bool,) =
+        sqlx::query_as("SELECT COUNT(*) > 0 FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_one(pool)
+            .await?;
+
+    if !exists {
+        sqlx::query(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// `DB_READ_ONLY_POOL`: serve reporting reads from their own query-only
+/// pool. Off unless set to `1`/`true`/`on`; reports then share the main pool.
+pub fn read_only_pool_enabled() -> bool {
+    matches!(
+        std::env::var("DB_READ_ONLY_POOL")
+            .ok()
+            .map(|value| value.trim().to_ascii_lowercase())
+            .as_deref(),
+        Some("1") | Some("true") | Some("on")
+    )
+}
+
+/// `DB_READ_ONLY_MAX_CONNECTIONS`: size of the pool serving reporting reads
+/// (default 5), kept apart from the pool taking writes.
+pub fn read_only_max_connections() -> u32 {
+    std::env::var("DB_READ_ONLY_MAX_CONNECTIONS")
+        .ok()
+        .and_then(|value| value.parse::<u32>().ok())
+        .filter(|&connections| connections > 0)
+        .unwrap_or(DEFAULT_MAX_CONNECTIONS)
+}
+
+/// Opens a second pool on the database at `url` for reporting reads
+/// (counts, summaries, exports, backups), so a long report doesn't hold
+/// connections writes are waiting on. Call after `init_db_with` so the
+/// schema exists.
+pub async fn init_read_only_pool(url: &str) -> Result<SqlitePool> {
+    if is_in_memory(url) {
+        bail!("A read-only pool can't share an in-memory database");
+    }
+    // Same connection settings as the main pool, so reports see what
+    // writes see
+    let options = SqlitePragmas::from_env()?.apply(SqliteConnectOptions::from_str(url)?);
+    read_only_pool(options).await
+}
+
+/// Builds a pool whose connections all run with `PRAGMA query_only = ON`, so
+/// any INSERT/UPDATE/DELETE or DDL issued through it fails instead of
+/// mutating data. Connections are pinged on checkout, like the main pool's.
+pub async fn read_only_pool(options: SqliteConnectOptions) -> Result<SqlitePool> {
+    let timeout = db_timeout();
+    let pool = PoolLifetimes::from_env()?
+        .apply(SqlitePoolOptions::new().max_connections(read_only_max_connections()))
+        .test_before_acquire(true)
+        .acquire_timeout(timeout)
+        .connect_with(options.busy_timeout(timeout).pragma("query_only", "ON"))
+        .await?;
+
     Ok(pool)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        MaintenanceRepository, SqliteMaintenanceRepository, SqliteTaskRepository,
+        SqliteUserRepository, TaskRepository, UserRepository,
+    };
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_add_column_if_missing_is_idempotent() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query("CREATE TABLE tasks (id INTEGER PRIMARY KEY)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        add_column_if_missing(&pool, "tasks", "assigned_user_id", "INTEGER")
+            .await
+            .unwrap();
+        add_column_if_missing(&pool, "tasks", "assigned_user_id", "INTEGER")
+            .await
+            .unwrap();
+
+        sqlx::query("SELECT assigned_user_id FROM tasks")
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_is_in_memory() {
+        assert!(is_in_memory("sqlite::memory:"));
+        assert!(is_in_memory("sqlite:file:demo?mode=memory&cache=shared"));
+        assert!(!is_in_memory("sqlite://tasks.db"));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_schema_persists_across_queries() {
+        let pool = init_db_with_url("sqlite::memory:").await.unwrap();
+
+        for _ in 0..3 {
+            sqlx::query("INSERT INTO tasks (title, description) VALUES ('t', '')")
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tasks")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_sqlite_pragmas_parse() {
+        assert_eq!(
+            SqlitePragmas::parse(None, None).unwrap(),
+            SqlitePragmas::default()
+        );
+
+        let pragmas = SqlitePragmas::parse(Some("normal"), Some("-64000")).unwrap();
+        assert_eq!(pragmas.synchronous, Some(SqliteSynchronous::Normal));
+        assert_eq!(pragmas.cache_size, Some(-64000));
+
+        assert!(SqlitePragmas::parse(Some("EXTRA"), None).is_err());
+        assert!(SqlitePragmas::parse(Some("2"), None).is_err());
+        assert!(SqlitePragmas::parse(None, Some("64MB")).is_err());
+    }
+
+    #[test]
+    fn test_pool_lifetimes_parse() {
+        assert_eq!(
+            PoolLifetimes::parse(None, Some("")).unwrap(),
+            PoolLifetimes::default()
+        );
+
+        let lifetimes = PoolLifetimes::parse(Some("60"), Some(" 1800 ")).unwrap();
+        assert_eq!(lifetimes.idle_timeout, Some(Duration::from_secs(60)));
+        assert_eq!(lifetimes.max_lifetime, Some(Duration::from_secs(1800)));
+
+        assert!(PoolLifetimes::parse(Some("0"), None).is_err());
+        assert!(PoolLifetimes::parse(None, Some("-5")).is_err());
+        assert!(PoolLifetimes::parse(Some("1m"), None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_pragmas_applied_to_connections() {
+        let pragmas = SqlitePragmas::parse(Some("OFF"), Some("1234")).unwrap();
+        let options = pragmas.apply(SqliteConnectOptions::from_str("sqlite::memory:").unwrap());
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+
+        let (synchronous,): (i64,) = sqlx::query_as("PRAGMA synchronous")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let (cache_size,): (i64,) = sqlx::query_as("PRAGMA cache_size")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(synchronous, 0);
+        assert_eq!(cache_size, 1234);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_database_is_upgraded_before_migrating() {
+        let url = "sqlite:file:legacy_upgrade?mode=memory&cache=shared";
+        let legacy = SqlitePool::connect(url).await.unwrap();
+        sqlx::query(
+            "CREATE TABLE tasks (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, description TEXT NOT NULL, completed BOOLEAN NOT NULL DEFAULT 0)",
+        )
+        .execute(&legacy)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO tasks (title, description, completed) VALUES ('Old', '', 0), ('Shipped', '', 1)")
+            .execute(&legacy)
+            .await
+            .unwrap();
+
+        let pool = init_db_with_url(url).await.unwrap();
+        let tasks = SqliteTaskRepository::new(pool);
+        let task: TaskModel = tasks.get(1).await.unwrap();
+
+        assert_eq!(task.title, "Old");
+        assert!(task.tags.is_empty());
+        assert_eq!(task.created_at, "1970-01-01T00:00:00.000Z");
+        assert_eq!(task.assigned_user_id, None);
+        assert_eq!(task.status, TaskStatus::Todo);
+
+        // Tasks completed before statuses existed are done
+        let shipped = tasks.get(2).await.unwrap();
+        assert_eq!(shipped.status, TaskStatus::Done);
+        assert!(shipped.completed);
+    }
+
+    async fn counts(pool: &SqlitePool) -> (i64, i64) {
+        sqlx::query_as("SELECT (SELECT COUNT(*) FROM users), (SELECT COUNT(*) FROM tasks)")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_seed_populates_empty_database() {
+        let pool = init_db_with_url("sqlite::memory:").await.unwrap();
+
+        seed(&pool).await.unwrap();
+
+        assert_eq!(
+            counts(&pool).await,
+            (SEED_USERS.len() as i64, SEED_TASKS.len() as i64)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_seed_is_noop_on_populated_database() {
+        let pool = init_db_with_url("sqlite::memory:").await.unwrap();
+        sqlx::query("INSERT INTO users (name, email) VALUES ('Existing', 'existing@example.com')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO tasks (title, description) VALUES ('Existing', '')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        seed(&pool).await.unwrap();
+        seed(&pool).await.unwrap();
+
+        assert_eq!(counts(&pool).await, (1, 1));
+    }
+
+    #[test]
+    fn test_table_prefix_validation() {
+        assert_eq!(
+            TablePrefix::new("tenantA_").unwrap().table("tasks"),
+            "tenantA_tasks"
+        );
+        assert!(TablePrefix::new("").unwrap().is_empty());
+        assert!(TablePrefix::new("a; DROP TABLE tasks; --").is_err());
+        assert!(TablePrefix::new("tenant-a").is_err());
+        assert!(TablePrefix::new("1tenant").is_err());
+    }
+
+    #[test]
+    fn test_prefix_identifiers() {
+        let prefix = TablePrefix::new("t1_").unwrap();
+        let sql = "CREATE UNIQUE INDEX idx_users_email ON users (email); SELECT * FROM tasks_old, tasks, task_tags";
+
+        assert_eq!(
+            prefix_identifiers(sql, &prefix),
+            "CREATE UNIQUE INDEX t1_idx_users_email ON t1_users (email); SELECT * FROM tasks_old, t1_tasks, t1_task_tags"
+        );
+
+        let sql = "CREATE TRIGGER trg_audit AFTER DELETE ON tasks BEGIN INSERT INTO task_audit (task_id) VALUES (OLD.id); END";
+        assert_eq!(
+            prefix_identifiers(sql, &prefix),
+            "CREATE TRIGGER t1_trg_audit AFTER DELETE ON t1_tasks BEGIN INSERT INTO t1_task_audit (task_id) VALUES (OLD.id); END"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prefixed_schemas_are_isolated() {
+        let url = "sqlite:file:prefixed_tenants?mode=memory&cache=shared";
+        let tenant_a = TablePrefix::new("tenantA_").unwrap();
+        let tenant_b = TablePrefix::new("tenantB_").unwrap();
+
+        let pool = init_db_with(url, &tenant_a).await.unwrap();
+        init_db_with(url, &tenant_b).await.unwrap();
+        // Re-running is a no-op
+        migrate_prefixed(&pool, &tenant_a).await.unwrap();
+
+        seed_with_prefix(&pool, &tenant_a).await.unwrap();
+
+        let (a,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tenantA_tasks")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let (b,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tenantB_tasks")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(a, SEED_TASKS.len() as i64);
+        assert_eq!(b, 0);
+
+        let unprefixed = sqlx::query("SELECT COUNT(*) FROM tasks")
+            .fetch_one(&pool)
+            .await;
+        assert!(unprefixed.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_init_db_creates_missing_directory() {
+        let dir = std::env::temp_dir().join(format!("init-db-{}", std::process::id()));
+        let path = dir.join("nested").join("tasks.db");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let pool = init_db_with_url(&format!("sqlite://{}", path.display()))
+            .await
+            .unwrap();
+        assert!(path.exists());
+        pool.close().await;
+
+        // A read-only file is refused with an explanation, not a raw error
+        let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions).unwrap();
+        let err = init_db_with_url(&format!("sqlite://{}", path.display()))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is read-only"), "{}", err);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_only_pool_rejects_writes() {
+        // A shared-cache in-memory database lets a writable pool create the
+        // schema that the read-only pool then sees.
+        let url = "sqlite:file:read_only_writes?mode=memory&cache=shared";
+        let writer = SqlitePool::connect(url).await.unwrap();
+        sqlx::query("CREATE TABLE tasks (id INTEGER PRIMARY KEY, title TEXT NOT NULL)")
+            .execute(&writer)
+            .await
+            .unwrap();
+
+        let reader = read_only_pool(SqliteConnectOptions::from_str(url).unwrap())
+            .await
+            .unwrap();
+        let result = sqlx::query("INSERT INTO tasks (title) VALUES (?)")
+            .bind("Should fail")
+            .execute(&reader)
+            .await;
+
+        assert!(result.is_err());
+
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tasks")
+            .fetch_one(&reader)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_pool_allows_reads() {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:").unwrap();
+        let pool = read_only_pool(options).await.unwrap();
+
+        let (value,): (i64,) = sqlx::query_as("SELECT 1").fetch_one(&pool).await.unwrap();
+
+        assert_eq!(value, 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_pool_keeps_pragmas() {
+        let pragmas = SqlitePragmas::parse(Some("NORMAL"), Some("-2000")).unwrap();
+        let options = pragmas.apply(SqliteConnectOptions::from_str("sqlite::memory:").unwrap());
+        let pool = read_only_pool(options).await.unwrap();
+
+        let pragma = |name: &'static str| {
+            let pool = pool.clone();
+            async move {
+                let (value,): (i64,) = sqlx::query_as(&format!("PRAGMA {}", name))
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+                value
+            }
+        };
+        assert_eq!(pragma("synchronous").await, 1);
+        assert_eq!(pragma("cache_size").await, -2000);
+        assert_eq!(pragma("foreign_keys").await, 1);
+        assert_eq!(pragma("query_only").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reports_read_through_read_only_pool() {
+        let url = "sqlite:file:read_only_reports?mode=memory&cache=shared";
+        let pool = init_db_with_url(url).await.unwrap();
+        let reader = read_only_pool(SqliteConnectOptions::from_str(url).unwrap())
+            .await
+            .unwrap();
+        let tasks = SqliteTaskRepository::new(pool.clone()).with_read_only_pool(reader.clone());
+        let users = SqliteUserRepository::new(pool.clone()).with_read_only_pool(reader.clone());
+        let maintenance =
+            SqliteMaintenanceRepository::new(pool.clone()).with_read_only_pool(reader.clone());
+
+        let task = tasks.create("Reported", "").await.unwrap();
+        let user = users
+            .create("Reporter", "reporter@example.com")
+            .await
+            .unwrap();
+        assert_eq!(tasks.count_by_status().await.unwrap().todo, 1);
+        assert_eq!(tasks.stream_all().count().await, 1);
+        assert_eq!(users.task_counts().await.unwrap().len(), 1);
+        assert_eq!(users.summary(user.id).await.unwrap().total, 0);
+        assert_eq!(maintenance.backup().await.unwrap().tasks.len(), 1);
+
+        // With the reader gone, reports fail while other calls carry on
+        reader.close().await;
+        assert!(tasks.count_by_status().await.is_err());
+        assert!(users.task_counts().await.is_err());
+        assert!(users.summary(user.id).await.is_err());
+        assert!(maintenance.backup().await.is_err());
+        assert!(tasks.get(task.id).await.is_ok());
+        assert_eq!(tasks.count(&Default::default()).await.unwrap(), 1);
+    }
+}
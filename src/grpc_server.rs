@@ -1,3 +1,15 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use axum::http::{header, HeaderName, Method};
+use tonic_reflection::server::v1::{ServerReflection, ServerReflectionServer};
+use tonic_web::GrpcWebLayer;
+use tower::layer::util::{Identity, Stack};
+use tower::ServiceBuilder;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use crate::rest::cors::parse_allowed_origins;
+
 // Include the generated proto code
 pub mod task {
     tonic::include_proto!("task");
@@ -10,3 +22,230 @@ pub mod user {
 
     pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("user_descriptor");
 }
+
+pub mod error {
+    use prost::Message;
+
+    tonic::include_proto!("error");
+
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("error_descriptor");
+
+    impl ErrorDetail {
+        pub fn new(reason: ErrorReason, resource_type: &str, resource_id: i64) -> Self {
+            Self {
+                reason: reason.into(),
+                resource_type: resource_type.to_string(),
+                resource_id,
+            }
+        }
+
+        /// `Status` with this detail attached.
+        pub fn into_status(self, code: tonic::Code, message: impl Into<String>) -> tonic::Status {
+            tonic::Status::with_details(code, message, self.encode_to_vec().into())
+        }
+
+        /// The detail attached to `status`, if it carries one.
+        pub fn from_status(status: &tonic::Status) -> Option<Self> {
+            let details = status.details();
+            if details.is_empty() {
+                return None;
+            }
+            Self::decode(details).ok()
+        }
+    }
+}
+
+/// Every compiled descriptor set. Add new protos here so reflection keeps
+/// exposing them.
+pub const FILE_DESCRIPTOR_SETS: &[&[u8]] = &[
+    task::FILE_DESCRIPTOR_SET,
+    user::FILE_DESCRIPTOR_SET,
+    error::FILE_DESCRIPTOR_SET,
+];
+
+/// Builds the gRPC reflection service over all descriptor sets.
+pub fn reflection_service(
+) -> Result<ServerReflectionServer<impl ServerReflection>, tonic_reflection::server::Error> {
+    FILE_DESCRIPTOR_SETS
+        .iter()
+        .fold(
+            tonic_reflection::server::Builder::configure(),
+            |builder, descriptor_set| builder.register_encoded_file_descriptor_set(descriptor_set),
+        )
+        .build_v1()
+}
+
+/// Reflection is on unless `GRPC_REFLECTION` is set to `0`/`false`/`off`.
+pub fn reflection_enabled() -> bool {
+    parse_reflection_flag(std::env::var("GRPC_REFLECTION").ok().as_deref())
+}
+
+fn parse_reflection_flag(value: Option<&str>) -> bool {
+    !matches!(
+        value.map(|v| v.trim().to_ascii_lowercase()).as_deref(),
+        Some("0") | Some("false") | Some("off")
+    )
+}
+
+/// gRPC-web is off unless `GRPC_WEB` is set to `1`/`true`/`on`.
+pub fn grpc_web_enabled() -> bool {
+    parse_grpc_web_flag(std::env::var("GRPC_WEB").ok().as_deref())
+}
+
+fn parse_grpc_web_flag(value: Option<&str>) -> bool {
+    matches!(
+        value.map(|v| v.trim().to_ascii_lowercase()).as_deref(),
+        Some("1") | Some("true") | Some("on")
+    )
+}
+
+/// Upper bound on `GRPC_MAX_MESSAGE_BYTES`, so a typo can't let a single
+/// message claim gigabytes.
+pub const MAX_GRPC_MESSAGE_BYTES: usize = 256 * 1024 * 1024;
+
+/// `GRPC_MAX_MESSAGE_BYTES` replaces tonic's 4 MiB limit on decoded and
+/// encoded messages; unset keeps it.
+pub fn max_message_size() -> Result<Option<usize>> {
+    parse_max_message_size(std::env::var("GRPC_MAX_MESSAGE_BYTES").ok().as_deref())
+}
+
+fn parse_max_message_size(value: Option<&str>) -> Result<Option<usize>> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => match value.parse::<usize>() {
+            Ok(bytes) if bytes > 0 && bytes <= MAX_GRPC_MESSAGE_BYTES => Ok(Some(bytes)),
+            _ => bail!(
+                "Invalid GRPC_MAX_MESSAGE_BYTES {:?}: expected a byte count from 1 to {}",
+                value,
+                MAX_GRPC_MESSAGE_BYTES
+            ),
+        },
+    }
+}
+
+/// Response headers a browser only hands to gRPC-web clients when exposed.
+pub const GRPC_WEB_EXPOSED_HEADERS: [&str; 3] =
+    ["grpc-status", "grpc-message", "grpc-status-details-bin"];
+
+/// What browser clients need on the gRPC server: CORS, restricted to
+/// `CORS_ALLOWED_ORIGINS` when it is set, in front of the gRPC-web
+/// translation.
+pub fn grpc_web_layer_from_env() -> ServiceBuilder<Stack<GrpcWebLayer, Stack<CorsLayer, Identity>>>
+{
+    ServiceBuilder::new()
+        .layer(grpc_web_cors_layer(
+            std::env::var("CORS_ALLOWED_ORIGINS").ok().as_deref(),
+        ))
+        .layer(GrpcWebLayer::new())
+}
+
+/// CORS for gRPC-web calls: a preflighted `POST` carrying the gRPC-web
+/// request headers, with the trailers-turned-headers exposed so clients can
+/// read the call's status.
+pub fn grpc_web_cors_layer(allowed_origins: Option<&str>) -> CorsLayer {
+    let allow_origin = match allowed_origins.and_then(parse_allowed_origins) {
+        Some(origins) => AllowOrigin::list(origins),
+        None => AllowOrigin::from(Any),
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::POST])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            HeaderName::from_static("x-grpc-web"),
+            HeaderName::from_static("x-user-agent"),
+            HeaderName::from_static("grpc-timeout"),
+        ])
+        .expose_headers(GRPC_WEB_EXPOSED_HEADERS.map(HeaderName::from_static))
+        .max_age(Duration::from_secs(24 * 60 * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reflection_service_builds() {
+        assert!(reflection_service().is_ok());
+    }
+
+    #[test]
+    fn test_parse_grpc_web_flag() {
+        assert!(!parse_grpc_web_flag(None));
+        assert!(!parse_grpc_web_flag(Some("0")));
+        assert!(parse_grpc_web_flag(Some("1")));
+        assert!(parse_grpc_web_flag(Some(" True ")));
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web_cors_exposes_grpc_status() {
+        use axum::body::Body;
+        use axum::http::{Request, Response};
+        use tower::{service_fn, Layer, ServiceExt};
+
+        let service = grpc_web_cors_layer(Some("https://app.example.com")).layer(service_fn(
+            |_: Request<Body>| async {
+                Ok::<_, std::convert::Infallible>(Response::new(Body::empty()))
+            },
+        ));
+
+        let response = service
+            .clone()
+            .oneshot(
+                Request::post("/task.TaskService/ListTasks")
+                    .header(header::ORIGIN, "https://app.example.com")
+                    .header(header::CONTENT_TYPE, "application/grpc-web+proto")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let exposed = response.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS]
+            .to_str()
+            .unwrap();
+        for name in GRPC_WEB_EXPOSED_HEADERS {
+            assert!(exposed.contains(name), "{}", exposed);
+        }
+
+        let preflight = service
+            .oneshot(
+                Request::options("/task.TaskService/ListTasks")
+                    .header(header::ORIGIN, "https://app.example.com")
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                    .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-grpc-web")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            preflight.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+    }
+
+    #[test]
+    fn test_parse_max_message_size() {
+        assert_eq!(parse_max_message_size(None).unwrap(), None);
+        assert_eq!(parse_max_message_size(Some(" ")).unwrap(), None);
+        assert_eq!(
+            parse_max_message_size(Some("16777216")).unwrap(),
+            Some(16 * 1024 * 1024)
+        );
+        assert!(parse_max_message_size(Some("0")).is_err());
+        assert!(parse_max_message_size(Some("-1")).is_err());
+        assert!(parse_max_message_size(Some("16MB")).is_err());
+        assert!(parse_max_message_size(Some(&(MAX_GRPC_MESSAGE_BYTES + 1).to_string())).is_err());
+    }
+
+    #[test]
+    fn test_parse_reflection_flag() {
+        assert!(parse_reflection_flag(None));
+        assert!(parse_reflection_flag(Some("1")));
+        assert!(!parse_reflection_flag(Some("0")));
+        assert!(!parse_reflection_flag(Some("false")));
+        assert!(!parse_reflection_flag(Some(" OFF ")));
+    }
+}
@@ -0,0 +1,275 @@
+//! Server-side timeouts per gRPC method, on top of any deadline the client
+//! sends, so slow batch and streaming calls can be given longer than gets.
+
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use axum::http;
+use futures_util::future::BoxFuture;
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+/// Timeout for methods without their own, in milliseconds.
+pub const DEFAULT_GRPC_TIMEOUT_MS: u64 = 30_000;
+
+/// Built-in overrides for the methods that do the most work per call:
+/// the client-streamed batch create and the batch gets.
+pub const DEFAULT_METHOD_TIMEOUTS_MS: &[(&str, u64)] = &[
+    ("/task.TaskService/CreateTasks", 120_000),
+    ("/task.TaskService/BatchGetTasks", 60_000),
+    ("/user.UserService/BatchGetUsers", 60_000),
+];
+
+/// Timeout per method path (`/package.Service/Method`), with a fallback for
+/// the rest. `None` means no server-side timeout.
+#[derive(Clone, Debug, Default)]
+pub struct MethodTimeouts {
+    default: Option<Duration>,
+    methods: BTreeMap<String, Option<Duration>>,
+}
+
+impl MethodTimeouts {
+    pub fn new(default: Option<Duration>) -> Self {
+        Self {
+            default,
+            methods: BTreeMap::new(),
+        }
+    }
+
+    /// Overrides the timeout for `method`, with or without the leading `/`.
+    pub fn with_method(mut self, method: &str, timeout: Option<Duration>) -> Self {
+        self.methods.insert(method_path(method), timeout);
+        self
+    }
+
+    /// `GRPC_TIMEOUT_MS` for every method (default 30000) and
+    /// `GRPC_METHOD_TIMEOUTS_MS`, a comma-separated list of
+    /// `package.Service/Method=ms`, on top of [`DEFAULT_METHOD_TIMEOUTS_MS`].
+    /// 0 turns the timeout off.
+    pub fn from_env() -> Result<Self> {
+        Self::parse(
+            std::env::var("GRPC_TIMEOUT_MS").ok().as_deref(),
+            std::env::var("GRPC_METHOD_TIMEOUTS_MS").ok().as_deref(),
+        )
+    }
+
+    fn parse(default: Option<&str>, methods: Option<&str>) -> Result<Self> {
+        let default = match default.map(str::trim) {
+            None | Some("") => DEFAULT_GRPC_TIMEOUT_MS,
+            Some(value) => match value.parse::<u64>() {
+                Ok(millis) => millis,
+                Err(_) => bail!("Invalid GRPC_TIMEOUT_MS {:?}: expected milliseconds", value),
+            },
+        };
+
+        let mut timeouts = DEFAULT_METHOD_TIMEOUTS_MS.iter().fold(
+            Self::new(timeout_from_millis(default)),
+            |timeouts, (method, millis)| timeouts.with_method(method, timeout_from_millis(*millis)),
+        );
+        for entry in methods
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let parsed = entry.split_once('=').and_then(|(method, millis)| {
+                let method = method.trim();
+                let millis = millis.trim().parse::<u64>().ok()?;
+                method.contains('/').then_some((method, millis))
+            });
+            let Some((method, millis)) = parsed else {
+                bail!(
+                    "Invalid GRPC_METHOD_TIMEOUTS_MS entry {:?}: expected package.Service/Method=ms",
+                    entry
+                );
+            };
+            timeouts = timeouts.with_method(method, timeout_from_millis(millis));
+        }
+
+        Ok(timeouts)
+    }
+
+    /// The timeout for the request path `method`.
+    pub fn timeout_for(&self, method: &str) -> Option<Duration> {
+        self.methods.get(method).copied().unwrap_or(self.default)
+    }
+
+    /// The fallback in milliseconds, 0 when off.
+    pub fn default_ms(&self) -> u64 {
+        self.default.map_or(0, |timeout| timeout.as_millis() as u64)
+    }
+
+    /// The per-method overrides in milliseconds, 0 when off.
+    pub fn methods_ms(&self) -> BTreeMap<String, u64> {
+        self.methods
+            .iter()
+            .map(|(method, timeout)| {
+                (
+                    method.clone(),
+                    timeout.map_or(0, |timeout| timeout.as_millis() as u64),
+                )
+            })
+            .collect()
+    }
+}
+
+fn timeout_from_millis(millis: u64) -> Option<Duration> {
+    (millis > 0).then(|| Duration::from_millis(millis))
+}
+
+fn method_path(method: &str) -> String {
+    format!("/{}", method.trim().trim_start_matches('/'))
+}
+
+/// Applies [`MethodTimeouts`] to every call on the server.
+#[derive(Clone, Debug)]
+pub struct MethodTimeoutLayer {
+    timeouts: Arc<MethodTimeouts>,
+}
+
+impl MethodTimeoutLayer {
+    pub fn new(timeouts: MethodTimeouts) -> Self {
+        Self {
+            timeouts: Arc::new(timeouts),
+        }
+    }
+}
+
+impl<S> Layer<S> for MethodTimeoutLayer {
+    type Service = MethodTimeout<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MethodTimeout {
+            inner,
+            timeouts: self.timeouts.clone(),
+        }
+    }
+}
+
+/// Answers `DEADLINE_EXCEEDED` when the inner service takes longer than the
+/// method's timeout, dropping the call.
+#[derive(Clone, Debug)]
+pub struct MethodTimeout<S> {
+    inner: S,
+    timeouts: Arc<MethodTimeouts>,
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for MethodTimeout<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let timeout = self.timeouts.timeout_for(request.uri().path());
+        let call = self.inner.call(request);
+
+        Box::pin(async move {
+            match timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, call).await {
+                    Ok(result) => result,
+                    Err(_) => Ok(Status::deadline_exceeded("Server timeout exceeded").into_http()),
+                },
+                None => call.await,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_method_timeouts() {
+        let timeouts = MethodTimeouts::parse(None, None).unwrap();
+        assert_eq!(
+            timeouts.timeout_for("/task.TaskService/GetTask"),
+            Some(Duration::from_millis(DEFAULT_GRPC_TIMEOUT_MS))
+        );
+        assert_eq!(
+            timeouts.timeout_for("/task.TaskService/CreateTasks"),
+            Some(Duration::from_secs(120))
+        );
+
+        let timeouts = MethodTimeouts::parse(
+            Some("0"),
+            Some(" task.TaskService/CreateTasks = 0, /user.UserService/ListUsers=250 "),
+        )
+        .unwrap();
+        assert_eq!(timeouts.timeout_for("/task.TaskService/GetTask"), None);
+        assert_eq!(timeouts.timeout_for("/task.TaskService/CreateTasks"), None);
+        assert_eq!(
+            timeouts.timeout_for("/user.UserService/ListUsers"),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(timeouts.methods_ms()["/user.UserService/ListUsers"], 250);
+
+        assert!(MethodTimeouts::parse(Some("1s"), None).is_err());
+        assert!(MethodTimeouts::parse(None, Some("GetTask=100")).is_err());
+        assert!(MethodTimeouts::parse(None, Some("task.TaskService/GetTask")).is_err());
+        assert!(MethodTimeouts::parse(None, Some("task.TaskService/GetTask=-1")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slow_method_answers_deadline_exceeded() {
+        use tower::{service_fn, ServiceExt};
+
+        let service = MethodTimeoutLayer::new(MethodTimeouts::new(None).with_method(
+            "task.TaskService/ListTasks",
+            Some(Duration::from_millis(10)),
+        ))
+        .layer(service_fn(|_: http::Request<BoxBody>| async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok::<_, std::convert::Infallible>(http::Response::new(tonic::body::empty_body()))
+        }));
+        let call = |path: &str| {
+            service.clone().oneshot(
+                http::Request::post(path)
+                    .body(tonic::body::empty_body())
+                    .unwrap(),
+            )
+        };
+
+        let response = call("/task.TaskService/ListTasks").await.unwrap();
+        let status = Status::from_header_map(response.headers()).unwrap();
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+
+        let response = call("/task.TaskService/GetTask").await.unwrap();
+        assert!(Status::from_header_map(response.headers()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_layered_server_builds() {
+        use crate::grpc_server;
+        use crate::repository::MockTaskRepository;
+        use crate::service::TaskServiceImpl;
+
+        let incoming = futures_util::stream::empty::<std::io::Result<tokio::net::TcpStream>>();
+
+        tonic::transport::Server::builder()
+            .accept_http1(true)
+            .layer(tower::util::option_layer(Some(
+                grpc_server::grpc_web_layer_from_env(),
+            )))
+            .layer(MethodTimeoutLayer::new(
+                MethodTimeouts::parse(None, None).unwrap(),
+            ))
+            .add_service(TaskServiceImpl::new(Arc::new(MockTaskRepository::new())).into_service())
+            .serve_with_incoming_shutdown(incoming, async {})
+            .await
+            .unwrap();
+    }
+}
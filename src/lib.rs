@@ -1,5 +1,9 @@
+pub mod config;
 pub mod db;
 pub mod grpc_server;
+pub mod grpc_timeout;
 pub mod repository;
 pub mod rest;
 pub mod service;
+pub mod timestamp;
+pub mod validation;
@@ -1,139 +1,269 @@
 use rust_grpc_sqlite::{
+    config::{self, Config, EnabledServers},
     db, grpc_server,
-    repository::{SqliteTaskRepository, SqliteUserRepository},
-    rest::{
-        CreateTaskRequest, CreateUserRequest, ErrorResponse, TaskResponse, UpdateTaskRequest,
-        UpdateUserRequest, UserResponse,
+    grpc_timeout::{MethodTimeoutLayer, MethodTimeouts},
+    repository::{
+        CacheClearingMaintenanceRepository, CachedTaskRepository, CachedUserRepository,
+        MaintenanceRepository, SqliteMaintenanceRepository, SqliteTaskRepository,
+        SqliteUserRepository, TaskRepository, TimedTaskRepository, TimedUserRepository,
+        UserRepository, WalCheckpointTask,
     },
+    rest,
     service::{TaskServiceImpl, UserServiceImpl},
 };
 
-use anyhow::Result;
-use axum::Router;
-use std::sync::Arc;
-use tokio::net::TcpListener;
+#[cfg(feature = "postgres")]
+use rust_grpc_sqlite::repository::postgres::{self, PgTaskRepository, PgUserRepository};
+
+use anyhow::{bail, Result};
+use std::{net::SocketAddr, sync::Arc};
+use tokio::{net::TcpListener, task::JoinHandle};
 use tonic::transport::Server;
-use tonic_web::GrpcWebLayer;
-use tower_http::cors::{Any, CorsLayer};
-use utoipa::OpenApi;
-use utoipa_swagger_ui::SwaggerUi;
-
-#[derive(OpenApi)]
-#[openapi(
-    paths(
-        rust_grpc_sqlite::rest::task_handlers::list_tasks,
-        rust_grpc_sqlite::rest::task_handlers::create_task,
-        rust_grpc_sqlite::rest::task_handlers::get_task,
-        rust_grpc_sqlite::rest::task_handlers::update_task,
-        rust_grpc_sqlite::rest::task_handlers::delete_task,
-        rust_grpc_sqlite::rest::user_handlers::list_users,
-        rust_grpc_sqlite::rest::user_handlers::create_user,
-        rust_grpc_sqlite::rest::user_handlers::get_user,
-        rust_grpc_sqlite::rest::user_handlers::update_user,
-        rust_grpc_sqlite::rest::user_handlers::delete_user,
-    ),
-    components(
-        schemas(
-            TaskResponse,
-            CreateTaskRequest,
-            UpdateTaskRequest,
-            UserResponse,
-            CreateUserRequest,
-            UpdateUserRequest,
-            ErrorResponse,
-        )
-    ),
-    tags(
-        (name = "tasks", description = "Task management endpoints"),
-        (name = "users", description = "User management endpoints")
-    ),
-    info(
-        title = "Rust gRPC SQLite REST API",
-        version = "1.0.0",
-        description = "REST API layer for the Rust gRPC SQLite application"
-    )
-)]
-struct ApiDoc;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let servers = EnabledServers::from_env()?;
+
+    // `/ready` answers 503 until the schema is migrated (and seeded)
+    let readiness = rest::Readiness::new();
+
     println!("Initializing database...");
-    let pool = db::init_db().await?;
+    let database_url = db::database_url();
+    let (mut task_repository, mut user_repository, mut maintenance) =
+        if db::is_postgres(&database_url) {
+            open_postgres(&database_url).await?
+        } else {
+            open_sqlite(&database_url).await?
+        };
     println!("Database initialized successfully");
+    readiness.mark_ready();
+
+    // Optionally truncate the WAL on a timer
+    if let Some(task) = maintenance.clone().and_then(|maintenance| {
+        WalCheckpointTask::new(maintenance, config::wal_checkpoint_interval())
+    }) {
+        println!("Checkpointing the WAL every {:?}", task.interval());
+        task.spawn();
+    }
 
-    // Create repositories and wrap in Arc for sharing
-    let task_repository = Arc::new(SqliteTaskRepository::new(pool.clone()));
-    let user_repository = Arc::new(SqliteUserRepository::new(pool));
+    // Warn about slow repository calls; wrapped inside the cache so cache
+    // hits aren't timed
+    if let Some(threshold) = config::slow_query_threshold() {
+        task_repository = Arc::new(TimedTaskRepository::new(task_repository, threshold));
+        user_repository = Arc::new(TimedUserRepository::new(user_repository, threshold));
+    }
+
+    // Optionally cache single-item reads
+    if let Some(ttl) = config::cache_ttl() {
+        println!("Caching get_task/get_user for {:?}", ttl);
+        let tasks = Arc::new(CachedTaskRepository::new(task_repository, ttl));
+        // Deleting a user can delete or unassign their tasks
+        let users = Arc::new(
+            CachedUserRepository::new(user_repository, ttl).with_task_cache(tasks.clone()),
+        );
+        // A restore replaces both tables behind the caches' backs
+        maintenance = maintenance.map(|maintenance| {
+            Arc::new(CacheClearingMaintenanceRepository::new(
+                maintenance,
+                tasks.clone(),
+                users.clone(),
+            )) as Arc<dyn MaintenanceRepository>
+        });
+        task_repository = tasks;
+        user_repository = users;
+    }
+
+    // Publish task writes from both servers to the REST event feed
+    let events = rest::TaskEvents::new();
+    task_repository = Arc::new(rest::PublishingTaskRepository::new(
+        task_repository,
+        events.clone(),
+    ));
 
     // Clone repositories for REST API
     let task_repo_rest = task_repository.clone();
     let user_repo_rest = user_repository.clone();
 
+    // Read before spawning so a bad value stops startup
+    let max_message_size = grpc_server::max_message_size()?;
+    let method_timeouts = MethodTimeouts::from_env()?;
+
     // Spawn gRPC server
-    let grpc_handle = tokio::spawn(async move {
-        let grpc_addr = "[::]:50051".parse().unwrap();
-
-        let task_service = TaskServiceImpl::new(task_repository).into_service();
-        let user_service = UserServiceImpl::new(user_repository).into_service();
-
-        let reflection_service = tonic_reflection::server::Builder::configure()
-            .register_encoded_file_descriptor_set(grpc_server::task::FILE_DESCRIPTOR_SET)
-            .register_encoded_file_descriptor_set(grpc_server::user::FILE_DESCRIPTOR_SET)
-            .build_v1()
-            .expect("Failed to build reflection service");
-
-        println!("gRPC server listening on {}", grpc_addr);
-
-        Server::builder()
-            .accept_http1(true)
-            .layer(GrpcWebLayer::new())
-            .add_service(task_service)
-            .add_service(user_service)
-            .add_service(reflection_service)
-            .serve(grpc_addr)
-            .await
-            .expect("gRPC server failed");
-    });
+    let grpc_handle = servers.grpc.then(|| {
+        tokio::spawn(async move {
+            let grpc_addr = config::GRPC_ADDR.parse().unwrap();
 
-    // Build REST API router
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
-    let app = Router::new()
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .nest(
-            "/api",
-            rust_grpc_sqlite::rest::task_routes(task_repo_rest)
-                .merge(rust_grpc_sqlite::rest::user_routes(user_repo_rest)),
-        )
-        .layer(cors);
+            let max_page_size = config::max_page_size();
+            let mut task_service =
+                TaskServiceImpl::new(task_repository).with_max_page_size(max_page_size);
+            let mut user_service =
+                UserServiceImpl::new(user_repository).with_max_page_size(max_page_size);
+            if let Some(bytes) = max_message_size {
+                task_service = task_service.with_max_message_size(bytes);
+                user_service = user_service.with_max_message_size(bytes);
+            }
+            let task_service = task_service.into_service();
+            let user_service = user_service.into_service();
 
-    // Start REST server
-    let rest_addr = "0.0.0.0:3000";
-    let listener = TcpListener::bind(rest_addr).await?;
+            let reflection_service = grpc_server::reflection_enabled().then(|| {
+                grpc_server::reflection_service().expect("Failed to build reflection service")
+            });
+
+            // gRPC-web arrives over HTTP/1.1, so plain gRPC needs neither
+            let grpc_web = grpc_server::grpc_web_enabled();
+            let grpc_web_layer = grpc_web.then(grpc_server::grpc_web_layer_from_env);
+
+            println!("gRPC server listening on {}", grpc_addr);
+
+            Server::builder()
+                .accept_http1(grpc_web)
+                .layer(tower::util::option_layer(grpc_web_layer))
+                // Inside gRPC-web so its DEADLINE_EXCEEDED gets translated too
+                .layer(MethodTimeoutLayer::new(method_timeouts))
+                .add_service(task_service)
+                .add_service(user_service)
+                .add_optional_service(reflection_service)
+                .serve(grpc_addr)
+                .await
+                .expect("gRPC server failed");
+        })
+    });
+
+    // Build REST API router and bind its listener
+    let rest_server = if servers.rest {
+        let mut router_config = rest::RouterConfig::from_env()
+            .with_readiness(readiness)
+            .with_events(events);
+        if let Some(maintenance) = maintenance {
+            router_config = router_config.with_maintenance(maintenance);
+        }
+        let effective_config = Config::from_env(&router_config)?;
+        let router_config = router_config.with_effective_config(effective_config);
+        let swagger_path = router_config.swagger_path.clone();
+        let app = rest::create_router_with_config(task_repo_rest, user_repo_rest, router_config)
+            .layer(rest::cors::cors_layer_from_env());
+        let listener = TcpListener::bind(config::REST_ADDR).await?;
+        Some((listener, app, swagger_path))
+    } else {
+        None
+    };
 
     println!("\n========================================");
     println!("Servers are running:");
     println!("========================================");
-    println!("  gRPC:    [::]:50051");
-    println!("  REST:    http://localhost:3000");
-    println!("  Swagger: http://localhost:3000/swagger-ui/");
+    if grpc_handle.is_some() {
+        println!("  gRPC:    [::]:50051");
+    } else {
+        println!("  gRPC:    disabled");
+    }
+    match &rest_server {
+        Some((_, _, swagger_path)) => {
+            println!("  REST:    http://localhost:3000");
+            match swagger_path {
+                Some(path) => println!("  Swagger: http://localhost:3000{}/", path),
+                None => println!("  Swagger: disabled"),
+            }
+        }
+        None => println!("  REST:    disabled"),
+    }
     println!("========================================");
     println!("\nPress Ctrl+C to stop");
 
-    let rest_handle = tokio::spawn(async move {
-        axum::serve(listener, app)
+    // Start REST server
+    let rest_handle = rest_server.map(|(listener, app, _)| {
+        tokio::spawn(async move {
+            // Connect info gives the rate limiter each client's address
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
             .await
             .expect("REST server failed");
+        })
     });
 
-    // Wait for both servers
+    // Wait for whichever servers were started; a disabled one never
+    // finishes, so the select comes down to the other
     tokio::select! {
-        _ = grpc_handle => println!("gRPC server stopped"),
-        _ = rest_handle => println!("REST server stopped"),
+        _ = until_stopped(grpc_handle) => println!("gRPC server stopped"),
+        _ = until_stopped(rest_handle) => println!("REST server stopped"),
     }
 
     Ok(())
 }
+
+/// Resolves when the server task ends, or never when it wasn't started.
+async fn until_stopped(handle: Option<JoinHandle<()>>) {
+    match handle {
+        Some(handle) => {
+            let _ = handle.await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// The task and user repositories, plus the maintenance operations the
+/// backend supports.
+type Repositories = (
+    Arc<dyn TaskRepository>,
+    Arc<dyn UserRepository>,
+    Option<Arc<dyn MaintenanceRepository>>,
+);
+
+async fn open_sqlite(database_url: &str) -> Result<Repositories> {
+    let table_prefix = db::TablePrefix::from_env()?;
+    let pool = db::init_db_with(database_url, &table_prefix).await?;
+
+    if std::env::var("SEED").is_ok_and(|value| value == "1") {
+        db::seed_with_prefix(&pool, &table_prefix).await?;
+        println!("Seeded sample data");
+    }
+
+    // With DB_READ_ONLY_POOL, reports read through their own query-only
+    // pool; an in-memory database only exists on the main pool's connection
+    let read_pool = if db::read_only_pool_enabled() && !db::is_in_memory(database_url) {
+        db::init_read_only_pool(database_url).await?
+    } else {
+        pool.clone()
+    };
+
+    Ok((
+        Arc::new(
+            SqliteTaskRepository::new(pool.clone())
+                .with_order(config::task_order()?)
+                .with_table_prefix(&table_prefix)
+                .with_read_only_pool(read_pool.clone()),
+        ),
+        Arc::new(
+            SqliteUserRepository::new(pool.clone())
+                .with_table_prefix(&table_prefix)
+                .with_read_only_pool(read_pool.clone()),
+        ),
+        Some(Arc::new(
+            SqliteMaintenanceRepository::new(pool)
+                .with_table_prefix(&table_prefix)
+                .with_read_only_pool(read_pool),
+        )),
+    ))
+}
+
+/// VACUUM and WAL checkpoints are SQLite operations, so Postgres runs
+/// without them.
+#[cfg(feature = "postgres")]
+async fn open_postgres(database_url: &str) -> Result<Repositories> {
+    if !db::TablePrefix::from_env()?.as_str().is_empty() {
+        bail!("TABLE_PREFIX is not supported with a Postgres DATABASE_URL");
+    }
+    let pool = postgres::init_db(database_url).await?;
+
+    Ok((
+        Arc::new(PgTaskRepository::new(pool.clone()).with_order(config::task_order()?)),
+        Arc::new(PgUserRepository::new(pool)),
+        None,
+    ))
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn open_postgres(_database_url: &str) -> Result<Repositories> {
+    bail!("DATABASE_URL is a Postgres URL, but this build lacks the `postgres` feature")
+}
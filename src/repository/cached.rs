@@ -0,0 +1,602 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+
+use super::{
+    ArchivedTask, Backup, CheckpointReport, DeleteUserPolicy, DuplicateTasks,
+    MaintenanceRepository, Page, PoolStats, TaskChanges, TaskFilter, TaskRepository,
+    TaskStatusCounts, TaskUpdate, UserRepository, UserSummary, UserTaskCounts, VacuumReport,
+};
+use crate::db::{TaskAuditModel, TaskModel, TaskStatus, UserModel};
+
+/// Per-id cache whose entries expire `ttl` after they were stored.
+struct TtlCache<V> {
+    ttl: Duration,
+    state: Mutex<CacheState<V>>,
+}
+
+struct CacheState<V> {
+    entries: HashMap<i64, (Instant, V)>,
+    /// Bumped by every eviction, so a read that started before a write can
+    /// tell its result may be stale
+    generation: u64,
+}
+
+impl<V: Clone> TtlCache<V> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                generation: 0,
+            }),
+        }
+    }
+
+    fn get(&self, id: i64) -> Option<V> {
+        let entries = &mut self.state.lock().unwrap().entries;
+        match entries.get(&id) {
+            Some((stored_at, value)) if stored_at.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(&id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// The generation to pass to [`TtlCache::fill`], taken before reading
+    /// the value from the database.
+    fn generation(&self) -> u64 {
+        self.state.lock().unwrap().generation
+    }
+
+    /// Stores `value` unless something was evicted since `generation`, in
+    /// which case the value may predate that write and is dropped.
+    fn fill(&self, id: i64, generation: u64, value: V) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation == generation {
+            state.entries.insert(id, (Instant::now(), value));
+        }
+    }
+
+    fn invalidate(&self, id: i64) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.remove(&id);
+        state.generation += 1;
+    }
+
+    fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.generation += 1;
+    }
+}
+
+/// Caches `get` results of any task repository. Updates and deletes through
+/// this repository evict the affected id; writes made elsewhere are only
+/// picked up once the entry expires.
+pub struct CachedTaskRepository {
+    inner: Arc<dyn TaskRepository>,
+    cache: TtlCache<TaskModel>,
+}
+
+impl CachedTaskRepository {
+    pub fn new(inner: Arc<dyn TaskRepository>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            cache: TtlCache::new(ttl),
+        }
+    }
+
+    /// Drops every cached task, for writes that bypassed this repository.
+    pub fn clear(&self) {
+        self.cache.clear();
+    }
+}
+
+#[async_trait]
+impl TaskRepository for CachedTaskRepository {
+    async fn create(&self, title: &str, description: &str) -> Result<TaskModel> {
+        self.inner.create(title, description).await
+    }
+
+    async fn create_for_user(
+        &self,
+        user_id: i64,
+        title: &str,
+        description: &str,
+    ) -> Result<TaskModel> {
+        self.inner
+            .create_for_user(user_id, title, description)
+            .await
+    }
+
+    async fn get(&self, id: i64) -> Result<TaskModel> {
+        if let Some(task) = self.cache.get(id) {
+            return Ok(task);
+        }
+
+        let generation = self.cache.generation();
+        let task = self.inner.get(id).await?;
+        self.cache.fill(id, generation, task.clone());
+        Ok(task)
+    }
+
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<TaskModel>> {
+        self.inner.get_many(ids).await
+    }
+
+    async fn create_with_tags(
+        &self,
+        assigned_user_id: Option<i64>,
+        title: &str,
+        description: &str,
+        tags: &[String],
+    ) -> Result<TaskModel> {
+        self.inner
+            .create_with_tags(assigned_user_id, title, description, tags)
+            .await
+    }
+
+    async fn create_returning_meta(
+        &self,
+        title: &str,
+        description: &str,
+    ) -> Result<(TaskModel, i64)> {
+        self.inner.create_returning_meta(title, description).await
+    }
+
+    async fn create_many(&self, tasks: &[(String, String)]) -> Result<Vec<TaskModel>> {
+        self.inner.create_many(tasks).await
+    }
+
+    async fn list(&self, page: Page, filter: &TaskFilter) -> Result<Vec<TaskModel>> {
+        self.inner.list(page, filter).await
+    }
+
+    fn stream_all(&self) -> BoxStream<'static, Result<TaskModel>> {
+        self.inner.stream_all()
+    }
+
+    async fn list_updated_since(&self, since: &str) -> Result<Vec<TaskModel>> {
+        self.inner.list_updated_since(since).await
+    }
+
+    async fn list_completed_between(&self, from: &str, to: &str) -> Result<Vec<TaskModel>> {
+        self.inner.list_completed_between(from, to).await
+    }
+
+    async fn count(&self, filter: &TaskFilter) -> Result<i64> {
+        self.inner.count(filter).await
+    }
+
+    async fn history(&self, id: i64) -> Result<Vec<TaskAuditModel>> {
+        self.inner.history(id).await
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+        assigned_user_id: Option<Option<i64>>,
+    ) -> Result<TaskModel> {
+        let result = self
+            .inner
+            .update(id, title, description, status, assigned_user_id)
+            .await;
+        self.cache.invalidate(id);
+        result
+    }
+
+    async fn update_if_version(
+        &self,
+        id: i64,
+        expected_updated_at: &str,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+    ) -> Result<TaskModel> {
+        let result = self
+            .inner
+            .update_if_version(id, expected_updated_at, title, description, status)
+            .await;
+        self.cache.invalidate(id);
+        result
+    }
+
+    async fn update_with_tags(&self, id: i64, update: &TaskUpdate) -> Result<TaskModel> {
+        let result = self.inner.update_with_tags(id, update).await;
+        self.cache.invalidate(id);
+        result
+    }
+
+    async fn set_tags(&self, id: i64, tags: &[String]) -> Result<TaskModel> {
+        let result = self.inner.set_tags(id, tags).await;
+        self.cache.invalidate(id);
+        result
+    }
+
+    async fn move_after(&self, id: i64, after_id: Option<i64>) -> Result<TaskModel> {
+        let result = self.inner.move_after(id, after_id).await;
+        self.cache.invalidate(id);
+        result
+    }
+
+    async fn delete(&self, id: i64) -> Result<bool> {
+        let result = self.inner.delete(id).await;
+        self.cache.invalidate(id);
+        result
+    }
+
+    async fn delete_returning(&self, id: i64) -> Result<Option<TaskModel>> {
+        let result = self.inner.delete_returning(id).await;
+        self.cache.invalidate(id);
+        result
+    }
+
+    async fn complete_all(&self) -> Result<u64> {
+        let result = self.inner.complete_all().await;
+        self.cache.clear();
+        result
+    }
+
+    async fn complete_matching(&self, filter: &TaskFilter) -> Result<u64> {
+        let result = self.inner.complete_matching(filter).await;
+        self.cache.clear();
+        result
+    }
+
+    async fn reassign(&self, from: i64, to: i64) -> Result<u64> {
+        let result = self.inner.reassign(from, to).await;
+        self.cache.clear();
+        result
+    }
+
+    async fn delete_all(&self) -> Result<u64> {
+        let result = self.inner.delete_all().await;
+        self.cache.clear();
+        result
+    }
+
+    async fn archive_completed(&self) -> Result<u64> {
+        let result = self.inner.archive_completed().await;
+        self.cache.clear();
+        result
+    }
+
+    async fn list_archived(&self, page: Page) -> Result<Vec<ArchivedTask>> {
+        self.inner.list_archived(page).await
+    }
+
+    async fn duplicates(&self) -> Result<Vec<DuplicateTasks>> {
+        self.inner.duplicates().await
+    }
+
+    async fn count_by_status(&self) -> Result<TaskStatusCounts> {
+        self.inner.count_by_status().await
+    }
+
+    async fn changes_since(&self, cursor: Option<i64>) -> Result<TaskChanges> {
+        self.inner.changes_since(cursor).await
+    }
+}
+
+/// User counterpart of [`CachedTaskRepository`].
+pub struct CachedUserRepository {
+    inner: Arc<dyn UserRepository>,
+    cache: TtlCache<UserModel>,
+    tasks: Option<Arc<CachedTaskRepository>>,
+}
+
+impl CachedUserRepository {
+    pub fn new(inner: Arc<dyn UserRepository>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            cache: TtlCache::new(ttl),
+            tasks: None,
+        }
+    }
+
+    /// Clears `tasks` whenever deleting a user also deletes or unassigns
+    /// their tasks.
+    pub fn with_task_cache(mut self, tasks: Arc<CachedTaskRepository>) -> Self {
+        self.tasks = Some(tasks);
+        self
+    }
+
+    /// Drops every cached user, for writes that bypassed this repository.
+    pub fn clear(&self) {
+        self.cache.clear();
+    }
+}
+
+#[async_trait]
+impl UserRepository for CachedUserRepository {
+    async fn create(&self, name: &str, email: &str) -> Result<UserModel> {
+        self.inner.create(name, email).await
+    }
+
+    async fn upsert_by_email(&self, name: &str, email: &str) -> Result<(UserModel, bool)> {
+        let (user, inserted) = self.inner.upsert_by_email(name, email).await?;
+        self.cache.invalidate(user.id);
+        Ok((user, inserted))
+    }
+
+    async fn get(&self, id: i64) -> Result<UserModel> {
+        if let Some(user) = self.cache.get(id) {
+            return Ok(user);
+        }
+
+        let generation = self.cache.generation();
+        let user = self.inner.get(id).await?;
+        self.cache.fill(id, generation, user.clone());
+        Ok(user)
+    }
+
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<UserModel>> {
+        self.inner.get_many(ids).await
+    }
+
+    async fn list(&self, page: Page, name: Option<&str>) -> Result<Vec<UserModel>> {
+        self.inner.list(page, name).await
+    }
+
+    async fn count(&self, name: Option<&str>) -> Result<i64> {
+        self.inner.count(name).await
+    }
+
+    async fn update(&self, id: i64, name: Option<&str>, email: Option<&str>) -> Result<UserModel> {
+        let result = self.inner.update(id, name, email).await;
+        self.cache.invalidate(id);
+        result
+    }
+
+    async fn delete(&self, id: i64, policy: DeleteUserPolicy) -> Result<bool> {
+        let result = self.inner.delete(id, policy).await;
+        self.cache.invalidate(id);
+        if policy != DeleteUserPolicy::Restrict {
+            if let Some(tasks) = &self.tasks {
+                tasks.clear();
+            }
+        }
+        result
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<UserModel>> {
+        self.inner.search(query).await
+    }
+
+    async fn task_counts(&self) -> Result<Vec<UserTaskCounts>> {
+        self.inner.task_counts().await
+    }
+
+    async fn summary(&self, id: i64) -> Result<UserSummary> {
+        self.inner.summary(id).await
+    }
+}
+
+/// Clears the task and user caches after a restore, which replaces both
+/// tables without going through the cached repositories.
+pub struct CacheClearingMaintenanceRepository {
+    inner: Arc<dyn MaintenanceRepository>,
+    tasks: Arc<CachedTaskRepository>,
+    users: Arc<CachedUserRepository>,
+}
+
+impl CacheClearingMaintenanceRepository {
+    pub fn new(
+        inner: Arc<dyn MaintenanceRepository>,
+        tasks: Arc<CachedTaskRepository>,
+        users: Arc<CachedUserRepository>,
+    ) -> Self {
+        Self {
+            inner,
+            tasks,
+            users,
+        }
+    }
+}
+
+#[async_trait]
+impl MaintenanceRepository for CacheClearingMaintenanceRepository {
+    async fn vacuum(&self) -> Result<VacuumReport> {
+        self.inner.vacuum().await
+    }
+
+    async fn wal_checkpoint(&self) -> Result<CheckpointReport> {
+        self.inner.wal_checkpoint().await
+    }
+
+    async fn backup(&self) -> Result<Backup> {
+        self.inner.backup().await
+    }
+
+    async fn restore(&self, backup: &Backup) -> Result<()> {
+        let result = self.inner.restore(backup).await;
+        self.tasks.clear();
+        self.users.clear();
+        result
+    }
+
+    fn pool_stats(&self) -> PoolStats {
+        self.inner.pool_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        SqliteMaintenanceRepository, SqliteTaskRepository, SqliteUserRepository,
+    };
+    use sqlx::SqlitePool;
+
+    async fn setup_test_pool() -> SqlitePool {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        crate::db::MIGRATOR.run(&pool).await.unwrap();
+
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_second_get_hits_cache() {
+        let inner = Arc::new(SqliteTaskRepository::new(setup_test_pool().await));
+        let repo = CachedTaskRepository::new(inner.clone(), Duration::from_secs(60));
+
+        let task = inner.create("Original", "Desc").await.unwrap();
+        repo.get(task.id).await.unwrap();
+
+        // Bypass the cache so only a cache hit can return the old title.
+        inner
+            .update(task.id, Some("Changed"), None, None, None)
+            .await
+            .unwrap();
+        let cached = repo.get(task.id).await.unwrap();
+
+        assert_eq!(cached.title, "Original");
+    }
+
+    #[tokio::test]
+    async fn test_update_invalidates_cache() {
+        let inner = Arc::new(SqliteTaskRepository::new(setup_test_pool().await));
+        let repo = CachedTaskRepository::new(inner.clone(), Duration::from_secs(60));
+
+        let task = inner.create("Original", "Desc").await.unwrap();
+        repo.get(task.id).await.unwrap();
+        repo.update(task.id, Some("Updated"), None, None, None)
+            .await
+            .unwrap();
+
+        let fetched = repo.get(task.id).await.unwrap();
+
+        assert_eq!(fetched.title, "Updated");
+    }
+
+    #[tokio::test]
+    async fn test_delete_invalidates_cache() {
+        let inner = Arc::new(SqliteTaskRepository::new(setup_test_pool().await));
+        let repo = CachedTaskRepository::new(inner.clone(), Duration::from_secs(60));
+
+        let task = inner.create("Delete Me", "Desc").await.unwrap();
+        repo.get(task.id).await.unwrap();
+        repo.delete(task.id).await.unwrap();
+
+        assert!(repo.get(task.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cache_entries_expire() {
+        let inner = Arc::new(SqliteTaskRepository::new(setup_test_pool().await));
+        let repo = CachedTaskRepository::new(inner.clone(), Duration::ZERO);
+
+        let task = inner.create("Original", "Desc").await.unwrap();
+        repo.get(task.id).await.unwrap();
+        inner
+            .update(task.id, Some("Changed"), None, None, None)
+            .await
+            .unwrap();
+
+        let fetched = repo.get(task.id).await.unwrap();
+
+        assert_eq!(fetched.title, "Changed");
+    }
+
+    #[tokio::test]
+    async fn test_user_update_invalidates_cache() {
+        let inner = Arc::new(SqliteUserRepository::new(setup_test_pool().await));
+        let repo = CachedUserRepository::new(inner.clone(), Duration::from_secs(60));
+
+        let user = inner.create("John Doe", "john@example.com").await.unwrap();
+        repo.get(user.id).await.unwrap();
+        inner.update(user.id, Some("Bypassed"), None).await.unwrap();
+        assert_eq!(repo.get(user.id).await.unwrap().name, "John Doe");
+
+        repo.update(user.id, Some("Jane Doe"), None).await.unwrap();
+
+        assert_eq!(repo.get(user.id).await.unwrap().name, "Jane Doe");
+    }
+
+    #[tokio::test]
+    async fn test_restore_clears_caches() {
+        let pool = setup_test_pool().await;
+        let tasks = Arc::new(CachedTaskRepository::new(
+            Arc::new(SqliteTaskRepository::new(pool.clone())),
+            Duration::from_secs(60),
+        ));
+        let users = Arc::new(CachedUserRepository::new(
+            Arc::new(SqliteUserRepository::new(pool.clone())),
+            Duration::from_secs(60),
+        ));
+        let maintenance = CacheClearingMaintenanceRepository::new(
+            Arc::new(SqliteMaintenanceRepository::new(pool)),
+            tasks.clone(),
+            users.clone(),
+        );
+
+        let task = tasks.create("Restored away", "Desc").await.unwrap();
+        let user = users.create("John Doe", "john@example.com").await.unwrap();
+        tasks.get(task.id).await.unwrap();
+        users.get(user.id).await.unwrap();
+
+        maintenance.restore(&Backup::default()).await.unwrap();
+
+        assert!(tasks.get(task.id).await.is_err());
+        assert!(users.get(user.id).await.is_err());
+    }
+
+    #[test]
+    fn test_fill_skipped_after_eviction() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+
+        // A read that started before a write finished must not cache the
+        // row it saw.
+        let generation = cache.generation();
+        cache.invalidate(1);
+        cache.fill(1, generation, "stale");
+        assert_eq!(cache.get(1), None);
+
+        let generation = cache.generation();
+        cache.fill(1, generation, "fresh");
+        assert_eq!(cache.get(1), Some("fresh"));
+    }
+
+    #[tokio::test]
+    async fn test_user_delete_clears_task_cache() {
+        let pool = setup_test_pool().await;
+        let tasks = Arc::new(CachedTaskRepository::new(
+            Arc::new(SqliteTaskRepository::new(pool.clone())),
+            Duration::from_secs(60),
+        ));
+        let users = CachedUserRepository::new(
+            Arc::new(SqliteUserRepository::new(pool)),
+            Duration::from_secs(60),
+        )
+        .with_task_cache(tasks.clone());
+
+        let user = users.create("John Doe", "john@example.com").await.unwrap();
+        let task = tasks
+            .create_for_user(user.id, "Owned", "Desc")
+            .await
+            .unwrap();
+        assert_eq!(
+            tasks.get(task.id).await.unwrap().assigned_user_id,
+            Some(user.id)
+        );
+
+        users
+            .delete(user.id, DeleteUserPolicy::Nullify)
+            .await
+            .unwrap();
+
+        assert_eq!(tasks.get(task.id).await.unwrap().assigned_user_id, None);
+    }
+}
@@ -0,0 +1,90 @@
+use std::borrow::Cow;
+
+/// SQL differences between the backends. Queries are written once with
+/// SQLite's `?` / `?N` placeholders and passed through [`Dialect::sql`];
+/// everything else that differs is a fragment spliced in with `format!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Sqlite,
+    Postgres,
+}
+
+impl Dialect {
+    /// Current time in the stored ISO-8601 UTC format, with milliseconds.
+    pub const fn now(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+            Dialect::Postgres => {
+                r#"to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"')"#
+            }
+        }
+    }
+
+    /// Case-insensitive `LIKE`. SQLite's `LIKE` already ignores ASCII case.
+    pub const fn ilike(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => "LIKE",
+            Dialect::Postgres => "ILIKE",
+        }
+    }
+
+    /// `query` with placeholders in this dialect's syntax: Postgres numbers
+    /// them `$1`, `$2`, ... Bare `?` are numbered in order and `?N` keeps its
+    /// number; a `?` inside a quoted literal is left alone.
+    pub fn sql(self, query: &str) -> Cow<'_, str> {
+        if self == Dialect::Sqlite || !query.contains('?') {
+            return Cow::Borrowed(query);
+        }
+
+        let mut rewritten = String::with_capacity(query.len() + 8);
+        let mut next = 1;
+        let mut quote = None;
+        let mut chars = query.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match (ch, quote) {
+                ('\'' | '"', None) => quote = Some(ch),
+                (_, Some(open)) if ch == open => quote = None,
+                ('?', None) => {
+                    rewritten.push('$');
+                    if chars.peek().is_some_and(char::is_ascii_digit) {
+                        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                            rewritten.push(digit);
+                        }
+                    } else {
+                        rewritten.push_str(&next.to_string());
+                        next += 1;
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            rewritten.push(ch);
+        }
+
+        Cow::Owned(rewritten)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_postgres_placeholders() {
+        let pg = Dialect::Postgres;
+
+        assert_eq!(
+            pg.sql("UPDATE tasks SET title = ? WHERE id = ?"),
+            "UPDATE tasks SET title = $1 WHERE id = $2"
+        );
+        assert_eq!(
+            pg.sql("WHERE ?1 IS NULL OR name LIKE ?1 LIMIT ?2"),
+            "WHERE $1 IS NULL OR name LIKE $1 LIMIT $2"
+        );
+        assert_eq!(
+            pg.sql("SELECT '?' AS q, \"a?\" FROM t WHERE x = ?"),
+            "SELECT '?' AS q, \"a?\" FROM t WHERE x = $1"
+        );
+        assert_eq!(Dialect::Sqlite.sql("WHERE id = ?"), "WHERE id = ?");
+    }
+}
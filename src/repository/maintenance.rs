@@ -0,0 +1,407 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+use tokio::task::JoinHandle;
+
+use super::Entity;
+use crate::db::{TablePrefix, TaskModel, UserModel};
+
+/// Outcome of [`MaintenanceRepository::vacuum`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VacuumReport {
+    pub size_before_bytes: i64,
+    pub size_after_bytes: i64,
+    pub vacuum_ms: u128,
+    pub optimize_ms: u128,
+}
+
+/// Outcome of [`MaintenanceRepository::wal_checkpoint`], as reported by
+/// `PRAGMA wal_checkpoint`. The page counts are -1 outside WAL mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointReport {
+    /// A reader or writer kept the checkpoint from finishing
+    pub busy: bool,
+    pub wal_pages: i64,
+    pub checkpointed_pages: i64,
+}
+
+/// Connection counts of the pool, from
+/// [`MaintenanceRepository::pool_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Open connections, idle or not
+    pub size: u32,
+    pub idle: u32,
+    /// Open connections checked out by a query or transaction
+    pub in_use: u32,
+    /// Most connections the pool will open
+    pub max: u32,
+}
+
+/// A task as stored, including its manual `position`.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TaskBackup {
+    #[sqlx(flatten)]
+    pub task: TaskModel,
+    pub position: Option<f64>,
+}
+
+/// Every task and user, ordered by id. Produced by
+/// [`MaintenanceRepository::backup`] and accepted by
+/// [`MaintenanceRepository::restore`].
+#[derive(Debug, Clone, Default)]
+pub struct Backup {
+    pub tasks: Vec<TaskBackup>,
+    pub users: Vec<UserModel>,
+}
+
+/// A backup the schema rejected, e.g. a task assigned to a user it doesn't
+/// contain or two users sharing an email.
+#[derive(Debug)]
+pub struct InvalidBackup(pub String);
+
+impl std::fmt::Display for InvalidBackup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid backup: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidBackup {}
+
+/// Whole-database housekeeping, kept apart from the per-entity repositories.
+#[async_trait]
+pub trait MaintenanceRepository: Send + Sync {
+    /// Rebuilds the database file to reclaim free pages, then refreshes the
+    /// query planner statistics.
+    async fn vacuum(&self) -> Result<VacuumReport>;
+    /// Copies the write-ahead log into the database and truncates it.
+    async fn wal_checkpoint(&self) -> Result<CheckpointReport>;
+    /// Reads every task and user in one snapshot.
+    async fn backup(&self) -> Result<Backup>;
+    /// Replaces every task and user with `backup`, keeping their ids. Runs
+    /// in one transaction, so on error nothing changes; constraint failures
+    /// surface as [`InvalidBackup`]. The audit log keeps its rows and
+    /// records the swap as deletes and creates.
+    async fn restore(&self, backup: &Backup) -> Result<()>;
+    /// The pool's connection counts right now, for diagnosing exhaustion.
+    fn pool_stats(&self) -> PoolStats;
+}
+
+#[derive(Clone)]
+pub struct SqliteMaintenanceRepository {
+    pool: SqlitePool,
+    /// Serves `backup`; the main pool unless one is set.
+    read_pool: SqlitePool,
+    prefix: TablePrefix,
+}
+
+impl SqliteMaintenanceRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            read_pool: pool.clone(),
+            pool,
+            prefix: TablePrefix::default(),
+        }
+    }
+
+    /// Runs `backup` on `pool`, e.g. one from
+    /// [`crate::db::init_read_only_pool`].
+    pub fn with_read_only_pool(mut self, pool: SqlitePool) -> Self {
+        self.read_pool = pool;
+        self
+    }
+
+    /// Backs up and restores the `{prefix}` tables instead of the
+    /// unprefixed ones.
+    pub fn with_table_prefix(mut self, prefix: &TablePrefix) -> Self {
+        self.prefix = prefix.clone();
+        self
+    }
+}
+
+#[async_trait]
+impl MaintenanceRepository for SqliteMaintenanceRepository {
+    async fn vacuum(&self) -> Result<VacuumReport> {
+        // VACUUM can't run inside a transaction, so it gets a dedicated
+        // connection rather than going through `pool.begin()`. It also needs
+        // every other connection to be idle; writers on them wait on the busy
+        // timeout until it finishes, and VACUUM itself fails with "database
+        // is locked" if a transaction stays open longer than that.
+        let mut conn = self.pool.acquire().await?;
+
+        let size_before_bytes = database_size(&mut conn).await?;
+
+        let started = Instant::now();
+        sqlx::query("VACUUM").execute(&mut *conn).await?;
+        let vacuum_ms = started.elapsed().as_millis();
+
+        let started = Instant::now();
+        sqlx::query("PRAGMA optimize").execute(&mut *conn).await?;
+        let optimize_ms = started.elapsed().as_millis();
+
+        let size_after_bytes = database_size(&mut conn).await?;
+
+        Ok(VacuumReport {
+            size_before_bytes,
+            size_after_bytes,
+            vacuum_ms,
+            optimize_ms,
+        })
+    }
+
+    async fn wal_checkpoint(&self) -> Result<CheckpointReport> {
+        let (busy, wal_pages, checkpointed_pages): (i64, i64, i64) =
+            sqlx::query_as("PRAGMA wal_checkpoint(TRUNCATE)")
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(CheckpointReport {
+            busy: busy != 0,
+            wal_pages,
+            checkpointed_pages,
+        })
+    }
+
+    async fn backup(&self) -> Result<Backup> {
+        let tasks = self.prefix.table("tasks");
+        let users = self.prefix.table("users");
+        // One read transaction, so a write landing between the two queries
+        // can't leave a task pointing at a user the backup lacks
+        let mut tx = self.read_pool.begin().await?;
+
+        let tasks = sqlx::query_as::<_, TaskBackup>(&format!(
+            "SELECT {} FROM {} ORDER BY id",
+            TaskModel::columns(&tasks, &self.prefix),
+            tasks
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+        let users = sqlx::query_as::<_, UserModel>(&format!(
+            "SELECT id, name, email FROM {} ORDER BY id",
+            users
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(Backup { tasks, users })
+    }
+
+    async fn restore(&self, backup: &Backup) -> Result<()> {
+        let tasks = self.prefix.table("tasks");
+        let users = self.prefix.table("users");
+        let tags = self.prefix.table("task_tags");
+        let mut tx = self.pool.begin().await?;
+
+        for table in [&tags, &tasks, &users] {
+            sqlx::query(&format!("DELETE FROM {}", table))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let insert_user = format!("INSERT INTO {} (id, name, email) VALUES (?, ?, ?)", users);
+        for user in &backup.users {
+            sqlx::query(&insert_user)
+                .bind(user.id)
+                .bind(&user.name)
+                .bind(&user.email)
+                .execute(&mut *tx)
+                .await
+                .map_err(invalid_backup)?;
+        }
+
+        let insert_task = format!(
+            "INSERT INTO {} (id, title, description, completed, status, assigned_user_id, \
+             created_at, updated_at, completed_at, position) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            tasks
+        );
+        let insert_tag = format!("INSERT INTO {} (task_id, tag) VALUES (?, ?)", tags);
+        for TaskBackup { task, position } in &backup.tasks {
+            sqlx::query(&insert_task)
+                .bind(task.id)
+                .bind(&task.title)
+                .bind(&task.description)
+                .bind(task.completed)
+                .bind(task.status.as_str())
+                .bind(task.assigned_user_id)
+                .bind(&task.created_at)
+                .bind(&task.updated_at)
+                .bind(&task.completed_at)
+                .bind(position)
+                .execute(&mut *tx)
+                .await
+                .map_err(invalid_backup)?;
+            for tag in &task.tags {
+                sqlx::query(&insert_tag)
+                    .bind(task.id)
+                    .bind(tag)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(invalid_backup)?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    fn pool_stats(&self) -> PoolStats {
+        let size = self.pool.size();
+        let idle = u32::try_from(self.pool.num_idle()).unwrap_or(u32::MAX);
+        PoolStats {
+            size,
+            idle,
+            in_use: size.saturating_sub(idle),
+            max: self.pool.options().get_max_connections(),
+        }
+    }
+}
+
+/// Maps a constraint failure while restoring to [`InvalidBackup`].
+fn invalid_backup(error: sqlx::Error) -> anyhow::Error {
+    match &error {
+        sqlx::Error::Database(db)
+            if db.is_unique_violation()
+                || db.is_foreign_key_violation()
+                || db.is_check_violation() =>
+        {
+            InvalidBackup(db.message().to_string()).into()
+        }
+        _ => error.into(),
+    }
+}
+
+/// Periodic [`MaintenanceRepository::wal_checkpoint`], so a steady stream
+/// of writes can't grow the WAL without bound between SQLite's automatic
+/// (non-truncating) checkpoints. Only useful once the database file is in
+/// WAL mode; otherwise each checkpoint is a no-op.
+pub struct WalCheckpointTask {
+    maintenance: Arc<dyn MaintenanceRepository>,
+    interval: Duration,
+}
+
+impl WalCheckpointTask {
+    /// `None` for a zero interval, which disables the task.
+    pub fn new(maintenance: Arc<dyn MaintenanceRepository>, interval: Duration) -> Option<Self> {
+        (!interval.is_zero()).then_some(Self {
+            maintenance,
+            interval,
+        })
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Runs until aborted. Failures are logged and retried on the next tick.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(self.interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately; skip it so startup isn't
+            // slowed by a checkpoint
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                match self.maintenance.wal_checkpoint().await {
+                    Ok(report) if report.busy => {
+                        tracing::warn!(?report, "WAL checkpoint could not finish")
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!(error = %e, "WAL checkpoint failed"),
+                }
+            }
+        })
+    }
+}
+
+async fn database_size(conn: &mut sqlx::SqliteConnection) -> Result<i64> {
+    let (size,): (i64,) = sqlx::query_as(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
+    use sqlx::{ConnectOptions, Connection};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_vacuum_reclaims_deleted_pages() {
+        let pool = crate::db::init_db_with_url("sqlite::memory:")
+            .await
+            .unwrap();
+        let description = "x".repeat(4096);
+        for _ in 0..50 {
+            sqlx::query("INSERT INTO tasks (title, description) VALUES ('t', ?)")
+                .bind(&description)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        sqlx::query("DELETE FROM tasks")
+            .execute(&pool)
+            .await
+            .unwrap();
+        // The audit trail keeps deleted rows; drop it so the pages are free
+        sqlx::query("DELETE FROM task_audit")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let report = SqliteMaintenanceRepository::new(pool)
+            .vacuum()
+            .await
+            .unwrap();
+
+        assert!(report.size_after_bytes < report.size_before_bytes);
+    }
+
+    #[tokio::test]
+    async fn test_wal_checkpoint_task() {
+        let path = std::env::temp_dir().join(format!("wal-checkpoint-{}.db", std::process::id()));
+        let url = format!("sqlite://{}", path.display());
+        // The journal mode is stored in the file, so switching it before the
+        // pool opens covers every connection
+        let conn = SqliteConnectOptions::from_str(&url)
+            .unwrap()
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .connect()
+            .await
+            .unwrap();
+        conn.close().await.unwrap();
+        let pool = crate::db::init_db_with_url(&url).await.unwrap();
+        sqlx::query("INSERT INTO tasks (title, description) VALUES ('t', '')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let maintenance = Arc::new(SqliteMaintenanceRepository::new(pool.clone()));
+
+        assert!(WalCheckpointTask::new(maintenance.clone(), Duration::ZERO).is_none());
+        let task = WalCheckpointTask::new(maintenance.clone(), Duration::from_secs(60)).unwrap();
+        assert_eq!(task.interval(), Duration::from_secs(60));
+        task.spawn().abort();
+
+        let report = maintenance.wal_checkpoint().await.unwrap();
+        assert!(!report.busy);
+        assert_eq!(report.wal_pages, 0);
+
+        pool.close().await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
@@ -0,0 +1,566 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use futures_util::stream::{self, BoxStream, StreamExt};
+
+use super::{
+    ArchivedTask, DuplicateTasks, Page, TaskChanges, TaskFilter, TaskRepository, TaskStatusCounts,
+    TaskUpdate,
+};
+use crate::db::{TaskAuditModel, TaskModel, TaskStatus};
+
+type Handler<A, T> = Arc<dyn Fn(A) -> Result<T> + Send + Sync>;
+
+/// `(title, description)` pairs as passed to `create_many`.
+pub type NewTasks = Vec<(String, String)>;
+
+/// `(assigned_user_id, title, description, tags)` as passed to
+/// `create_with_tags`.
+pub type TaggedNewTask = (Option<i64>, String, String, Vec<String>);
+
+/// `(id, title, description, status, assigned_user_id)` as passed to
+/// `update`.
+pub type UpdateArgs = (
+    i64,
+    Option<String>,
+    Option<String>,
+    Option<TaskStatus>,
+    Option<Option<i64>>,
+);
+
+/// `(id, expected_updated_at, title, description, status)` as passed to
+/// `update_if_version`.
+pub type VersionedUpdateArgs = (
+    i64,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<TaskStatus>,
+);
+
+/// `TaskRepository` whose results are programmed per method, for exercising
+/// handler error paths without a database. Methods that were not programmed
+/// return an error naming the method.
+#[derive(Default)]
+pub struct MockTaskRepository {
+    create: Option<Handler<(String, String), TaskModel>>,
+    create_for_user: Option<Handler<(i64, String, String), TaskModel>>,
+    create_with_tags: Option<Handler<TaggedNewTask, TaskModel>>,
+    create_returning_meta: Option<Handler<(String, String), (TaskModel, i64)>>,
+    create_many: Option<Handler<NewTasks, Vec<TaskModel>>>,
+    get: Option<Handler<i64, TaskModel>>,
+    get_many: Option<Handler<Vec<i64>, Vec<TaskModel>>>,
+    list: Option<Handler<(Page, TaskFilter), Vec<TaskModel>>>,
+    stream_all: Option<Handler<(), Vec<TaskModel>>>,
+    list_updated_since: Option<Handler<String, Vec<TaskModel>>>,
+    list_completed_between: Option<Handler<(String, String), Vec<TaskModel>>>,
+    count: Option<Handler<TaskFilter, i64>>,
+    history: Option<Handler<i64, Vec<TaskAuditModel>>>,
+    update: Option<Handler<UpdateArgs, TaskModel>>,
+    update_if_version: Option<Handler<VersionedUpdateArgs, TaskModel>>,
+    update_with_tags: Option<Handler<(i64, TaskUpdate), TaskModel>>,
+    set_tags: Option<Handler<(i64, Vec<String>), TaskModel>>,
+    move_after: Option<Handler<(i64, Option<i64>), TaskModel>>,
+    delete: Option<Handler<i64, bool>>,
+    delete_returning: Option<Handler<i64, Option<TaskModel>>>,
+    complete_all: Option<Handler<(), u64>>,
+    complete_matching: Option<Handler<TaskFilter, u64>>,
+    reassign: Option<Handler<(i64, i64), u64>>,
+    delete_all: Option<Handler<(), u64>>,
+    archive_completed: Option<Handler<(), u64>>,
+    list_archived: Option<Handler<Page, Vec<ArchivedTask>>>,
+    duplicates: Option<Handler<(), Vec<DuplicateTasks>>>,
+    count_by_status: Option<Handler<(), TaskStatusCounts>>,
+    changes_since: Option<Handler<Option<i64>, TaskChanges>>,
+    latency: Option<Duration>,
+    calls: AtomicUsize,
+}
+
+impl MockTaskRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_create(
+        mut self,
+        f: impl Fn((String, String)) -> Result<TaskModel> + Send + Sync + 'static,
+    ) -> Self {
+        self.create = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_create_for_user(
+        mut self,
+        f: impl Fn((i64, String, String)) -> Result<TaskModel> + Send + Sync + 'static,
+    ) -> Self {
+        self.create_for_user = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_create_with_tags(
+        mut self,
+        f: impl Fn(TaggedNewTask) -> Result<TaskModel> + Send + Sync + 'static,
+    ) -> Self {
+        self.create_with_tags = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_create_returning_meta(
+        mut self,
+        f: impl Fn((String, String)) -> Result<(TaskModel, i64)> + Send + Sync + 'static,
+    ) -> Self {
+        self.create_returning_meta = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_create_many(
+        mut self,
+        f: impl Fn(NewTasks) -> Result<Vec<TaskModel>> + Send + Sync + 'static,
+    ) -> Self {
+        self.create_many = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_get(mut self, f: impl Fn(i64) -> Result<TaskModel> + Send + Sync + 'static) -> Self {
+        self.get = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_get_many(
+        mut self,
+        f: impl Fn(Vec<i64>) -> Result<Vec<TaskModel>> + Send + Sync + 'static,
+    ) -> Self {
+        self.get_many = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_list(
+        mut self,
+        f: impl Fn((Page, TaskFilter)) -> Result<Vec<TaskModel>> + Send + Sync + 'static,
+    ) -> Self {
+        self.list = Some(Arc::new(f));
+        self
+    }
+
+    /// The returned tasks are streamed one by one; an error ends the stream
+    /// after a single `Err` item.
+    pub fn on_stream_all(
+        mut self,
+        f: impl Fn(()) -> Result<Vec<TaskModel>> + Send + Sync + 'static,
+    ) -> Self {
+        self.stream_all = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_list_updated_since(
+        mut self,
+        f: impl Fn(String) -> Result<Vec<TaskModel>> + Send + Sync + 'static,
+    ) -> Self {
+        self.list_updated_since = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_list_completed_between(
+        mut self,
+        f: impl Fn((String, String)) -> Result<Vec<TaskModel>> + Send + Sync + 'static,
+    ) -> Self {
+        self.list_completed_between = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_count(
+        mut self,
+        f: impl Fn(TaskFilter) -> Result<i64> + Send + Sync + 'static,
+    ) -> Self {
+        self.count = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_history(
+        mut self,
+        f: impl Fn(i64) -> Result<Vec<TaskAuditModel>> + Send + Sync + 'static,
+    ) -> Self {
+        self.history = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_update(
+        mut self,
+        f: impl Fn(UpdateArgs) -> Result<TaskModel> + Send + Sync + 'static,
+    ) -> Self {
+        self.update = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_update_if_version(
+        mut self,
+        f: impl Fn(VersionedUpdateArgs) -> Result<TaskModel> + Send + Sync + 'static,
+    ) -> Self {
+        self.update_if_version = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_update_with_tags(
+        mut self,
+        f: impl Fn((i64, TaskUpdate)) -> Result<TaskModel> + Send + Sync + 'static,
+    ) -> Self {
+        self.update_with_tags = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_set_tags(
+        mut self,
+        f: impl Fn((i64, Vec<String>)) -> Result<TaskModel> + Send + Sync + 'static,
+    ) -> Self {
+        self.set_tags = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_move_after(
+        mut self,
+        f: impl Fn((i64, Option<i64>)) -> Result<TaskModel> + Send + Sync + 'static,
+    ) -> Self {
+        self.move_after = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_delete(mut self, f: impl Fn(i64) -> Result<bool> + Send + Sync + 'static) -> Self {
+        self.delete = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_delete_returning(
+        mut self,
+        f: impl Fn(i64) -> Result<Option<TaskModel>> + Send + Sync + 'static,
+    ) -> Self {
+        self.delete_returning = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_complete_all(
+        mut self,
+        f: impl Fn(()) -> Result<u64> + Send + Sync + 'static,
+    ) -> Self {
+        self.complete_all = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_complete_matching(
+        mut self,
+        f: impl Fn(TaskFilter) -> Result<u64> + Send + Sync + 'static,
+    ) -> Self {
+        self.complete_matching = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_reassign(
+        mut self,
+        f: impl Fn((i64, i64)) -> Result<u64> + Send + Sync + 'static,
+    ) -> Self {
+        self.reassign = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_delete_all(mut self, f: impl Fn(()) -> Result<u64> + Send + Sync + 'static) -> Self {
+        self.delete_all = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_archive_completed(
+        mut self,
+        f: impl Fn(()) -> Result<u64> + Send + Sync + 'static,
+    ) -> Self {
+        self.archive_completed = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_list_archived(
+        mut self,
+        f: impl Fn(Page) -> Result<Vec<ArchivedTask>> + Send + Sync + 'static,
+    ) -> Self {
+        self.list_archived = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_duplicates(
+        mut self,
+        f: impl Fn(()) -> Result<Vec<DuplicateTasks>> + Send + Sync + 'static,
+    ) -> Self {
+        self.duplicates = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_count_by_status(
+        mut self,
+        f: impl Fn(()) -> Result<TaskStatusCounts> + Send + Sync + 'static,
+    ) -> Self {
+        self.count_by_status = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_changes_since(
+        mut self,
+        f: impl Fn(Option<i64>) -> Result<TaskChanges> + Send + Sync + 'static,
+    ) -> Self {
+        self.changes_since = Some(Arc::new(f));
+        self
+    }
+
+    /// Sleeps for `latency` before answering each call, to stand in for a
+    /// slow database. `stream_all` is not delayed.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Number of repository calls made so far, programmed or not.
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    async fn call<A, T>(
+        &self,
+        method: &str,
+        handler: &Option<Handler<A, T>>,
+        args: A,
+    ) -> Result<T> {
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+        self.call_now(method, handler, args)
+    }
+
+    fn call_now<A, T>(&self, method: &str, handler: &Option<Handler<A, T>>, args: A) -> Result<T> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        match handler {
+            Some(f) => f(args),
+            None => bail!("MockTaskRepository::{} was not programmed", method),
+        }
+    }
+}
+
+#[async_trait]
+impl TaskRepository for MockTaskRepository {
+    async fn create(&self, title: &str, description: &str) -> Result<TaskModel> {
+        self.call(
+            "create",
+            &self.create,
+            (title.to_string(), description.to_string()),
+        )
+        .await
+    }
+
+    async fn create_for_user(
+        &self,
+        user_id: i64,
+        title: &str,
+        description: &str,
+    ) -> Result<TaskModel> {
+        self.call(
+            "create_for_user",
+            &self.create_for_user,
+            (user_id, title.to_string(), description.to_string()),
+        )
+        .await
+    }
+
+    async fn create_with_tags(
+        &self,
+        assigned_user_id: Option<i64>,
+        title: &str,
+        description: &str,
+        tags: &[String],
+    ) -> Result<TaskModel> {
+        self.call(
+            "create_with_tags",
+            &self.create_with_tags,
+            (
+                assigned_user_id,
+                title.to_string(),
+                description.to_string(),
+                tags.to_vec(),
+            ),
+        )
+        .await
+    }
+
+    async fn create_returning_meta(
+        &self,
+        title: &str,
+        description: &str,
+    ) -> Result<(TaskModel, i64)> {
+        self.call(
+            "create_returning_meta",
+            &self.create_returning_meta,
+            (title.to_string(), description.to_string()),
+        )
+        .await
+    }
+
+    async fn create_many(&self, tasks: &[(String, String)]) -> Result<Vec<TaskModel>> {
+        self.call("create_many", &self.create_many, tasks.to_vec())
+            .await
+    }
+
+    async fn get(&self, id: i64) -> Result<TaskModel> {
+        self.call("get", &self.get, id).await
+    }
+
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<TaskModel>> {
+        self.call("get_many", &self.get_many, ids.to_vec()).await
+    }
+
+    async fn list(&self, page: Page, filter: &TaskFilter) -> Result<Vec<TaskModel>> {
+        self.call("list", &self.list, (page, filter.clone())).await
+    }
+
+    fn stream_all(&self) -> BoxStream<'static, Result<TaskModel>> {
+        match self.call_now("stream_all", &self.stream_all, ()) {
+            Ok(tasks) => stream::iter(tasks.into_iter().map(Ok)).boxed(),
+            Err(e) => stream::once(async move { Err(e) }).boxed(),
+        }
+    }
+
+    async fn list_updated_since(&self, since: &str) -> Result<Vec<TaskModel>> {
+        self.call(
+            "list_updated_since",
+            &self.list_updated_since,
+            since.to_string(),
+        )
+        .await
+    }
+
+    async fn list_completed_between(&self, from: &str, to: &str) -> Result<Vec<TaskModel>> {
+        self.call(
+            "list_completed_between",
+            &self.list_completed_between,
+            (from.to_string(), to.to_string()),
+        )
+        .await
+    }
+
+    async fn count(&self, filter: &TaskFilter) -> Result<i64> {
+        self.call("count", &self.count, filter.clone()).await
+    }
+
+    async fn history(&self, id: i64) -> Result<Vec<TaskAuditModel>> {
+        self.call("history", &self.history, id).await
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+        assigned_user_id: Option<Option<i64>>,
+    ) -> Result<TaskModel> {
+        self.call(
+            "update",
+            &self.update,
+            (
+                id,
+                title.map(str::to_string),
+                description.map(str::to_string),
+                status,
+                assigned_user_id,
+            ),
+        )
+        .await
+    }
+
+    async fn update_if_version(
+        &self,
+        id: i64,
+        expected_updated_at: &str,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+    ) -> Result<TaskModel> {
+        self.call(
+            "update_if_version",
+            &self.update_if_version,
+            (
+                id,
+                expected_updated_at.to_string(),
+                title.map(str::to_string),
+                description.map(str::to_string),
+                status,
+            ),
+        )
+        .await
+    }
+
+    async fn update_with_tags(&self, id: i64, update: &TaskUpdate) -> Result<TaskModel> {
+        self.call(
+            "update_with_tags",
+            &self.update_with_tags,
+            (id, update.clone()),
+        )
+        .await
+    }
+
+    async fn set_tags(&self, id: i64, tags: &[String]) -> Result<TaskModel> {
+        self.call("set_tags", &self.set_tags, (id, tags.to_vec()))
+            .await
+    }
+
+    async fn move_after(&self, id: i64, after_id: Option<i64>) -> Result<TaskModel> {
+        self.call("move_after", &self.move_after, (id, after_id))
+            .await
+    }
+
+    async fn delete(&self, id: i64) -> Result<bool> {
+        self.call("delete", &self.delete, id).await
+    }
+
+    async fn delete_returning(&self, id: i64) -> Result<Option<TaskModel>> {
+        self.call("delete_returning", &self.delete_returning, id)
+            .await
+    }
+
+    async fn complete_all(&self) -> Result<u64> {
+        self.call("complete_all", &self.complete_all, ()).await
+    }
+
+    async fn complete_matching(&self, filter: &TaskFilter) -> Result<u64> {
+        self.call("complete_matching", &self.complete_matching, filter.clone())
+            .await
+    }
+
+    async fn reassign(&self, from: i64, to: i64) -> Result<u64> {
+        self.call("reassign", &self.reassign, (from, to)).await
+    }
+
+    async fn delete_all(&self) -> Result<u64> {
+        self.call("delete_all", &self.delete_all, ()).await
+    }
+
+    async fn archive_completed(&self) -> Result<u64> {
+        self.call("archive_completed", &self.archive_completed, ())
+            .await
+    }
+
+    async fn list_archived(&self, page: Page) -> Result<Vec<ArchivedTask>> {
+        self.call("list_archived", &self.list_archived, page).await
+    }
+
+    async fn duplicates(&self) -> Result<Vec<DuplicateTasks>> {
+        self.call("duplicates", &self.duplicates, ()).await
+    }
+
+    async fn count_by_status(&self) -> Result<TaskStatusCounts> {
+        self.call("count_by_status", &self.count_by_status, ())
+            .await
+    }
+
+    async fn changes_since(&self, cursor: Option<i64>) -> Result<TaskChanges> {
+        self.call("changes_since", &self.changes_since, cursor)
+            .await
+    }
+}
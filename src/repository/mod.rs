@@ -1,5 +1,130 @@
+mod cached;
+mod dialect;
+mod maintenance;
+#[cfg(any(test, feature = "test-util"))]
+mod mock;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+mod sqlite;
 mod task;
+mod timed;
+mod unit_of_work;
 mod user;
 
-pub use task::{SqliteTaskRepository, TaskRepository};
-pub use user::{SqliteUserRepository, UserRepository};
+pub use cached::{CacheClearingMaintenanceRepository, CachedTaskRepository, CachedUserRepository};
+pub use dialect::Dialect;
+pub use maintenance::{
+    Backup, CheckpointReport, InvalidBackup, MaintenanceRepository, PoolStats,
+    SqliteMaintenanceRepository, TaskBackup, VacuumReport, WalCheckpointTask,
+};
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::MockTaskRepository;
+pub use sqlite::{Entity, SqliteRepository};
+pub use task::{
+    ArchivedTask, DuplicateTasks, SqliteTaskRepository, TaskChanges, TaskFilter, TaskOrder,
+    TaskRepository, TaskSortKey, TaskStatusCounts, TaskUpdate, UnknownTask, UnknownUser,
+    VersionMismatch,
+};
+pub use timed::{TimedTaskRepository, TimedUserRepository};
+pub use unit_of_work::{SqliteUnitOfWork, UnitOfWork};
+pub use user::{
+    DeleteUserPolicy, EmailTaken, SqliteUserRepository, UserHasTasks, UserRepository, UserSummary,
+    UserTaskCounts,
+};
+
+/// Window into a list query. `None` means no limit / start from the first row.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Page {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl Page {
+    pub fn new(limit: Option<i64>, offset: Option<i64>) -> Self {
+        Self { limit, offset }
+    }
+
+    /// Page for a client-supplied `limit`, lowered to `max`. A missing limit
+    /// means `max` too; zero or negative is rejected.
+    pub fn capped(limit: Option<i64>, offset: Option<i64>, max: i64) -> Result<Self, String> {
+        match limit {
+            Some(limit) if limit <= 0 => {
+                Err(format!("limit must be a positive integer, got {}", limit))
+            }
+            limit => Ok(Self::new(Some(limit.unwrap_or(max).min(max)), offset)),
+        }
+    }
+
+    /// SQLite treats a negative LIMIT as "no limit".
+    pub(crate) fn sql_limit(&self) -> i64 {
+        self.limit.unwrap_or(-1)
+    }
+
+    pub(crate) fn sql_offset(&self) -> i64 {
+        self.offset.unwrap_or(0)
+    }
+}
+
+/// The distinct ids of a batch lookup, in ascending order. More than `max`
+/// distinct ids is rejected, the same cap a list page gets.
+pub fn batch_ids(ids: &[i64], max: i64) -> Result<Vec<i64>, String> {
+    let mut ids = ids.to_vec();
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.len() as i64 > max {
+        return Err(format!(
+            "at most {} ids may be requested at once, got {}",
+            max,
+            ids.len()
+        ));
+    }
+    Ok(ids)
+}
+
+/// Builds a `LIKE '%term%'` pattern that matches `term` literally. Use with
+/// `ESCAPE '\'`.
+pub(crate) fn like_contains(term: &str) -> String {
+    let mut pattern = String::with_capacity(term.len() + 2);
+    pattern.push('%');
+    for ch in term.chars() {
+        if matches!(ch, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(ch);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_capped() {
+        assert_eq!(
+            Page::capped(Some(10), Some(5), 100).unwrap(),
+            Page::new(Some(10), Some(5))
+        );
+        assert_eq!(
+            Page::capped(Some(1_000_000), None, 100).unwrap().limit,
+            Some(100)
+        );
+        assert_eq!(Page::capped(None, None, 100).unwrap().limit, Some(100));
+        assert!(Page::capped(Some(0), None, 100).is_err());
+        assert!(Page::capped(Some(-1), None, 100).is_err());
+    }
+
+    #[test]
+    fn test_batch_ids_dedupes_and_caps() {
+        assert_eq!(batch_ids(&[3, 1, 3, 2, 1], 3).unwrap(), vec![1, 2, 3]);
+        assert_eq!(batch_ids(&[], 3).unwrap(), Vec::<i64>::new());
+        assert!(batch_ids(&[1, 2, 3, 4], 3).is_err());
+    }
+
+    #[test]
+    fn test_like_contains_escapes_wildcards() {
+        assert_eq!(like_contains("doe"), "%doe%");
+        assert_eq!(like_contains("50%_off\\"), "%50\\%\\_off\\\\%");
+    }
+}
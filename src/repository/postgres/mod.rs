@@ -0,0 +1,41 @@
+//! PostgreSQL implementations of the repository traits, for deployments that
+//! outgrow a single SQLite file. Enabled by the `postgres` feature and picked
+//! when `DATABASE_URL` is a `postgres://` URL.
+//!
+//! The queries mirror the SQLite ones through [`Dialect`]; the remaining
+//! differences are Postgres' native `BOOLEAN`, `ANY($1)` for id lists and a
+//! `NULL` rather than negative `LIMIT` for "no limit". Table prefixes are
+//! not supported.
+
+mod task;
+mod user;
+
+pub use task::PgTaskRepository;
+pub use user::PgUserRepository;
+
+use anyhow::Result;
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+
+use super::Dialect;
+use crate::db;
+
+/// Schema migrations for Postgres, kept apart from the SQLite ones because
+/// the DDL differs.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations_postgres");
+
+const NOW: &str = Dialect::Postgres.now();
+
+/// Opens a pool for `url` and creates or upgrades the schema.
+pub async fn init_db(url: &str) -> Result<PgPool> {
+    let pool = PgPoolOptions::new()
+        .max_connections(db::max_connections(url))
+        .acquire_timeout(db::db_timeout())
+        .test_before_acquire(true)
+        .connect(url)
+        .await?;
+
+    MIGRATOR.run(&pool).await?;
+
+    Ok(pool)
+}
@@ -0,0 +1,852 @@
+use std::sync::LazyLock;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+
+use super::NOW;
+use crate::db::{TaskAuditModel, TaskModel, TaskStatus};
+use crate::repository::task::{
+    group_duplicates, position_between, KeyedTask, POSITION_KEY, STREAM_BUFFER, TITLE_KEY,
+};
+use crate::repository::{
+    ArchivedTask, Dialect, DuplicateTasks, Page, TaskChanges, TaskFilter, TaskOrder,
+    TaskRepository, TaskStatusCounts, TaskUpdate, UnknownTask, UnknownUser, VersionMismatch,
+};
+
+/// Select list for a task row, with its sorted tags as a JSON array.
+const COLUMNS: &str = "*, (SELECT COALESCE(json_agg(tag ORDER BY tag), '[]'::json) FROM task_tags WHERE task_id = tasks.id) AS tags";
+
+/// `query` with Postgres placeholders.
+fn sql(query: &str) -> String {
+    Dialect::Postgres.sql(query).into_owned()
+}
+
+// The fixed queries, converted to Postgres placeholders on first use rather
+// than on every call. Caller values only ever go in as bound parameters.
+
+static INSERT: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        r#"
+        INSERT INTO tasks (title, description, completed, created_at, updated_at)
+        VALUES (?, ?, FALSE, {NOW}, {NOW})
+        RETURNING {COLUMNS}
+        "#
+    ))
+});
+
+// Selecting from users makes the insert a no-op for a missing user
+static INSERT_FOR_USER: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        r#"
+        INSERT INTO tasks (title, description, completed, created_at, updated_at, assigned_user_id)
+        SELECT ?, ?, FALSE, {NOW}, {NOW}, id
+        FROM users WHERE id = ?
+        RETURNING {COLUMNS}
+        "#
+    ))
+});
+
+static SELECT: LazyLock<String> = LazyLock::new(|| format!("SELECT {COLUMNS} FROM tasks"));
+
+static SELECT_BY_ID: LazyLock<String> =
+    LazyLock::new(|| sql(&format!("SELECT {COLUMNS} FROM tasks WHERE id = ?")));
+
+static SELECT_BY_IDS: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        "SELECT {COLUMNS} FROM tasks WHERE id = ANY(?) ORDER BY id"
+    ))
+});
+
+static SELECT_ALL: LazyLock<String> =
+    LazyLock::new(|| format!("SELECT {COLUMNS} FROM tasks ORDER BY id"));
+
+static UPDATED_SINCE: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        "SELECT {COLUMNS} FROM tasks WHERE updated_at >= ? ORDER BY updated_at DESC, id DESC"
+    ))
+});
+
+static COMPLETED_BETWEEN: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        "SELECT {COLUMNS} FROM tasks WHERE completed_at BETWEEN ? AND ? ORDER BY completed_at, id"
+    ))
+});
+
+static UPDATE: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        r#"
+        UPDATE tasks
+        SET title = ?, description = ?, completed = ?, status = ?,
+            completed_at = CASE WHEN ? THEN COALESCE(completed_at, {NOW}) END,
+            assigned_user_id = ?,
+            updated_at = {NOW}
+        WHERE id = ? AND (?::TEXT IS NULL OR updated_at = ?)
+        RETURNING {COLUMNS}
+        "#
+    ))
+});
+
+static CLEAR_TAGS: LazyLock<String> =
+    LazyLock::new(|| sql("DELETE FROM task_tags WHERE task_id = ?"));
+
+static INSERT_TAG: LazyLock<String> =
+    LazyLock::new(|| sql("INSERT INTO task_tags (task_id, tag) VALUES (?, ?)"));
+
+static TOUCH: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        "UPDATE tasks SET updated_at = {NOW} WHERE id = ? RETURNING {COLUMNS}"
+    ))
+});
+
+static FIRST_POSITION: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        "SELECT MIN({POSITION_KEY})::float8 FROM tasks WHERE id <> ?"
+    ))
+});
+
+static POSITION: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        "SELECT {POSITION_KEY}::float8 FROM tasks WHERE id = ?"
+    ))
+});
+
+static NEXT_POSITION: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        r#"
+        SELECT {POSITION_KEY}::float8 FROM tasks
+        WHERE id <> ? AND ({POSITION_KEY} > ? OR ({POSITION_KEY} = ? AND id < ?))
+        ORDER BY {POSITION_KEY} ASC, id DESC
+        LIMIT 1
+        "#
+    ))
+});
+
+static ORDERED_IDS: LazyLock<String> =
+    LazyLock::new(|| format!("SELECT id FROM tasks ORDER BY {POSITION_KEY} ASC, id DESC"));
+
+static SET_POSITION: LazyLock<String> =
+    LazyLock::new(|| sql("UPDATE tasks SET position = ? WHERE id = ?"));
+
+static MOVE: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        "UPDATE tasks SET position = ?, updated_at = {NOW} WHERE id = ? RETURNING {COLUMNS}"
+    ))
+});
+
+static HISTORY: LazyLock<String> =
+    LazyLock::new(|| sql("SELECT * FROM task_audit WHERE task_id = ? ORDER BY id"));
+
+static DUPLICATES: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+        SELECT {COLUMNS}, {TITLE_KEY} AS title_key FROM tasks
+        WHERE {TITLE_KEY} IN (
+            SELECT {TITLE_KEY} FROM tasks GROUP BY {TITLE_KEY} HAVING COUNT(*) > 1
+        )
+        ORDER BY title_key, id
+        "#
+    )
+});
+
+static SYNC_ENTRY_EXISTS: LazyLock<String> =
+    LazyLock::new(|| sql("SELECT EXISTS (SELECT 1 FROM task_audit WHERE id = ?)"));
+
+static SYNC_CHANGED: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        r#"
+        SELECT {COLUMNS} FROM tasks
+        WHERE id IN (SELECT task_id FROM task_audit WHERE id > ? AND id <= ?)
+        ORDER BY updated_at, id
+        "#
+    ))
+});
+
+static SYNC_DELETED: LazyLock<String> = LazyLock::new(|| {
+    sql(r#"
+        SELECT DISTINCT task_id FROM task_audit
+        WHERE id > ? AND id <= ? AND task_id NOT IN (SELECT id FROM tasks)
+        ORDER BY task_id
+        "#)
+});
+
+static DELETE: LazyLock<String> = LazyLock::new(|| sql("DELETE FROM tasks WHERE id = ?"));
+
+static SELECT_FOR_DELETE: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        "SELECT {COLUMNS} FROM tasks WHERE id = ? FOR UPDATE"
+    ))
+});
+
+static FIND_USER: LazyLock<String> = LazyLock::new(|| sql("SELECT id FROM users WHERE id = ?"));
+
+static REASSIGN: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        "UPDATE tasks SET assigned_user_id = ?, updated_at = {NOW} WHERE assigned_user_id = ?"
+    ))
+});
+
+static COMPLETE_ALL: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+        UPDATE tasks
+        SET completed = TRUE, status = 'done', completed_at = {NOW}, updated_at = {NOW}
+        WHERE status IN ('todo', 'in_progress')
+        "#
+    )
+});
+
+static COMPLETE: LazyLock<String> = LazyLock::new(|| {
+    format!("UPDATE tasks SET completed = TRUE, status = 'done', completed_at = {NOW}, updated_at = {NOW}")
+});
+
+static ARCHIVE_COMPLETED: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+        INSERT INTO tasks_archive (id, title, description, completed, status, assigned_user_id,
+            created_at, updated_at, completed_at, position, tags, archived_at)
+        SELECT id, title, description, completed, status, assigned_user_id,
+            created_at, updated_at, completed_at, position, tags::jsonb, {NOW}
+        FROM (SELECT {COLUMNS} FROM tasks WHERE completed) AS completed_tasks
+        "#
+    )
+});
+
+static LIST_ARCHIVED: LazyLock<String> = LazyLock::new(|| {
+    sql("SELECT * FROM tasks_archive ORDER BY archived_at DESC, id DESC LIMIT ? OFFSET ?")
+});
+
+/// `{head} WHERE <filter>`, for the caller to finish.
+fn filtered<'a>(head: &str, filter: &TaskFilter) -> QueryBuilder<'a, Postgres> {
+    let mut query = QueryBuilder::new(head);
+    query.push(" WHERE ");
+    filter.push_conditions(&mut query, "tasks", "task_tags");
+    query
+}
+
+/// Keys of the tasks `id` would sit between if placed right after
+/// `after_id`, or first with `None`.
+async fn neighbours(
+    conn: &mut PgConnection,
+    id: i64,
+    after_id: Option<i64>,
+) -> Result<(Option<f64>, Option<f64>)> {
+    let Some(after_id) = after_id else {
+        let (first,): (Option<f64>,) = sqlx::query_as(&FIRST_POSITION)
+            .bind(id)
+            .fetch_one(&mut *conn)
+            .await?;
+        return Ok((None, first));
+    };
+
+    let (before,): (f64,) = sqlx::query_as(&POSITION)
+        .bind(after_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(UnknownTask { task_id: after_id })?;
+    let after: Option<(f64,)> = sqlx::query_as(&NEXT_POSITION)
+        .bind(id)
+        .bind(before)
+        .bind(before)
+        .bind(after_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    Ok((Some(before), after.map(|(after,)| after)))
+}
+
+/// Rewrites every position as 0, 1, 2, ... in the current order, making room
+/// between neighbours that repeated moves have pushed together.
+async fn renumber_positions(conn: &mut PgConnection) -> Result<()> {
+    let ids: Vec<(i64,)> = sqlx::query_as(&ORDERED_IDS).fetch_all(&mut *conn).await?;
+    for (position, (id,)) in ids.into_iter().enumerate() {
+        sqlx::query(&SET_POSITION)
+            .bind(position as f64)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[derive(Clone)]
+pub struct PgTaskRepository {
+    pool: PgPool,
+    order: TaskOrder,
+}
+
+impl PgTaskRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            order: TaskOrder::default(),
+        }
+    }
+
+    /// Overrides the order used by `list`.
+    pub fn with_order(mut self, order: TaskOrder) -> Self {
+        self.order = order;
+        self
+    }
+    /// Writes `update`'s changes over `existing` on `conn`, also requiring
+    /// its `updated_at` to still be `expected_updated_at` when given.
+    #[allow(clippy::too_many_arguments)]
+    async fn write_update(
+        &self,
+        conn: &mut PgConnection,
+        existing: TaskModel,
+        expected_updated_at: Option<&str>,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+        assigned_user_id: Option<Option<i64>>,
+    ) -> Result<TaskModel> {
+        let id = existing.id;
+        let mismatch = |expected: &str| VersionMismatch {
+            task_id: id,
+            expected: expected.to_string(),
+        };
+        if let Some(expected) = expected_updated_at.filter(|&v| v != existing.updated_at) {
+            return Err(mismatch(expected).into());
+        }
+
+        let new_title = title.unwrap_or(&existing.title);
+        let new_description = description.unwrap_or(&existing.description);
+        let new_status = status.unwrap_or(existing.status);
+        let new_assigned_user_id = assigned_user_id.unwrap_or(existing.assigned_user_id);
+
+        // Nothing changes: skip the write so `updated_at` keeps its value
+        if new_title == existing.title
+            && new_description == existing.description
+            && new_status == existing.status
+            && new_assigned_user_id == existing.assigned_user_id
+        {
+            return Ok(existing);
+        }
+
+        let task = sqlx::query_as::<_, TaskModel>(&UPDATE)
+            .bind(new_title)
+            .bind(new_description)
+            .bind(new_status.is_done())
+            .bind(new_status.as_str())
+            .bind(new_status.is_done())
+            .bind(new_assigned_user_id)
+            .bind(id)
+            .bind(expected_updated_at)
+            .bind(expected_updated_at)
+            .fetch_one(conn)
+            .await
+            .map_err(|e| match (&e, expected_updated_at, new_assigned_user_id) {
+                (sqlx::Error::Database(db), _, Some(user_id)) if db.is_foreign_key_violation() => {
+                    UnknownUser { user_id }.into()
+                }
+                // Changed between the read and the write
+                (sqlx::Error::RowNotFound, Some(expected), _) => mismatch(expected).into(),
+                _ => anyhow::Error::from(e),
+            })?;
+
+        Ok(task)
+    }
+
+    /// Replaces the tags of `existing` on `conn`, leaving it untouched when
+    /// they already match.
+    async fn write_tags(
+        &self,
+        conn: &mut PgConnection,
+        existing: TaskModel,
+        tags: &[String],
+    ) -> Result<TaskModel> {
+        let mut wanted: Vec<&str> = tags.iter().map(String::as_str).collect();
+        wanted.sort_unstable();
+        wanted.dedup();
+        if existing.tags == wanted {
+            return Ok(existing);
+        }
+
+        let id = existing.id;
+        sqlx::query(&CLEAR_TAGS)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        for tag in &wanted {
+            sqlx::query(&INSERT_TAG)
+                .bind(id)
+                .bind(tag)
+                .execute(&mut *conn)
+                .await?;
+        }
+        let task = sqlx::query_as::<_, TaskModel>(&TOUCH)
+            .bind(id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok(task)
+    }
+}
+
+#[async_trait]
+impl TaskRepository for PgTaskRepository {
+    async fn create(&self, title: &str, description: &str) -> Result<TaskModel> {
+        let task = sqlx::query_as::<_, TaskModel>(&INSERT)
+            .bind(title)
+            .bind(description)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(task)
+    }
+
+    async fn create_for_user(
+        &self,
+        user_id: i64,
+        title: &str,
+        description: &str,
+    ) -> Result<TaskModel> {
+        let task = sqlx::query_as::<_, TaskModel>(&INSERT_FOR_USER)
+            .bind(title)
+            .bind(description)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        task.ok_or_else(|| UnknownUser { user_id }.into())
+    }
+
+    async fn create_with_tags(
+        &self,
+        assigned_user_id: Option<i64>,
+        title: &str,
+        description: &str,
+        tags: &[String],
+    ) -> Result<TaskModel> {
+        let mut tx = self.pool.begin().await?;
+        let task = match assigned_user_id {
+            Some(user_id) => sqlx::query_as::<_, TaskModel>(&INSERT_FOR_USER)
+                .bind(title)
+                .bind(description)
+                .bind(user_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or(UnknownUser { user_id })?,
+            None => {
+                sqlx::query_as::<_, TaskModel>(&INSERT)
+                    .bind(title)
+                    .bind(description)
+                    .fetch_one(&mut *tx)
+                    .await?
+            }
+        };
+        let task = self.write_tags(&mut tx, task, tags).await?;
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    /// Postgres has no rowid; the id stands in for it.
+    async fn create_returning_meta(
+        &self,
+        title: &str,
+        description: &str,
+    ) -> Result<(TaskModel, i64)> {
+        let task = self.create(title, description).await?;
+        let id = task.id;
+
+        Ok((task, id))
+    }
+
+    async fn create_many(&self, tasks: &[(String, String)]) -> Result<Vec<TaskModel>> {
+        let mut tx = self.pool.begin().await?;
+        let mut created = Vec::with_capacity(tasks.len());
+        for (title, description) in tasks {
+            let task = sqlx::query_as::<_, TaskModel>(&INSERT)
+                .bind(title)
+                .bind(description)
+                .fetch_one(&mut *tx)
+                .await?;
+            created.push(task);
+        }
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    async fn get(&self, id: i64) -> Result<TaskModel> {
+        let task = sqlx::query_as::<_, TaskModel>(&SELECT_BY_ID)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(task)
+    }
+
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<TaskModel>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tasks = sqlx::query_as::<_, TaskModel>(&SELECT_BY_IDS)
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    async fn list(&self, page: Page, filter: &TaskFilter) -> Result<Vec<TaskModel>> {
+        let mut query = filtered(&SELECT, filter);
+        // LIMIT NULL is no limit
+        query
+            .push(format!(
+                " ORDER BY {} LIMIT ",
+                filter.order.unwrap_or(self.order).sql()
+            ))
+            .push_bind(page.limit)
+            .push(" OFFSET ")
+            .push_bind(page.sql_offset());
+        let tasks = query
+            .build_query_as::<TaskModel>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    fn stream_all(&self) -> BoxStream<'static, Result<TaskModel>> {
+        let pool = self.pool.clone();
+
+        // Same hand-off as the SQLite repository: the row stream borrows the
+        // pool and query, so it runs in its own task
+        let (sender, receiver) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(async move {
+            let mut rows = sqlx::query_as::<_, TaskModel>(&SELECT_ALL).fetch(&pool);
+            while let Some(row) = rows.next().await {
+                if sender.send(row.map_err(Into::into)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|row| (row, receiver))
+        })
+        .boxed()
+    }
+
+    async fn list_updated_since(&self, since: &str) -> Result<Vec<TaskModel>> {
+        let tasks = sqlx::query_as::<_, TaskModel>(&UPDATED_SINCE)
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    async fn list_completed_between(&self, from: &str, to: &str) -> Result<Vec<TaskModel>> {
+        let tasks = sqlx::query_as::<_, TaskModel>(&COMPLETED_BETWEEN)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    async fn count(&self, filter: &TaskFilter) -> Result<i64> {
+        let (count,) = filtered("SELECT COUNT(*) FROM tasks", filter)
+            .build_query_as::<(i64,)>()
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    async fn history(&self, id: i64) -> Result<Vec<TaskAuditModel>> {
+        let entries = sqlx::query_as::<_, TaskAuditModel>(&HISTORY)
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(entries)
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+        assigned_user_id: Option<Option<i64>>,
+    ) -> Result<TaskModel> {
+        let existing = self.get(id).await?;
+        let mut conn = self.pool.acquire().await?;
+        self.write_update(
+            &mut conn,
+            existing,
+            None,
+            title,
+            description,
+            status,
+            assigned_user_id,
+        )
+        .await
+    }
+
+    async fn update_if_version(
+        &self,
+        id: i64,
+        expected_updated_at: &str,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+    ) -> Result<TaskModel> {
+        let existing = self.get(id).await?;
+        let mut conn = self.pool.acquire().await?;
+        self.write_update(
+            &mut conn,
+            existing,
+            Some(expected_updated_at),
+            title,
+            description,
+            status,
+            None,
+        )
+        .await
+    }
+
+    async fn update_with_tags(&self, id: i64, update: &TaskUpdate) -> Result<TaskModel> {
+        let mut tx = self.pool.begin().await?;
+        let existing = sqlx::query_as::<_, TaskModel>(&SELECT_BY_ID)
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let mut task = self
+            .write_update(
+                &mut tx,
+                existing,
+                update.expected_updated_at.as_deref(),
+                update.title.as_deref(),
+                update.description.as_deref(),
+                update.status,
+                update.assigned_user_id,
+            )
+            .await?;
+        if let Some(tags) = &update.tags {
+            task = self.write_tags(&mut tx, task, tags).await?;
+        }
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    async fn set_tags(&self, id: i64, tags: &[String]) -> Result<TaskModel> {
+        let mut tx = self.pool.begin().await?;
+        let existing = sqlx::query_as::<_, TaskModel>(&SELECT_BY_ID)
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let task = self.write_tags(&mut tx, existing, tags).await?;
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    async fn move_after(&self, id: i64, after_id: Option<i64>) -> Result<TaskModel> {
+        if after_id == Some(id) {
+            return self.get(id).await;
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let (before, after) = neighbours(&mut tx, id, after_id).await?;
+        let position = match position_between(before, after) {
+            Some(position) => position,
+            None => {
+                renumber_positions(&mut tx).await?;
+                let (before, after) = neighbours(&mut tx, id, after_id).await?;
+                position_between(before, after)
+                    .ok_or_else(|| anyhow!("No room to move task {}", id))?
+            }
+        };
+        let task = sqlx::query_as::<_, TaskModel>(&MOVE)
+            .bind(position)
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    async fn delete(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query(&DELETE).bind(id).execute(&self.pool).await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_returning(&self, id: i64) -> Result<Option<TaskModel>> {
+        let mut tx = self.pool.begin().await?;
+        let Some(task) = sqlx::query_as::<_, TaskModel>(&SELECT_FOR_DELETE)
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            return Ok(None);
+        };
+        sqlx::query(&DELETE).bind(id).execute(&mut *tx).await?;
+        tx.commit().await?;
+
+        Ok(Some(task))
+    }
+
+    async fn delete_all(&self) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query("DELETE FROM tasks").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM task_audit")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("SELECT setval(pg_get_serial_sequence('tasks', 'id'), 1, false)")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn archive_completed(&self) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(&ARCHIVE_COMPLETED).execute(&mut *tx).await?;
+        let result = sqlx::query("DELETE FROM tasks WHERE completed")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn list_archived(&self, page: Page) -> Result<Vec<ArchivedTask>> {
+        // LIMIT NULL is no limit
+        let tasks = sqlx::query_as::<_, ArchivedTask>(&LIST_ARCHIVED)
+            .bind(page.limit)
+            .bind(page.sql_offset())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    async fn duplicates(&self) -> Result<Vec<DuplicateTasks>> {
+        let rows = sqlx::query_as::<_, KeyedTask>(&DUPLICATES)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(group_duplicates(rows))
+    }
+
+    async fn count_by_status(&self) -> Result<TaskStatusCounts> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT status, COUNT(*) FROM tasks GROUP BY status",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        TaskStatusCounts::from_rows(rows)
+    }
+
+    async fn changes_since(&self, cursor: Option<i64>) -> Result<TaskChanges> {
+        // Repeatable read, so every query sees the snapshot the cursor
+        // comes from. An entry whose transaction commits after a later one
+        // can still land behind a cursor already handed out.
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")
+            .execute(&mut *tx)
+            .await?;
+        let (latest,): (i64,) = sqlx::query_as("SELECT COALESCE(MAX(id), 0) FROM task_audit")
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let since = match cursor {
+            Some(cursor) => {
+                let (exists,): (bool,) = sqlx::query_as(&SYNC_ENTRY_EXISTS)
+                    .bind(cursor)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                exists.then_some(cursor)
+            }
+            None => None,
+        };
+        let Some(since) = since else {
+            let tasks = sqlx::query_as::<_, TaskModel>(&SELECT_ALL)
+                .fetch_all(&mut *tx)
+                .await?;
+            return Ok(TaskChanges {
+                tasks,
+                deleted: Vec::new(),
+                cursor: latest,
+                reset: true,
+            });
+        };
+
+        let tasks = sqlx::query_as::<_, TaskModel>(&SYNC_CHANGED)
+            .bind(since)
+            .bind(latest)
+            .fetch_all(&mut *tx)
+            .await?;
+        let deleted: Vec<(i64,)> = sqlx::query_as(&SYNC_DELETED)
+            .bind(since)
+            .bind(latest)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        Ok(TaskChanges {
+            tasks,
+            deleted: deleted.into_iter().map(|(id,)| id).collect(),
+            cursor: latest,
+            reset: false,
+        })
+    }
+
+    async fn reassign(&self, from: i64, to: i64) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        for user_id in [from, to] {
+            let found: Option<(i64,)> = sqlx::query_as(&FIND_USER)
+                .bind(user_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            if found.is_none() {
+                return Err(UnknownUser { user_id }.into());
+            }
+        }
+        if from == to {
+            return Ok(0);
+        }
+
+        let result = sqlx::query(&REASSIGN)
+            .bind(to)
+            .bind(from)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn complete_all(&self) -> Result<u64> {
+        let result = sqlx::query(&COMPLETE_ALL).execute(&self.pool).await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn complete_matching(&self, filter: &TaskFilter) -> Result<u64> {
+        let mut query = filtered(&COMPLETE, filter);
+        query.push(" AND status IN ('todo', 'in_progress')");
+        let result = query.build().execute(&self.pool).await?;
+
+        Ok(result.rows_affected())
+    }
+}
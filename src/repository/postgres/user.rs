@@ -0,0 +1,268 @@
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use crate::db::UserModel;
+use crate::repository::user::map_email_conflict;
+use crate::repository::{
+    like_contains, DeleteUserPolicy, Dialect, EmailTaken, Page, UserHasTasks, UserRepository,
+    UserSummary, UserTaskCounts,
+};
+
+const ILIKE: &str = Dialect::Postgres.ilike();
+
+/// `query` with Postgres placeholders.
+fn sql(query: &str) -> String {
+    Dialect::Postgres.sql(query).into_owned()
+}
+
+/// A row returned by [`UPSERT_BY_EMAIL`].
+#[derive(sqlx::FromRow)]
+struct Upserted {
+    #[sqlx(flatten)]
+    user: UserModel,
+    inserted: bool,
+}
+
+// The fixed queries, converted to Postgres placeholders on first use rather
+// than on every call. Caller values only ever go in as bound parameters.
+
+static EMAIL_EXISTS: LazyLock<String> =
+    LazyLock::new(|| sql("SELECT EXISTS (SELECT 1 FROM users WHERE lower(email) = lower(?))"));
+
+static INSERT: LazyLock<String> =
+    LazyLock::new(|| sql("INSERT INTO users (name, email) VALUES (?, ?) RETURNING *"));
+
+// `xmax` is only zero on a row this statement inserted
+static UPSERT_BY_EMAIL: LazyLock<String> = LazyLock::new(|| {
+    sql(r#"
+        INSERT INTO users (name, email) VALUES (?, ?)
+        ON CONFLICT (lower(email)) DO UPDATE SET name = excluded.name
+        RETURNING *, (xmax = 0) AS inserted
+        "#)
+});
+
+static SELECT_BY_ID: LazyLock<String> = LazyLock::new(|| sql("SELECT * FROM users WHERE id = ?"));
+
+static SELECT_BY_IDS: LazyLock<String> =
+    LazyLock::new(|| sql("SELECT * FROM users WHERE id = ANY(?) ORDER BY id"));
+
+static LIST: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        r#"
+        SELECT * FROM users
+        WHERE ?1::text IS NULL OR name {ILIKE} ?1 ESCAPE '\'
+        ORDER BY id DESC
+        LIMIT ?2 OFFSET ?3
+        "#
+    ))
+});
+
+static COUNT: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        r#"SELECT COUNT(*) FROM users WHERE ?1::text IS NULL OR name {ILIKE} ?1 ESCAPE '\'"#
+    ))
+});
+
+static SEARCH: LazyLock<String> = LazyLock::new(|| {
+    sql(&format!(
+        r#"SELECT * FROM users WHERE name {ILIKE} ? ESCAPE '\' ORDER BY name, id"#
+    ))
+});
+
+static UPDATE: LazyLock<String> =
+    LazyLock::new(|| sql("UPDATE users SET name = ?, email = ? WHERE id = ? RETURNING *"));
+
+static COUNT_TASKS: LazyLock<String> =
+    LazyLock::new(|| sql("SELECT COUNT(*) FROM tasks WHERE assigned_user_id = ?"));
+
+static UNASSIGN_TASKS: LazyLock<String> =
+    LazyLock::new(|| sql("UPDATE tasks SET assigned_user_id = NULL WHERE assigned_user_id = ?"));
+
+static DELETE_TASKS: LazyLock<String> =
+    LazyLock::new(|| sql("DELETE FROM tasks WHERE assigned_user_id = ?"));
+
+static SUMMARY: LazyLock<String> = LazyLock::new(|| {
+    sql(r#"
+        SELECT u.id, u.name, u.email,
+               COUNT(t.id) AS total,
+               COUNT(t.id) FILTER (WHERE t.completed) AS completed
+        FROM users u
+        LEFT JOIN tasks t ON t.assigned_user_id = u.id
+        WHERE u.id = ?
+        GROUP BY u.id
+        "#)
+});
+
+static DELETE: LazyLock<String> = LazyLock::new(|| sql("DELETE FROM users WHERE id = ?"));
+
+#[derive(Clone)]
+pub struct PgUserRepository {
+    pool: PgPool,
+}
+
+impl PgUserRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for PgUserRepository {
+    async fn create(&self, name: &str, email: &str) -> Result<UserModel> {
+        let (exists,): (bool,) = sqlx::query_as(&EMAIL_EXISTS)
+            .bind(email)
+            .fetch_one(&self.pool)
+            .await?;
+        if exists {
+            return Err(EmailTaken.into());
+        }
+
+        // A concurrent create can still win between the check and the
+        // insert; the unique index then reports the same `EmailTaken`
+        sqlx::query_as::<_, UserModel>(&INSERT)
+            .bind(name)
+            .bind(email)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(map_email_conflict)
+    }
+
+    async fn upsert_by_email(&self, name: &str, email: &str) -> Result<(UserModel, bool)> {
+        let upserted = sqlx::query_as::<_, Upserted>(&UPSERT_BY_EMAIL)
+            .bind(name)
+            .bind(email)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok((upserted.user, upserted.inserted))
+    }
+
+    async fn get(&self, id: i64) -> Result<UserModel> {
+        let user = sqlx::query_as::<_, UserModel>(&SELECT_BY_ID)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(user)
+    }
+
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<UserModel>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let users = sqlx::query_as::<_, UserModel>(&SELECT_BY_IDS)
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(users)
+    }
+
+    async fn list(&self, page: Page, name: Option<&str>) -> Result<Vec<UserModel>> {
+        let users = sqlx::query_as::<_, UserModel>(&LIST)
+            .bind(name.map(like_contains))
+            .bind(page.limit)
+            .bind(page.sql_offset())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(users)
+    }
+
+    async fn count(&self, name: Option<&str>) -> Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(&COUNT)
+            .bind(name.map(like_contains))
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<UserModel>> {
+        let users = sqlx::query_as::<_, UserModel>(&SEARCH)
+            .bind(like_contains(query))
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(users)
+    }
+
+    async fn update(&self, id: i64, name: Option<&str>, email: Option<&str>) -> Result<UserModel> {
+        let existing = self.get(id).await?;
+
+        let new_name = name.unwrap_or(&existing.name);
+        let new_email = email.unwrap_or(&existing.email);
+
+        let user = sqlx::query_as::<_, UserModel>(&UPDATE)
+            .bind(new_name)
+            .bind(new_email)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(map_email_conflict)?;
+
+        Ok(user)
+    }
+
+    async fn delete(&self, id: i64, policy: DeleteUserPolicy) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let (task_count,): (i64,) = sqlx::query_as(&COUNT_TASKS)
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        if task_count > 0 {
+            let query = match policy {
+                DeleteUserPolicy::Restrict => {
+                    return Err(UserHasTasks {
+                        user_id: id,
+                        task_count,
+                    }
+                    .into())
+                }
+                DeleteUserPolicy::Nullify => &UNASSIGN_TASKS,
+                DeleteUserPolicy::Delete => &DELETE_TASKS,
+            };
+
+            sqlx::query(query).bind(id).execute(&mut *tx).await?;
+        }
+
+        let result = sqlx::query(&DELETE).bind(id).execute(&mut *tx).await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn task_counts(&self) -> Result<Vec<UserTaskCounts>> {
+        let counts = sqlx::query_as::<_, UserTaskCounts>(
+            r#"
+            SELECT u.id AS user_id, u.name,
+                   COUNT(t.id) AS total,
+                   COUNT(t.id) FILTER (WHERE t.completed) AS completed
+            FROM users u
+            LEFT JOIN tasks t ON t.assigned_user_id = u.id
+            GROUP BY u.id
+            ORDER BY u.id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    async fn summary(&self, id: i64) -> Result<UserSummary> {
+        let summary = sqlx::query_as::<_, UserSummary>(&SUMMARY)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(summary)
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
+use sqlx::{sqlite::SqliteRow, FromRow, SqliteConnection, SqlitePool};
+
+use crate::db::{TablePrefix, TaskModel, UserModel};
+
+/// Row type stored in its own table keyed by an integer `id`.
+pub trait Entity: for<'r> FromRow<'r, SqliteRow> + Send + Unpin + 'static {
+    /// Table name before any tenant prefix.
+    const TABLE: &'static str;
+
+    /// Select list producing a row of `table`, for `SELECT` and `RETURNING`.
+    /// Entities with columns derived from other tables extend `*`.
+    fn columns(_table: &str, _prefix: &TablePrefix) -> String {
+        "*".to_string()
+    }
+}
+
+impl Entity for TaskModel {
+    const TABLE: &'static str = "tasks";
+
+    fn columns(table: &str, prefix: &TablePrefix) -> String {
+        format!(
+            "*, (SELECT json_group_array(tag) FROM (SELECT tag FROM {tags} WHERE task_id = {table}.id ORDER BY tag)) AS tags",
+            table = table,
+            tags = prefix.table("task_tags"),
+        )
+    }
+}
+
+impl Entity for UserModel {
+    const TABLE: &'static str = "users";
+}
+
+/// SQLite storage for one entity: the pool, prefixed table naming and the
+/// id-keyed queries every entity shares. An entity's repository is this type
+/// plus an impl of its own trait for the queries specific to it, so a new
+/// entity only needs an [`Entity`] impl and those.
+pub struct SqliteRepository<E> {
+    pub(crate) pool: SqlitePool,
+    /// Serves the reporting reads; the main pool unless one is set with
+    /// [`Self::with_read_only_pool`].
+    pub(crate) read_pool: SqlitePool,
+    prefix: TablePrefix,
+    /// Name of the entity's table, including any tenant prefix.
+    pub(crate) table: String,
+    /// Query strings already built for these table names, shared by clones.
+    statements: Arc<RwLock<HashMap<&'static str, Arc<str>>>>,
+    entity: PhantomData<fn() -> E>,
+}
+
+impl<E> Clone for SqliteRepository<E> {
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            read_pool: self.read_pool.clone(),
+            prefix: self.prefix.clone(),
+            table: self.table.clone(),
+            statements: self.statements.clone(),
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<E: Entity> SqliteRepository<E> {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            read_pool: pool.clone(),
+            pool,
+            prefix: TablePrefix::default(),
+            table: E::TABLE.to_string(),
+            statements: Arc::default(),
+            entity: PhantomData,
+        }
+    }
+
+    /// Uses `{prefix}` tables instead of the unprefixed ones.
+    pub fn with_table_prefix(mut self, prefix: &TablePrefix) -> Self {
+        self.table = prefix.table(E::TABLE);
+        self.prefix = prefix.clone();
+        self.statements = Arc::default();
+        self
+    }
+
+    /// Runs the reporting reads on `pool`, e.g. one from
+    /// [`crate::db::init_read_only_pool`].
+    pub fn with_read_only_pool(mut self, pool: SqlitePool) -> Self {
+        self.read_pool = pool;
+        self
+    }
+
+    /// The query stored under `key`, built by `build` on first use. Table
+    /// names are only known once the prefix is set, so queries can't be
+    /// `const`s; this formats each one once per repository instead of per
+    /// call, and hands sqlx the same string so its per-connection statement
+    /// cache keeps hitting. Values must only ever go in as bound parameters.
+    pub(crate) fn statement(&self, key: &'static str, build: impl FnOnce() -> String) -> Arc<str> {
+        if let Some(query) = self.statements.read().unwrap().get(key) {
+            return query.clone();
+        }
+
+        // Built outside the lock: `build` may look up other statements
+        let query: Arc<str> = build().into();
+        self.statements
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_insert(query)
+            .clone()
+    }
+
+    /// [`Entity::columns`] for this repository's table.
+    pub(crate) fn columns(&self) -> Arc<str> {
+        self.statement("columns", || E::columns(&self.table, &self.prefix))
+    }
+
+    /// Name of another table under the same prefix.
+    pub(crate) fn related_table(&self, name: &str) -> String {
+        self.prefix.table(name)
+    }
+
+    pub(crate) async fn find_by_id(&self, id: i64) -> Result<E> {
+        let mut conn = self.pool.acquire().await?;
+        self.find_by_id_on(&mut conn, id).await
+    }
+
+    /// `find_by_id` on `conn`, so it can run inside a caller's transaction.
+    pub(crate) async fn find_by_id_on(&self, conn: &mut SqliteConnection, id: i64) -> Result<E> {
+        let query = self.statement("find_by_id", || {
+            format!("SELECT {} FROM {} WHERE id = ?", self.columns(), self.table)
+        });
+        let row = sqlx::query_as::<_, E>(&query)
+            .bind(id)
+            .fetch_one(conn)
+            .await?;
+
+        Ok(row)
+    }
+
+    /// Rows with any of `ids`, in id order; ids with no row are skipped.
+    pub(crate) async fn find_many_by_id(&self, ids: &[i64]) -> Result<Vec<E>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let query = format!(
+            "SELECT {} FROM {} WHERE id IN ({}) ORDER BY id",
+            self.columns(),
+            self.table,
+            placeholders
+        );
+        let mut rows = sqlx::query_as::<_, E>(&query);
+        for id in ids {
+            rows = rows.bind(id);
+        }
+
+        Ok(rows.fetch_all(&self.pool).await?)
+    }
+
+    pub(crate) async fn count_all(&self) -> Result<i64> {
+        let query = self.statement("count_all", || {
+            format!("SELECT COUNT(*) FROM {}", self.table)
+        });
+        let (count,): (i64,) = sqlx::query_as(&query).fetch_one(&self.pool).await?;
+
+        Ok(count)
+    }
+
+    pub(crate) async fn delete_by_id(&self, id: i64) -> Result<bool> {
+        let query = self.statement("delete_by_id", || {
+            format!("DELETE FROM {} WHERE id = ?", self.table)
+        });
+        let result = sqlx::query(&query).bind(id).execute(&self.pool).await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_statement_built_once_per_prefix() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let repo = SqliteRepository::<UserModel>::new(pool);
+        let mut builds = 0;
+        let mut build = |repo: &SqliteRepository<UserModel>| {
+            repo.statement("count_all", || {
+                builds += 1;
+                format!("SELECT COUNT(*) FROM {}", repo.table)
+            })
+        };
+
+        assert_eq!(&*build(&repo), "SELECT COUNT(*) FROM users");
+        assert_eq!(&*build(&repo.clone()), "SELECT COUNT(*) FROM users");
+
+        let prefixed = repo.with_table_prefix(&TablePrefix::new("tenantA_").unwrap());
+        assert_eq!(&*build(&prefixed), "SELECT COUNT(*) FROM tenantA_users");
+        assert_eq!(builds, 2);
+    }
+}
@@ -1,170 +1,2040 @@
-use anyhow::Result;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use sqlx::SqlitePool;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use sqlx::{Database, Encode, QueryBuilder, Sqlite, SqliteConnection, SqlitePool, Type};
+
+use super::{Dialect, Page, SqliteRepository};
+use crate::db::{TablePrefix, TaskAuditModel, TaskModel, TaskStatus};
+
+/// Column a task list is sorted by. `id` is always appended as a tiebreaker
+/// so rows with equal keys keep a stable order across pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskSortKey {
+    Id,
+    Title,
+    CreatedAt,
+    UpdatedAt,
+    /// The manual order set by [`TaskRepository::move_after`].
+    Position,
+}
+
+impl TaskSortKey {
+    fn column(self) -> &'static str {
+        match self {
+            TaskSortKey::Id => "id",
+            TaskSortKey::Title => "title",
+            TaskSortKey::CreatedAt => "created_at",
+            TaskSortKey::UpdatedAt => "updated_at",
+            TaskSortKey::Position => "position",
+        }
+    }
+}
+
+/// Where a task sits in the manual order: its `position`, or `-id` until it
+/// is first moved, so unmoved tasks sit newest first.
+pub(crate) const POSITION_KEY: &str = "COALESCE(position, -id)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskOrder {
+    pub key: TaskSortKey,
+    pub descending: bool,
+}
+
+impl Default for TaskOrder {
+    /// The manual order, which is newest first for tasks never moved.
+    fn default() -> Self {
+        Self {
+            key: TaskSortKey::Position,
+            descending: false,
+        }
+    }
+}
+
+impl TaskOrder {
+    /// `ORDER BY` clause for this order. Spelled out per variant so the
+    /// clause is always one of these literals, never text from a caller.
+    pub(crate) fn sql(&self) -> &'static str {
+        match (self.key, self.descending) {
+            (TaskSortKey::Id, false) => "id ASC",
+            (TaskSortKey::Id, true) => "id DESC",
+            (TaskSortKey::Title, false) => "title ASC, id ASC",
+            (TaskSortKey::Title, true) => "title DESC, id DESC",
+            (TaskSortKey::CreatedAt, false) => "created_at ASC, id ASC",
+            (TaskSortKey::CreatedAt, true) => "created_at DESC, id DESC",
+            (TaskSortKey::UpdatedAt, false) => "updated_at ASC, id ASC",
+            (TaskSortKey::UpdatedAt, true) => "updated_at DESC, id DESC",
+            // Same expression as POSITION_KEY; ties break newest first
+            (TaskSortKey::Position, false) => "COALESCE(position, -id) ASC, id DESC",
+            (TaskSortKey::Position, true) => "COALESCE(position, -id) DESC, id ASC",
+        }
+    }
+}
+
+/// Formats as the string [`FromStr`] accepts, e.g. `-created_at`.
+impl std::fmt::Display for TaskOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.descending { "-" } else { "" };
+        write!(f, "{}{}", sign, self.key.column())
+    }
+}
+
+/// Parses `field` (ascending) or `-field` (descending), e.g. `-created_at`.
+impl FromStr for TaskOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (descending, field) = match s.trim().strip_prefix('-') {
+            Some(field) => (true, field),
+            None => (false, s.trim()),
+        };
+
+        let key = match field {
+            "id" => TaskSortKey::Id,
+            "title" => TaskSortKey::Title,
+            "created_at" => TaskSortKey::CreatedAt,
+            "updated_at" => TaskSortKey::UpdatedAt,
+            "position" => TaskSortKey::Position,
+            other => return Err(anyhow!("Unknown sort field: {}", other)),
+        };
+
+        Ok(Self { key, descending })
+    }
+}
+
+/// Narrows `list`, `count` and `complete_matching`. The default matches
+/// every task; set fields combine with AND.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskFilter {
+    /// Only tasks carrying all of these tags.
+    pub tags: Vec<String>,
+    /// Only tasks assigned to this user.
+    pub assigned_user_id: Option<i64>,
+    /// Only completed tasks with `Some(true)`, only pending ones with
+    /// `Some(false)`.
+    pub completed: Option<bool>,
+    /// Only tasks with this status.
+    pub status: Option<TaskStatus>,
+    /// Only tasks still open: `todo` or `in_progress`.
+    pub open: bool,
+    /// Overrides the repository's order for this `list` call; `count`
+    /// ignores it.
+    pub order: Option<TaskOrder>,
+}
+
+impl TaskFilter {
+    /// Whether the filter matches every task.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+            && self.assigned_user_id.is_none()
+            && self.completed.is_none()
+            && self.status.is_none()
+            && !self.open
+    }
+
+    /// Appends the condition over `tasks` to `query`, binding the filter's
+    /// values. The builder numbers placeholders for its own database, so
+    /// both backends share this, and callers add whatever comes before the
+    /// condition and after it.
+    pub(crate) fn push_conditions<'a, DB>(
+        &self,
+        query: &mut QueryBuilder<'a, DB>,
+        tasks: &str,
+        task_tags: &str,
+    ) where
+        DB: Database,
+        i64: Encode<'a, DB> + Type<DB>,
+        bool: Encode<'a, DB> + Type<DB>,
+        String: Encode<'a, DB> + Type<DB>,
+    {
+        query.push("1 = 1");
+        if let Some(user_id) = self.assigned_user_id {
+            query
+                .push(format!(" AND {}.assigned_user_id = ", tasks))
+                .push_bind(user_id);
+        }
+        if let Some(completed) = self.completed {
+            query
+                .push(format!(" AND {}.completed = ", tasks))
+                .push_bind(completed);
+        }
+        if let Some(status) = self.status {
+            query
+                .push(format!(" AND {}.status = ", tasks))
+                .push_bind(status.as_str().to_owned());
+        }
+        if self.open {
+            query.push(format!(" AND {}.status IN ('todo', 'in_progress')", tasks));
+        }
+        for tag in &self.tags {
+            query
+                .push(format!(
+                    " AND EXISTS (SELECT 1 FROM {} WHERE task_id = {}.id AND tag = ",
+                    task_tags, tasks
+                ))
+                .push_bind(tag.clone())
+                .push(")");
+        }
+    }
+}
+
+/// Returned by [`TaskRepository::create_for_user`] and
+/// [`TaskRepository::reassign`] when a user doesn't exist.
+#[derive(Debug)]
+pub struct UnknownUser {
+    pub user_id: i64,
+}
+
+impl std::fmt::Display for UnknownUser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "User with id {} not found", self.user_id)
+    }
+}
+
+impl std::error::Error for UnknownUser {}
+
+/// Returned by [`TaskRepository::move_after`] when the task to move after
+/// doesn't exist.
+#[derive(Debug)]
+pub struct UnknownTask {
+    pub task_id: i64,
+}
+
+impl std::fmt::Display for UnknownTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Task with id {} not found", self.task_id)
+    }
+}
+
+impl std::error::Error for UnknownTask {}
+
+/// Returned by [`TaskRepository::update_if_version`] when the task changed
+/// since the version the caller read.
+#[derive(Debug)]
+pub struct VersionMismatch {
+    pub task_id: i64,
+    pub expected: String,
+}
+
+impl std::fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Task with id {} was changed after version {}",
+            self.task_id, self.expected
+        )
+    }
+}
+
+impl std::error::Error for VersionMismatch {}
+
+/// A completed task moved to `tasks_archive` by
+/// [`TaskRepository::archive_completed`].
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ArchivedTask {
+    #[sqlx(flatten)]
+    pub task: TaskModel,
+    /// ISO-8601 UTC timestamp of the move.
+    pub archived_at: String,
+}
+
+/// How titles are compared when looking for duplicates: trimmed and
+/// lower-cased, the same on both backends except that SQLite's `lower` only
+/// folds ASCII letters.
+pub(crate) const TITLE_KEY: &str = "lower(trim(title))";
+
+/// Tasks sharing a normalized title, from [`TaskRepository::duplicates`].
+#[derive(Debug, Clone)]
+pub struct DuplicateTasks {
+    /// The title they share, normalized as [`TITLE_KEY`] does.
+    pub title: String,
+    /// The tasks in the group, in id order. Always at least two.
+    pub tasks: Vec<TaskModel>,
+}
+
+/// A task row tagged with its [`TITLE_KEY`].
+#[derive(sqlx::FromRow)]
+pub(crate) struct KeyedTask {
+    #[sqlx(flatten)]
+    task: TaskModel,
+    title_key: String,
+}
+
+/// Groups rows sorted by title key into [`DuplicateTasks`].
+pub(crate) fn group_duplicates(rows: Vec<KeyedTask>) -> Vec<DuplicateTasks> {
+    let mut groups: Vec<DuplicateTasks> = Vec::new();
+    for row in rows {
+        match groups.last_mut() {
+            Some(group) if group.title == row.title_key => group.tasks.push(row.task),
+            _ => groups.push(DuplicateTasks {
+                title: row.title_key,
+                tasks: vec![row.task],
+            }),
+        }
+    }
+    groups
+}
+
+/// How many tasks have each status, from
+/// [`TaskRepository::count_by_status`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskStatusCounts {
+    pub todo: i64,
+    pub in_progress: i64,
+    pub done: i64,
+    pub cancelled: i64,
+}
+
+impl TaskStatusCounts {
+    /// Totals `(status, count)` rows; a status with no tasks has no row.
+    pub(crate) fn from_rows(rows: Vec<(String, i64)>) -> Result<Self> {
+        let mut counts = Self::default();
+        for (status, count) in rows {
+            match status.parse()? {
+                TaskStatus::Todo => counts.todo += count,
+                TaskStatus::InProgress => counts.in_progress += count,
+                TaskStatus::Done => counts.done += count,
+                TaskStatus::Cancelled => counts.cancelled += count,
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Tasks whose `completed` flag is set.
+    pub fn completed(&self) -> i64 {
+        self.done
+    }
+
+    /// Every other task, cancelled ones included.
+    pub fn pending(&self) -> i64 {
+        self.todo + self.in_progress + self.cancelled
+    }
+}
+
+/// What changed since a sync cursor, from
+/// [`TaskRepository::changes_since`].
+#[derive(Debug, Clone)]
+pub struct TaskChanges {
+    /// Tasks created or updated since the cursor, as they are now, least
+    /// recently updated first.
+    pub tasks: Vec<TaskModel>,
+    /// Ids of tasks deleted (or archived) since the cursor, ascending.
+    pub deleted: Vec<i64>,
+    /// Id of the latest `task_audit` entry covered, the next cursor.
+    pub cursor: i64,
+    /// `tasks` is every task rather than the changes, because there was no
+    /// cursor or its history is gone. Clients replace their copy.
+    pub reset: bool,
+}
+
+/// The changes `update_with_tags` writes. `None` leaves a field unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskUpdate {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub status: Option<TaskStatus>,
+    /// As in `update`: `Some(None)` unassigns the task.
+    pub assigned_user_id: Option<Option<i64>>,
+    /// Replaces the task's tags when set; duplicates are dropped.
+    pub tags: Option<Vec<String>>,
+    /// Only write while the task's `updated_at` is still this, as in
+    /// `update_if_version`.
+    pub expected_updated_at: Option<String>,
+}
+
+/// Position for a task placed between `before` and `after`, the keys of its
+/// new neighbours (`None` past either end). `None` when the two are too
+/// close to fit another value between, and positions need renumbering.
+pub(crate) fn position_between(before: Option<f64>, after: Option<f64>) -> Option<f64> {
+    let position = match (before, after) {
+        (None, None) => 0.0,
+        (None, Some(after)) => after - 1.0,
+        (Some(before), None) => before + 1.0,
+        (Some(before), Some(after)) => before + (after - before) / 2.0,
+    };
+    let fits =
+        before.is_none_or(|before| position > before) && after.is_none_or(|after| position < after);
+    fits.then_some(position)
+}
+
+#[async_trait]
+pub trait TaskRepository: Send + Sync {
+    async fn create(&self, title: &str, description: &str) -> Result<TaskModel>;
+    /// Like `create`, assigning the task to `user_id`. Fails with
+    /// [`UnknownUser`] if there is no such user.
+    async fn create_for_user(
+        &self,
+        user_id: i64,
+        title: &str,
+        description: &str,
+    ) -> Result<TaskModel>;
+    /// Like `create`, also returning the row's `last_insert_rowid`.
+    async fn create_returning_meta(
+        &self,
+        title: &str,
+        description: &str,
+    ) -> Result<(TaskModel, i64)>;
+    /// Like `create`, or `create_for_user` with an `assigned_user_id`, also
+    /// setting the task's `tags`. One transaction, so a failure keeps
+    /// neither the task nor its tags.
+    async fn create_with_tags(
+        &self,
+        assigned_user_id: Option<i64>,
+        title: &str,
+        description: &str,
+        tags: &[String],
+    ) -> Result<TaskModel>;
+    /// Creates `(title, description)` pairs in a single transaction; if any
+    /// insert fails, none are kept.
+    async fn create_many(&self, tasks: &[(String, String)]) -> Result<Vec<TaskModel>>;
+    async fn get(&self, id: i64) -> Result<TaskModel>;
+    /// Tasks with any of `ids`, in id order. Missing ids are skipped.
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<TaskModel>>;
+    /// Tasks matching `filter`, in the configured order.
+    async fn list(&self, page: Page, filter: &TaskFilter) -> Result<Vec<TaskModel>>;
+    /// Every task in id order, produced row by row so callers can export
+    /// tables too large to hold in memory.
+    fn stream_all(&self) -> BoxStream<'static, Result<TaskModel>>;
+    /// Tasks with `updated_at >= since`, most recently updated first.
+    /// `since` must be in the stored timestamp format.
+    async fn list_updated_since(&self, since: &str) -> Result<Vec<TaskModel>>;
+    /// Tasks with `from <= completed_at <= to`, earliest completed first.
+    /// Both bounds must be in the stored timestamp format.
+    async fn list_completed_between(&self, from: &str, to: &str) -> Result<Vec<TaskModel>>;
+    /// Number of tasks `list` would return without paging.
+    async fn count(&self, filter: &TaskFilter) -> Result<i64>;
+    /// Recorded changes to task `id`, oldest first. Entries outlive the
+    /// task, so a deleted task keeps its history.
+    async fn history(&self, id: i64) -> Result<Vec<TaskAuditModel>>;
+    /// `None` leaves a field unchanged. `assigned_user_id` is nullable, so
+    /// `Some(None)` unassigns the task and `Some(Some(user))` assigns it,
+    /// failing with [`UnknownUser`] if that user is missing. Setting the
+    /// status sets `completed` to match.
+    async fn update(
+        &self,
+        id: i64,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+        assigned_user_id: Option<Option<i64>>,
+    ) -> Result<TaskModel>;
+    /// Like `update` without reassigning, but only while the task's
+    /// `updated_at` is still `expected_updated_at`: otherwise nothing is
+    /// written and it fails with [`VersionMismatch`]. The check is part of
+    /// the write, so a concurrent update can't slip in between.
+    async fn update_if_version(
+        &self,
+        id: i64,
+        expected_updated_at: &str,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+    ) -> Result<TaskModel>;
+    /// `update` (or `update_if_version`) and `set_tags` in one transaction:
+    /// if either fails, neither change is kept.
+    async fn update_with_tags(&self, id: i64, update: &TaskUpdate) -> Result<TaskModel>;
+    /// Replaces the task's tags; duplicates are dropped.
+    async fn set_tags(&self, id: i64, tags: &[String]) -> Result<TaskModel>;
+    /// Places task `id` right after `after_id` in the manual order, or first
+    /// with `None`. Fails with [`UnknownTask`] if `after_id` is missing.
+    async fn move_after(&self, id: i64, after_id: Option<i64>) -> Result<TaskModel>;
+    async fn delete(&self, id: i64) -> Result<bool>;
+    /// Reads and deletes the task in one transaction, returning it as it was
+    /// just before the delete; `None` if there was no such task.
+    async fn delete_returning(&self, id: i64) -> Result<Option<TaskModel>>;
+    /// Marks every open (`todo` or `in_progress`) task completed and returns
+    /// how many changed. Cancelled tasks stay cancelled.
+    async fn complete_all(&self) -> Result<u64>;
+    /// Like `complete_all`, limited to the open tasks matching `filter`
+    /// (its order is ignored).
+    async fn complete_matching(&self, filter: &TaskFilter) -> Result<u64>;
+    /// Moves every task assigned to `from` over to `to` and returns how many
+    /// moved. Fails with [`UnknownUser`] if either user is missing.
+    async fn reassign(&self, from: i64, to: i64) -> Result<u64>;
+    /// Deletes every task and resets the id sequence, so the next task
+    /// created gets id 1. Returns how many were deleted. History is cleared
+    /// too, since the ids it refers to will be reused.
+    async fn delete_all(&self) -> Result<u64>;
+    /// Moves every completed task, with its tags, into the archive table
+    /// and deletes it from the active one, in one transaction. Returns how
+    /// many moved.
+    async fn archive_completed(&self) -> Result<u64>;
+    /// Archived tasks, most recently archived first.
+    async fn list_archived(&self, page: Page) -> Result<Vec<ArchivedTask>>;
+    /// Groups of two or more tasks whose titles match once trimmed and
+    /// lower-cased, ordered by that title.
+    async fn duplicates(&self) -> Result<Vec<DuplicateTasks>>;
+    /// Task counts per status, from one grouped query.
+    async fn count_by_status(&self) -> Result<TaskStatusCounts>;
+    /// Changes recorded in `task_audit` after entry `cursor`, or every task
+    /// with `None`. A cursor whose entry `delete_all` cleared also gets every
+    /// task, with `reset` set.
+    async fn changes_since(&self, cursor: Option<i64>) -> Result<TaskChanges>;
+
+    /// Flips `completed` through the regular update path: a done task goes
+    /// back to todo, any other becomes done.
+    async fn toggle(&self, id: i64) -> Result<TaskModel> {
+        let existing = self.get(id).await?;
+        let status = TaskStatus::from_completed(!existing.completed);
+        self.update(id, None, None, Some(status), None).await
+    }
+}
+
+#[derive(Clone)]
+pub struct SqliteTaskRepository {
+    base: SqliteRepository<TaskModel>,
+    order: TaskOrder,
+}
+
+/// Rows `stream_all` reads ahead of a slow consumer.
+pub(crate) const STREAM_BUFFER: usize = 64;
+
+const NOW: &str = Dialect::Sqlite.now();
+
+impl SqliteTaskRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            base: SqliteRepository::new(pool),
+            order: TaskOrder::default(),
+        }
+    }
+
+    /// Uses the `{prefix}tasks` table instead of `tasks`.
+    pub fn with_table_prefix(mut self, prefix: &TablePrefix) -> Self {
+        self.base = self.base.with_table_prefix(prefix);
+        self
+    }
+
+    /// Runs `count_by_status` and `stream_all` on `pool`, e.g. one from
+    /// [`crate::db::init_read_only_pool`].
+    pub fn with_read_only_pool(mut self, pool: SqlitePool) -> Self {
+        self.base = self.base.with_read_only_pool(pool);
+        self
+    }
+
+    /// Overrides the order used by `list`.
+    pub fn with_order(mut self, order: TaskOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// `{head} WHERE <filter>`, for the caller to finish.
+    fn filtered<'a>(&self, head: &str, filter: &TaskFilter) -> QueryBuilder<'a, Sqlite> {
+        let mut query = QueryBuilder::new(head);
+        query.push(" WHERE ");
+        filter.push_conditions(
+            &mut query,
+            &self.base.table,
+            &self.base.related_table("task_tags"),
+        );
+        query
+    }
+
+    /// Keys of the tasks `id` would sit between if placed right after
+    /// `after_id`, or first with `None`.
+    async fn neighbours(
+        &self,
+        conn: &mut SqliteConnection,
+        id: i64,
+        after_id: Option<i64>,
+    ) -> Result<(Option<f64>, Option<f64>)> {
+        let Some(after_id) = after_id else {
+            let query = self.base.statement("first_position", || {
+                format!(
+                    "SELECT CAST(MIN({POSITION_KEY}) AS REAL) FROM {} WHERE id <> ?",
+                    self.base.table
+                )
+            });
+            let (first,): (Option<f64>,) = sqlx::query_as(&query)
+                .bind(id)
+                .fetch_one(&mut *conn)
+                .await?;
+            return Ok((None, first));
+        };
+
+        let key = self.base.statement("position", || {
+            format!(
+                "SELECT CAST({POSITION_KEY} AS REAL) FROM {} WHERE id = ?",
+                self.base.table
+            )
+        });
+        let next = self.base.statement("next_position", || {
+            format!(
+                r#"
+                SELECT CAST({POSITION_KEY} AS REAL) FROM {}
+                WHERE id <> ? AND ({POSITION_KEY} > ? OR ({POSITION_KEY} = ? AND id < ?))
+                ORDER BY {POSITION_KEY} ASC, id DESC
+                LIMIT 1
+                "#,
+                self.base.table
+            )
+        });
+        let (before,): (f64,) = sqlx::query_as(&key)
+            .bind(after_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or(UnknownTask { task_id: after_id })?;
+        let after: Option<(f64,)> = sqlx::query_as(&next)
+            .bind(id)
+            .bind(before)
+            .bind(before)
+            .bind(after_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        Ok((Some(before), after.map(|(after,)| after)))
+    }
+
+    /// Rewrites every position as 0, 1, 2, ... in the current order, making
+    /// room between neighbours that repeated moves have pushed together.
+    async fn renumber_positions(&self, conn: &mut SqliteConnection) -> Result<()> {
+        let ordered = self.base.statement("ordered_ids", || {
+            format!(
+                "SELECT id FROM {} ORDER BY {POSITION_KEY} ASC, id DESC",
+                self.base.table
+            )
+        });
+        let set = self.base.statement("set_position", || {
+            format!("UPDATE {} SET position = ? WHERE id = ?", self.base.table)
+        });
+
+        let ids: Vec<(i64,)> = sqlx::query_as(&ordered).fetch_all(&mut *conn).await?;
+        for (position, (id,)) in ids.into_iter().enumerate() {
+            sqlx::query(&set)
+                .bind(position as f64)
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes `update`'s changes over `existing` on `conn`, also requiring
+    /// its `updated_at` to still be `expected_updated_at` when given.
+    #[allow(clippy::too_many_arguments)]
+    async fn write_update(
+        &self,
+        conn: &mut SqliteConnection,
+        existing: TaskModel,
+        expected_updated_at: Option<&str>,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+        assigned_user_id: Option<Option<i64>>,
+    ) -> Result<TaskModel> {
+        let id = existing.id;
+        let mismatch = |expected: &str| VersionMismatch {
+            task_id: id,
+            expected: expected.to_string(),
+        };
+        if let Some(expected) = expected_updated_at.filter(|&v| v != existing.updated_at) {
+            return Err(mismatch(expected).into());
+        }
+
+        let new_title = title.unwrap_or(&existing.title);
+        let new_description = description.unwrap_or(&existing.description);
+        let new_status = status.unwrap_or(existing.status);
+        let new_assigned_user_id = assigned_user_id.unwrap_or(existing.assigned_user_id);
+
+        // Nothing changes: skip the write so `updated_at` keeps its value
+        if new_title == existing.title
+            && new_description == existing.description
+            && new_status == existing.status
+            && new_assigned_user_id == existing.assigned_user_id
+        {
+            return Ok(existing);
+        }
+
+        let query = self.base.statement("update", || {
+            format!(
+                r#"
+                UPDATE {}
+                SET title = ?, description = ?, completed = ?, status = ?,
+                    completed_at = CASE
+                        WHEN ? THEN COALESCE(completed_at, {NOW})
+                    END,
+                    assigned_user_id = ?,
+                    updated_at = {NOW}
+                WHERE id = ? AND (? IS NULL OR updated_at = ?)
+                RETURNING {}
+                "#,
+                self.base.table,
+                self.base.columns()
+            )
+        });
+        let task = sqlx::query_as::<_, TaskModel>(&query)
+            .bind(new_title)
+            .bind(new_description)
+            .bind(new_status.is_done())
+            .bind(new_status.as_str())
+            .bind(new_status.is_done())
+            .bind(new_assigned_user_id)
+            .bind(id)
+            .bind(expected_updated_at)
+            .bind(expected_updated_at)
+            .fetch_one(conn)
+            .await
+            .map_err(|e| match (&e, expected_updated_at, new_assigned_user_id) {
+                (sqlx::Error::Database(db), _, Some(user_id)) if db.is_foreign_key_violation() => {
+                    UnknownUser { user_id }.into()
+                }
+                // Changed between the read and the write
+                (sqlx::Error::RowNotFound, Some(expected), _) => mismatch(expected).into(),
+                _ => anyhow::Error::from(e),
+            })?;
+
+        Ok(task)
+    }
+
+    /// The single-row insert behind `create` and `create_many`.
+    fn insert(&self) -> Arc<str> {
+        self.base.statement("insert", || {
+            format!(
+                r#"
+                INSERT INTO {} (title, description, completed, created_at, updated_at)
+                VALUES (?, ?, 0, {NOW}, {NOW})
+                RETURNING {}
+                "#,
+                self.base.table,
+                self.base.columns()
+            )
+        })
+    }
+
+    /// Replaces the tags of `existing` on `conn`, leaving it untouched when
+    /// they already match.
+    async fn write_tags(
+        &self,
+        conn: &mut SqliteConnection,
+        existing: TaskModel,
+        tags: &[String],
+    ) -> Result<TaskModel> {
+        let mut wanted: Vec<&str> = tags.iter().map(String::as_str).collect();
+        wanted.sort_unstable();
+        wanted.dedup();
+        if existing.tags == wanted {
+            return Ok(existing);
+        }
+
+        let clear = self.base.statement("clear_tags", || {
+            format!(
+                "DELETE FROM {} WHERE task_id = ?",
+                self.base.related_table("task_tags")
+            )
+        });
+        let insert = self.base.statement("insert_tag", || {
+            format!(
+                "INSERT INTO {} (task_id, tag) VALUES (?, ?)",
+                self.base.related_table("task_tags")
+            )
+        });
+        let touch = self.base.statement("touch", || {
+            format!(
+                r#"
+                UPDATE {}
+                SET updated_at = {NOW}
+                WHERE id = ?
+                RETURNING {}
+                "#,
+                self.base.table,
+                self.base.columns()
+            )
+        });
+
+        let id = existing.id;
+        sqlx::query(&clear).bind(id).execute(&mut *conn).await?;
+        for tag in &wanted {
+            sqlx::query(&insert)
+                .bind(id)
+                .bind(tag)
+                .execute(&mut *conn)
+                .await?;
+        }
+        let task = sqlx::query_as::<_, TaskModel>(&touch)
+            .bind(id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok(task)
+    }
+
+    /// `create_for_user` on `conn`, so it can run inside a caller's
+    /// transaction.
+    pub(crate) async fn create_for_user_on(
+        &self,
+        conn: &mut SqliteConnection,
+        user_id: i64,
+        title: &str,
+        description: &str,
+    ) -> Result<TaskModel> {
+        // Selecting from users makes the insert a no-op for a missing user
+        let query = self.base.statement("create_for_user", || {
+            format!(
+                r#"
+                INSERT INTO {} (title, description, completed, created_at, updated_at, assigned_user_id)
+                SELECT ?, ?, 0, {NOW}, {NOW}, id
+                FROM {} WHERE id = ?
+                RETURNING {}
+                "#,
+                self.base.table,
+                self.base.related_table("users"),
+                self.base.columns()
+            )
+        });
+        let task = sqlx::query_as::<_, TaskModel>(&query)
+            .bind(title)
+            .bind(description)
+            .bind(user_id)
+            .fetch_optional(conn)
+            .await?;
+
+        task.ok_or_else(|| UnknownUser { user_id }.into())
+    }
+}
+
+#[async_trait]
+impl TaskRepository for SqliteTaskRepository {
+    async fn create(&self, title: &str, description: &str) -> Result<TaskModel> {
+        let task = sqlx::query_as::<_, TaskModel>(&self.insert())
+            .bind(title)
+            .bind(description)
+            .fetch_one(&self.base.pool)
+            .await?;
+
+        Ok(task)
+    }
+
+    async fn create_for_user(
+        &self,
+        user_id: i64,
+        title: &str,
+        description: &str,
+    ) -> Result<TaskModel> {
+        let mut conn = self.base.pool.acquire().await?;
+        self.create_for_user_on(&mut conn, user_id, title, description)
+            .await
+    }
+
+    async fn create_with_tags(
+        &self,
+        assigned_user_id: Option<i64>,
+        title: &str,
+        description: &str,
+        tags: &[String],
+    ) -> Result<TaskModel> {
+        let mut tx = self.base.pool.begin().await?;
+        let task = match assigned_user_id {
+            Some(user_id) => {
+                self.create_for_user_on(&mut tx, user_id, title, description)
+                    .await?
+            }
+            None => {
+                sqlx::query_as::<_, TaskModel>(&self.insert())
+                    .bind(title)
+                    .bind(description)
+                    .fetch_one(&mut *tx)
+                    .await?
+            }
+        };
+        let task = self.write_tags(&mut tx, task, tags).await?;
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    async fn create_returning_meta(
+        &self,
+        title: &str,
+        description: &str,
+    ) -> Result<(TaskModel, i64)> {
+        // last_insert_rowid is per connection, so insert and read back on one
+        let mut conn = self.base.pool.acquire().await?;
+
+        let insert = self.base.statement("insert_without_returning", || {
+            format!(
+                r#"
+                INSERT INTO {} (title, description, completed, created_at, updated_at)
+                VALUES (?, ?, 0, {NOW}, {NOW})
+                "#,
+                self.base.table
+            )
+        });
+        let rowid = sqlx::query(&insert)
+            .bind(title)
+            .bind(description)
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid();
+
+        let query = self.base.statement("find_by_rowid", || {
+            format!(
+                "SELECT {} FROM {} WHERE rowid = ?",
+                self.base.columns(),
+                self.base.table
+            )
+        });
+        let task = sqlx::query_as::<_, TaskModel>(&query)
+            .bind(rowid)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok((task, rowid))
+    }
+
+    async fn create_many(&self, tasks: &[(String, String)]) -> Result<Vec<TaskModel>> {
+        let query = self.insert();
+
+        let mut tx = self.base.pool.begin().await?;
+        let mut created = Vec::with_capacity(tasks.len());
+        for (title, description) in tasks {
+            let task = sqlx::query_as::<_, TaskModel>(&query)
+                .bind(title)
+                .bind(description)
+                .fetch_one(&mut *tx)
+                .await?;
+            created.push(task);
+        }
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    async fn get(&self, id: i64) -> Result<TaskModel> {
+        self.base.find_by_id(id).await
+    }
+
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<TaskModel>> {
+        self.base.find_many_by_id(ids).await
+    }
+
+    async fn list(&self, page: Page, filter: &TaskFilter) -> Result<Vec<TaskModel>> {
+        let head = self.base.statement("select", || {
+            format!("SELECT {} FROM {}", self.base.columns(), self.base.table)
+        });
+        let mut query = self.filtered(&head, filter);
+        query
+            .push(format!(
+                " ORDER BY {} LIMIT ",
+                filter.order.unwrap_or(self.order).sql()
+            ))
+            .push_bind(page.sql_limit())
+            .push(" OFFSET ")
+            .push_bind(page.sql_offset());
+        let tasks = query
+            .build_query_as::<TaskModel>()
+            .fetch_all(&self.base.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    fn stream_all(&self) -> BoxStream<'static, Result<TaskModel>> {
+        let pool = self.base.read_pool.clone();
+        let query = self.base.statement("stream_all", || {
+            format!(
+                "SELECT {} FROM {} ORDER BY id",
+                self.base.columns(),
+                self.base.table
+            )
+        });
+
+        // The row stream borrows the pool and query, so it runs in its own
+        // task and hands rows over a bounded channel: a slow reader pauses
+        // the query. It holds one pooled connection until it finishes or
+        // the receiver is dropped.
+        let (sender, receiver) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(async move {
+            let mut rows = sqlx::query_as::<_, TaskModel>(&query).fetch(&pool);
+            while let Some(row) = rows.next().await {
+                if sender.send(row.map_err(Into::into)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|row| (row, receiver))
+        })
+        .boxed()
+    }
+
+    async fn list_updated_since(&self, since: &str) -> Result<Vec<TaskModel>> {
+        let query = self.base.statement("list_updated_since", || {
+            format!(
+                "SELECT {} FROM {} WHERE updated_at >= ? ORDER BY updated_at DESC, id DESC",
+                self.base.columns(),
+                self.base.table
+            )
+        });
+        let tasks = sqlx::query_as::<_, TaskModel>(&query)
+            .bind(since)
+            .fetch_all(&self.base.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    async fn list_completed_between(&self, from: &str, to: &str) -> Result<Vec<TaskModel>> {
+        let query = self.base.statement("list_completed_between", || {
+            format!(
+                "SELECT {} FROM {} WHERE completed_at BETWEEN ? AND ? ORDER BY completed_at, id",
+                self.base.columns(),
+                self.base.table
+            )
+        });
+        let tasks = sqlx::query_as::<_, TaskModel>(&query)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.base.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    async fn count(&self, filter: &TaskFilter) -> Result<i64> {
+        if filter.is_empty() {
+            return self.base.count_all().await;
+        }
+
+        let head = self.base.statement("count", || {
+            format!("SELECT COUNT(*) FROM {}", self.base.table)
+        });
+        let mut query = self.filtered(&head, filter);
+        let (count,) = query
+            .build_query_as::<(i64,)>()
+            .fetch_one(&self.base.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    async fn history(&self, id: i64) -> Result<Vec<TaskAuditModel>> {
+        let query = self.base.statement("history", || {
+            format!(
+                "SELECT * FROM {} WHERE task_id = ? ORDER BY id",
+                self.base.related_table("task_audit")
+            )
+        });
+        let entries = sqlx::query_as::<_, TaskAuditModel>(&query)
+            .bind(id)
+            .fetch_all(&self.base.pool)
+            .await?;
+
+        Ok(entries)
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+        assigned_user_id: Option<Option<i64>>,
+    ) -> Result<TaskModel> {
+        let existing = self.get(id).await?;
+        let mut conn = self.base.pool.acquire().await?;
+        self.write_update(
+            &mut conn,
+            existing,
+            None,
+            title,
+            description,
+            status,
+            assigned_user_id,
+        )
+        .await
+    }
+
+    async fn update_if_version(
+        &self,
+        id: i64,
+        expected_updated_at: &str,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+    ) -> Result<TaskModel> {
+        let existing = self.get(id).await?;
+        let mut conn = self.base.pool.acquire().await?;
+        self.write_update(
+            &mut conn,
+            existing,
+            Some(expected_updated_at),
+            title,
+            description,
+            status,
+            None,
+        )
+        .await
+    }
+
+    async fn update_with_tags(&self, id: i64, update: &TaskUpdate) -> Result<TaskModel> {
+        let mut tx = self.base.pool.begin().await?;
+        let existing = self.base.find_by_id_on(&mut tx, id).await?;
+        let mut task = self
+            .write_update(
+                &mut tx,
+                existing,
+                update.expected_updated_at.as_deref(),
+                update.title.as_deref(),
+                update.description.as_deref(),
+                update.status,
+                update.assigned_user_id,
+            )
+            .await?;
+        if let Some(tags) = &update.tags {
+            task = self.write_tags(&mut tx, task, tags).await?;
+        }
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    async fn set_tags(&self, id: i64, tags: &[String]) -> Result<TaskModel> {
+        let mut tx = self.base.pool.begin().await?;
+        let existing = self.base.find_by_id_on(&mut tx, id).await?;
+        let task = self.write_tags(&mut tx, existing, tags).await?;
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    async fn move_after(&self, id: i64, after_id: Option<i64>) -> Result<TaskModel> {
+        if after_id == Some(id) {
+            return self.get(id).await;
+        }
+
+        let query = self.base.statement("move", || {
+            format!(
+                r#"
+                UPDATE {}
+                SET position = ?, updated_at = {NOW}
+                WHERE id = ?
+                RETURNING {}
+                "#,
+                self.base.table,
+                self.base.columns()
+            )
+        });
+
+        let mut tx = self.base.pool.begin().await?;
+        let (before, after) = self.neighbours(&mut tx, id, after_id).await?;
+        let position = match position_between(before, after) {
+            Some(position) => position,
+            None => {
+                self.renumber_positions(&mut tx).await?;
+                let (before, after) = self.neighbours(&mut tx, id, after_id).await?;
+                position_between(before, after)
+                    .ok_or_else(|| anyhow!("No room to move task {}", id))?
+            }
+        };
+        let task = sqlx::query_as::<_, TaskModel>(&query)
+            .bind(position)
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    async fn delete(&self, id: i64) -> Result<bool> {
+        self.base.delete_by_id(id).await
+    }
+
+    async fn delete_returning(&self, id: i64) -> Result<Option<TaskModel>> {
+        let select = self.base.statement("delete_returning_select", || {
+            format!(
+                "SELECT {} FROM {} WHERE id = ?",
+                self.base.columns(),
+                self.base.table
+            )
+        });
+        let delete = self.base.statement("delete_returning", || {
+            format!("DELETE FROM {} WHERE id = ?", self.base.table)
+        });
+
+        let mut tx = self.base.pool.begin().await?;
+        let Some(task) = sqlx::query_as::<_, TaskModel>(&select)
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            return Ok(None);
+        };
+        sqlx::query(&delete).bind(id).execute(&mut *tx).await?;
+        tx.commit().await?;
+
+        Ok(Some(task))
+    }
+
+    async fn delete_all(&self) -> Result<u64> {
+        let query = self
+            .base
+            .statement("delete_all", || format!("DELETE FROM {}", self.base.table));
+        let clear_history = self.base.statement("clear_history", || {
+            format!("DELETE FROM {}", self.base.related_table("task_audit"))
+        });
+        let mut tx = self.base.pool.begin().await?;
+
+        let result = sqlx::query(&query).execute(&mut *tx).await?;
+        sqlx::query(&clear_history).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM sqlite_sequence WHERE name = ?")
+            .bind(&self.base.table)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn archive_completed(&self) -> Result<u64> {
+        let archive = self.base.statement("archive_completed", || {
+            format!(
+                r#"
+                INSERT INTO {} (id, title, description, completed, status, assigned_user_id,
+                    created_at, updated_at, completed_at, position, tags, archived_at)
+                SELECT id, title, description, completed, status, assigned_user_id,
+                    created_at, updated_at, completed_at, position, tags, {NOW}
+                FROM (SELECT {} FROM {} WHERE completed = 1)
+                "#,
+                self.base.related_table("tasks_archive"),
+                self.base.columns(),
+                self.base.table
+            )
+        });
+        let delete = self.base.statement("delete_completed", || {
+            format!("DELETE FROM {} WHERE completed = 1", self.base.table)
+        });
+        let mut tx = self.base.pool.begin().await?;
+
+        sqlx::query(&archive).execute(&mut *tx).await?;
+        let result = sqlx::query(&delete).execute(&mut *tx).await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn list_archived(&self, page: Page) -> Result<Vec<ArchivedTask>> {
+        let query = self.base.statement("list_archived", || {
+            format!(
+                "SELECT * FROM {} ORDER BY archived_at DESC, id DESC LIMIT ? OFFSET ?",
+                self.base.related_table("tasks_archive")
+            )
+        });
+        let tasks = sqlx::query_as::<_, ArchivedTask>(&query)
+            .bind(page.sql_limit())
+            .bind(page.sql_offset())
+            .fetch_all(&self.base.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    async fn duplicates(&self) -> Result<Vec<DuplicateTasks>> {
+        let query = self.base.statement("duplicates", || {
+            format!(
+                r#"
+                SELECT {columns}, {TITLE_KEY} AS title_key FROM {table}
+                WHERE {TITLE_KEY} IN (
+                    SELECT {TITLE_KEY} FROM {table} GROUP BY {TITLE_KEY} HAVING COUNT(*) > 1
+                )
+                ORDER BY title_key, id
+                "#,
+                columns = self.base.columns(),
+                table = self.base.table,
+            )
+        });
+        let rows = sqlx::query_as::<_, KeyedTask>(&query)
+            .fetch_all(&self.base.pool)
+            .await?;
+
+        Ok(group_duplicates(rows))
+    }
+
+    async fn count_by_status(&self) -> Result<TaskStatusCounts> {
+        let query = self.base.statement("count_by_status", || {
+            format!(
+                "SELECT status, COUNT(*) FROM {} GROUP BY status",
+                self.base.table
+            )
+        });
+        let rows = sqlx::query_as::<_, (String, i64)>(&query)
+            .fetch_all(&self.base.read_pool)
+            .await?;
+
+        TaskStatusCounts::from_rows(rows)
+    }
+
+    async fn changes_since(&self, cursor: Option<i64>) -> Result<TaskChanges> {
+        let latest = self.base.statement("sync_latest", || {
+            format!(
+                "SELECT COALESCE(MAX(id), 0) FROM {}",
+                self.base.related_table("task_audit")
+            )
+        });
+        let entry_exists = self.base.statement("sync_entry_exists", || {
+            format!(
+                "SELECT EXISTS (SELECT 1 FROM {} WHERE id = ?)",
+                self.base.related_table("task_audit")
+            )
+        });
+        let changed = self.base.statement("sync_changed", || {
+            format!(
+                r#"
+                SELECT {} FROM {}
+                WHERE id IN (SELECT task_id FROM {} WHERE id > ? AND id <= ?)
+                ORDER BY updated_at, id
+                "#,
+                self.base.columns(),
+                self.base.table,
+                self.base.related_table("task_audit")
+            )
+        });
+        let deleted = self.base.statement("sync_deleted", || {
+            format!(
+                r#"
+                SELECT DISTINCT task_id FROM {}
+                WHERE id > ? AND id <= ? AND task_id NOT IN (SELECT id FROM {})
+                ORDER BY task_id
+                "#,
+                self.base.related_table("task_audit"),
+                self.base.table
+            )
+        });
+        let snapshot = self.base.statement("stream_all", || {
+            format!(
+                "SELECT {} FROM {} ORDER BY id",
+                self.base.columns(),
+                self.base.table
+            )
+        });
+
+        // One read transaction, so the rows match the cursor handed back
+        let mut tx = self.base.pool.begin().await?;
+        let (latest,): (i64,) = sqlx::query_as(&latest).fetch_one(&mut *tx).await?;
+
+        let since = match cursor {
+            Some(cursor) => {
+                let (exists,): (bool,) = sqlx::query_as(&entry_exists)
+                    .bind(cursor)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                exists.then_some(cursor)
+            }
+            None => None,
+        };
+        let Some(since) = since else {
+            let tasks = sqlx::query_as::<_, TaskModel>(&snapshot)
+                .fetch_all(&mut *tx)
+                .await?;
+            return Ok(TaskChanges {
+                tasks,
+                deleted: Vec::new(),
+                cursor: latest,
+                reset: true,
+            });
+        };
+
+        let tasks = sqlx::query_as::<_, TaskModel>(&changed)
+            .bind(since)
+            .bind(latest)
+            .fetch_all(&mut *tx)
+            .await?;
+        let deleted: Vec<(i64,)> = sqlx::query_as(&deleted)
+            .bind(since)
+            .bind(latest)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        Ok(TaskChanges {
+            tasks,
+            deleted: deleted.into_iter().map(|(id,)| id).collect(),
+            cursor: latest,
+            reset: false,
+        })
+    }
+
+    async fn reassign(&self, from: i64, to: i64) -> Result<u64> {
+        let find_user = self.base.statement("find_user", || {
+            format!(
+                "SELECT id FROM {} WHERE id = ?",
+                self.base.related_table("users")
+            )
+        });
+        let reassign = self.base.statement("reassign", || {
+            format!(
+                r#"
+                UPDATE {}
+                SET assigned_user_id = ?, updated_at = {NOW}
+                WHERE assigned_user_id = ?
+                "#,
+                self.base.table
+            )
+        });
+        let mut tx = self.base.pool.begin().await?;
+
+        for user_id in [from, to] {
+            let found: Option<(i64,)> = sqlx::query_as(&find_user)
+                .bind(user_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            if found.is_none() {
+                return Err(UnknownUser { user_id }.into());
+            }
+        }
+        if from == to {
+            return Ok(0);
+        }
+
+        let result = sqlx::query(&reassign)
+            .bind(to)
+            .bind(from)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn complete_all(&self) -> Result<u64> {
+        let query = self.base.statement("complete_all", || {
+            format!(
+                r#"
+                UPDATE {}
+                SET completed = 1,
+                    status = 'done',
+                    completed_at = {NOW},
+                    updated_at = {NOW}
+                WHERE status IN ('todo', 'in_progress')
+                "#,
+                self.base.table
+            )
+        });
+        let result = sqlx::query(&query).execute(&self.base.pool).await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn complete_matching(&self, filter: &TaskFilter) -> Result<u64> {
+        let head = self.base.statement("complete_matching", || {
+            format!(
+                "UPDATE {} SET completed = 1, status = 'done', completed_at = {NOW}, updated_at = {NOW}",
+                self.base.table
+            )
+        });
+        let mut query = self.filtered(&head, filter);
+        query.push(" AND status IN ('todo', 'in_progress')");
+        let result = query.build().execute(&self.base.pool).await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup_test_repository() -> SqliteTaskRepository {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        crate::db::MIGRATOR.run(&pool).await.unwrap();
+
+        SqliteTaskRepository::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_create_task() {
+        let repo = setup_test_repository().await;
+
+        let task = repo.create("Test Task", "Test Description").await.unwrap();
+
+        assert_eq!(task.title, "Test Task");
+        assert_eq!(task.description, "Test Description");
+        assert_eq!(task.completed, false);
+        assert!(task.id > 0);
+    }
+
+    #[tokio::test]
+    async fn test_create_returning_meta_rowid_matches_id() {
+        let repo = setup_test_repository().await;
+
+        let (first, first_rowid) = repo.create_returning_meta("One", "").await.unwrap();
+        let (second, second_rowid) = repo.create_returning_meta("Two", "").await.unwrap();
+
+        assert_eq!(first_rowid, first.id);
+        assert_eq!(second_rowid, second.id);
+        assert_eq!(second.id, first.id + 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_task() {
+        let repo = setup_test_repository().await;
+
+        let created = repo.create("Find Me", "Description").await.unwrap();
+        let retrieved = repo.get(created.id).await.unwrap();
+
+        assert_eq!(retrieved.id, created.id);
+        assert_eq!(retrieved.title, "Find Me");
+    }
+
+    #[tokio::test]
+    async fn test_get_many_skips_missing_ids() {
+        let repo = setup_test_repository().await;
+        let first = repo.create("First", "").await.unwrap();
+        let second = repo.create("Second", "").await.unwrap();
+
+        let tasks = repo.get_many(&[second.id, 999, first.id]).await.unwrap();
+
+        let ids: Vec<i64> = tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![first.id, second.id]);
+        assert!(repo.get_many(&[]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_task_not_found() {
+        let repo = setup_test_repository().await;
+
+        let result = repo.get(999).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks() {
+        let repo = setup_test_repository().await;
+
+        let task1 = repo.create("Task 1", "Desc 1").await.unwrap();
+        let task2 = repo.create("Task 2", "Desc 2").await.unwrap();
+
+        let tasks = repo
+            .list(Page::default(), &TaskFilter::default())
+            .await
+            .unwrap();
+
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].id, task2.id);
+        assert_eq!(tasks[1].id, task1.id);
+    }
+
+    #[tokio::test]
+    async fn test_stream_all_yields_every_task_in_id_order() {
+        let repo = setup_test_repository().await;
+        let batch: Vec<(String, String)> = (0..100)
+            .map(|i| (format!("Task {}", i), String::new()))
+            .collect();
+        repo.create_many(&batch).await.unwrap();
+
+        let ids: Vec<i64> = repo
+            .stream_all()
+            .map(|task| task.unwrap().id)
+            .collect()
+            .await;
+
+        assert_eq!(ids, (1..=100).collect::<Vec<i64>>());
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_paginated() {
+        let repo = setup_test_repository().await;
+
+        let task1 = repo.create("Task 1", "Desc 1").await.unwrap();
+        repo.create("Task 2", "Desc 2").await.unwrap();
+
+        let tasks = repo
+            .list(Page::new(Some(1), Some(1)), &TaskFilter::default())
+            .await
+            .unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, task1.id);
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_stable_order_with_equal_timestamps() {
+        let repo = setup_test_repository()
+            .await
+            .with_order("-created_at".parse().unwrap());
+
+        for title in ["First", "Second", "Third"] {
+            sqlx::query(
+                "INSERT INTO tasks (title, description, created_at) VALUES (?, '', '2025-01-01T00:00:00.000Z')",
+            )
+            .bind(title)
+            .execute(&repo.base.pool)
+            .await
+            .unwrap();
+        }
+
+        let first = repo
+            .list(Page::default(), &TaskFilter::default())
+            .await
+            .unwrap();
+        let ids: Vec<i64> = first.iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![3, 2, 1]);
+
+        for _ in 0..5 {
+            let again: Vec<i64> = repo
+                .list(Page::default(), &TaskFilter::default())
+                .await
+                .unwrap()
+                .iter()
+                .map(|task| task.id)
+                .collect();
+            assert_eq!(again, ids);
+        }
+
+        let second_page = repo
+            .list(Page::new(Some(1), Some(1)), &TaskFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(second_page[0].id, 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_configured_order() {
+        let repo = setup_test_repository()
+            .await
+            .with_order("title".parse().unwrap());
+
+        repo.create("Banana", "").await.unwrap();
+        repo.create("Apple", "").await.unwrap();
+
+        let tasks = repo
+            .list(Page::default(), &TaskFilter::default())
+            .await
+            .unwrap();
+
+        assert_eq!(tasks[0].title, "Apple");
+        assert_eq!(tasks[1].title, "Banana");
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_set_tags_sorts_and_dedups() {
+        let repo = setup_test_repository().await;
+        let task = repo.create("Tagged", "").await.unwrap();
+        assert!(task.tags.is_empty());
+
+        let tagged = repo
+            .set_tags(task.id, &tags(&["urgent", "work", "urgent"]))
+            .await
+            .unwrap();
+        assert_eq!(tagged.tags, vec!["urgent", "work"]);
+        assert_eq!(repo.get(task.id).await.unwrap().tags, tagged.tags);
 
-use crate::db::TaskModel;
+        let cleared = repo.set_tags(task.id, &[]).await.unwrap();
+        assert!(cleared.tags.is_empty());
+    }
 
-#[async_trait]
-pub trait TaskRepository: Send + Sync {
-    async fn create(&self, title: &str, description: &str) -> Result<TaskModel>;
-    async fn get(&self, id: i64) -> Result<TaskModel>;
-    async fn list(&self) -> Result<Vec<TaskModel>>;
-    async fn update(
-        &self,
-        id: i64,
-        title: Option<&str>,
-        description: Option<&str>,
-        completed: Option<bool>,
-    ) -> Result<TaskModel>;
-    async fn delete(&self, id: i64) -> Result<bool>;
-}
+    #[tokio::test]
+    async fn test_tags_are_written_with_the_task_or_not_at_all() {
+        let repo = setup_test_repository().await;
+        let created = repo
+            .create_with_tags(None, "Tagged", "", &tags(&["work", "urgent"]))
+            .await
+            .unwrap();
+        assert_eq!(created.tags, vec!["urgent", "work"]);
 
-#[derive(Clone)]
-pub struct SqliteTaskRepository {
-    pool: SqlitePool,
-}
+        sqlx::query(
+            "CREATE TRIGGER reject_tag BEFORE INSERT ON task_tags WHEN NEW.tag = 'rejected' \
+             BEGIN SELECT RAISE(ABORT, 'tag rejected'); END",
+        )
+        .execute(&repo.base.pool)
+        .await
+        .unwrap();
 
-impl SqliteTaskRepository {
-    pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        let result = repo
+            .create_with_tags(None, "Never kept", "", &tags(&["rejected"]))
+            .await;
+        assert!(result.is_err());
+        assert_eq!(repo.count(&TaskFilter::default()).await.unwrap(), 1);
+
+        let update = TaskUpdate {
+            title: Some("Never kept".to_string()),
+            tags: Some(tags(&["rejected"])),
+            ..TaskUpdate::default()
+        };
+        assert!(repo.update_with_tags(created.id, &update).await.is_err());
+        let unchanged = repo.get(created.id).await.unwrap();
+        assert_eq!(unchanged.title, "Tagged");
+        assert_eq!(unchanged.tags, created.tags);
     }
-}
 
-#[async_trait]
-impl TaskRepository for SqliteTaskRepository {
-    async fn create(&self, title: &str, description: &str) -> Result<TaskModel> {
-        let task = sqlx::query_as::<_, TaskModel>(
-            "INSERT INTO tasks (title, description, completed) VALUES (?, ?, 0) RETURNING *",
-        )
-        .bind(title)
-        .bind(description)
-        .fetch_one(&self.pool)
-        .await?;
+    #[tokio::test]
+    async fn test_list_filters_by_tags() {
+        let repo = setup_test_repository().await;
+        let both = repo.create("Both", "").await.unwrap();
+        let work = repo.create("Work", "").await.unwrap();
+        repo.create("Untagged", "").await.unwrap();
+        repo.set_tags(both.id, &tags(&["work", "urgent"]))
+            .await
+            .unwrap();
+        repo.set_tags(work.id, &tags(&["work"])).await.unwrap();
 
-        Ok(task)
+        let single = TaskFilter {
+            tags: tags(&["work"]),
+            ..Default::default()
+        };
+        let mut ids: Vec<i64> = repo
+            .list(Page::default(), &single)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|task| task.id)
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![both.id, work.id]);
+        assert_eq!(repo.count(&single).await.unwrap(), 2);
+
+        let multi = TaskFilter {
+            tags: tags(&["work", "urgent"]),
+            ..Default::default()
+        };
+        let tasks = repo.list(Page::default(), &multi).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, both.id);
+        assert_eq!(repo.count(&multi).await.unwrap(), 1);
     }
 
-    async fn get(&self, id: i64) -> Result<TaskModel> {
-        let task = sqlx::query_as::<_, TaskModel>("SELECT * FROM tasks WHERE id = ?")
-            .bind(id)
-            .fetch_one(&self.pool)
-            .await?;
+    #[tokio::test]
+    async fn test_delete_removes_tags() {
+        let repo = setup_test_repository().await;
+        let task = repo.create("Doomed", "").await.unwrap();
+        repo.set_tags(task.id, &tags(&["work"])).await.unwrap();
 
-        Ok(task)
+        assert!(repo.delete(task.id).await.unwrap());
+
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM task_tags")
+            .fetch_one(&repo.base.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
     }
 
-    async fn list(&self) -> Result<Vec<TaskModel>> {
-        let tasks = sqlx::query_as::<_, TaskModel>("SELECT * FROM tasks ORDER BY id DESC")
-            .fetch_all(&self.pool)
-            .await?;
+    #[tokio::test]
+    async fn test_list_updated_since_skips_older_tasks() {
+        let repo = setup_test_repository().await;
+        for (title, updated_at) in [
+            ("Old", "2024-06-01T00:00:00.000Z"),
+            ("Boundary", "2025-01-01T00:00:00.000Z"),
+            ("New", "2025-03-01T12:00:00.000Z"),
+        ] {
+            let task = repo.create(title, "").await.unwrap();
+            sqlx::query("UPDATE tasks SET updated_at = ? WHERE id = ?")
+                .bind(updated_at)
+                .bind(task.id)
+                .execute(&repo.base.pool)
+                .await
+                .unwrap();
+        }
 
-        Ok(tasks)
+        let titles: Vec<String> = repo
+            .list_updated_since("2025-01-01T00:00:00.000Z")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|task| task.title)
+            .collect();
+
+        assert_eq!(titles, vec!["New", "Boundary"]);
     }
 
-    async fn update(
-        &self,
-        id: i64,
-        title: Option<&str>,
-        description: Option<&str>,
-        completed: Option<bool>,
-    ) -> Result<TaskModel> {
-        let existing = self.get(id).await?;
+    #[tokio::test]
+    async fn test_completing_sets_and_reopening_clears_completed_at() {
+        let repo = setup_test_repository().await;
+        let task = repo.create("Ship it", "").await.unwrap();
+        assert_eq!(task.completed_at, None);
 
-        let new_title = title.unwrap_or(&existing.title);
-        let new_description = description.unwrap_or(&existing.description);
-        let new_completed = completed.unwrap_or(existing.completed);
+        let completed = repo.toggle(task.id).await.unwrap();
+        let completed_at = completed.completed_at.clone().unwrap();
+        assert!(completed_at >= task.created_at);
 
-        let task = sqlx::query_as::<_, TaskModel>(
-            "UPDATE tasks SET title = ?, description = ?, completed = ? WHERE id = ? RETURNING *",
-        )
-        .bind(new_title)
-        .bind(new_description)
-        .bind(new_completed)
-        .bind(id)
-        .fetch_one(&self.pool)
-        .await?;
+        // Other edits leave the completion time alone
+        let renamed = repo
+            .update(task.id, Some("Shipped"), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(renamed.completed_at, Some(completed_at));
 
-        Ok(task)
+        let reopened = repo.toggle(task.id).await.unwrap();
+        assert_eq!(reopened.completed_at, None);
+
+        repo.complete_all().await.unwrap();
+        assert!(repo.get(task.id).await.unwrap().completed_at.is_some());
     }
 
-    async fn delete(&self, id: i64) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM tasks WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
+    #[tokio::test]
+    async fn test_list_completed_between_is_inclusive() {
+        let repo = setup_test_repository().await;
+        repo.create("Pending", "").await.unwrap();
+        for (title, completed_at) in [
+            ("Before", "2024-12-31T23:59:59.999Z"),
+            ("Start", "2025-01-06T00:00:00.000Z"),
+            ("Midweek", "2025-01-08T12:00:00.000Z"),
+            ("End", "2025-01-12T23:59:59.999Z"),
+            ("After", "2025-01-13T00:00:00.000Z"),
+        ] {
+            let task = repo.create(title, "").await.unwrap();
+            sqlx::query(
+                "UPDATE tasks SET completed = 1, status = 'done', completed_at = ? WHERE id = ?",
+            )
+            .bind(completed_at)
+            .bind(task.id)
+            .execute(&repo.base.pool)
+            .await
+            .unwrap();
+        }
 
-        Ok(result.rows_affected() > 0)
+        let titles: Vec<String> = repo
+            .list_completed_between("2025-01-06T00:00:00.000Z", "2025-01-12T23:59:59.999Z")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|task| task.title)
+            .collect();
+
+        assert_eq!(titles, vec!["Start", "Midweek", "End"]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_update_with_identical_values_keeps_updated_at() {
+        let repo = setup_test_repository().await;
+        let task = repo.create("Same", "Unchanged").await.unwrap();
+        sqlx::query("UPDATE tasks SET updated_at = '2025-01-01T00:00:00.000Z' WHERE id = ?")
+            .bind(task.id)
+            .execute(&repo.base.pool)
+            .await
+            .unwrap();
 
-    async fn setup_test_repository() -> SqliteTaskRepository {
+        let updated = repo
+            .update(
+                task.id,
+                Some("Same"),
+                Some("Unchanged"),
+                Some(TaskStatus::Todo),
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(updated.updated_at, "2025-01-01T00:00:00.000Z");
+        assert_eq!(
+            repo.get(task.id).await.unwrap().updated_at,
+            updated.updated_at
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_table_prefix() {
         let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let prefix = TablePrefix::new("tenantA_").unwrap();
+        crate::db::migrate_prefixed(&pool, &prefix).await.unwrap();
+        let repo = SqliteTaskRepository::new(pool).with_table_prefix(&prefix);
+
+        let task = repo.create("Tenant task", "").await.unwrap();
+        repo.update(task.id, None, None, Some(TaskStatus::Done), None)
+            .await
+            .unwrap();
+        repo.set_tags(task.id, &["tenant".to_string()])
+            .await
+            .unwrap();
 
+        let filter = TaskFilter {
+            tags: vec!["tenant".to_string()],
+            ..Default::default()
+        };
+        let tasks = repo.list(Page::default(), &filter).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert!(tasks[0].completed);
+        assert_eq!(tasks[0].tags, vec!["tenant"]);
+
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tenantA_tasks")
+            .fetch_one(&repo.base.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        assert!(repo.delete(task.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_create_many_rolls_back_on_failure() {
+        let repo = setup_test_repository().await;
         sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS tasks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                title TEXT NOT NULL,
-                description TEXT NOT NULL,
-                completed BOOLEAN NOT NULL DEFAULT 0
-            )
-            "#,
+            "CREATE TRIGGER reject_bad BEFORE INSERT ON tasks WHEN NEW.title = 'bad' BEGIN SELECT RAISE(ABORT, 'rejected'); END",
         )
-        .execute(&pool)
+        .execute(&repo.base.pool)
         .await
         .unwrap();
 
-        SqliteTaskRepository::new(pool)
+        let batch = vec![
+            ("One".to_string(), String::new()),
+            ("bad".to_string(), String::new()),
+        ];
+        assert!(repo.create_many(&batch).await.is_err());
+        assert_eq!(repo.count(&TaskFilter::default()).await.unwrap(), 0);
+
+        let created = repo.create_many(&batch[..1]).await.unwrap();
+        assert_eq!(created.len(), 1);
     }
 
     #[tokio::test]
-    async fn test_create_task() {
+    async fn test_delete_all_resets_ids() {
         let repo = setup_test_repository().await;
+        repo.create("One", "").await.unwrap();
+        repo.create("Two", "").await.unwrap();
 
-        let task = repo.create("Test Task", "Test Description").await.unwrap();
+        assert_eq!(repo.delete_all().await.unwrap(), 2);
+        assert_eq!(repo.count(&TaskFilter::default()).await.unwrap(), 0);
 
-        assert_eq!(task.title, "Test Task");
-        assert_eq!(task.description, "Test Description");
-        assert_eq!(task.completed, false);
-        assert!(task.id > 0);
+        let task = repo.create("Fresh", "").await.unwrap();
+        assert_eq!(task.id, 1);
+    }
+
+    async fn listed_ids(repo: &SqliteTaskRepository) -> Vec<i64> {
+        repo.list(Page::default(), &TaskFilter::default())
+            .await
+            .unwrap()
+            .iter()
+            .map(|task| task.id)
+            .collect()
     }
 
     #[tokio::test]
-    async fn test_get_task() {
+    async fn test_move_after_reorders_list() {
         let repo = setup_test_repository().await;
+        let a = repo.create("a", "").await.unwrap().id;
+        let b = repo.create("b", "").await.unwrap().id;
+        let c = repo.create("c", "").await.unwrap().id;
+        let d = repo.create("d", "").await.unwrap().id;
+        // Unmoved tasks keep the newest-first order
+        assert_eq!(listed_ids(&repo).await, vec![d, c, b, a]);
 
-        let created = repo.create("Find Me", "Description").await.unwrap();
-        let retrieved = repo.get(created.id).await.unwrap();
+        repo.move_after(a, None).await.unwrap();
+        assert_eq!(listed_ids(&repo).await, vec![a, d, c, b]);
+        repo.move_after(d, Some(b)).await.unwrap();
+        assert_eq!(listed_ids(&repo).await, vec![a, c, b, d]);
+        repo.move_after(b, Some(a)).await.unwrap();
+        assert_eq!(listed_ids(&repo).await, vec![a, b, c, d]);
 
-        assert_eq!(retrieved.id, created.id);
-        assert_eq!(retrieved.title, "Find Me");
+        let err = repo.move_after(a, Some(999)).await.unwrap_err();
+        assert!(err.is::<UnknownTask>());
+        assert_eq!(listed_ids(&repo).await, vec![a, b, c, d]);
     }
 
     #[tokio::test]
-    async fn test_get_task_not_found() {
+    async fn test_move_after_renumbers_when_out_of_room() {
         let repo = setup_test_repository().await;
+        let first = repo.create("first", "").await.unwrap().id;
+        let second = repo.create("second", "").await.unwrap().id;
+        let third = repo.create("third", "").await.unwrap().id;
+        repo.move_after(first, None).await.unwrap();
 
-        let result = repo.get(999).await;
+        // Each move halves the gap after `first`, so this runs out of room
+        for _ in 0..100 {
+            repo.move_after(second, Some(first)).await.unwrap();
+            repo.move_after(third, Some(first)).await.unwrap();
+        }
+        assert_eq!(listed_ids(&repo).await, vec![first, third, second]);
+    }
 
-        assert!(result.is_err());
+    #[test]
+    fn test_position_between() {
+        assert_eq!(position_between(None, None), Some(0.0));
+        assert_eq!(position_between(None, Some(3.0)), Some(2.0));
+        assert_eq!(position_between(Some(3.0), None), Some(4.0));
+        assert_eq!(position_between(Some(1.0), Some(2.0)), Some(1.5));
+        assert_eq!(position_between(Some(1.0), Some(1.0)), None);
+        let next = f64::from_bits(1.0f64.to_bits() + 1);
+        assert_eq!(position_between(Some(1.0), Some(next)), None);
+    }
+
+    #[test]
+    fn test_parse_task_order() {
+        assert_eq!(
+            "-created_at".parse::<TaskOrder>().unwrap(),
+            TaskOrder {
+                key: TaskSortKey::CreatedAt,
+                descending: true
+            }
+        );
+        assert_eq!(
+            "title".parse::<TaskOrder>().unwrap().sql(),
+            "title ASC, id ASC"
+        );
+        assert_eq!("-id".parse::<TaskOrder>().unwrap().sql(), "id DESC");
+        assert_eq!(TaskOrder::default().to_string(), "position");
+        assert!("id; DROP TABLE tasks".parse::<TaskOrder>().is_err());
     }
 
     #[tokio::test]
-    async fn test_list_tasks() {
+    async fn test_filtered_list_never_interpolates_caller_values() {
         let repo = setup_test_repository().await;
+        let task = repo.create("One", "").await.unwrap();
+        repo.set_tags(task.id, &["work".to_string()]).await.unwrap();
 
-        let task1 = repo.create("Task 1", "Desc 1").await.unwrap();
-        let task2 = repo.create("Task 2", "Desc 2").await.unwrap();
+        // Sort values only get into SQL as a parsed TaskOrder
+        assert!("title; DROP TABLE tasks; --".parse::<TaskOrder>().is_err());
+        assert!("-created_at, (SELECT 1)".parse::<TaskOrder>().is_err());
 
-        let tasks = repo.list().await.unwrap();
+        let filter = TaskFilter {
+            tags: vec!["work' OR '1'='1".to_string()],
+            order: Some("-title".parse().unwrap()),
+            ..Default::default()
+        };
+        assert!(repo
+            .list(Page::default(), &filter)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(repo.count(&filter).await.unwrap(), 0);
+        assert_eq!(repo.count(&TaskFilter::default()).await.unwrap(), 1);
+    }
 
-        assert_eq!(tasks.len(), 2);
-        assert_eq!(tasks[0].id, task2.id);
-        assert_eq!(tasks[1].id, task1.id);
+    #[tokio::test]
+    async fn test_update_task_bumps_updated_at() {
+        let repo = setup_test_repository().await;
+
+        let task = repo.create("Original", "Desc").await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let updated = repo
+            .update(task.id, Some("Updated"), None, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(updated.created_at, task.created_at);
+        assert!(updated.updated_at > task.updated_at);
+    }
+
+    #[tokio::test]
+    async fn test_update_if_version_refuses_stale_version() {
+        let repo = setup_test_repository().await;
+        let task = repo.create("Original", "Desc").await.unwrap();
+
+        let err = repo
+            .update_if_version(
+                task.id,
+                "2000-01-01T00:00:00.000Z",
+                Some("Lost"),
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.is::<VersionMismatch>());
+        assert_eq!(repo.get(task.id).await.unwrap().title, "Original");
+
+        let updated = repo
+            .update_if_version(task.id, &task.updated_at, Some("Updated"), None, None)
+            .await
+            .unwrap();
+        assert_eq!(updated.title, "Updated");
     }
 
     #[tokio::test]
@@ -173,7 +2043,7 @@ mod tests {
 
         let task = repo.create("Original", "Original Desc").await.unwrap();
         let updated = repo
-            .update(task.id, Some("Updated"), None, Some(true))
+            .update(task.id, Some("Updated"), None, Some(TaskStatus::Done), None)
             .await
             .unwrap();
 
@@ -182,6 +2052,243 @@ mod tests {
         assert_eq!(updated.completed, true);
     }
 
+    #[tokio::test]
+    async fn test_status_transitions_keep_completed_in_step() {
+        let repo = setup_test_repository().await;
+        let task = repo.create("Ship it", "").await.unwrap();
+        assert_eq!(task.status, TaskStatus::Todo);
+
+        let started = repo
+            .update(task.id, None, None, Some(TaskStatus::InProgress), None)
+            .await
+            .unwrap();
+        assert!(!started.completed && started.completed_at.is_none());
+
+        let done = repo
+            .update(task.id, None, None, Some(TaskStatus::Done), None)
+            .await
+            .unwrap();
+        assert!(done.completed && done.completed_at.is_some());
+
+        let cancelled = repo
+            .update(task.id, None, None, Some(TaskStatus::Cancelled), None)
+            .await
+            .unwrap();
+        assert!(!cancelled.completed && cancelled.completed_at.is_none());
+
+        // Toggling anything but a done task completes it
+        assert_eq!(repo.toggle(task.id).await.unwrap().status, TaskStatus::Done);
+        assert_eq!(repo.toggle(task.id).await.unwrap().status, TaskStatus::Todo);
+
+        repo.complete_all().await.unwrap();
+        let filter = TaskFilter {
+            status: Some(TaskStatus::Done),
+            ..Default::default()
+        };
+        assert_eq!(repo.count(&filter).await.unwrap(), 1);
+        assert!(repo.get(task.id).await.unwrap().completed);
+    }
+
+    #[tokio::test]
+    async fn test_history_records_each_change() {
+        let repo = setup_test_repository().await;
+        let task = repo.create("Original", "Desc").await.unwrap();
+        repo.update(task.id, Some("Renamed"), None, None, None)
+            .await
+            .unwrap();
+        // A no-op update writes nothing, so it records nothing
+        repo.update(task.id, Some("Renamed"), None, None, None)
+            .await
+            .unwrap();
+        repo.delete(task.id).await.unwrap();
+
+        let history = repo.history(task.id).await.unwrap();
+        let ops: Vec<&str> = history.iter().map(|entry| entry.op.as_str()).collect();
+        assert_eq!(ops, vec!["create", "update", "delete"]);
+
+        let update = &history[1];
+        let old: serde_json::Value =
+            serde_json::from_str(update.old_json.as_deref().unwrap()).unwrap();
+        let new: serde_json::Value =
+            serde_json::from_str(update.new_json.as_deref().unwrap()).unwrap();
+        assert_eq!(old["title"], "Original");
+        assert_eq!(new["title"], "Renamed");
+        assert_eq!(new["completed"], false);
+        assert!(history[0].old_json.is_none());
+        assert!(history[2].new_json.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_group_by_normalized_title() {
+        let repo = setup_test_repository().await;
+        let first = repo.create("Buy milk", "").await.unwrap();
+        repo.create("Unique", "").await.unwrap();
+        let second = repo.create("BUY MILK", "").await.unwrap();
+
+        let groups = repo.duplicates().await.unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].title, "buy milk");
+        let ids: Vec<i64> = groups[0].tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![first.id, second.id]);
+    }
+
+    #[tokio::test]
+    async fn test_count_by_status() {
+        let repo = setup_test_repository().await;
+        assert_eq!(
+            repo.count_by_status().await.unwrap(),
+            TaskStatusCounts::default()
+        );
+
+        for status in [
+            None,
+            None,
+            Some(TaskStatus::InProgress),
+            Some(TaskStatus::Done),
+        ] {
+            let task = repo.create("Task", "").await.unwrap();
+            repo.update(task.id, None, None, status, None)
+                .await
+                .unwrap();
+        }
+
+        let counts = repo.count_by_status().await.unwrap();
+        assert_eq!(
+            counts,
+            TaskStatusCounts {
+                todo: 2,
+                in_progress: 1,
+                done: 1,
+                cancelled: 0
+            }
+        );
+        assert_eq!((counts.completed(), counts.pending()), (1, 3));
+    }
+
+    #[tokio::test]
+    async fn test_changes_since_cursor() {
+        let repo = setup_test_repository().await;
+        let kept = repo.create("Kept", "").await.unwrap();
+        let gone = repo.create("Gone", "").await.unwrap();
+
+        let full = repo.changes_since(None).await.unwrap();
+        assert!(full.reset);
+        assert_eq!(full.tasks.len(), 2);
+
+        repo.update(kept.id, Some("Edited"), None, None, None)
+            .await
+            .unwrap();
+        repo.delete(gone.id).await.unwrap();
+
+        let changes = repo.changes_since(Some(full.cursor)).await.unwrap();
+        assert!(!changes.reset);
+        assert_eq!(changes.tasks.len(), 1);
+        assert_eq!(changes.tasks[0].title, "Edited");
+        assert_eq!(changes.deleted, vec![gone.id]);
+        assert!(changes.cursor > full.cursor);
+
+        let unchanged = repo.changes_since(Some(changes.cursor)).await.unwrap();
+        assert!(unchanged.tasks.is_empty() && unchanged.deleted.is_empty());
+        assert_eq!(unchanged.cursor, changes.cursor);
+
+        // delete_all clears the history the cursor points into
+        repo.delete_all().await.unwrap();
+        let reset = repo.changes_since(Some(changes.cursor)).await.unwrap();
+        assert!(reset.reset);
+        assert!(reset.tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_toggle_task() {
+        let repo = setup_test_repository().await;
+
+        let task = repo.create("Toggle Me", "Description").await.unwrap();
+
+        let toggled = repo.toggle(task.id).await.unwrap();
+        assert!(toggled.completed);
+
+        let toggled_back = repo.toggle(task.id).await.unwrap();
+        assert!(!toggled_back.completed);
+        assert_eq!(toggled_back.title, "Toggle Me");
+    }
+
+    #[tokio::test]
+    async fn test_toggle_task_not_found() {
+        let repo = setup_test_repository().await;
+
+        assert!(repo.toggle(999).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_complete_all_tasks() {
+        let repo = setup_test_repository().await;
+
+        let done = repo.create("Done", "Desc").await.unwrap();
+        repo.update(done.id, None, None, Some(TaskStatus::Done), None)
+            .await
+            .unwrap();
+        repo.create("Pending 1", "Desc").await.unwrap();
+        repo.create("Pending 2", "Desc").await.unwrap();
+
+        let changed = repo.complete_all().await.unwrap();
+
+        assert_eq!(changed, 2);
+        let tasks = repo
+            .list(Page::default(), &TaskFilter::default())
+            .await
+            .unwrap();
+        assert!(tasks.iter().all(|task| task.completed));
+        assert_eq!(repo.complete_all().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_complete_matching_by_user_and_tag() {
+        let repo = setup_test_repository().await;
+        sqlx::query("INSERT INTO users (id, name, email) VALUES (3, 'Ada', 'ada@example.com')")
+            .execute(&repo.base.pool)
+            .await
+            .unwrap();
+        let mine = repo.create_for_user(3, "Mine", "").await.unwrap();
+        let mine_work = repo.create_for_user(3, "Mine at work", "").await.unwrap();
+        repo.set_tags(mine_work.id, &["work".to_string()])
+            .await
+            .unwrap();
+        let work = repo.create("Work", "").await.unwrap();
+        repo.set_tags(work.id, &["work".to_string()]).await.unwrap();
+        let other = repo.create("Other", "").await.unwrap();
+
+        let both = TaskFilter {
+            assigned_user_id: Some(3),
+            tags: vec!["work".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(repo.complete_matching(&both).await.unwrap(), 1);
+        let by_user = TaskFilter {
+            assigned_user_id: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(repo.complete_matching(&by_user).await.unwrap(), 1);
+        let by_tag = TaskFilter {
+            tags: vec!["work".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(repo.complete_matching(&by_tag).await.unwrap(), 1);
+
+        for id in [mine.id, mine_work.id, work.id] {
+            let task = repo.get(id).await.unwrap();
+            assert!(task.completed && task.completed_at.is_some());
+        }
+        assert!(!repo.get(other.id).await.unwrap().completed);
+        assert_eq!(repo.count(&by_user).await.unwrap(), 2);
+        let pending = TaskFilter {
+            completed: Some(false),
+            ..Default::default()
+        };
+        let pending = repo.list(Page::default(), &pending).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, other.id);
+    }
+
     #[tokio::test]
     async fn test_delete_task() {
         let repo = setup_test_repository().await;
@@ -194,4 +2301,42 @@ mod tests {
         let result = repo.get(task.id).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_delete_returning() {
+        let repo = setup_test_repository().await;
+        let task = repo.create("Delete Me", "Description").await.unwrap();
+
+        let deleted = repo.delete_returning(task.id).await.unwrap().unwrap();
+
+        assert_eq!(deleted.id, task.id);
+        assert_eq!(deleted.title, "Delete Me");
+        assert!(repo.get(task.id).await.is_err());
+        assert!(repo.delete_returning(task.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_archive_completed_moves_only_completed_tasks() {
+        let repo = setup_test_repository().await;
+        let pending = repo.create("Pending", "Still open").await.unwrap();
+        let done = repo.create("Done", "Finished").await.unwrap();
+        repo.set_tags(done.id, &tags(&["b", "a"])).await.unwrap();
+        repo.update(done.id, None, None, Some(TaskStatus::Done), None)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.archive_completed().await.unwrap(), 1);
+
+        assert_eq!(listed_ids(&repo).await, vec![pending.id]);
+        let archived = repo.list_archived(Page::default()).await.unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].task.id, done.id);
+        assert_eq!(archived[0].task.title, "Done");
+        assert!(archived[0].task.completed);
+        assert!(archived[0].task.completed_at.is_some());
+        assert_eq!(archived[0].task.tags, tags(&["a", "b"]));
+
+        assert_eq!(repo.archive_completed().await.unwrap(), 0);
+        assert_eq!(repo.list_archived(Page::default()).await.unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,391 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+
+use super::{
+    ArchivedTask, DeleteUserPolicy, DuplicateTasks, Page, TaskChanges, TaskFilter, TaskRepository,
+    TaskStatusCounts, TaskUpdate, UserRepository, UserSummary, UserTaskCounts,
+};
+use crate::db::{TaskAuditModel, TaskModel, TaskStatus, UserModel};
+
+/// Times repository calls and warns about the ones that take at least
+/// `threshold`.
+struct SlowQueryLog {
+    repository: &'static str,
+    threshold: Duration,
+    slow_calls: AtomicUsize,
+}
+
+impl SlowQueryLog {
+    fn new(repository: &'static str, threshold: Duration) -> Self {
+        Self {
+            repository,
+            threshold,
+            slow_calls: AtomicUsize::new(0),
+        }
+    }
+
+    async fn time<T>(&self, method: &'static str, call: impl Future<Output = T>) -> T {
+        let started = Instant::now();
+        let result = call.await;
+        let elapsed = started.elapsed();
+        if elapsed >= self.threshold {
+            self.slow_calls.fetch_add(1, Ordering::SeqCst);
+            tracing::warn!(
+                repository = self.repository,
+                method,
+                elapsed_ms = elapsed.as_millis() as u64,
+                threshold_ms = self.threshold.as_millis() as u64,
+                "slow query"
+            );
+        }
+        result
+    }
+}
+
+/// Logs a warning for every call to the wrapped task repository that takes
+/// at least `threshold`, with the method name and elapsed time. `stream_all`
+/// is passed through untimed, since its rows arrive after it returns.
+pub struct TimedTaskRepository {
+    inner: Arc<dyn TaskRepository>,
+    log: SlowQueryLog,
+}
+
+impl TimedTaskRepository {
+    pub fn new(inner: Arc<dyn TaskRepository>, threshold: Duration) -> Self {
+        Self {
+            inner,
+            log: SlowQueryLog::new("tasks", threshold),
+        }
+    }
+
+    /// Number of calls so far that crossed the threshold.
+    pub fn slow_calls(&self) -> usize {
+        self.log.slow_calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl TaskRepository for TimedTaskRepository {
+    async fn create(&self, title: &str, description: &str) -> Result<TaskModel> {
+        self.log
+            .time("create", self.inner.create(title, description))
+            .await
+    }
+
+    async fn create_for_user(
+        &self,
+        user_id: i64,
+        title: &str,
+        description: &str,
+    ) -> Result<TaskModel> {
+        self.log
+            .time(
+                "create_for_user",
+                self.inner.create_for_user(user_id, title, description),
+            )
+            .await
+    }
+
+    async fn get(&self, id: i64) -> Result<TaskModel> {
+        self.log.time("get", self.inner.get(id)).await
+    }
+
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<TaskModel>> {
+        self.log.time("get_many", self.inner.get_many(ids)).await
+    }
+
+    async fn create_with_tags(
+        &self,
+        assigned_user_id: Option<i64>,
+        title: &str,
+        description: &str,
+        tags: &[String],
+    ) -> Result<TaskModel> {
+        self.log
+            .time(
+                "create_with_tags",
+                self.inner
+                    .create_with_tags(assigned_user_id, title, description, tags),
+            )
+            .await
+    }
+
+    async fn create_returning_meta(
+        &self,
+        title: &str,
+        description: &str,
+    ) -> Result<(TaskModel, i64)> {
+        self.log
+            .time(
+                "create_returning_meta",
+                self.inner.create_returning_meta(title, description),
+            )
+            .await
+    }
+
+    async fn create_many(&self, tasks: &[(String, String)]) -> Result<Vec<TaskModel>> {
+        self.log
+            .time("create_many", self.inner.create_many(tasks))
+            .await
+    }
+
+    async fn list(&self, page: Page, filter: &TaskFilter) -> Result<Vec<TaskModel>> {
+        self.log.time("list", self.inner.list(page, filter)).await
+    }
+
+    fn stream_all(&self) -> BoxStream<'static, Result<TaskModel>> {
+        self.inner.stream_all()
+    }
+
+    async fn list_updated_since(&self, since: &str) -> Result<Vec<TaskModel>> {
+        self.log
+            .time("list_updated_since", self.inner.list_updated_since(since))
+            .await
+    }
+
+    async fn list_completed_between(&self, from: &str, to: &str) -> Result<Vec<TaskModel>> {
+        self.log
+            .time(
+                "list_completed_between",
+                self.inner.list_completed_between(from, to),
+            )
+            .await
+    }
+
+    async fn count(&self, filter: &TaskFilter) -> Result<i64> {
+        self.log.time("count", self.inner.count(filter)).await
+    }
+
+    async fn history(&self, id: i64) -> Result<Vec<TaskAuditModel>> {
+        self.log.time("history", self.inner.history(id)).await
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+        assigned_user_id: Option<Option<i64>>,
+    ) -> Result<TaskModel> {
+        self.log
+            .time(
+                "update",
+                self.inner
+                    .update(id, title, description, status, assigned_user_id),
+            )
+            .await
+    }
+
+    async fn update_if_version(
+        &self,
+        id: i64,
+        expected_updated_at: &str,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+    ) -> Result<TaskModel> {
+        self.log
+            .time(
+                "update_if_version",
+                self.inner
+                    .update_if_version(id, expected_updated_at, title, description, status),
+            )
+            .await
+    }
+
+    async fn update_with_tags(&self, id: i64, update: &TaskUpdate) -> Result<TaskModel> {
+        self.log
+            .time("update_with_tags", self.inner.update_with_tags(id, update))
+            .await
+    }
+
+    async fn set_tags(&self, id: i64, tags: &[String]) -> Result<TaskModel> {
+        self.log
+            .time("set_tags", self.inner.set_tags(id, tags))
+            .await
+    }
+
+    async fn move_after(&self, id: i64, after_id: Option<i64>) -> Result<TaskModel> {
+        self.log
+            .time("move_after", self.inner.move_after(id, after_id))
+            .await
+    }
+
+    async fn delete(&self, id: i64) -> Result<bool> {
+        self.log.time("delete", self.inner.delete(id)).await
+    }
+
+    async fn delete_returning(&self, id: i64) -> Result<Option<TaskModel>> {
+        self.log
+            .time("delete_returning", self.inner.delete_returning(id))
+            .await
+    }
+
+    async fn complete_all(&self) -> Result<u64> {
+        self.log
+            .time("complete_all", self.inner.complete_all())
+            .await
+    }
+
+    async fn complete_matching(&self, filter: &TaskFilter) -> Result<u64> {
+        self.log
+            .time("complete_matching", self.inner.complete_matching(filter))
+            .await
+    }
+
+    async fn reassign(&self, from: i64, to: i64) -> Result<u64> {
+        self.log
+            .time("reassign", self.inner.reassign(from, to))
+            .await
+    }
+
+    async fn delete_all(&self) -> Result<u64> {
+        self.log.time("delete_all", self.inner.delete_all()).await
+    }
+
+    async fn archive_completed(&self) -> Result<u64> {
+        self.log
+            .time("archive_completed", self.inner.archive_completed())
+            .await
+    }
+
+    async fn list_archived(&self, page: Page) -> Result<Vec<ArchivedTask>> {
+        self.log
+            .time("list_archived", self.inner.list_archived(page))
+            .await
+    }
+
+    async fn duplicates(&self) -> Result<Vec<DuplicateTasks>> {
+        self.log.time("duplicates", self.inner.duplicates()).await
+    }
+
+    async fn count_by_status(&self) -> Result<TaskStatusCounts> {
+        self.log
+            .time("count_by_status", self.inner.count_by_status())
+            .await
+    }
+
+    async fn changes_since(&self, cursor: Option<i64>) -> Result<TaskChanges> {
+        self.log
+            .time("changes_since", self.inner.changes_since(cursor))
+            .await
+    }
+}
+
+/// User counterpart of [`TimedTaskRepository`].
+pub struct TimedUserRepository {
+    inner: Arc<dyn UserRepository>,
+    log: SlowQueryLog,
+}
+
+impl TimedUserRepository {
+    pub fn new(inner: Arc<dyn UserRepository>, threshold: Duration) -> Self {
+        Self {
+            inner,
+            log: SlowQueryLog::new("users", threshold),
+        }
+    }
+
+    /// Number of calls so far that crossed the threshold.
+    pub fn slow_calls(&self) -> usize {
+        self.log.slow_calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl UserRepository for TimedUserRepository {
+    async fn create(&self, name: &str, email: &str) -> Result<UserModel> {
+        self.log
+            .time("create", self.inner.create(name, email))
+            .await
+    }
+
+    async fn upsert_by_email(&self, name: &str, email: &str) -> Result<(UserModel, bool)> {
+        self.log
+            .time("upsert_by_email", self.inner.upsert_by_email(name, email))
+            .await
+    }
+
+    async fn get(&self, id: i64) -> Result<UserModel> {
+        self.log.time("get", self.inner.get(id)).await
+    }
+
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<UserModel>> {
+        self.log.time("get_many", self.inner.get_many(ids)).await
+    }
+
+    async fn list(&self, page: Page, name: Option<&str>) -> Result<Vec<UserModel>> {
+        self.log.time("list", self.inner.list(page, name)).await
+    }
+
+    async fn count(&self, name: Option<&str>) -> Result<i64> {
+        self.log.time("count", self.inner.count(name)).await
+    }
+
+    async fn update(&self, id: i64, name: Option<&str>, email: Option<&str>) -> Result<UserModel> {
+        self.log
+            .time("update", self.inner.update(id, name, email))
+            .await
+    }
+
+    async fn delete(&self, id: i64, policy: DeleteUserPolicy) -> Result<bool> {
+        self.log.time("delete", self.inner.delete(id, policy)).await
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<UserModel>> {
+        self.log.time("search", self.inner.search(query)).await
+    }
+
+    async fn task_counts(&self) -> Result<Vec<UserTaskCounts>> {
+        self.log.time("task_counts", self.inner.task_counts()).await
+    }
+
+    async fn summary(&self, id: i64) -> Result<UserSummary> {
+        self.log.time("summary", self.inner.summary(id)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{MockTaskRepository, SqliteUserRepository};
+
+    #[tokio::test]
+    async fn test_slow_call_is_reported() {
+        let inner = MockTaskRepository::new()
+            .on_count(|_| Ok(3))
+            .with_latency(Duration::from_millis(20));
+        let repo = TimedTaskRepository::new(Arc::new(inner), Duration::from_millis(10));
+
+        assert_eq!(repo.count(&TaskFilter::default()).await.unwrap(), 3);
+
+        assert_eq!(repo.slow_calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fast_call_is_not_reported() {
+        let pool = crate::db::init_db_with_url("sqlite::memory:")
+            .await
+            .unwrap();
+        let repo = TimedUserRepository::new(
+            Arc::new(SqliteUserRepository::new(pool)),
+            Duration::from_secs(60),
+        );
+
+        let user = repo.create("John Doe", "john@example.com").await.unwrap();
+        assert_eq!(repo.get(user.id).await.unwrap().name, "John Doe");
+
+        assert_eq!(repo.slow_calls(), 0);
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::Result;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+use super::{SqliteTaskRepository, SqliteUserRepository};
+use crate::db::{TablePrefix, TaskModel, UserModel};
+
+/// Starts [`UnitOfWork`]s over the task and user tables, for flows that
+/// must change both or neither. The repository traits each run on their own
+/// pooled connection, so they can't share a transaction themselves.
+#[derive(Clone)]
+pub struct SqliteUnitOfWork {
+    pool: SqlitePool,
+    tasks: SqliteTaskRepository,
+    users: SqliteUserRepository,
+}
+
+impl SqliteUnitOfWork {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            tasks: SqliteTaskRepository::new(pool.clone()),
+            users: SqliteUserRepository::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Uses `{prefix}` tables instead of the unprefixed ones.
+    pub fn with_table_prefix(mut self, prefix: &TablePrefix) -> Self {
+        self.tasks = self.tasks.with_table_prefix(prefix);
+        self.users = self.users.with_table_prefix(prefix);
+        self
+    }
+
+    pub async fn begin(&self) -> Result<UnitOfWork<'_>> {
+        Ok(UnitOfWork {
+            tx: self.pool.begin().await?,
+            repositories: self,
+        })
+    }
+}
+
+/// Repository operations on one open transaction. Nothing is visible to
+/// other connections until [`commit`](Self::commit); dropping it, or
+/// returning early on an error, rolls everything back.
+pub struct UnitOfWork<'a> {
+    tx: Transaction<'static, Sqlite>,
+    repositories: &'a SqliteUnitOfWork,
+}
+
+impl UnitOfWork<'_> {
+    /// See [`UserRepository::create`](super::UserRepository::create).
+    pub async fn create_user(&mut self, name: &str, email: &str) -> Result<UserModel> {
+        self.repositories
+            .users
+            .create_on(&mut self.tx, name, email)
+            .await
+    }
+
+    /// See [`TaskRepository::create_for_user`](super::TaskRepository::create_for_user).
+    /// Users created earlier in this unit of work count as existing.
+    pub async fn create_task_for_user(
+        &mut self,
+        user_id: i64,
+        title: &str,
+        description: &str,
+    ) -> Result<TaskModel> {
+        self.repositories
+            .tasks
+            .create_for_user_on(&mut self.tx, user_id, title, description)
+            .await
+    }
+
+    pub async fn commit(self) -> Result<()> {
+        self.tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn rollback(self) -> Result<()> {
+        self.tx.rollback().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{UnknownUser, UserRepository};
+
+    #[tokio::test]
+    async fn test_failed_step_rolls_back_earlier_ones() {
+        let pool = crate::db::init_db_with_url("sqlite::memory:")
+            .await
+            .unwrap();
+        let units = SqliteUnitOfWork::new(pool.clone());
+
+        let mut work = units.begin().await.unwrap();
+        work.create_user("Ada", "ada@example.com").await.unwrap();
+        let err = work
+            .create_task_for_user(999, "Orphan", "")
+            .await
+            .unwrap_err();
+        assert!(err.is::<UnknownUser>());
+        drop(work);
+
+        let users = SqliteUserRepository::new(pool);
+        assert_eq!(users.count(None).await.unwrap(), 0);
+    }
+}
@@ -1,54 +1,250 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use sqlx::SqlitePool;
+use sqlx::SqliteConnection;
 
+use super::{like_contains, Page, SqliteRepository};
 use crate::db::UserModel;
 
+/// How many tasks are assigned to a user, and how many of those are done.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct UserTaskCounts {
+    pub user_id: i64,
+    pub name: String,
+    pub total: i64,
+    pub completed: i64,
+}
+
+/// A user together with the counts of [`UserTaskCounts`].
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct UserSummary {
+    #[sqlx(flatten)]
+    pub user: UserModel,
+    pub total: i64,
+    pub completed: i64,
+}
+
+/// What deleting a user does to the tasks assigned to them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeleteUserPolicy {
+    /// Refuse to delete a user that still has tasks.
+    #[default]
+    Restrict,
+    /// Unassign the tasks and keep them.
+    Nullify,
+    /// Delete the tasks together with the user.
+    Delete,
+}
+
+/// Returned by [`UserRepository::delete`] under [`DeleteUserPolicy::Restrict`].
+#[derive(Debug)]
+pub struct UserHasTasks {
+    pub user_id: i64,
+    pub task_count: i64,
+}
+
+impl std::fmt::Display for UserHasTasks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "User {} still has {} assigned task(s)",
+            self.user_id, self.task_count
+        )
+    }
+}
+
+impl std::error::Error for UserHasTasks {}
+
+/// Returned by [`UserRepository::create`] and [`UserRepository::update`] when
+/// another user already has the email, compared case-insensitively.
+#[derive(Debug)]
+pub struct EmailTaken;
+
+impl std::fmt::Display for EmailTaken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "email already registered")
+    }
+}
+
+impl std::error::Error for EmailTaken {}
+
+/// Maps a unique violation on `users.email` to [`EmailTaken`].
+pub(crate) fn map_email_conflict(error: sqlx::Error) -> anyhow::Error {
+    match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => EmailTaken.into(),
+        _ => error.into(),
+    }
+}
+
 #[async_trait]
 pub trait UserRepository: Send + Sync {
     async fn create(&self, name: &str, email: &str) -> Result<UserModel>;
+    /// Creates the user, or renames the one already registered with
+    /// `email` (compared case-insensitively, keeping its stored email).
+    /// `true` alongside the user when it was inserted.
+    async fn upsert_by_email(&self, name: &str, email: &str) -> Result<(UserModel, bool)>;
     async fn get(&self, id: i64) -> Result<UserModel>;
-    async fn list(&self) -> Result<Vec<UserModel>>;
+    /// Users with any of `ids`, in id order. Missing ids are skipped.
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<UserModel>>;
+    /// Lists users newest first, optionally keeping only names containing
+    /// `name` (case-insensitive).
+    async fn list(&self, page: Page, name: Option<&str>) -> Result<Vec<UserModel>>;
+    /// Number of users `list` would return without paging.
+    async fn count(&self, name: Option<&str>) -> Result<i64>;
+    /// Users whose name contains `query` (case-insensitive, `%` and `_`
+    /// matched literally), ordered by name.
+    async fn search(&self, query: &str) -> Result<Vec<UserModel>>;
     async fn update(&self, id: i64, name: Option<&str>, email: Option<&str>) -> Result<UserModel>;
-    async fn delete(&self, id: i64) -> Result<bool>;
+    async fn delete(&self, id: i64, policy: DeleteUserPolicy) -> Result<bool>;
+    /// Task counts for every user, including those with no tasks, in id
+    /// order.
+    async fn task_counts(&self) -> Result<Vec<UserTaskCounts>>;
+    /// The user and their task counts in one query. Fails with "no rows"
+    /// when the user doesn't exist.
+    async fn summary(&self, id: i64) -> Result<UserSummary>;
 }
 
-#[derive(Clone)]
-pub struct SqliteUserRepository {
-    pool: SqlitePool,
-}
+pub type SqliteUserRepository = SqliteRepository<UserModel>;
 
 impl SqliteUserRepository {
-    pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+    /// `create` on `conn`, so it can run inside a caller's transaction.
+    pub(crate) async fn create_on(
+        &self,
+        conn: &mut SqliteConnection,
+        name: &str,
+        email: &str,
+    ) -> Result<UserModel> {
+        if self.email_exists(&mut *conn, email).await? {
+            return Err(EmailTaken.into());
+        }
+
+        self.insert(conn, name, email).await
+    }
+
+    async fn email_exists(&self, conn: &mut SqliteConnection, email: &str) -> Result<bool> {
+        let query = self.statement("email_exists", || {
+            format!(
+                "SELECT EXISTS (SELECT 1 FROM {} WHERE email = ? COLLATE NOCASE)",
+                self.table
+            )
+        });
+        let (exists,): (bool,) = sqlx::query_as(&query).bind(email).fetch_one(conn).await?;
+
+        Ok(exists)
+    }
+
+    /// The insert behind `create`, without the pre-check. A concurrent
+    /// create can still win between the check and this insert; the unique
+    /// index then reports the same `EmailTaken`.
+    async fn insert(
+        &self,
+        conn: &mut SqliteConnection,
+        name: &str,
+        email: &str,
+    ) -> Result<UserModel> {
+        let query = self.statement("insert", || {
+            format!(
+                "INSERT INTO {} (name, email) VALUES (?, ?) RETURNING *",
+                self.table
+            )
+        });
+        sqlx::query_as::<_, UserModel>(&query)
+            .bind(name)
+            .bind(email)
+            .fetch_one(conn)
+            .await
+            .map_err(map_email_conflict)
     }
 }
 
 #[async_trait]
 impl UserRepository for SqliteUserRepository {
     async fn create(&self, name: &str, email: &str) -> Result<UserModel> {
-        let user = sqlx::query_as::<_, UserModel>(
-            "INSERT INTO users (name, email) VALUES (?, ?) RETURNING *",
-        )
-        .bind(name)
-        .bind(email)
-        .fetch_one(&self.pool)
-        .await?;
+        let mut conn = self.pool.acquire().await?;
+        self.create_on(&mut conn, name, email).await
+    }
 
-        Ok(user)
+    async fn upsert_by_email(&self, name: &str, email: &str) -> Result<(UserModel, bool)> {
+        // The conflict target is the case-insensitive email index
+        let query = self.statement("upsert_by_email", || {
+            format!(
+                r#"
+                INSERT INTO {} (name, email) VALUES (?, ?)
+                ON CONFLICT (email COLLATE NOCASE) DO UPDATE SET name = excluded.name
+                RETURNING *
+                "#,
+                self.table
+            )
+        });
+        let mut tx = self.pool.begin().await?;
+
+        // A write committed since this read fails the upsert with
+        // SQLITE_BUSY rather than making `inserted` wrong
+        let inserted = !self.email_exists(&mut tx, email).await?;
+        let user = sqlx::query_as::<_, UserModel>(&query)
+            .bind(name)
+            .bind(email)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok((user, inserted))
     }
 
     async fn get(&self, id: i64) -> Result<UserModel> {
-        let user = sqlx::query_as::<_, UserModel>("SELECT * FROM users WHERE id = ?")
-            .bind(id)
+        self.find_by_id(id).await
+    }
+
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<UserModel>> {
+        self.find_many_by_id(ids).await
+    }
+
+    async fn list(&self, page: Page, name: Option<&str>) -> Result<Vec<UserModel>> {
+        let query = self.statement("list", || {
+            format!(
+                r#"
+                SELECT * FROM {}
+                WHERE ?1 IS NULL OR name LIKE ?1 ESCAPE '\'
+                ORDER BY id DESC
+                LIMIT ?2 OFFSET ?3
+                "#,
+                self.table
+            )
+        });
+        let users = sqlx::query_as::<_, UserModel>(&query)
+            .bind(name.map(like_contains))
+            .bind(page.sql_limit())
+            .bind(page.sql_offset())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(users)
+    }
+
+    async fn count(&self, name: Option<&str>) -> Result<i64> {
+        let query = self.statement("count", || {
+            format!(
+                r#"SELECT COUNT(*) FROM {} WHERE ?1 IS NULL OR name LIKE ?1 ESCAPE '\'"#,
+                self.table
+            )
+        });
+        let (count,): (i64,) = sqlx::query_as(&query)
+            .bind(name.map(like_contains))
             .fetch_one(&self.pool)
             .await?;
 
-        Ok(user)
+        Ok(count)
     }
 
-    async fn list(&self) -> Result<Vec<UserModel>> {
-        let users = sqlx::query_as::<_, UserModel>("SELECT * FROM users ORDER BY id DESC")
+    async fn search(&self, query: &str) -> Result<Vec<UserModel>> {
+        let search = self.statement("search", || {
+            format!(
+                r#"SELECT * FROM {} WHERE name LIKE ? ESCAPE '\' ORDER BY name, id"#,
+                self.table
+            )
+        });
+        let users = sqlx::query_as::<_, UserModel>(&search)
+            .bind(like_contains(query))
             .fetch_all(&self.pool)
             .await?;
 
@@ -61,49 +257,233 @@ impl UserRepository for SqliteUserRepository {
         let new_name = name.unwrap_or(&existing.name);
         let new_email = email.unwrap_or(&existing.email);
 
-        let user = sqlx::query_as::<_, UserModel>(
-            "UPDATE users SET name = ?, email = ? WHERE id = ? RETURNING *",
-        )
-        .bind(new_name)
-        .bind(new_email)
-        .bind(id)
-        .fetch_one(&self.pool)
-        .await?;
+        let query = self.statement("update", || {
+            format!(
+                "UPDATE {} SET name = ?, email = ? WHERE id = ? RETURNING *",
+                self.table
+            )
+        });
+        let user = sqlx::query_as::<_, UserModel>(&query)
+            .bind(new_name)
+            .bind(new_email)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(map_email_conflict)?;
 
         Ok(user)
     }
 
-    async fn delete(&self, id: i64) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM users WHERE id = ?")
+    async fn delete(&self, id: i64, policy: DeleteUserPolicy) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let query = self.statement("count_tasks", || {
+            format!(
+                "SELECT COUNT(*) FROM {} WHERE assigned_user_id = ?",
+                self.related_table("tasks")
+            )
+        });
+        let (task_count,): (i64,) = sqlx::query_as(&query).bind(id).fetch_one(&mut *tx).await?;
+
+        if task_count > 0 {
+            let query = match policy {
+                DeleteUserPolicy::Restrict => {
+                    return Err(UserHasTasks {
+                        user_id: id,
+                        task_count,
+                    }
+                    .into())
+                }
+                DeleteUserPolicy::Nullify => self.statement("unassign_tasks", || {
+                    format!(
+                        "UPDATE {} SET assigned_user_id = NULL WHERE assigned_user_id = ?",
+                        self.related_table("tasks")
+                    )
+                }),
+                DeleteUserPolicy::Delete => self.statement("delete_tasks", || {
+                    format!(
+                        "DELETE FROM {} WHERE assigned_user_id = ?",
+                        self.related_table("tasks")
+                    )
+                }),
+            };
+
+            sqlx::query(&query).bind(id).execute(&mut *tx).await?;
+        }
+
+        let query = self.statement("delete_by_id", || {
+            format!("DELETE FROM {} WHERE id = ?", self.table)
+        });
+        let result = sqlx::query(&query).bind(id).execute(&mut *tx).await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn task_counts(&self) -> Result<Vec<UserTaskCounts>> {
+        // Grouping by the user rather than `assigned_user_id` keeps users
+        // without tasks as their own zero rows
+        let query = self.statement("task_counts", || {
+            format!(
+                r#"
+                SELECT u.id AS user_id, u.name,
+                       COUNT(t.id) AS total,
+                       COALESCE(SUM(t.completed), 0) AS completed
+                FROM {} u
+                LEFT JOIN {} t ON t.assigned_user_id = u.id
+                GROUP BY u.id
+                ORDER BY u.id
+                "#,
+                self.table,
+                self.related_table("tasks")
+            )
+        });
+        let counts = sqlx::query_as::<_, UserTaskCounts>(&query)
+            .fetch_all(&self.read_pool)
+            .await?;
+
+        Ok(counts)
+    }
+
+    async fn summary(&self, id: i64) -> Result<UserSummary> {
+        let query = self.statement("summary", || {
+            format!(
+                r#"
+                SELECT u.id, u.name, u.email,
+                       COUNT(t.id) AS total,
+                       COALESCE(SUM(t.completed), 0) AS completed
+                FROM {} u
+                LEFT JOIN {} t ON t.assigned_user_id = u.id
+                WHERE u.id = ?
+                GROUP BY u.id
+                "#,
+                self.table,
+                self.related_table("tasks")
+            )
+        });
+        let summary = sqlx::query_as::<_, UserSummary>(&query)
             .bind(id)
-            .execute(&self.pool)
+            .fetch_one(&self.read_pool)
             .await?;
 
-        Ok(result.rows_affected() > 0)
+        Ok(summary)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::TablePrefix;
+    use sqlx::SqlitePool;
 
     async fn setup_test_repository() -> SqliteUserRepository {
         let pool = SqlitePool::connect(":memory:").await.unwrap();
+        crate::db::MIGRATOR.run(&pool).await.unwrap();
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS users (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                email TEXT NOT NULL UNIQUE
-            )
-            "#,
+        SqliteUserRepository::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_create_user_duplicate_email_any_case() {
+        let repo = setup_test_repository().await;
+        repo.create("Ada", "ada@example.com").await.unwrap();
+
+        let err = repo
+            .create("Imposter", "ADA@Example.com")
+            .await
+            .unwrap_err();
+
+        assert!(err.is::<EmailTaken>());
+        assert_eq!(err.to_string(), "email already registered");
+    }
+
+    #[tokio::test]
+    async fn test_insert_losing_race_maps_constraint_to_email_taken() {
+        let repo = setup_test_repository().await;
+        repo.create("Ada", "ada@example.com").await.unwrap();
+
+        // Skip the pre-check, as a create that raced past it would
+        let mut conn = repo.pool.acquire().await.unwrap();
+        let err = repo
+            .insert(&mut conn, "Imposter", "Ada@example.com")
+            .await
+            .unwrap_err();
+
+        assert!(err.is::<EmailTaken>());
+    }
+
+    #[tokio::test]
+    async fn test_update_user_to_taken_email() {
+        let repo = setup_test_repository().await;
+        repo.create("Ada", "ada@example.com").await.unwrap();
+        let grace = repo.create("Grace", "grace@example.com").await.unwrap();
+
+        let err = repo
+            .update(grace.id, None, Some("ada@example.com"))
+            .await
+            .unwrap_err();
+
+        assert!(err.is::<EmailTaken>());
+    }
+
+    #[tokio::test]
+    async fn test_count_users_with_filter() {
+        let repo = setup_test_repository().await;
+        repo.create("Ada", "ada@example.com").await.unwrap();
+        repo.create("Adam", "adam@example.com").await.unwrap();
+        repo.create("Grace", "grace@example.com").await.unwrap();
+
+        assert_eq!(repo.count(None).await.unwrap(), 3);
+        assert_eq!(repo.count(Some("ada")).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_custom_table_prefix() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let prefix = TablePrefix::new("tenantA_").unwrap();
+        crate::db::migrate_prefixed(&pool, &prefix).await.unwrap();
+        let repo = SqliteUserRepository::new(pool).with_table_prefix(&prefix);
+
+        let user = repo.create("Ada", "ada@example.com").await.unwrap();
+        let err = repo.create("Ada", "ADA@example.com").await.unwrap_err();
+        assert!(err.is::<EmailTaken>());
+
+        sqlx::query("INSERT INTO tenantA_tasks (title, description, assigned_user_id) VALUES ('Task', '', ?)")
+            .bind(user.id)
+            .execute(&repo.pool)
+            .await
+            .unwrap();
+        let err = repo
+            .delete(user.id, DeleteUserPolicy::Restrict)
+            .await
+            .unwrap_err();
+        assert!(err.is::<UserHasTasks>());
+        assert!(repo
+            .delete(user.id, DeleteUserPolicy::Delete)
+            .await
+            .unwrap());
+    }
+
+    async fn assign_task(repo: &SqliteUserRepository, user_id: i64) -> i64 {
+        let (id,): (i64,) = sqlx::query_as(
+            "INSERT INTO tasks (title, description, assigned_user_id) VALUES ('Task', '', ?) RETURNING id",
         )
-        .execute(&pool)
+        .bind(user_id)
+        .fetch_one(&repo.pool)
         .await
         .unwrap();
 
-        SqliteUserRepository::new(pool)
+        id
+    }
+
+    async fn task_owner(repo: &SqliteUserRepository, task_id: i64) -> Option<Option<i64>> {
+        sqlx::query_as::<_, (Option<i64>,)>("SELECT assigned_user_id FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .fetch_optional(&repo.pool)
+            .await
+            .unwrap()
+            .map(|(owner,)| owner)
     }
 
     #[tokio::test]
@@ -117,6 +497,28 @@ mod tests {
         assert!(user.id > 0);
     }
 
+    #[tokio::test]
+    async fn test_upsert_by_email() {
+        let repo = setup_test_repository().await;
+
+        let (created, inserted) = repo
+            .upsert_by_email("John Doe", "john@example.com")
+            .await
+            .unwrap();
+        assert!(inserted);
+        assert_eq!(created.name, "John Doe");
+
+        let (updated, inserted) = repo
+            .upsert_by_email("Johnny Doe", "JOHN@example.com")
+            .await
+            .unwrap();
+        assert!(!inserted);
+        assert_eq!(updated.id, created.id);
+        assert_eq!(updated.name, "Johnny Doe");
+        assert_eq!(updated.email, "john@example.com");
+        assert_eq!(repo.list(Page::default(), None).await.unwrap().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_get_user() {
         let repo = setup_test_repository().await;
@@ -145,13 +547,94 @@ mod tests {
         let user1 = repo.create("User 1", "user1@example.com").await.unwrap();
         let user2 = repo.create("User 2", "user2@example.com").await.unwrap();
 
-        let users = repo.list().await.unwrap();
+        let users = repo.list(Page::default(), None).await.unwrap();
+
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].id, user2.id);
+        assert_eq!(users[1].id, user1.id);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_paginated() {
+        let repo = setup_test_repository().await;
+
+        let user1 = repo.create("User 1", "user1@example.com").await.unwrap();
+        let user2 = repo.create("User 2", "user2@example.com").await.unwrap();
+        repo.create("User 3", "user3@example.com").await.unwrap();
+
+        let users = repo.list(Page::new(Some(2), Some(1)), None).await.unwrap();
 
         assert_eq!(users.len(), 2);
         assert_eq!(users[0].id, user2.id);
         assert_eq!(users[1].id, user1.id);
     }
 
+    #[tokio::test]
+    async fn test_list_users_offset_out_of_range() {
+        let repo = setup_test_repository().await;
+
+        repo.create("User 1", "user1@example.com").await.unwrap();
+
+        let users = repo.list(Page::new(None, Some(10)), None).await.unwrap();
+
+        assert!(users.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_users_filtered_by_name() {
+        let repo = setup_test_repository().await;
+
+        let john = repo.create("John Doe", "john@example.com").await.unwrap();
+        repo.create("Jane Roe", "jane@example.com").await.unwrap();
+        let johnny = repo
+            .create("Johnny Cash", "johnny@example.com")
+            .await
+            .unwrap();
+
+        let users = repo.list(Page::default(), Some("john")).await.unwrap();
+
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].id, johnny.id);
+        assert_eq!(users[1].id, john.id);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_name_filter_escapes_wildcards() {
+        let repo = setup_test_repository().await;
+
+        repo.create("John Doe", "john@example.com").await.unwrap();
+        let literal = repo.create("100% Real", "real@example.com").await.unwrap();
+
+        let users = repo.list(Page::default(), Some("%")).await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id, literal.id);
+
+        let users = repo.list(Page::default(), Some("_")).await.unwrap();
+        assert!(users.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_users_sorted_by_name_escaping_wildcards() {
+        let repo = setup_test_repository().await;
+        repo.create("Zoe Ann", "zoe@example.com").await.unwrap();
+        repo.create("Annabel", "annabel@example.com").await.unwrap();
+        repo.create("Bob", "bob@example.com").await.unwrap();
+        repo.create("50% Ann", "half@example.com").await.unwrap();
+
+        let names: Vec<String> = repo
+            .search("ANN")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|user| user.name)
+            .collect();
+        assert_eq!(names, vec!["50% Ann", "Annabel", "Zoe Ann"]);
+
+        let users = repo.search("0%").await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert!(repo.search("_").await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_update_user() {
         let repo = setup_test_repository().await;
@@ -177,11 +660,69 @@ mod tests {
             .create("Delete Me", "delete@example.com")
             .await
             .unwrap();
-        let deleted = repo.delete(user.id).await.unwrap();
+        let deleted = repo
+            .delete(user.id, DeleteUserPolicy::default())
+            .await
+            .unwrap();
 
         assert_eq!(deleted, true);
 
         let result = repo.get(user.id).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_delete_user_with_tasks_restrict() {
+        let repo = setup_test_repository().await;
+
+        let user = repo.create("Owner", "owner@example.com").await.unwrap();
+        let task_id = assign_task(&repo, user.id).await;
+
+        let err = repo
+            .delete(user.id, DeleteUserPolicy::Restrict)
+            .await
+            .unwrap_err();
+
+        let has_tasks = err.downcast_ref::<UserHasTasks>().unwrap();
+        assert_eq!(has_tasks.task_count, 1);
+        assert!(repo.get(user.id).await.is_ok());
+        assert_eq!(task_owner(&repo, task_id).await, Some(Some(user.id)));
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_with_tasks_nullify() {
+        let repo = setup_test_repository().await;
+
+        let user = repo.create("Owner", "owner@example.com").await.unwrap();
+        let task_id = assign_task(&repo, user.id).await;
+
+        let deleted = repo
+            .delete(user.id, DeleteUserPolicy::Nullify)
+            .await
+            .unwrap();
+
+        assert!(deleted);
+        assert!(repo.get(user.id).await.is_err());
+        assert_eq!(task_owner(&repo, task_id).await, Some(None));
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_with_tasks_delete() {
+        let repo = setup_test_repository().await;
+
+        let user = repo.create("Owner", "owner@example.com").await.unwrap();
+        let other = repo.create("Other", "other@example.com").await.unwrap();
+        let task_id = assign_task(&repo, user.id).await;
+        let other_task_id = assign_task(&repo, other.id).await;
+
+        let deleted = repo
+            .delete(user.id, DeleteUserPolicy::Delete)
+            .await
+            .unwrap();
+
+        assert!(deleted);
+        assert!(repo.get(user.id).await.is_err());
+        assert_eq!(task_owner(&repo, task_id).await, None);
+        assert_eq!(task_owner(&repo, other_task_id).await, Some(Some(other.id)));
+    }
 }
@@ -0,0 +1,370 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+
+use subtle::ConstantTimeEq;
+
+use super::inflight::{CancelError, InflightRegistry};
+use super::json::JsonBody;
+use super::{
+    BackupDocument, BackupTask, ErrorResponse, InflightRequestResponse, PoolStatsResponse,
+    UserResponse, VacuumResponse,
+};
+use crate::config::Config;
+use crate::db::{TaskModel, TaskStatus, UserModel};
+use crate::repository::{Backup, InvalidBackup, MaintenanceRepository, TaskBackup};
+
+/// Shared state of the `/admin` routes.
+#[derive(Clone)]
+pub struct AdminState {
+    pub inflight: InflightRegistry,
+    /// Database housekeeping; the maintenance endpoints answer 503 without it.
+    pub maintenance: Option<Arc<dyn MaintenanceRepository>>,
+    /// Served by `GET /admin/config`; 503 without it.
+    pub config: Option<Arc<Config>>,
+}
+
+/// Admin routes. Every request must carry `Authorization: Bearer <token>`;
+/// without a `token` they all answer 503.
+pub fn admin_routes(state: AdminState, token: Option<String>) -> Router {
+    Router::new()
+        .route("/admin/inflight", get(list_inflight))
+        .route("/admin/inflight/{request_id}/cancel", post(cancel_inflight))
+        .route("/admin/vacuum", post(vacuum))
+        .route("/admin/backup", get(backup))
+        .route("/admin/restore", post(restore))
+        .route("/admin/config", get(effective_config))
+        .route("/admin/pool", get(pool_stats))
+        .route_layer(middleware::from_fn_with_state(
+            token.map(Arc::<str>::from),
+            require_admin_token,
+        ))
+        .with_state(state)
+}
+
+async fn require_admin_token(
+    State(token): State<Option<Arc<str>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(token) = token else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse::new(
+                "Admin routes are disabled; set ADMIN_TOKEN to enable them",
+            )),
+        )
+            .into_response();
+    };
+
+    // Constant time, so response timing doesn't reveal how much matched
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|provided| bool::from(provided.as_bytes().ct_eq(token.as_bytes())));
+
+    if authorized {
+        next.run(request).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse::new("Missing or invalid admin token")),
+        )
+            .into_response()
+    }
+}
+
+/// List in-flight requests
+#[utoipa::path(
+    get,
+    path = "/api/admin/inflight",
+    responses(
+        (status = 200, description = "Requests currently being handled", body = Vec<InflightRequestResponse>),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 503, description = "ADMIN_TOKEN not set", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn list_inflight(State(state): State<AdminState>) -> Json<Vec<InflightRequestResponse>> {
+    Json(state.inflight.list())
+}
+
+/// Cancel an in-flight request
+///
+/// Only REST requests are tracked. Clients choose their own request ids, so
+/// when several requests in flight share one, none is cancelled.
+#[utoipa::path(
+    post,
+    path = "/api/admin/inflight/{request_id}/cancel",
+    params(
+        ("request_id" = String, Path, description = "Request ID")
+    ),
+    responses(
+        (status = 204, description = "Cancellation signalled"),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 404, description = "Request not in flight", body = ErrorResponse),
+        (status = 409, description = "Several requests in flight share this id", body = ErrorResponse),
+        (status = 503, description = "ADMIN_TOKEN not set", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn cancel_inflight(
+    State(state): State<AdminState>,
+    Path(request_id): Path<String>,
+) -> Result<StatusCode, impl IntoResponse> {
+    match state.inflight.cancel(&request_id) {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(CancelError::NotInFlight) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new(format!(
+                "Request {} is not in flight",
+                request_id
+            ))),
+        )),
+        Err(CancelError::Ambiguous(count)) => Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse::new(format!(
+                "{} requests with id {} are in flight; none was cancelled",
+                count, request_id
+            ))),
+        )),
+    }
+}
+
+/// Run VACUUM and PRAGMA optimize
+#[utoipa::path(
+    post,
+    path = "/api/admin/vacuum",
+    responses(
+        (status = 200, description = "Database compacted", body = VacuumResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+        (status = 503, description = "Maintenance not configured, or ADMIN_TOKEN not set", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn vacuum(
+    State(state): State<AdminState>,
+) -> Result<Json<VacuumResponse>, impl IntoResponse> {
+    let Some(maintenance) = state.maintenance else {
+        return Err(maintenance_unavailable());
+    };
+
+    match maintenance.vacuum().await {
+        Ok(report) => Ok(Json(VacuumResponse {
+            size_before_bytes: report.size_before_bytes,
+            size_after_bytes: report.size_after_bytes,
+            vacuum_ms: report.vacuum_ms as u64,
+            optimize_ms: report.optimize_ms as u64,
+        })),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Show database connection pool usage
+///
+/// For diagnosing pool exhaustion: when `in_use` sits at `max`, requests
+/// are queueing for a connection.
+#[utoipa::path(
+    get,
+    path = "/api/admin/pool",
+    responses(
+        (status = 200, description = "Connection counts of the pool", body = PoolStatsResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 503, description = "Maintenance not configured, or ADMIN_TOKEN not set", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn pool_stats(
+    State(state): State<AdminState>,
+) -> Result<Json<PoolStatsResponse>, impl IntoResponse> {
+    let Some(maintenance) = state.maintenance else {
+        return Err(maintenance_unavailable());
+    };
+
+    let stats = maintenance.pool_stats();
+    Ok(Json(PoolStatsResponse {
+        size: stats.size,
+        idle: stats.idle,
+        in_use: stats.in_use,
+        max: stats.max,
+    }))
+}
+
+impl From<Backup> for BackupDocument {
+    fn from(backup: Backup) -> Self {
+        BackupDocument {
+            tasks: backup
+                .tasks
+                .into_iter()
+                .map(|TaskBackup { task, position }| BackupTask {
+                    id: task.id,
+                    title: task.title,
+                    description: task.description,
+                    completed: task.completed,
+                    status: Some(task.status),
+                    assigned_user_id: task.assigned_user_id,
+                    tags: task.tags,
+                    created_at: task.created_at,
+                    updated_at: task.updated_at,
+                    completed_at: task.completed_at,
+                    position,
+                })
+                .collect(),
+            users: backup.users.into_iter().map(UserResponse::from).collect(),
+        }
+    }
+}
+
+impl From<BackupDocument> for Backup {
+    fn from(document: BackupDocument) -> Self {
+        Backup {
+            tasks: document
+                .tasks
+                .into_iter()
+                .map(|task| {
+                    let status = task
+                        .status
+                        .unwrap_or(TaskStatus::from_completed(task.completed));
+                    TaskBackup {
+                        task: TaskModel {
+                            id: task.id,
+                            title: task.title,
+                            description: task.description,
+                            completed: status.is_done(),
+                            status,
+                            assigned_user_id: task.assigned_user_id,
+                            created_at: task.created_at,
+                            updated_at: task.updated_at,
+                            completed_at: task.completed_at,
+                            tags: task.tags,
+                        },
+                        position: task.position,
+                    }
+                })
+                .collect(),
+            users: document
+                .users
+                .into_iter()
+                .map(|user| UserModel {
+                    id: user.id,
+                    name: user.name,
+                    email: user.email,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn maintenance_unavailable() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ErrorResponse::new("Database maintenance is not configured")),
+    )
+}
+
+/// Export every task and user
+#[utoipa::path(
+    get,
+    path = "/api/admin/backup",
+    responses(
+        (status = 200, description = "All tasks and users, in id order", body = BackupDocument),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+        (status = 503, description = "Maintenance not configured, or ADMIN_TOKEN not set", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn backup(
+    State(state): State<AdminState>,
+) -> Result<Json<BackupDocument>, impl IntoResponse> {
+    let Some(maintenance) = state.maintenance else {
+        return Err(maintenance_unavailable());
+    };
+
+    match maintenance.backup().await {
+        Ok(backup) => Ok(Json(BackupDocument::from(backup))),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Replace every task and user with a backup
+///
+/// All or nothing: when any row is rejected the database is left as it
+/// was. Ids are kept, so references between tasks and users survive.
+#[utoipa::path(
+    post,
+    path = "/api/admin/restore",
+    request_body = BackupDocument,
+    responses(
+        (status = 204, description = "Backup restored"),
+        (status = 400, description = "Backup violates the schema; nothing was changed", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+        (status = 503, description = "Maintenance not configured, or ADMIN_TOKEN not set", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn restore(
+    State(state): State<AdminState>,
+    JsonBody(document): JsonBody<BackupDocument>,
+) -> Result<StatusCode, impl IntoResponse> {
+    let Some(maintenance) = state.maintenance else {
+        return Err(maintenance_unavailable());
+    };
+
+    match maintenance.restore(&Backup::from(document)).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e) if e.is::<InvalidBackup>() => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Show the effective configuration
+///
+/// Secrets are never included; `admin_token_set` only says whether one is
+/// configured.
+#[utoipa::path(
+    get,
+    path = "/api/admin/config",
+    responses(
+        (status = 200, description = "Settings resolved at startup", body = Config),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 503, description = "Configuration not provided to the router, or ADMIN_TOKEN not set", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn effective_config(
+    State(state): State<AdminState>,
+) -> Result<Json<Config>, impl IntoResponse> {
+    match state.config {
+        Some(config) => Ok(Json(Config::clone(&config))),
+        None => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse::new(
+                "Effective configuration is not available",
+            )),
+        )),
+    }
+}
@@ -0,0 +1,28 @@
+//! Turns a panicking handler into a `500` instead of a dropped connection.
+
+use std::any::Any;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use super::ErrorResponse;
+
+/// Response for `CatchPanicLayer`. The panic message is logged but kept out
+/// of the body, since it can carry internals.
+pub fn panic_response(panic: Box<dyn Any + Send + 'static>) -> Response {
+    let message = panic
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| panic.downcast_ref::<&str>().copied())
+        .unwrap_or("<non-string panic payload>");
+    tracing::error!(panic = message, "request handler panicked");
+
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("Internal server error")),
+    )
+        .into_response()
+}
@@ -0,0 +1,183 @@
+use std::io::Write;
+
+use axum::{
+    body::{self, Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use flate2::{write::GzEncoder, Compression};
+
+use super::json_stream::is_streamed;
+
+/// Bodies smaller than this aren't worth the gzip header overhead.
+const MIN_COMPRESS_BYTES: usize = 32;
+
+/// Larger bodies, and bodies of unknown length, are sent uncompressed rather
+/// than buffered in memory.
+const MAX_COMPRESS_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Content types that are compressed. Streaming types (`text/event-stream`,
+/// NDJSON) are left alone because buffering them would break streaming.
+const COMPRESSIBLE_TYPES: &[&str] = &[
+    "application/json",
+    "application/javascript",
+    "text/html",
+    "text/css",
+    "text/javascript",
+    "text/plain",
+];
+
+/// Gzips responses for clients that send `Accept-Encoding: gzip`. Streamed
+/// JSON arrays are sent as they are, like the other streaming types.
+///
+/// tower-http's `CompressionLayer` needs async-compression, so this buffers
+/// the body and gzips it with flate2 instead. Only bodies whose size is
+/// known and at most [`MAX_COMPRESS_BYTES`] are buffered.
+pub async fn compression_middleware(request: Request, next: Next) -> Response {
+    let accepts_gzip = accepts_gzip(request.headers());
+    let response = next.run(request).await;
+
+    if !accepts_gzip || is_streamed(&response) || !is_compressible(response.headers()) {
+        return response;
+    }
+
+    let buffered = response
+        .body()
+        .size_hint()
+        .upper()
+        .is_some_and(|len| len <= MAX_COMPRESS_BYTES);
+    if !buffered {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match body::to_bytes(body, MAX_COMPRESS_BYTES as usize).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+
+    if bytes.len() < MIN_COMPRESS_BYTES {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let compressed = match encoder.write_all(&bytes).and_then(|_| encoder.finish()) {
+        Ok(compressed) => compressed,
+        Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+
+    Response::from_parts(parts, Body::from(compressed))
+}
+
+/// True when `Accept-Encoding` lists `gzip` (or `*`) without `q=0`.
+pub fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut params = coding.split(';').map(str::trim);
+            let name = params.next().unwrap_or_default();
+            let rejected = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            (name.eq_ignore_ascii_case("gzip") || name == "*") && !rejected
+        })
+}
+
+fn is_compressible(headers: &HeaderMap) -> bool {
+    if headers.contains_key(header::CONTENT_ENCODING) {
+        return false;
+    }
+
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| COMPRESSIBLE_TYPES.contains(&mime.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn headers(accept_encoding: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_str(accept_encoding).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_accepts_gzip() {
+        assert!(accepts_gzip(&headers("gzip")));
+        assert!(accepts_gzip(&headers("br, gzip;q=0.8")));
+        assert!(accepts_gzip(&headers("*")));
+        assert!(!accepts_gzip(&headers("gzip;q=0")));
+        assert!(!accepts_gzip(&headers("br, deflate")));
+        assert!(!accepts_gzip(&HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn test_large_and_unsized_bodies_sent_uncompressed() {
+        let app = Router::new()
+            .route(
+                "/large",
+                get(|| async {
+                    (
+                        [(header::CONTENT_TYPE, "text/plain")],
+                        "a".repeat(MAX_COMPRESS_BYTES as usize + 1),
+                    )
+                }),
+            )
+            .route(
+                "/unsized",
+                get(|| async {
+                    let chunks =
+                        futures_util::stream::iter(["chunk"; 100].map(Ok::<_, std::io::Error>));
+                    (
+                        [(header::CONTENT_TYPE, "text/plain")],
+                        Body::from_stream(chunks),
+                    )
+                }),
+            )
+            .layer(middleware::from_fn(compression_middleware));
+
+        for uri in ["/large", "/unsized"] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::get(uri)
+                        .header(header::ACCEPT_ENCODING, "gzip")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert!(
+                !response.headers().contains_key(header::CONTENT_ENCODING),
+                "{}",
+                uri
+            );
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use axum::http::{HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use super::REQUEST_ID_HEADER;
+
+/// CORS layer for the REST API, restricted to `CORS_ALLOWED_ORIGINS` when it
+/// is set.
+pub fn cors_layer_from_env() -> CorsLayer {
+    cors_layer(std::env::var("CORS_ALLOWED_ORIGINS").ok().as_deref())
+}
+
+pub fn cors_layer(allowed_origins: Option<&str>) -> CorsLayer {
+    let allow_origin = match allowed_origins.and_then(parse_allowed_origins) {
+        Some(origins) => AllowOrigin::list(origins),
+        None => AllowOrigin::from(Any),
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers(Any)
+        .expose_headers([REQUEST_ID_HEADER])
+}
+
+/// Parses a comma-separated origin list. Returns `None` (any origin) when the
+/// value is empty or `*`; entries that aren't valid header values are skipped.
+pub fn parse_allowed_origins(value: &str) -> Option<Vec<HeaderValue>> {
+    let origins: Vec<&str> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .collect();
+
+    if origins.is_empty() || origins.contains(&"*") {
+        return None;
+    }
+
+    Some(
+        origins
+            .into_iter()
+            .filter_map(|origin| HeaderValue::from_str(origin).ok())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_allowed_origins_list() {
+        let origins =
+            parse_allowed_origins(" https://app.example.com, http://localhost:5173 ,").unwrap();
+
+        assert_eq!(
+            origins,
+            vec![
+                HeaderValue::from_static("https://app.example.com"),
+                HeaderValue::from_static("http://localhost:5173"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_allowed_origins_any() {
+        assert!(parse_allowed_origins("").is_none());
+        assert!(parse_allowed_origins(" , ").is_none());
+        assert!(parse_allowed_origins("*").is_none());
+    }
+}
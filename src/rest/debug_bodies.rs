@@ -0,0 +1,137 @@
+//! Debug-level logging of JSON request and response bodies, for diagnosing
+//! client issues. Only installed with `DEBUG_BODIES=1`, since it buffers
+//! bodies and can put user data in the logs.
+
+use axum::{
+    body::{self, Body, Bytes},
+    extract::Request,
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use super::json_stream::is_streamed;
+use super::ErrorResponse;
+
+/// Logged bodies are cut to this many bytes.
+pub const MAX_LOGGED_BODY_BYTES: usize = 2048;
+
+/// Headers whose values never reach the log.
+const REDACTED_HEADERS: [HeaderName; 4] = [
+    header::AUTHORIZATION,
+    header::PROXY_AUTHORIZATION,
+    header::COOKIE,
+    header::SET_COOKIE,
+];
+
+/// Logs JSON request and response bodies at debug level. Other content
+/// types pass through untouched so streams (SSE, NDJSON, WebSockets) keep
+/// streaming, as do streamed JSON arrays. Must run inside the body limit layer, which bounds what gets
+/// buffered here.
+pub async fn log_bodies(request: Request, next: Next) -> Response {
+    let request = if is_json(request.headers()) {
+        let (parts, body) = request.into_parts();
+        let bytes = match body::to_bytes(body, usize::MAX).await {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                return (
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    Json(ErrorResponse::new("Request body too large")),
+                )
+                    .into_response()
+            }
+        };
+        tracing::debug!(
+            method = %parts.method,
+            uri = %parts.uri,
+            headers = %redacted(&parts.headers),
+            body = %truncated(&bytes),
+            "request body"
+        );
+        Request::from_parts(parts, Body::from(bytes))
+    } else {
+        request
+    };
+
+    let response = next.run(request).await;
+    if !is_json(response.headers()) || is_streamed(&response) {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = match body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+    tracing::debug!(
+        status = parts.status.as_u16(),
+        body = %truncated(&bytes),
+        "response body"
+    );
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+/// `name: value` pairs, with credentials replaced by `<redacted>`.
+fn redacted(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if REDACTED_HEADERS.contains(name) {
+                "<redacted>"
+            } else {
+                value.to_str().unwrap_or("<binary>")
+            };
+            format!("{}: {}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The body as text, cut to [`MAX_LOGGED_BODY_BYTES`] with the full length
+/// noted.
+fn truncated(bytes: &Bytes) -> String {
+    if bytes.len() <= MAX_LOGGED_BODY_BYTES {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    format!(
+        "{}... ({} bytes)",
+        String::from_utf8_lossy(&bytes[..MAX_LOGGED_BODY_BYTES]),
+        bytes.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_redacts_credentials_and_truncates() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer s3cret"),
+        );
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        let logged = redacted(&headers);
+        assert!(logged.contains("authorization: <redacted>"));
+        assert!(logged.contains("content-type: application/json"));
+        assert!(!logged.contains("s3cret"));
+
+        let long = Bytes::from("x".repeat(MAX_LOGGED_BODY_BYTES + 10));
+        let logged = truncated(&long);
+        assert!(logged.ends_with(&format!("... ({} bytes)", MAX_LOGGED_BODY_BYTES + 10)));
+        assert_eq!(truncated(&Bytes::from_static(b"{}")), "{}");
+    }
+}
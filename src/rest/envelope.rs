@@ -0,0 +1,113 @@
+//! Opt-in `{ "data": ..., "meta": {...} }` wrapping of JSON responses.
+
+use axum::{
+    body::{self, Body},
+    extract::Request,
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::json_stream::is_streamed;
+use super::RequestId;
+
+/// A successful response body in envelope mode.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub data: T,
+    pub meta: EnvelopeMeta,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EnvelopeMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Number of items, when `data` is a list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+}
+
+/// Wraps successful JSON responses in an [`Envelope`] for clients that ask
+/// with `?envelope=true` or `Accept: application/json; profile="envelope"`.
+/// Errors, non-JSON and streamed bodies and other clients get the bare shape.
+pub async fn envelope_middleware(request: Request, next: Next) -> Response {
+    let wanted = envelope_requested(request.uri().query(), request.headers());
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.clone());
+    let response = next.run(request).await;
+
+    if !wanted
+        || !response.status().is_success()
+        || !is_json(response.headers())
+        || is_streamed(&response)
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+    let Ok(data) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let meta = EnvelopeMeta {
+        request_id,
+        count: data.as_array().map(Vec::len),
+    };
+    let wrapped = serde_json::to_vec(&Envelope { data, meta }).expect("Envelope serializes");
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(wrapped))
+}
+
+fn envelope_requested(query: Option<&str>, headers: &HeaderMap) -> bool {
+    let by_query = form_urlencoded::parse(query.unwrap_or("").as_bytes())
+        .any(|(key, value)| key == "envelope" && matches!(value.as_ref(), "true" | "1"));
+
+    let by_profile = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .flat_map(|range| range.split(';').skip(1))
+        .filter_map(|param| param.trim().strip_prefix("profile="))
+        .any(|profile| profile.trim_matches('"') == "envelope");
+
+    by_query || by_profile
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim() == "application/json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_envelope_requested_by_query_or_profile() {
+        let none = HeaderMap::new();
+        assert!(envelope_requested(Some("limit=5&envelope=true"), &none));
+        assert!(!envelope_requested(Some("envelope=false"), &none));
+        assert!(!envelope_requested(None, &none));
+
+        let mut profile = HeaderMap::new();
+        profile.insert(
+            header::ACCEPT,
+            HeaderValue::from_static("application/json; profile=\"envelope\""),
+        );
+        assert!(envelope_requested(None, &profile));
+    }
+}
@@ -0,0 +1,69 @@
+//! Entity tags for conditional `GET`s.
+
+use axum::http::{header, HeaderMap, HeaderValue};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+
+use super::negotiate::Format;
+
+/// Weak ETag over the JSON form of `body` and the `format` it is sent in, so
+/// the JSON and XML representations never share a tag. Weak, because the
+/// body may go out compressed or not; any change to a field changes the tag.
+pub fn etag<T: Serialize>(format: Format, body: &T) -> HeaderValue {
+    let json = serde_json::to_vec(body).expect("response bodies serialize");
+    let representation: &[u8] = match format {
+        Format::Json => b"json",
+        Format::Xml => b"xml",
+    };
+    let digest = Sha1::new()
+        .chain_update(representation)
+        .chain_update(&json)
+        .finalize();
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    HeaderValue::from_str(&format!("W/\"{}\"", hex)).expect("hex is a valid header value")
+}
+
+/// True when `If-None-Match` lists `etag` or is `*`, using the weak
+/// comparison RFC 9110 prescribes for it.
+pub fn if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|candidate| candidate.trim() == "*" || opaque(candidate) == opaque(etag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_if_none_match() {
+        let tag = etag(Format::Json, &serde_json::json!({ "id": 1 }));
+        assert_eq!(tag, etag(Format::Json, &serde_json::json!({ "id": 1 })));
+        assert_ne!(tag, etag(Format::Json, &serde_json::json!({ "id": 2 })));
+        assert_ne!(tag, etag(Format::Xml, &serde_json::json!({ "id": 1 })));
+
+        let mut headers = HeaderMap::new();
+        assert!(!if_none_match(&headers, &tag));
+
+        let strong = tag.to_str().unwrap().trim_start_matches("W/").to_string();
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_str(&format!("\"other\", {}", strong)).unwrap(),
+        );
+        assert!(if_none_match(&headers, &tag));
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("*"));
+        assert!(if_none_match(&headers, &tag));
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"other\""));
+        assert!(!if_none_match(&headers, &tag));
+    }
+}
@@ -0,0 +1,408 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use axum::{
+    extract::{Request, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::get,
+    Router,
+};
+use futures_util::stream::{self, BoxStream, Stream};
+use serde::Serialize;
+use tokio::io::ReadHalf;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+use crate::db::{TaskAuditModel, TaskModel, TaskStatus};
+use crate::repository::{
+    ArchivedTask, DuplicateTasks, Page, TaskChanges, TaskFilter, TaskRepository, TaskStatusCounts,
+    TaskUpdate,
+};
+
+use super::websocket::{self, OPCODE_CLOSE, OPCODE_PING, OPCODE_PONG, OPCODE_TEXT};
+use super::TaskResponse;
+
+/// Events buffered per subscriber; a subscriber that falls further behind
+/// skips the oldest ones.
+const EVENT_BUFFER: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskEventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// Pushed to subscribers after every successful task write.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TaskEvent {
+    #[serde(rename = "type")]
+    pub kind: TaskEventKind,
+    pub task: TaskResponse,
+}
+
+/// Fan-out of [`TaskEvent`]s to every connected subscriber.
+#[derive(Clone)]
+pub struct TaskEvents {
+    sender: broadcast::Sender<TaskEvent>,
+}
+
+impl Default for TaskEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.sender.subscribe()
+    }
+
+    fn publish(&self, kind: TaskEventKind, task: TaskModel) {
+        // No subscribers is not an error
+        let _ = self.sender.send(TaskEvent {
+            kind,
+            task: task.into(),
+        });
+    }
+}
+
+/// Publishes a [`TaskEvent`] for each task created, updated or deleted
+/// through the wrapped repository. Bulk operations (`complete_all`,
+/// `complete_matching`, `reassign`, `delete_all`, `archive_completed`) are
+/// forwarded without events.
+pub struct PublishingTaskRepository<T: ?Sized> {
+    inner: Arc<T>,
+    events: TaskEvents,
+}
+
+impl<T: ?Sized> PublishingTaskRepository<T> {
+    pub fn new(inner: Arc<T>, events: TaskEvents) -> Self {
+        Self { inner, events }
+    }
+}
+
+#[async_trait]
+impl<T: TaskRepository + ?Sized> TaskRepository for PublishingTaskRepository<T> {
+    async fn create(&self, title: &str, description: &str) -> Result<TaskModel> {
+        let task = self.inner.create(title, description).await?;
+        self.events.publish(TaskEventKind::Created, task.clone());
+        Ok(task)
+    }
+
+    async fn create_for_user(
+        &self,
+        user_id: i64,
+        title: &str,
+        description: &str,
+    ) -> Result<TaskModel> {
+        let task = self
+            .inner
+            .create_for_user(user_id, title, description)
+            .await?;
+        self.events.publish(TaskEventKind::Created, task.clone());
+        Ok(task)
+    }
+
+    async fn create_with_tags(
+        &self,
+        assigned_user_id: Option<i64>,
+        title: &str,
+        description: &str,
+        tags: &[String],
+    ) -> Result<TaskModel> {
+        let task = self
+            .inner
+            .create_with_tags(assigned_user_id, title, description, tags)
+            .await?;
+        self.events.publish(TaskEventKind::Created, task.clone());
+        Ok(task)
+    }
+
+    async fn create_returning_meta(
+        &self,
+        title: &str,
+        description: &str,
+    ) -> Result<(TaskModel, i64)> {
+        let (task, rowid) = self.inner.create_returning_meta(title, description).await?;
+        self.events.publish(TaskEventKind::Created, task.clone());
+        Ok((task, rowid))
+    }
+
+    async fn create_many(&self, tasks: &[(String, String)]) -> Result<Vec<TaskModel>> {
+        let created = self.inner.create_many(tasks).await?;
+        for task in &created {
+            self.events.publish(TaskEventKind::Created, task.clone());
+        }
+        Ok(created)
+    }
+
+    async fn get(&self, id: i64) -> Result<TaskModel> {
+        self.inner.get(id).await
+    }
+
+    async fn get_many(&self, ids: &[i64]) -> Result<Vec<TaskModel>> {
+        self.inner.get_many(ids).await
+    }
+
+    async fn list(&self, page: Page, filter: &TaskFilter) -> Result<Vec<TaskModel>> {
+        self.inner.list(page, filter).await
+    }
+
+    async fn list_updated_since(&self, since: &str) -> Result<Vec<TaskModel>> {
+        self.inner.list_updated_since(since).await
+    }
+
+    async fn list_completed_between(&self, from: &str, to: &str) -> Result<Vec<TaskModel>> {
+        self.inner.list_completed_between(from, to).await
+    }
+
+    fn stream_all(&self) -> BoxStream<'static, Result<TaskModel>> {
+        self.inner.stream_all()
+    }
+
+    async fn count(&self, filter: &TaskFilter) -> Result<i64> {
+        self.inner.count(filter).await
+    }
+
+    async fn history(&self, id: i64) -> Result<Vec<TaskAuditModel>> {
+        self.inner.history(id).await
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+        assigned_user_id: Option<Option<i64>>,
+    ) -> Result<TaskModel> {
+        let task = self
+            .inner
+            .update(id, title, description, status, assigned_user_id)
+            .await?;
+        self.events.publish(TaskEventKind::Updated, task.clone());
+        Ok(task)
+    }
+
+    async fn update_if_version(
+        &self,
+        id: i64,
+        expected_updated_at: &str,
+        title: Option<&str>,
+        description: Option<&str>,
+        status: Option<TaskStatus>,
+    ) -> Result<TaskModel> {
+        let task = self
+            .inner
+            .update_if_version(id, expected_updated_at, title, description, status)
+            .await?;
+        self.events.publish(TaskEventKind::Updated, task.clone());
+        Ok(task)
+    }
+
+    async fn update_with_tags(&self, id: i64, update: &TaskUpdate) -> Result<TaskModel> {
+        let task = self.inner.update_with_tags(id, update).await?;
+        self.events.publish(TaskEventKind::Updated, task.clone());
+        Ok(task)
+    }
+
+    async fn set_tags(&self, id: i64, tags: &[String]) -> Result<TaskModel> {
+        let task = self.inner.set_tags(id, tags).await?;
+        self.events.publish(TaskEventKind::Updated, task.clone());
+        Ok(task)
+    }
+
+    async fn move_after(&self, id: i64, after_id: Option<i64>) -> Result<TaskModel> {
+        let task = self.inner.move_after(id, after_id).await?;
+        self.events.publish(TaskEventKind::Updated, task.clone());
+        Ok(task)
+    }
+
+    async fn delete(&self, id: i64) -> Result<bool> {
+        // The event carries the task as the delete saw it
+        Ok(self.delete_returning(id).await?.is_some())
+    }
+
+    async fn delete_returning(&self, id: i64) -> Result<Option<TaskModel>> {
+        let task = self.inner.delete_returning(id).await?;
+        if let Some(task) = &task {
+            self.events.publish(TaskEventKind::Deleted, task.clone());
+        }
+        Ok(task)
+    }
+
+    async fn complete_all(&self) -> Result<u64> {
+        self.inner.complete_all().await
+    }
+
+    async fn complete_matching(&self, filter: &TaskFilter) -> Result<u64> {
+        self.inner.complete_matching(filter).await
+    }
+
+    async fn reassign(&self, from: i64, to: i64) -> Result<u64> {
+        self.inner.reassign(from, to).await
+    }
+
+    async fn delete_all(&self) -> Result<u64> {
+        self.inner.delete_all().await
+    }
+
+    async fn archive_completed(&self) -> Result<u64> {
+        self.inner.archive_completed().await
+    }
+
+    async fn list_archived(&self, page: Page) -> Result<Vec<ArchivedTask>> {
+        self.inner.list_archived(page).await
+    }
+
+    async fn duplicates(&self) -> Result<Vec<DuplicateTasks>> {
+        self.inner.duplicates().await
+    }
+
+    async fn count_by_status(&self) -> Result<TaskStatusCounts> {
+        self.inner.count_by_status().await
+    }
+
+    async fn changes_since(&self, cursor: Option<i64>) -> Result<TaskChanges> {
+        self.inner.changes_since(cursor).await
+    }
+}
+
+pub fn event_routes(events: TaskEvents) -> Router {
+    Router::new()
+        .route("/tasks/ws", get(task_events_ws))
+        .route("/tasks/events", get(task_events_sse))
+        .with_state(events)
+}
+
+/// Live task events over a WebSocket
+///
+/// Each text message is a JSON `TaskEvent`.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/ws",
+    responses(
+        (status = 101, description = "Switching to the WebSocket protocol", body = TaskEvent),
+        (status = 400, description = "Not a WebSocket upgrade request", body = super::ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn task_events_ws(State(events): State<TaskEvents>, request: Request) -> Response {
+    let (response, upgraded) = match websocket::upgrade(request) {
+        Ok(upgrade) => upgrade,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    // Subscribe before answering so no event after the handshake is missed
+    let receiver = events.subscribe();
+    tokio::spawn(async move {
+        if let Some(socket) = upgraded.await {
+            push_events(socket, receiver).await;
+        }
+    });
+
+    response.into_response()
+}
+
+/// Live task events as server-sent events
+///
+/// Each `data:` line is a JSON `TaskEvent`, the same payload as `/api/tasks/ws`.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/events",
+    responses(
+        (status = 200, description = "`text/event-stream` of task events", body = TaskEvent),
+    ),
+    tag = "tasks"
+)]
+pub async fn task_events_sse(
+    State(events): State<TaskEvents>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = stream::unfold(events.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let event = Event::default()
+                        .json_data(&event)
+                        .expect("TaskEvent serializes");
+                    return Some((Ok(event), receiver));
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Forwards events until the client closes or goes away.
+async fn push_events(socket: websocket::Upgraded, mut events: broadcast::Receiver<TaskEvent>) {
+    let (reader, mut writer) = tokio::io::split(socket);
+    // `read_frame` isn't cancel-safe, so frames are read in their own task
+    // and handed over a channel, whose `recv` is.
+    let (mut frames, reading) = read_client_frames(reader);
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let payload = serde_json::to_vec(&event).expect("TaskEvent serializes");
+                    if websocket::write_frame(&mut writer, OPCODE_TEXT, &payload).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => {
+                    let _ = websocket::write_frame(&mut writer, OPCODE_CLOSE, &[]).await;
+                    break;
+                }
+            },
+            frame = frames.recv() => match frame {
+                Some((OPCODE_PING, payload)) => {
+                    if websocket::write_frame(&mut writer, OPCODE_PONG, &payload).await.is_err() {
+                        break;
+                    }
+                }
+                Some((OPCODE_CLOSE, payload)) => {
+                    let _ = websocket::write_frame(&mut writer, OPCODE_CLOSE, &payload).await;
+                    break;
+                }
+                Some(_) => {}
+                // Read error, bad frame or disconnect
+                None => break,
+            },
+        }
+    }
+
+    reading.abort();
+}
+
+/// Reads client frames in a task of their own until the connection fails
+/// or the receiver is dropped.
+fn read_client_frames(
+    mut reader: ReadHalf<websocket::Upgraded>,
+) -> (mpsc::Receiver<(u8, Vec<u8>)>, JoinHandle<()>) {
+    let (sender, receiver) = mpsc::channel(1);
+    let reading = tokio::spawn(async move {
+        while let Ok(frame) = websocket::read_frame(&mut reader).await {
+            if sender.send(frame).await.is_err() {
+                break;
+            }
+        }
+    });
+    (receiver, reading)
+}
@@ -0,0 +1,79 @@
+//! Refusing plaintext behind a TLS-terminating proxy. The proxy reports the
+//! scheme the client used in `X-Forwarded-Proto`; only installed with
+//! `REQUIRE_HTTPS=1`, since anyone can send the header when there's no
+//! proxy to overwrite it.
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use super::ErrorResponse;
+
+/// Redirects `GET` and `HEAD` requests the proxy received over plain HTTP
+/// to the same URL on `https` with `308`, and answers the rest `403`, since
+/// a redirect would drop their body. Requests without the header, such as
+/// health checks sent straight to the server, pass through.
+pub async fn require_https(request: Request, next: Next) -> Response {
+    if !arrived_over_http(request.headers()) {
+        return next.run(request).await;
+    }
+
+    let redirect = matches!(*request.method(), Method::GET | Method::HEAD)
+        .then(|| https_location(&request))
+        .flatten();
+    match redirect {
+        Some(location) => (
+            StatusCode::PERMANENT_REDIRECT,
+            [(header::LOCATION, location)],
+        )
+            .into_response(),
+        None => (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse::new("HTTPS is required")),
+        )
+            .into_response(),
+    }
+}
+
+/// Whether the first proxy's `X-Forwarded-Proto` says `http`.
+fn arrived_over_http(headers: &HeaderMap) -> bool {
+    headers
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("http"))
+}
+
+/// The request's URL on `https`, from its `Host`; `None` without one.
+fn https_location(request: &Request) -> Option<HeaderValue> {
+    let host = request.headers().get(header::HOST)?.to_str().ok()?;
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or("/", |path| path.as_str());
+    HeaderValue::from_str(&format!("https://{}{}", host, path)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrived_over_http() {
+        let with = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-forwarded-proto", HeaderValue::from_static(value));
+            arrived_over_http(&headers)
+        };
+
+        assert!(with("http"));
+        assert!(with("HTTP, https"));
+        assert!(!with("https"));
+        assert!(!with("https, http"));
+        assert!(!arrived_over_http(&HeaderMap::new()));
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tokio_util::sync::CancellationToken;
+
+use super::{ErrorResponse, InflightRequestResponse, RequestId};
+
+struct InflightEntry {
+    /// The `X-Request-Id`, which clients may repeat
+    request_id: String,
+    method: String,
+    path: String,
+    started: Instant,
+    token: CancellationToken,
+}
+
+/// Shared registry of the REST requests currently being handled, so
+/// operators can inspect and cancel them. gRPC calls aren't tracked. Entries
+/// are keyed by an id the registry assigns, since clients can send the same
+/// request id twice.
+#[derive(Clone, Default)]
+pub struct InflightRegistry {
+    entries: Arc<Mutex<HashMap<i64, InflightEntry>>>,
+    last_id: Arc<AtomicI64>,
+}
+
+impl InflightRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot of in-flight requests, oldest first.
+    pub fn list(&self) -> Vec<InflightRequestResponse> {
+        let entries = self.entries.lock().unwrap();
+        let mut requests: Vec<_> = entries
+            .iter()
+            .map(|(id, entry)| (entry.started, id, entry))
+            .collect();
+        requests.sort_by_key(|(started, _, _)| *started);
+
+        requests
+            .into_iter()
+            .map(|(started, _, entry)| InflightRequestResponse {
+                request_id: entry.request_id.clone(),
+                method: entry.method.clone(),
+                path: entry.path.clone(),
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            })
+            .collect()
+    }
+
+    /// Signals cancellation to the request with the given request id. Nothing
+    /// is cancelled when no request, or more than one, carries that id.
+    pub fn cancel(&self, request_id: &str) -> Result<(), CancelError> {
+        let entries = self.entries.lock().unwrap();
+        let matching: Vec<_> = entries
+            .values()
+            .filter(|entry| entry.request_id == request_id)
+            .collect();
+        match matching.as_slice() {
+            [] => Err(CancelError::NotInFlight),
+            [entry] => {
+                entry.token.cancel();
+                Ok(())
+            }
+            _ => Err(CancelError::Ambiguous(matching.len())),
+        }
+    }
+
+    fn register(&self, request_id: String, method: String, path: String) -> InflightGuard {
+        let id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let token = CancellationToken::new();
+        self.entries.lock().unwrap().insert(
+            id,
+            InflightEntry {
+                request_id,
+                method,
+                path,
+                started: Instant::now(),
+                token: token.clone(),
+            },
+        );
+
+        InflightGuard {
+            registry: self.clone(),
+            id,
+            token,
+        }
+    }
+}
+
+/// Why [`InflightRegistry::cancel`] signalled nothing.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CancelError {
+    NotInFlight,
+    /// This many in-flight requests share the request id.
+    Ambiguous(usize),
+}
+
+/// Removes the entry once the request finishes, is cancelled, or its future
+/// is dropped (client disconnect).
+struct InflightGuard {
+    registry: InflightRegistry,
+    id: i64,
+    token: CancellationToken,
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.registry.entries.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Tracks each request in the registry and aborts the handler if an operator
+/// cancels it. Must run inside the request id middleware.
+pub async fn track_inflight(
+    State(registry): State<InflightRegistry>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(RequestId(request_id)) = request.extensions().get::<RequestId>().cloned() else {
+        return next.run(request).await;
+    };
+
+    let guard = registry.register(
+        request_id,
+        request.method().to_string(),
+        request.uri().path().to_string(),
+    );
+
+    tokio::select! {
+        response = next.run(request) => response,
+        _ = guard.token.cancelled() => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse::new("Request cancelled by operator".to_string())),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_request_ids_get_their_own_entries() {
+        let registry = InflightRegistry::new();
+        let first = registry.register("dup".to_string(), "GET".to_string(), "/a".to_string());
+        let second = registry.register("dup".to_string(), "GET".to_string(), "/b".to_string());
+        assert_eq!(registry.list().len(), 2);
+
+        assert_eq!(registry.cancel("dup"), Err(CancelError::Ambiguous(2)));
+        assert!(!first.token.is_cancelled() && !second.token.is_cancelled());
+        assert_eq!(registry.cancel("other"), Err(CancelError::NotInFlight));
+
+        // Finishing one request leaves the other's entry in place
+        drop(first);
+        let remaining = registry.list();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].path, "/b");
+
+        assert_eq!(registry.cancel("dup"), Ok(()));
+        assert!(second.token.is_cancelled());
+    }
+}
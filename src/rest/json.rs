@@ -0,0 +1,47 @@
+//! `Json` with rejections in the API's `ErrorResponse` shape.
+
+use axum::{
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+use super::ErrorResponse;
+
+/// Drop-in for `Json` as a request extractor. Bodies that aren't valid JSON,
+/// or don't match the expected type, are answered with
+/// `422 Unprocessable Entity` and `{ "error": "..." }`. A missing or
+/// non-JSON `Content-Type` gets `415 Unsupported Media Type` before the body
+/// is read; other rejections (oversized body) keep their status.
+pub struct JsonBody<T>(pub T);
+
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(request, state).await {
+            Ok(Json(value)) => Ok(Self(value)),
+            Err(rejection) => Err(rejection_response(rejection)),
+        }
+    }
+}
+
+fn rejection_response(rejection: JsonRejection) -> Response {
+    let (status, message) = match &rejection {
+        JsonRejection::JsonSyntaxError(_) | JsonRejection::JsonDataError(_) => {
+            (StatusCode::UNPROCESSABLE_ENTITY, rejection.body_text())
+        }
+        JsonRejection::MissingJsonContentType(_) => (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Content-Type must be application/json".to_string(),
+        ),
+        other => (other.status(), rejection.body_text()),
+    };
+    (status, Json(ErrorResponse::new(message))).into_response()
+}
@@ -0,0 +1,78 @@
+//! JSON arrays written element by element, for lists too large to buffer.
+
+use anyhow::Result;
+use axum::{
+    body::Body,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde::Serialize;
+
+/// Marks a response whose body is produced as it's sent. Middleware that
+/// would buffer the body (compression, envelopes, body logging) leaves these
+/// alone.
+#[derive(Debug, Clone, Copy)]
+pub struct Streamed;
+
+/// Whether `response` carries the [`Streamed`] marker.
+pub fn is_streamed(response: &Response) -> bool {
+    response.extensions().get::<Streamed>().is_some()
+}
+
+/// An `application/json` response writing `items` as one array, each
+/// element serialized when the stream yields it. An error ends the body
+/// early, leaving the array unterminated so clients can't mistake a partial
+/// list for a complete one.
+pub fn json_array<T>(items: BoxStream<'static, Result<T>>) -> Response
+where
+    T: Serialize + Send + 'static,
+{
+    let elements = items.enumerate().map(|(index, item)| {
+        item.map(|item| {
+            let mut chunk = if index == 0 { Vec::new() } else { vec![b','] };
+            serde_json::to_writer(&mut chunk, &item).expect("array element serializes");
+            chunk
+        })
+    });
+    let body = stream::once(async { Ok(b"[".to_vec()) })
+        .chain(elements)
+        .chain(stream::once(async { Ok(b"]".to_vec()) }));
+
+    let mut response = (
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from_stream(body),
+    )
+        .into_response();
+    response.extensions_mut().insert(Streamed);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body;
+
+    #[tokio::test]
+    async fn test_json_array_joins_elements() {
+        let read = |items: Vec<i64>| async move {
+            let response = json_array(stream::iter(items.into_iter().map(Ok)).boxed());
+            assert!(is_streamed(&response));
+            let bytes = body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+
+        assert_eq!(read(vec![]).await, "[]");
+        assert_eq!(read(vec![1]).await, "[1]");
+        assert_eq!(read(vec![1, 2, 3]).await, "[1,2,3]");
+    }
+
+    #[tokio::test]
+    async fn test_json_array_fails_body_on_error() {
+        let items = stream::iter(vec![Ok(1), Err(anyhow::anyhow!("connection lost"))]).boxed();
+        let result = body::to_bytes(json_array(items).into_body(), usize::MAX).await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! Response counters by route and status class, served as Prometheus text
+//! on `GET /metrics` so error-rate spikes can be alerted on.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Route label for requests no route matched, so probes of random paths
+/// can't grow the label set.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// `(method, route template, status class)`
+type RouteKey = (&'static str, String, &'static str);
+
+/// Response counts per method, route template and status class. Clones
+/// share the counts.
+#[derive(Clone, Debug, Default)]
+pub struct RouteMetrics {
+    counts: Arc<Mutex<BTreeMap<RouteKey, u64>>>,
+}
+
+impl RouteMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Responses counted so far for `method` on `route` in `class`
+    /// (`"2xx"`, `"4xx"`, ...).
+    pub fn count(&self, method: &Method, route: &str, class: &str) -> u64 {
+        let method = method_label(method);
+        self.counts
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|((m, r, c), _)| *m == method && r == route && *c == class)
+            .map(|(_, count)| *count)
+            .unwrap_or(0)
+    }
+
+    fn record(&self, method: &Method, route: &str, status: StatusCode) {
+        let key = (
+            method_label(method),
+            route.to_string(),
+            status_class(status),
+        );
+        *self.counts.lock().unwrap().entry(key).or_insert(0) += 1;
+    }
+
+    /// The counters in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::from(
+            "# HELP http_responses_total REST responses by route template and status class.\n\
+             # TYPE http_responses_total counter\n",
+        );
+        for ((method, route, class), count) in self.counts.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "http_responses_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                method,
+                escape_label(route),
+                class,
+                count
+            );
+        }
+        out
+    }
+}
+
+/// Standard methods by name; anything else is `OTHER`, so made-up methods
+/// don't add labels.
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        _ => "OTHER",
+    }
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Counts every response under the route template that matched, never the
+/// raw path. Must be added with `Router::layer` so [`MatchedPath`] is set.
+pub async fn track_route_metrics(
+    State(metrics): State<RouteMetrics>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+
+    let response = next.run(request).await;
+    metrics.record(&method, &route, response.status());
+    response
+}
+
+/// `GET /metrics`
+pub async fn metrics(State(metrics): State<RouteMetrics>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_groups_by_status_class() {
+        let metrics = RouteMetrics::new();
+        metrics.record(&Method::GET, "/api/tasks/{id}", StatusCode::OK);
+        metrics.record(&Method::GET, "/api/tasks/{id}", StatusCode::NOT_FOUND);
+        metrics.record(&Method::GET, "/api/tasks/{id}", StatusCode::BAD_REQUEST);
+        metrics.record(
+            &Method::from_bytes(b"BREW").unwrap(),
+            UNMATCHED_ROUTE,
+            StatusCode::NOT_FOUND,
+        );
+
+        assert_eq!(metrics.count(&Method::GET, "/api/tasks/{id}", "4xx"), 2);
+        assert_eq!(metrics.count(&Method::GET, "/api/tasks/{id}", "5xx"), 0);
+
+        let text = metrics.render();
+        assert!(text.contains(
+            "http_responses_total{method=\"GET\",route=\"/api/tasks/{id}\",status=\"2xx\"} 1\n"
+        ));
+        assert!(text.contains(
+            "http_responses_total{method=\"OTHER\",route=\"unmatched\",status=\"4xx\"} 1\n"
+        ));
+    }
+}
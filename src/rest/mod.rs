@@ -1,48 +1,651 @@
+pub mod admin_handlers;
+pub mod catch_panic;
+pub mod compression;
+pub mod cors;
+pub mod debug_bodies;
+pub mod envelope;
+pub mod etag;
+pub mod events;
+pub mod https;
+pub mod inflight;
+pub mod json;
+pub mod json_stream;
+pub mod metrics;
+pub mod negotiate;
+pub mod openapi;
+pub mod pagination;
+pub mod path;
+pub mod rate_limit;
+pub mod readiness;
+pub mod request_id;
+pub mod sync_token;
 pub mod task_handlers;
 pub mod user_handlers;
+pub mod websocket;
 
+pub use admin_handlers::{admin_routes, AdminState};
+pub use events::{event_routes, PublishingTaskRepository, TaskEvent, TaskEventKind, TaskEvents};
+pub use inflight::InflightRegistry;
+pub use metrics::RouteMetrics;
+pub use openapi::ApiDoc;
+pub use pagination::MaxPageSize;
+pub use rate_limit::{RateLimit, RateLimiter};
+pub use readiness::Readiness;
+pub use request_id::{RequestId, REQUEST_ID_HEADER};
 pub use task_handlers::task_routes;
 pub use user_handlers::user_routes;
 
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::DefaultBodyLimit, http::StatusCode, middleware, routing::get, Extension, Json, Router,
+};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use tower_http::{
+    catch_panic::CatchPanicLayer, limit::RequestBodyLimitLayer, timeout::TimeoutLayer,
+};
+use utoipa::{IntoParams, ToSchema};
+use utoipa_swagger_ui::{Config as SwaggerConfig, SwaggerUi};
+
+use crate::config::{self, Config};
+use crate::db::TaskStatus;
+use crate::repository::{MaintenanceRepository, TaskRepository, UserRepository};
+use crate::validation::ValidationErrors;
+
+/// Request bodies larger than this are rejected with `413 Payload Too Large`.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Requests still running after this get `504 Gateway Timeout`.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where the REST routes are mounted unless `API_PREFIX` says otherwise.
+/// The `path`s in the OpenAPI annotations are written against it.
+pub const DEFAULT_API_PREFIX: &str = "/api";
+
+/// Where Swagger UI is mounted unless `SWAGGER_PATH` says otherwise.
+pub const DEFAULT_SWAGGER_PATH: &str = "/swagger-ui";
+
+/// Tunables for the middleware applied by `create_router`.
+#[derive(Clone)]
+pub struct RouterConfig {
+    pub max_body_bytes: usize,
+    pub request_timeout: Duration,
+    /// Mount point of the REST routes, e.g. `/api`; empty serves them from
+    /// the root. The OpenAPI spec lists its paths under the same prefix.
+    pub api_prefix: String,
+    /// Mount point of Swagger UI. `None` leaves out both the UI and
+    /// `/api-docs/openapi.json`.
+    pub swagger_path: Option<String>,
+    /// Bearer token required on `/api/admin` routes; unset turns them off.
+    pub admin_token: Option<String>,
+    /// Backs `POST /api/admin/vacuum` and the other database endpoints
+    /// under `/api/admin`.
+    pub maintenance: Option<Arc<dyn MaintenanceRepository>>,
+    /// Reported by `GET /api/admin/config`.
+    pub effective_config: Option<Arc<Config>>,
+    /// Answer a panicking handler with `500` instead of dropping the
+    /// connection.
+    pub catch_panics: bool,
+    /// Largest `limit` the list endpoints honour, and their page size when
+    /// none is given.
+    pub max_page_size: i64,
+    /// Backs `GET /ready`; unset means ready from the start.
+    pub readiness: Option<Readiness>,
+    /// Log JSON request and response bodies at debug level.
+    pub debug_bodies: bool,
+    /// Per-client-IP request limit; unset leaves clients unlimited.
+    pub rate_limit: Option<RateLimit>,
+    /// Refuse requests the proxy says arrived over plain HTTP.
+    pub require_https: bool,
+    /// Feed behind `/api/tasks/ws` and `/api/tasks/events`. When set, the
+    /// task repository handed to the router must already publish to it
+    /// (see [`PublishingTaskRepository`]), so writes made outside REST reach
+    /// subscribers too; unset, the router wraps the repository itself.
+    pub events: Option<TaskEvents>,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            api_prefix: DEFAULT_API_PREFIX.to_string(),
+            swagger_path: Some(DEFAULT_SWAGGER_PATH.to_string()),
+            admin_token: None,
+            maintenance: None,
+            effective_config: None,
+            catch_panics: true,
+            max_page_size: config::DEFAULT_MAX_PAGE_SIZE,
+            readiness: None,
+            debug_bodies: false,
+            rate_limit: None,
+            require_https: false,
+            events: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for RouterConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RouterConfig")
+            .field("max_body_bytes", &self.max_body_bytes)
+            .field("request_timeout", &self.request_timeout)
+            .field("api_prefix", &self.api_prefix)
+            .field("swagger_path", &self.swagger_path)
+            .field(
+                "admin_token",
+                &self.admin_token.as_ref().map(|_| "<redacted>"),
+            )
+            .field("maintenance", &self.maintenance.is_some())
+            .field("effective_config", &self.effective_config)
+            .field("catch_panics", &self.catch_panics)
+            .field("max_page_size", &self.max_page_size)
+            .field("readiness", &self.readiness)
+            .field("debug_bodies", &self.debug_bodies)
+            .field("rate_limit", &self.rate_limit)
+            .field("require_https", &self.require_https)
+            .field("events", &self.events.is_some())
+            .finish()
+    }
+}
+
+impl RouterConfig {
+    /// Reads `MAX_BODY_BYTES`, `REQUEST_TIMEOUT_SECS`, `API_PREFIX`,
+    /// `SWAGGER_ENABLED`, `SWAGGER_PATH`, `ADMIN_TOKEN`, `CATCH_PANICS`,
+    /// `MAX_PAGE_SIZE`, `DEBUG_BODIES`, the `RATE_LIMIT_*` settings and
+    /// `REQUIRE_HTTPS`, falling back to the defaults for unset or unparsable values.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_body_bytes: env_parse("MAX_BODY_BYTES").unwrap_or(defaults.max_body_bytes),
+            request_timeout: env_parse("REQUEST_TIMEOUT_SECS")
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.request_timeout),
+            api_prefix: api_prefix_from(std::env::var("API_PREFIX").ok().as_deref()),
+            swagger_path: swagger_path_from(
+                std::env::var("SWAGGER_ENABLED").ok().as_deref(),
+                std::env::var("SWAGGER_PATH").ok().as_deref(),
+            ),
+            admin_token: std::env::var("ADMIN_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+            maintenance: None,
+            effective_config: None,
+            catch_panics: !is_off(std::env::var("CATCH_PANICS").ok().as_deref()),
+            max_page_size: config::max_page_size(),
+            readiness: None,
+            debug_bodies: is_on(std::env::var("DEBUG_BODIES").ok().as_deref()),
+            rate_limit: RateLimit::from_env(),
+            require_https: is_on(std::env::var("REQUIRE_HTTPS").ok().as_deref()),
+            events: None,
+        }
+    }
+
+    pub fn with_maintenance(mut self, maintenance: Arc<dyn MaintenanceRepository>) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    pub fn with_effective_config(mut self, config: Config) -> Self {
+        self.effective_config = Some(Arc::new(config));
+        self
+    }
+
+    pub fn with_readiness(mut self, readiness: Readiness) -> Self {
+        self.readiness = Some(readiness);
+        self
+    }
+
+    pub fn with_events(mut self, events: TaskEvents) -> Self {
+        self.events = Some(events);
+        self
+    }
+}
+
+/// Swagger is on unless `enabled` is `0`, `false` or `off`; `path` gets a
+/// leading `/` and loses any trailing one.
+fn swagger_path_from(enabled: Option<&str>, path: Option<&str>) -> Option<String> {
+    if is_off(enabled) {
+        return None;
+    }
+
+    let path = path.map(str::trim).unwrap_or_default().trim_matches('/');
+    if path.is_empty() {
+        Some(DEFAULT_SWAGGER_PATH.to_string())
+    } else {
+        Some(format!("/{}", path))
+    }
+}
+
+/// Unset means [`DEFAULT_API_PREFIX`]; otherwise `prefix` gets a leading
+/// `/` and loses any trailing one, so `/` serves the routes from the root.
+fn api_prefix_from(prefix: Option<&str>) -> String {
+    match prefix.map(|prefix| prefix.trim().trim_matches('/')) {
+        None => DEFAULT_API_PREFIX.to_string(),
+        Some("") => String::new(),
+        Some(prefix) => format!("/{}", prefix),
+    }
+}
+
+/// Whether a flag is set to `0`, `false` or `off`.
+fn is_off(value: Option<&str>) -> bool {
+    matches!(
+        value.map(|v| v.trim().to_ascii_lowercase()).as_deref(),
+        Some("0") | Some("false") | Some("off")
+    )
+}
+
+/// Whether a flag is set to `1`, `true` or `on`.
+fn is_on(value: Option<&str>) -> bool {
+    matches!(
+        value.map(|v| v.trim().to_ascii_lowercase()).as_deref(),
+        Some("1") | Some("true") | Some("on")
+    )
+}
+
+fn env_parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+}
+
+/// Builds the full REST application: the `/api` routes, Swagger UI and the
+/// middleware shared by every route, configured from the environment.
+pub fn create_router<T, U>(task_repository: Arc<T>, user_repository: Arc<U>) -> Router
+where
+    T: TaskRepository + ?Sized + 'static,
+    U: UserRepository + ?Sized + 'static,
+{
+    create_router_with_config(task_repository, user_repository, RouterConfig::from_env())
+}
+
+pub fn create_router_with_config<T, U>(
+    task_repository: Arc<T>,
+    user_repository: Arc<U>,
+    config: RouterConfig,
+) -> Router
+where
+    T: TaskRepository + ?Sized + 'static,
+    U: UserRepository + ?Sized + 'static,
+{
+    let inflight = InflightRegistry::new();
+    let route_metrics = RouteMetrics::new();
+    let tasks = match config.events {
+        Some(events) => task_routes(task_repository).merge(event_routes(events)),
+        None => {
+            let events = TaskEvents::new();
+            let task_repository = Arc::new(PublishingTaskRepository::new(
+                task_repository,
+                events.clone(),
+            ));
+            task_routes(task_repository).merge(event_routes(events))
+        }
+    };
+
+    let mut router = Router::new()
+        .merge(
+            Router::new()
+                .route("/ready", get(readiness::ready))
+                .with_state(config.readiness.unwrap_or_else(Readiness::ready)),
+        )
+        .merge(
+            Router::new()
+                .route("/metrics", get(metrics::metrics))
+                .with_state(route_metrics.clone()),
+        );
+    if let Some(path) = config.swagger_path {
+        // Served by our own handler so `?tag=` can scope the spec
+        let spec = Router::new()
+            .route(openapi::OPENAPI_PATH, get(openapi::openapi_json))
+            .with_state(Arc::new(openapi::api_doc(&config.api_prefix)));
+        router = router
+            .merge(SwaggerUi::new(path).config(SwaggerConfig::from(openapi::OPENAPI_PATH)))
+            .merge(spec);
+    }
+
+    let api = tasks
+        .merge(user_routes(user_repository))
+        .merge(admin_routes(
+            AdminState {
+                inflight: inflight.clone(),
+                maintenance: config.maintenance,
+                config: config.effective_config,
+            },
+            config.admin_token,
+        ))
+        .layer(Extension(MaxPageSize(config.max_page_size)))
+        .layer(middleware::from_fn(envelope::envelope_middleware));
+    // axum can't nest at the root, so an empty prefix merges instead
+    router = if config.api_prefix.is_empty() {
+        router.merge(api)
+    } else {
+        router.nest(&config.api_prefix, api)
+    };
+    router = router.fallback(route_not_found);
+    if config.catch_panics {
+        router = router.layer(CatchPanicLayer::custom(catch_panic::panic_response));
+    }
+    if config.debug_bodies {
+        // Inside the body limit, which bounds what it buffers
+        router = router.layer(middleware::from_fn(debug_bodies::log_bodies));
+    }
+
+    router = router
+        // Replace axum's built-in 2MB extractor limit so ours is the only one
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(config.max_body_bytes))
+        .layer(middleware::from_fn_with_state(
+            inflight,
+            inflight::track_inflight,
+        ))
+        .layer(TimeoutLayer::with_status_code(
+            StatusCode::GATEWAY_TIMEOUT,
+            config.request_timeout,
+        ));
+    if let Some(limit) = config.rate_limit {
+        // Inside the metrics so 429s are counted
+        router = router.layer(middleware::from_fn_with_state(
+            RateLimiter::new(limit),
+            rate_limit::limit_per_client,
+        ));
+    }
+    if config.require_https {
+        // Outside the rate limit, so refused requests don't use up tokens
+        router = router.layer(middleware::from_fn(https::require_https));
+    }
+
+    router
+        // Outside the timeout so 504s are counted too
+        .layer(middleware::from_fn_with_state(
+            route_metrics,
+            metrics::track_route_metrics,
+        ))
+        .layer(middleware::from_fn(compression::compression_middleware))
+        .layer(middleware::from_fn(request_id::request_id_middleware))
+}
 
 // ============================================================================
 // Task DTOs
 // ============================================================================
 
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct TaskResponse {
     pub id: i64,
     pub title: String,
     pub description: String,
+    /// Whether `status` is `done`, kept for older clients
     pub completed: bool,
+    pub status: TaskStatus,
+    pub assigned_user_id: Option<i64>,
+    /// Sorted, without duplicates
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// When the task was last marked completed; unset while pending
+    pub completed_at: Option<String>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateTaskRequest {
     pub title: String,
     pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
+/// Partial update: omitted fields keep their value. Nullable fields tell
+/// `null` apart from omission, so `"assigned_user_id": null` unassigns the
+/// task while leaving the key out keeps the current assignee.
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateTaskRequest {
     pub title: Option<String>,
     pub description: Option<String>,
+    /// `true` sets the status to `done` and `false` to `todo`
     pub completed: Option<bool>,
+    /// Wins over `completed`, which must agree with it if also sent
+    pub status: Option<TaskStatus>,
+    /// Replaces every tag on the task when present
+    pub tags: Option<Vec<String>>,
+    /// User to assign the task to, or `null` to unassign it
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<i64>, nullable = true)]
+    pub assigned_user_id: Option<Option<i64>>,
+}
+
+/// Deserializes a present field, `null` included, as `Some`. Paired with
+/// `#[serde(default)]`, an absent field stays `None`.
+fn nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListTasksQuery {
+    /// Maximum number of tasks to return, capped at `MAX_PAGE_SIZE`
+    pub limit: Option<i64>,
+    /// Number of tasks to skip
+    pub offset: Option<i64>,
+    /// Comma-separated fields to include, e.g. `id,title`
+    pub fields: Option<String>,
+    /// Only tasks with this status
+    pub status: Option<TaskStatus>,
+    /// With `true`, every task is written as one JSON array while rows are
+    /// read, in id order, instead of a page. Can't be combined with paging,
+    /// `fields` or filters
+    pub stream: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CreateTaskQuery {
+    /// With `false`, an over-long description is accepted and reported in a
+    /// `Warning` header instead of failing the request
+    pub strict: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RecentTasksQuery {
+    /// RFC 3339 timestamp, e.g. `2025-01-02T03:04:05Z`
+    pub since: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LatestTasksQuery {
+    /// How many tasks to return; 10 when absent, capped at `MAX_PAGE_SIZE`
+    pub n: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CompletedTasksQuery {
+    /// RFC 3339 timestamp; the range includes it
+    pub from: Option<String>,
+    /// RFC 3339 timestamp; the range includes it
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchGetTasksRequest {
+    /// Ids to fetch; ids with no task are left out of the response
+    pub ids: Vec<i64>,
+}
+
+/// Answer of `POST /api/tasks/validate` for a payload that passes.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ValidationResponse {
+    pub valid: bool,
+}
+
+/// Where `POST /api/tasks/{id}/move` puts the task.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct MoveTaskRequest {
+    /// Task to place it right after; omitted or `null` moves it to the top
+    #[serde(default)]
+    pub after_id: Option<i64>,
+}
+
+/// Which tasks `POST /api/tasks/bulk-complete` marks done. At least one
+/// filter is required; when both are given a task must match both.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkCompleteRequest {
+    /// Only tasks assigned to this user
+    pub assigned_user_id: Option<i64>,
+    /// Only tasks carrying this tag
+    pub tag: Option<String>,
+}
+
+/// One recorded change to a task, as returned by
+/// `GET /api/tasks/{id}/history`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TaskHistoryEntry {
+    pub id: i64,
+    /// `create`, `update` or `delete`
+    pub op: String,
+    /// The task's columns before the change; unset for `create`
+    #[schema(value_type = Option<Object>)]
+    pub old: Option<serde_json::Value>,
+    /// The task's columns after the change; unset for `delete`
+    #[schema(value_type = Option<Object>)]
+    pub new: Option<serde_json::Value>,
+    pub at: String,
+}
+
+/// Result of a bulk operation.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AffectedResponse {
+    /// Number of rows the operation changed, or would change on a dry run
+    pub affected: u64,
+    /// Ids of the tasks a dry run would change, in id order; left out when
+    /// the operation ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ids: Option<Vec<i64>>,
+}
+
+/// What a successful `DELETE /api/tasks/{id}` sends back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReturnPreference {
+    /// `204 No Content`
+    #[default]
+    Minimal,
+    /// `200 OK` with the deleted task
+    Representation,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteTaskQuery {
+    /// `representation` returns the deleted task instead of an empty body
+    #[serde(rename = "return")]
+    #[param(rename = "return")]
+    pub return_preference: Option<ReturnPreference>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DryRunQuery {
+    /// With `true`, report what would change without changing anything
+    pub dry_run: Option<bool>,
+}
+
+/// A task moved out of the active list by
+/// `POST /api/tasks/archive-completed`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ArchivedTaskResponse {
+    /// The task as it was when archived
+    pub task: TaskResponse,
+    pub archived_at: String,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ArchivedTasksQuery {
+    /// Maximum number of tasks to return, capped at `MAX_PAGE_SIZE`
+    pub limit: Option<i64>,
+    /// Number of tasks to skip
+    pub offset: Option<i64>,
+}
+
+/// Tasks whose titles match once trimmed and lower-cased.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DuplicateTasksResponse {
+    /// The shared title, trimmed and lower-cased
+    pub title: String,
+    pub count: usize,
+    /// The tasks, in id order
+    pub tasks: Vec<TaskResponse>,
+}
+
+/// Task counts by completion, for summary widgets.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TaskCountsResponse {
+    /// Tasks with status `done`
+    pub completed: i64,
+    /// Every other task, cancelled ones included
+    pub pending: i64,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SyncTasksQuery {
+    /// The `token` from the previous sync; omit it for a full sync
+    pub token: Option<String>,
+}
+
+/// Task changes since a sync token.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TaskSyncResponse {
+    /// Tasks created or updated since the token, least recently updated
+    /// first; every task when `reset` is true
+    pub tasks: Vec<TaskResponse>,
+    /// Ids of tasks deleted or archived since the token
+    pub deleted: Vec<i64>,
+    /// Pass as `token` on the next sync
+    pub token: String,
+    /// `tasks` is the full list, either because no token was given or
+    /// because the token's history was cleared; replace the local copy
+    pub reset: bool,
 }
 
 // ============================================================================
 // User DTOs
 // ============================================================================
 
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UserResponse {
     pub id: i64,
     pub name: String,
     pub email: String,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserTaskCountsResponse {
+    pub user_id: i64,
+    pub name: String,
+    /// Tasks assigned to the user
+    pub total: i64,
+    /// Of those, how many are completed
+    pub completed: i64,
+}
+
+/// A user with counts of the tasks assigned to them, for
+/// `GET /api/users/{id}/summary`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserSummaryResponse {
+    pub user: UserResponse,
+    /// Tasks assigned to the user
+    pub task_total: i64,
+    /// Of those, how many are completed
+    pub task_completed: i64,
+}
+
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateUserRequest {
     pub name: String,
@@ -55,11 +658,193 @@ pub struct UpdateUserRequest {
     pub email: Option<String>,
 }
 
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListUsersQuery {
+    /// Maximum number of users to return, capped at `MAX_PAGE_SIZE`
+    pub limit: Option<i64>,
+    /// Number of users to skip
+    pub offset: Option<i64>,
+    /// Only return users whose name contains this text (case-insensitive)
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchUsersQuery {
+    /// Text the name must contain (case-insensitive)
+    pub q: Option<String>,
+}
+
+/// What to do with the user's assigned tasks on delete.
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OnTasks {
+    /// Reject the delete with 409 if the user has tasks
+    #[default]
+    Restrict,
+    /// Unassign the tasks
+    Nullify,
+    /// Delete the tasks too
+    Delete,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteUserQuery {
+    /// Policy for tasks assigned to the user (default `restrict`)
+    pub on_tasks: Option<OnTasks>,
+}
+
+// ============================================================================
+// Admin DTOs
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct InflightRequestResponse {
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct VacuumResponse {
+    /// Database size before compaction
+    pub size_before_bytes: i64,
+    /// Database size after compaction
+    pub size_after_bytes: i64,
+    /// Time spent in `VACUUM`
+    pub vacuum_ms: u64,
+    /// Time spent in `PRAGMA optimize`
+    pub optimize_ms: u64,
+}
+
+/// Answer of `GET /api/admin/pool`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PoolStatsResponse {
+    /// Open connections, idle or not
+    pub size: u32,
+    /// Open connections waiting to be checked out
+    pub idle: u32,
+    /// Open connections checked out by a query or transaction
+    pub in_use: u32,
+    /// Most connections the pool will open
+    pub max: u32,
+}
+
+/// Every task and user, as served by `GET /api/admin/backup` and accepted
+/// by `POST /api/admin/restore`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackupDocument {
+    pub tasks: Vec<BackupTask>,
+    pub users: Vec<UserResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackupTask {
+    pub id: i64,
+    pub title: String,
+    pub description: String,
+    pub completed: bool,
+    /// Derived from `completed` when missing, as in older backups
+    #[serde(default)]
+    pub status: Option<TaskStatus>,
+    pub assigned_user_id: Option<i64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    /// Manual sort position; unset until the task is first moved
+    #[serde(default)]
+    pub position: Option<f64>,
+}
+
 // ============================================================================
 // Error Response
 // ============================================================================
 
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ErrorResponse {
     pub error: String,
+    /// Per-field problems, present on validation errors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldErrorResponse>,
+}
+
+impl ErrorResponse {
+    pub fn new(error: impl Into<String>) -> Self {
+        Self {
+            error: error.into(),
+            details: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FieldErrorResponse {
+    pub field: String,
+    pub message: String,
+}
+
+impl From<ValidationErrors> for ErrorResponse {
+    fn from(errors: ValidationErrors) -> Self {
+        Self {
+            error: errors.to_string(),
+            details: errors
+                .0
+                .into_iter()
+                .map(|error| FieldErrorResponse {
+                    field: error.field.to_string(),
+                    message: error.message,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// `400 Bad Request` listing every validation failure.
+pub fn validation_error(errors: ValidationErrors) -> (StatusCode, Json<ErrorResponse>) {
+    (StatusCode::BAD_REQUEST, Json(errors.into()))
+}
+
+/// `404 Not Found` for paths no route matches, so they get the same JSON
+/// error body as every other failure.
+pub async fn route_not_found() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse::new("route not found")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swagger_path_from_env_values() {
+        assert_eq!(
+            swagger_path_from(None, None).as_deref(),
+            Some("/swagger-ui")
+        );
+        assert_eq!(
+            swagger_path_from(Some("1"), Some("docs/")).as_deref(),
+            Some("/docs")
+        );
+        assert_eq!(
+            swagger_path_from(None, Some("/api/docs")).as_deref(),
+            Some("/api/docs")
+        );
+        assert_eq!(swagger_path_from(Some("false"), Some("/docs")), None);
+    }
+
+    #[test]
+    fn test_api_prefix_from_env_values() {
+        assert_eq!(api_prefix_from(None), "/api");
+        assert_eq!(api_prefix_from(Some(" v1/ ")), "/v1");
+        assert_eq!(api_prefix_from(Some("/internal/api")), "/internal/api");
+        assert_eq!(api_prefix_from(Some("/")), "");
+    }
 }
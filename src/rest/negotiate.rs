@@ -0,0 +1,204 @@
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::ErrorResponse;
+
+/// Response encodings a client can ask for with `Accept`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Xml,
+}
+
+/// Picks the encoding for `Accept`, preferring the highest `q` and JSON on
+/// ties. No header means JSON; `None` means nothing acceptable is supported.
+pub fn negotiate(headers: &HeaderMap) -> Option<Format> {
+    let Some(accept) = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Some(Format::Json);
+    };
+
+    let mut best: Option<(f32, Format)> = None;
+    for range in accept.split(',') {
+        let mut params = range.split(';').map(str::trim);
+        let media = params.next().unwrap_or_default().to_ascii_lowercase();
+        let q = params
+            .find_map(|param| param.strip_prefix("q="))
+            .and_then(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        let format = match media.as_str() {
+            "application/json" | "application/*" | "*/*" => Format::Json,
+            "application/xml" | "text/xml" => Format::Xml,
+            _ => continue,
+        };
+
+        if q > 0.0 && best.is_none_or(|(best_q, _)| q > best_q) {
+            best = Some((q, format));
+        }
+    }
+
+    best.map(|(_, format)| format)
+}
+
+/// `406 Not Acceptable` for an `Accept` header `negotiate` rejected.
+pub fn not_acceptable() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_ACCEPTABLE,
+        Json(ErrorResponse::new(
+            "Supported response types: application/json, application/xml".to_string(),
+        )),
+    )
+}
+
+/// Adds `Vary: Accept` to a negotiated route's responses, so shared caches
+/// keep the JSON and XML forms apart. Applied per route rather than in the
+/// handler so it also covers 304s and errors.
+pub async fn vary_accept(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+    response
+}
+
+/// Body encoded as JSON or XML depending on the negotiated format. XML wraps
+/// the body in `root`, and each array element in `item`; it is rendered from
+/// the body's `serde_json::Value` so no XML crate is needed for flat DTOs.
+pub struct Negotiated<T> {
+    format: Format,
+    root: &'static str,
+    item: &'static str,
+    body: T,
+}
+
+impl<T: Serialize> Negotiated<T> {
+    pub fn new(format: Format, root: &'static str, body: T) -> Self {
+        Self {
+            format,
+            root,
+            item: "item",
+            body,
+        }
+    }
+
+    /// Element name for array entries, e.g. `task` inside `<tasks>`.
+    pub fn item(mut self, item: &'static str) -> Self {
+        self.item = item;
+        self
+    }
+}
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        match self.format {
+            Format::Json => Json(self.body).into_response(),
+            Format::Xml => {
+                let value = match serde_json::to_value(&self.body) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(ErrorResponse::new(e.to_string())),
+                        )
+                            .into_response()
+                    }
+                };
+
+                let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+                write_element(&mut xml, self.root, self.item, &value);
+
+                (
+                    [(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/xml"),
+                    )],
+                    xml,
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+fn write_element(out: &mut String, name: &str, item: &str, value: &Value) {
+    match value {
+        Value::Null => {
+            out.push_str(&format!("<{}/>", name));
+        }
+        Value::Object(fields) => {
+            out.push_str(&format!("<{}>", name));
+            for (key, field) in fields {
+                write_element(out, key, "item", field);
+            }
+            out.push_str(&format!("</{}>", name));
+        }
+        Value::Array(items) => {
+            out.push_str(&format!("<{}>", name));
+            for entry in items {
+                write_element(out, item, "item", entry);
+            }
+            out.push_str(&format!("</{}>", name));
+        }
+        Value::String(text) => {
+            out.push_str(&format!("<{}>{}</{}>", name, escape(text), name));
+        }
+        Value::Bool(_) | Value::Number(_) => {
+            out.push_str(&format!("<{}>{}</{}>", name, value, name));
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(negotiate(&HeaderMap::new()), Some(Format::Json));
+        assert_eq!(negotiate(&accept("*/*")), Some(Format::Json));
+        assert_eq!(negotiate(&accept("application/xml")), Some(Format::Xml));
+        assert_eq!(
+            negotiate(&accept("application/json;q=0.5, text/xml")),
+            Some(Format::Xml)
+        );
+        assert_eq!(negotiate(&accept("text/html")), None);
+        assert_eq!(negotiate(&accept("application/xml;q=0")), None);
+    }
+
+    #[test]
+    fn test_xml_escapes_text() {
+        let mut out = String::new();
+        write_element(
+            &mut out,
+            "task",
+            "item",
+            &serde_json::json!({ "title": "<b>&</b>", "assigned_user_id": null }),
+        );
+
+        assert_eq!(
+            out,
+            "<task><assigned_user_id/><title>&lt;b&gt;&amp;&lt;/b&gt;</title></task>"
+        );
+    }
+}
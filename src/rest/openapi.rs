@@ -0,0 +1,284 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use utoipa::openapi::path::Operation;
+use utoipa::OpenApi;
+
+use crate::config::Config;
+
+use super::{
+    AffectedResponse, ArchivedTaskResponse, BackupDocument, BackupTask, BatchGetTasksRequest,
+    BulkCompleteRequest, CreateTaskRequest, CreateUserRequest, DuplicateTasksResponse,
+    ErrorResponse, FieldErrorResponse, InflightRequestResponse, MoveTaskRequest, OnTasks,
+    PoolStatsResponse, ReturnPreference, TaskCountsResponse, TaskHistoryEntry, TaskResponse,
+    TaskSyncResponse, UpdateTaskRequest, UpdateUserRequest, UserResponse, UserSummaryResponse,
+    UserTaskCountsResponse, VacuumResponse, ValidationResponse, DEFAULT_API_PREFIX,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::task_handlers::list_tasks,
+        super::task_handlers::create_task,
+        super::task_handlers::validate_task,
+        super::task_handlers::get_task,
+        super::task_handlers::update_task,
+        super::task_handlers::delete_task,
+        super::task_handlers::toggle_task,
+        super::task_handlers::task_history,
+        super::task_handlers::move_task,
+        super::task_handlers::batch_get_tasks,
+        super::task_handlers::export_tasks,
+        super::task_handlers::recent_tasks,
+        super::task_handlers::latest_tasks,
+        super::task_handlers::completed_tasks,
+        super::task_handlers::reassign_tasks,
+        super::task_handlers::create_user_task,
+        super::task_handlers::complete_all_tasks,
+        super::task_handlers::bulk_complete_tasks,
+        super::task_handlers::archive_completed_tasks,
+        super::task_handlers::archived_tasks,
+        super::task_handlers::sync_tasks,
+        super::task_handlers::duplicate_tasks,
+        super::task_handlers::count_tasks_by_status,
+        super::events::task_events_ws,
+        super::events::task_events_sse,
+        super::task_handlers::delete_all_tasks,
+        super::user_handlers::list_users,
+        super::user_handlers::search_users,
+        super::user_handlers::user_task_counts,
+        super::user_handlers::create_user,
+        super::user_handlers::upsert_user,
+        super::user_handlers::get_user,
+        super::user_handlers::user_summary,
+        super::user_handlers::update_user,
+        super::user_handlers::delete_user,
+        super::admin_handlers::list_inflight,
+        super::admin_handlers::cancel_inflight,
+        super::admin_handlers::vacuum,
+        super::admin_handlers::backup,
+        super::admin_handlers::restore,
+        super::admin_handlers::effective_config,
+        super::admin_handlers::pool_stats,
+    ),
+    components(
+        schemas(
+            TaskResponse,
+            crate::db::TaskStatus,
+            TaskHistoryEntry,
+            super::TaskEvent,
+            super::TaskEventKind,
+            CreateTaskRequest,
+            ValidationResponse,
+            UpdateTaskRequest,
+            AffectedResponse,
+            ArchivedTaskResponse,
+            TaskSyncResponse,
+            DuplicateTasksResponse,
+            TaskCountsResponse,
+            BatchGetTasksRequest,
+            BulkCompleteRequest,
+            MoveTaskRequest,
+            UserResponse,
+            CreateUserRequest,
+            UpdateUserRequest,
+            UserTaskCountsResponse,
+            UserSummaryResponse,
+            OnTasks,
+            ReturnPreference,
+            InflightRequestResponse,
+            VacuumResponse,
+            PoolStatsResponse,
+            BackupDocument,
+            BackupTask,
+            Config,
+            ErrorResponse,
+            FieldErrorResponse,
+        )
+    ),
+    tags(
+        (name = "tasks", description = "Task management endpoints"),
+        (name = "users", description = "User management endpoints"),
+        (name = "admin", description = "Operational endpoints")
+    ),
+    info(
+        title = "Rust gRPC SQLite REST API",
+        version = "1.0.0",
+        description = "REST API layer for the Rust gRPC SQLite application"
+    )
+)]
+pub struct ApiDoc;
+
+/// Where the spec is served, and what Swagger UI loads.
+pub const OPENAPI_PATH: &str = "/api-docs/openapi.json";
+
+#[derive(Debug, Deserialize)]
+pub struct OpenApiQuery {
+    /// Only include operations with this tag, e.g. `tasks`
+    pub tag: Option<String>,
+}
+
+/// The annotated spec with its paths moved from [`DEFAULT_API_PREFIX`] to
+/// `prefix`, where the routes are actually mounted.
+pub fn api_doc(prefix: &str) -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    if prefix != DEFAULT_API_PREFIX {
+        doc.paths.paths = std::mem::take(&mut doc.paths.paths)
+            .into_iter()
+            .map(|(path, item)| match path.strip_prefix(DEFAULT_API_PREFIX) {
+                Some(rest) => (format!("{}{}", prefix, rest), item),
+                None => (path, item),
+            })
+            .collect();
+    }
+    doc
+}
+
+/// The OpenAPI spec, optionally scoped to one tag. An unknown tag gets
+/// `404 Not Found`.
+pub async fn openapi_json(
+    State(doc): State<Arc<utoipa::openapi::OpenApi>>,
+    Query(query): Query<OpenApiQuery>,
+) -> Result<Json<utoipa::openapi::OpenApi>, (StatusCode, Json<ErrorResponse>)> {
+    let doc = doc.as_ref().clone();
+    match query.tag {
+        None => Ok(Json(doc)),
+        Some(tag) => scoped_to_tag(doc, &tag).map(Json).ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse::new(format!("Unknown tag: {}", tag))),
+            )
+        }),
+    }
+}
+
+/// `doc` cut down to the operations tagged `tag` and the schemas they
+/// reference, directly or through other schemas. `None` if `doc` doesn't
+/// declare the tag.
+pub fn scoped_to_tag(
+    mut doc: utoipa::openapi::OpenApi,
+    tag: &str,
+) -> Option<utoipa::openapi::OpenApi> {
+    let tags = doc.tags.as_mut()?;
+    tags.retain(|declared| declared.name == tag);
+    if tags.is_empty() {
+        return None;
+    }
+
+    doc.paths.paths.retain(|_, item| {
+        let mut kept = false;
+        for operation in [
+            &mut item.get,
+            &mut item.put,
+            &mut item.post,
+            &mut item.delete,
+            &mut item.options,
+            &mut item.head,
+            &mut item.patch,
+            &mut item.trace,
+        ] {
+            *operation = operation.take().filter(|operation| has_tag(operation, tag));
+            kept |= operation.is_some();
+        }
+        kept
+    });
+
+    if let Some(components) = doc.components.as_mut() {
+        let mut used = BTreeSet::new();
+        let mut pending = Vec::new();
+        collect_schema_refs(
+            &serde_json::to_value(&doc.paths).unwrap_or_default(),
+            &mut pending,
+        );
+        while let Some(name) = pending.pop() {
+            if !used.insert(name.clone()) {
+                continue;
+            }
+            if let Some(schema) = components.schemas.get(&name) {
+                collect_schema_refs(
+                    &serde_json::to_value(schema).unwrap_or_default(),
+                    &mut pending,
+                );
+            }
+        }
+        components.schemas.retain(|name, _| used.contains(name));
+    }
+
+    Some(doc)
+}
+
+fn has_tag(operation: &Operation, tag: &str) -> bool {
+    operation
+        .tags
+        .as_ref()
+        .is_some_and(|tags| tags.iter().any(|t| t == tag))
+}
+
+/// Names of the component schemas `value` points at with `$ref`.
+fn collect_schema_refs(value: &Value, refs: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("$ref", Value::String(reference)) => {
+                        if let Some(name) = reference.strip_prefix("#/components/schemas/") {
+                            refs.push(name.to_string());
+                        }
+                    }
+                    _ => collect_schema_refs(value, refs),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                collect_schema_refs(value, refs);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scoped_to_tag_keeps_referenced_schemas() {
+        let doc = scoped_to_tag(ApiDoc::openapi(), "admin").unwrap();
+        let schemas = doc.components.unwrap().schemas;
+
+        assert!(schemas.contains_key("VacuumResponse"));
+        assert!(schemas.contains_key("ErrorResponse"));
+        // Referenced through BackupDocument
+        assert!(schemas.contains_key("UserResponse"));
+        assert!(!schemas.contains_key("TaskResponse"));
+        assert!(!schemas.contains_key("CreateUserRequest"));
+    }
+
+    #[test]
+    fn test_api_doc_moves_paths_to_prefix() {
+        let doc = api_doc("/v1");
+        assert!(doc.paths.paths.contains_key("/v1/tasks/{id}"));
+        assert!(doc
+            .paths
+            .paths
+            .keys()
+            .all(|path| !path.starts_with("/api/")));
+
+        let root = api_doc("");
+        assert!(root.paths.paths.contains_key("/tasks/{id}"));
+        assert_eq!(root.paths.paths.len(), ApiDoc::openapi().paths.paths.len());
+    }
+
+    #[test]
+    fn test_scoped_to_unknown_tag() {
+        assert!(scoped_to_tag(ApiDoc::openapi(), "projects").is_none());
+    }
+}
@@ -0,0 +1,124 @@
+use axum::http::{HeaderValue, StatusCode, Uri};
+use axum::Json;
+
+use super::ErrorResponse;
+use crate::repository::{batch_ids, Page};
+
+/// `RouterConfig::max_page_size`, handed to the list handlers as an
+/// extension.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxPageSize(pub i64);
+
+/// The page a list handler reads: `limit` capped at `max` (and `max` when
+/// absent). A zero or negative limit gets `400 Bad Request`.
+pub fn capped_page(
+    limit: Option<i64>,
+    offset: Option<i64>,
+    MaxPageSize(max): MaxPageSize,
+) -> Result<Page, (StatusCode, Json<ErrorResponse>)> {
+    Page::capped(limit, offset, max)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse::new(error))))
+}
+
+/// The distinct ids of a batch lookup. More than `max` gets `400 Bad
+/// Request`.
+pub fn capped_ids(
+    ids: &[i64],
+    MaxPageSize(max): MaxPageSize,
+) -> Result<Vec<i64>, (StatusCode, Json<ErrorResponse>)> {
+    batch_ids(ids, max).map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse::new(error))))
+}
+
+/// RFC 8288 `Link` header with `first`/`prev`/`next`/`last` relations for an
+/// offset-paginated list of `total` rows. Other query parameters in `uri`
+/// are kept. Returns `None` without a `limit`, since everything fits on one
+/// page.
+pub fn link_header(
+    uri: &Uri,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    total: i64,
+) -> Option<HeaderValue> {
+    let limit = limit.filter(|&limit| limit > 0)?;
+    let offset = offset.unwrap_or(0).max(0);
+    let last = if total > 0 {
+        (total - 1) / limit * limit
+    } else {
+        0
+    };
+
+    let mut links = vec![(0, "first")];
+    if offset > 0 {
+        links.push(((offset - limit).max(0), "prev"));
+    }
+    if offset + limit < total {
+        links.push((offset + limit, "next"));
+    }
+    links.push((last, "last"));
+
+    let value = links
+        .into_iter()
+        .map(|(offset, rel)| format!("<{}>; rel=\"{}\"", page_url(uri, limit, offset), rel))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    HeaderValue::from_str(&value).ok()
+}
+
+fn page_url(uri: &Uri, limit: i64, offset: i64) -> String {
+    let mut params: Vec<String> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            !pair.is_empty() && key != "limit" && key != "offset"
+        })
+        .map(str::to_owned)
+        .collect();
+    params.push(format!("limit={}", limit));
+    params.push(format!("offset={}", offset));
+
+    format!("{}?{}", uri.path(), params.join("&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(uri: &str, limit: Option<i64>, offset: Option<i64>, total: i64) -> Option<String> {
+        link_header(&uri.parse().unwrap(), limit, offset, total)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_link_header_first_page() {
+        assert_eq!(
+            links("/api/tasks?limit=10", Some(10), None, 25).unwrap(),
+            "</api/tasks?limit=10&offset=0>; rel=\"first\", \
+             </api/tasks?limit=10&offset=10>; rel=\"next\", \
+             </api/tasks?limit=10&offset=20>; rel=\"last\""
+        );
+    }
+
+    #[test]
+    fn test_link_header_last_page_keeps_other_params() {
+        assert_eq!(
+            links(
+                "/api/users?name=a&offset=20&limit=10",
+                Some(10),
+                Some(20),
+                25
+            )
+            .unwrap(),
+            "</api/users?name=a&limit=10&offset=0>; rel=\"first\", \
+             </api/users?name=a&limit=10&offset=10>; rel=\"prev\", \
+             </api/users?name=a&limit=10&offset=20>; rel=\"last\""
+        );
+    }
+
+    #[test]
+    fn test_link_header_without_limit() {
+        assert_eq!(links("/api/tasks", None, None, 25), None);
+    }
+}
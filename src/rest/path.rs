@@ -0,0 +1,52 @@
+//! `Path` for id segments, with rejections in the API's `ErrorResponse` shape.
+
+use axum::{
+    extract::{FromRequestParts, Path},
+    http::{request::Parts, StatusCode},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+use super::ErrorResponse;
+
+/// Ids that can appear in a path. Ids are always positive.
+pub trait PathIds: DeserializeOwned + Send {
+    fn all_positive(&self) -> bool;
+}
+
+impl PathIds for i64 {
+    fn all_positive(&self) -> bool {
+        *self > 0
+    }
+}
+
+impl PathIds for (i64, i64) {
+    fn all_positive(&self) -> bool {
+        self.0 > 0 && self.1 > 0
+    }
+}
+
+/// Drop-in for `Path<i64>` (or a tuple of ids). A segment that isn't a
+/// positive integer within `i64` range gets `400 Bad Request` and
+/// `{ "error": "..." }` instead of axum's plain-text rejection.
+pub struct IdPath<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for IdPath<T>
+where
+    T: PathIds,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, Json<ErrorResponse>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Path::<T>::from_request_parts(parts, state).await {
+            Ok(Path(ids)) if ids.all_positive() => Ok(Self(ids)),
+            Ok(_) => Err(bad_id("Ids must be positive integers".to_string())),
+            Err(rejection) => Err(bad_id(format!("Invalid id: {}", rejection.body_text()))),
+        }
+    }
+}
+
+fn bad_id(message: String) -> (StatusCode, Json<ErrorResponse>) {
+    (StatusCode::BAD_REQUEST, Json(ErrorResponse::new(message)))
+}
@@ -0,0 +1,290 @@
+//! Per-client rate limiting: a token bucket for each client IP, so one busy
+//! client gets `429`s without using up everyone else's allowance.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use super::ErrorResponse;
+
+/// Past this many tracked clients, buckets that have refilled are dropped,
+/// since a full bucket is the same as none. Clients arriving while the map
+/// is still full share the `None` bucket.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Least time between two sweeps of refilled buckets, so a full map costs
+/// one pass per interval rather than one per request.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many requests each client may make.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Sustained requests per second.
+    pub per_second: u32,
+    /// Requests a client may make at once after being idle.
+    pub burst: u32,
+    /// Key clients on the last `X-Forwarded-For` address, the one the proxy
+    /// in front appended, instead of the peer address. Only safe behind a
+    /// proxy that appends to the header.
+    pub trust_forwarded_for: bool,
+}
+
+impl RateLimit {
+    /// `RATE_LIMIT_PER_SEC` turns limiting on; `RATE_LIMIT_BURST` defaults to
+    /// the same number and `RATE_LIMIT_TRUST_FORWARDED_FOR=1` keys on
+    /// `X-Forwarded-For`. `None` when unset or 0.
+    pub fn from_env() -> Option<Self> {
+        Self::parse(
+            std::env::var("RATE_LIMIT_PER_SEC").ok().as_deref(),
+            std::env::var("RATE_LIMIT_BURST").ok().as_deref(),
+            super::is_on(
+                std::env::var("RATE_LIMIT_TRUST_FORWARDED_FOR")
+                    .ok()
+                    .as_deref(),
+            ),
+        )
+    }
+
+    fn parse(
+        per_second: Option<&str>,
+        burst: Option<&str>,
+        trust_forwarded_for: bool,
+    ) -> Option<Self> {
+        let per_second = per_second?
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|&rate| rate > 0)?;
+        let burst = burst
+            .and_then(|burst| burst.trim().parse::<u32>().ok())
+            .filter(|&burst| burst > 0)
+            .unwrap_or(per_second);
+
+        Some(Self {
+            per_second,
+            burst,
+            trust_forwarded_for,
+        })
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct Buckets {
+    by_client: HashMap<Option<IpAddr>, Bucket>,
+    /// Earliest time the next sweep may run.
+    next_sweep: Instant,
+}
+
+/// The buckets of every client seen, keyed by IP. Clones share them.
+/// Requests whose address can't be told share the `None` bucket.
+#[derive(Clone)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Arc::new(Mutex::new(Buckets {
+                by_client: HashMap::new(),
+                next_sweep: Instant::now(),
+            })),
+        }
+    }
+
+    /// Takes a token from `client`'s bucket, or says how long until one is
+    /// available.
+    fn acquire(&self, client: Option<IpAddr>, now: Instant) -> Result<(), Duration> {
+        let rate = f64::from(self.limit.per_second);
+        let burst = f64::from(self.limit.burst);
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.by_client.len() >= MAX_TRACKED_CLIENTS && now >= buckets.next_sweep {
+            buckets.by_client.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate < burst
+            });
+            buckets.next_sweep = now + SWEEP_INTERVAL;
+        }
+
+        let client = if buckets.by_client.len() >= MAX_TRACKED_CLIENTS
+            && !buckets.by_client.contains_key(&client)
+        {
+            None
+        } else {
+            client
+        };
+        let bucket = buckets.by_client.entry(client).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let refilled = now.duration_since(bucket.updated).as_secs_f64() * rate;
+        bucket.tokens = (bucket.tokens + refilled).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    /// The last `X-Forwarded-For` address when trusted and present,
+    /// otherwise the peer address.
+    fn client_ip(&self, request: &Request) -> Option<IpAddr> {
+        let forwarded = if self.limit.trust_forwarded_for {
+            forwarded_for(request.headers())
+        } else {
+            None
+        };
+        forwarded.or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        })
+    }
+}
+
+/// The right-most address of the last `X-Forwarded-For` header: the one
+/// the trusted proxy appended. Entries left of it come from the client and
+/// can be anything.
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .next_back()?
+        .to_str()
+        .ok()?
+        .rsplit(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Answers `429 Too Many Requests` with `Retry-After` once a client's
+/// bucket is empty. The peer address comes from `ConnectInfo`, so the app
+/// must be served with `into_make_service_with_connect_info`.
+pub async fn limit_per_client(
+    State(limiter): State<RateLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = limiter.client_ip(&request);
+    match limiter.acquire(client, Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(ErrorResponse::new("Too many requests")),
+            )
+                .into_response();
+            // Whole seconds, rounded up so a retry on time succeeds
+            let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds.max(1)));
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_limit() {
+        assert_eq!(RateLimit::parse(None, Some("5"), false), None);
+        assert_eq!(RateLimit::parse(Some("0"), None, false), None);
+        assert_eq!(
+            RateLimit::parse(Some("10"), None, true),
+            Some(RateLimit {
+                per_second: 10,
+                burst: 10,
+                trust_forwarded_for: true
+            })
+        );
+        assert_eq!(
+            RateLimit::parse(Some(" 2 "), Some("20"), false).map(|limit| limit.burst),
+            Some(20)
+        );
+    }
+
+    #[test]
+    fn test_buckets_refill_per_client() {
+        let limiter = RateLimiter::new(RateLimit {
+            per_second: 2,
+            burst: 2,
+            trust_forwarded_for: false,
+        });
+        let a = Some(IpAddr::from([10, 0, 0, 1]));
+        let b = Some(IpAddr::from([10, 0, 0, 2]));
+        let start = Instant::now();
+
+        assert!(limiter.acquire(a, start).is_ok());
+        assert!(limiter.acquire(a, start).is_ok());
+        assert_eq!(limiter.acquire(a, start), Err(Duration::from_millis(500)));
+        assert!(limiter.acquire(b, start).is_ok());
+
+        assert!(limiter
+            .acquire(a, start + Duration::from_millis(500))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_forwarded_for_takes_proxy_appended_address() {
+        let mut headers = HeaderMap::new();
+        headers.append("x-forwarded-for", "1.2.3.4, 10.0.0.7".parse().unwrap());
+        assert_eq!(forwarded_for(&headers), Some(IpAddr::from([10, 0, 0, 7])));
+
+        headers.append("x-forwarded-for", "10.0.0.8".parse().unwrap());
+        assert_eq!(forwarded_for(&headers), Some(IpAddr::from([10, 0, 0, 8])));
+    }
+
+    #[test]
+    fn test_full_map_is_swept_once_per_interval() {
+        let limiter = RateLimiter::new(RateLimit {
+            per_second: 1,
+            burst: 1,
+            trust_forwarded_for: false,
+        });
+        let start = Instant::now();
+        for i in 0..MAX_TRACKED_CLIENTS as u32 {
+            assert!(limiter
+                .acquire(Some(IpAddr::from(i.to_be_bytes())), start)
+                .is_ok());
+        }
+
+        // Nothing has refilled, so newcomers share the overflow bucket
+        let newcomer = |i: u8| Some(IpAddr::from([192, 168, 0, i]));
+        assert!(limiter.acquire(newcomer(1), start).is_ok());
+        assert!(limiter.acquire(newcomer(2), start).is_err());
+        assert_eq!(
+            limiter.buckets.lock().unwrap().by_client.len(),
+            MAX_TRACKED_CLIENTS + 1
+        );
+
+        // Once the buckets have refilled, the next sweep drops them
+        assert!(limiter.acquire(newcomer(2), start + SWEEP_INTERVAL).is_ok());
+        assert_eq!(limiter.buckets.lock().unwrap().by_client.len(), 1);
+    }
+}
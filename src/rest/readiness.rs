@@ -0,0 +1,53 @@
+//! `GET /ready`, so a load balancer or orchestrator holds traffic until
+//! startup work such as migrations has finished.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+
+use super::ErrorResponse;
+
+/// Flag flipped once startup has finished. Clones share it.
+#[derive(Clone, Debug, Default)]
+pub struct Readiness {
+    ready: Arc<AtomicBool>,
+}
+
+impl Readiness {
+    /// Not ready until [`Readiness::mark_ready`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Already ready, for callers with no startup work left to wait for.
+    pub fn ready() -> Self {
+        let readiness = Self::new();
+        readiness.mark_ready();
+        readiness
+    }
+
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+}
+
+/// `200` once ready, `503 Service Unavailable` before.
+pub async fn ready(State(readiness): State<Readiness>) -> impl IntoResponse {
+    if readiness.is_ready() {
+        (StatusCode::OK, Json(json!({ "status": "ready" }))).into_response()
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse::new("starting up")),
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,38 @@
+//! Opaque tokens for `GET /api/tasks/sync`, wrapping the repository's
+//! `task_audit` cursor so clients don't build or compare them.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+/// Version tag inside every token, so the format can change later.
+const PREFIX: &str = "v1:";
+
+/// The token for `cursor`.
+pub fn encode(cursor: i64) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}{}", PREFIX, cursor))
+}
+
+/// The cursor in `token`; `None` for anything [`encode`] didn't produce.
+pub fn decode(token: &str) -> Option<i64> {
+    let bytes = URL_SAFE_NO_PAD.decode(token.trim()).ok()?;
+    let text = String::from_utf8(bytes).ok()?;
+    text.strip_prefix(PREFIX)?
+        .parse::<i64>()
+        .ok()
+        .filter(|&cursor| cursor >= 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_rejects_foreign_tokens() {
+        assert_eq!(decode(&encode(42)), Some(42));
+        assert_eq!(decode(&encode(0)), Some(0));
+
+        assert_eq!(decode("42"), None);
+        assert_eq!(decode(&URL_SAFE_NO_PAD.encode("v2:42")), None);
+        assert_eq!(decode(&URL_SAFE_NO_PAD.encode("v1:-1")), None);
+        assert_eq!(decode("not base64!"), None);
+    }
+}
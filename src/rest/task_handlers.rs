@@ -1,27 +1,86 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::get,
-    Json, Router,
+    body::Body,
+    extract::{OriginalUri, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    middleware::map_response,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
 };
+use futures_util::StreamExt;
+use serde_json::{Map, Value};
 
-use crate::db::TaskModel;
-use crate::repository::TaskRepository;
+use crate::db::{TaskAuditModel, TaskModel};
+use crate::repository::{
+    Page, TaskFilter, TaskOrder, TaskRepository, TaskSortKey, TaskUpdate, UnknownTask, UnknownUser,
+};
+use crate::timestamp::parse_rfc3339;
+use crate::validation::{
+    normalize_text, requested_status, validate_new_task, validate_new_task_with_tags,
+    validate_tags, validate_task_update,
+};
+
+use super::etag::{etag, if_none_match};
+use super::json::JsonBody;
+use super::json_stream::json_array;
+use super::negotiate::{negotiate, not_acceptable, vary_accept, Format, Negotiated};
+use super::pagination::{capped_ids, capped_page, link_header, MaxPageSize};
+use super::path::IdPath;
+use super::sync_token;
+use super::{
+    validation_error, AffectedResponse, ArchivedTaskResponse, ArchivedTasksQuery,
+    BatchGetTasksRequest, BulkCompleteRequest, CompletedTasksQuery, CreateTaskQuery,
+    CreateTaskRequest, DeleteTaskQuery, DryRunQuery, DuplicateTasksResponse, ErrorResponse,
+    LatestTasksQuery, ListTasksQuery, MoveTaskRequest, RecentTasksQuery, ReturnPreference,
+    SyncTasksQuery, TaskCountsResponse, TaskHistoryEntry, TaskResponse, TaskSyncResponse,
+    UpdateTaskRequest, ValidationResponse,
+};
 
-use super::{CreateTaskRequest, ErrorResponse, TaskResponse, UpdateTaskRequest};
+/// Tasks returned by `GET /api/tasks/latest` without `n`.
+const DEFAULT_LATEST_TASKS: i64 = 10;
 
-pub fn task_routes<R: TaskRepository + 'static>(repo: Arc<R>) -> Router {
+pub fn task_routes<R: TaskRepository + ?Sized + 'static>(repo: Arc<R>) -> Router {
     Router::new()
-        .route("/tasks", get(list_tasks::<R>).post(create_task::<R>))
+        .route(
+            "/tasks",
+            get(list_tasks::<R>)
+                .layer(map_response(vary_accept))
+                .post(create_task::<R>)
+                .delete(delete_all_tasks::<R>),
+        )
         .route(
             "/tasks/{id}",
             get(get_task::<R>)
+                .layer(map_response(vary_accept))
                 .put(update_task::<R>)
                 .delete(delete_task::<R>),
         )
+        .route("/tasks/validate", post(validate_task))
+        .route("/tasks/batch-get", post(batch_get_tasks::<R>))
+        .route("/tasks/export.ndjson", get(export_tasks::<R>))
+        .route("/tasks/recent", get(recent_tasks::<R>))
+        .route("/tasks/latest", get(latest_tasks::<R>))
+        .route("/tasks/completed", get(completed_tasks::<R>))
+        .route("/tasks/complete-all", post(complete_all_tasks::<R>))
+        .route("/tasks/bulk-complete", post(bulk_complete_tasks::<R>))
+        .route(
+            "/tasks/archive-completed",
+            post(archive_completed_tasks::<R>),
+        )
+        .route("/tasks/archived", get(archived_tasks::<R>))
+        .route("/tasks/sync", get(sync_tasks::<R>))
+        .route("/tasks/duplicates", get(duplicate_tasks::<R>))
+        .route("/tasks/count-by-status", get(count_tasks_by_status::<R>))
+        .route("/tasks/{id}/toggle", post(toggle_task::<R>))
+        .route("/tasks/{id}/history", get(task_history::<R>))
+        .route("/tasks/{id}/move", post(move_task::<R>))
+        .route("/users/{id}/tasks", post(create_user_task::<R>))
+        .route(
+            "/users/{from}/reassign-tasks/{to}",
+            post(reassign_tasks::<R>),
+        )
         .with_state(repo)
 }
 
@@ -32,83 +91,374 @@ impl From<TaskModel> for TaskResponse {
             title: model.title,
             description: model.description,
             completed: model.completed,
+            status: model.status,
+            assigned_user_id: model.assigned_user_id,
+            tags: model.tags,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+            completed_at: model.completed_at,
+        }
+    }
+}
+
+impl From<TaskAuditModel> for TaskHistoryEntry {
+    fn from(model: TaskAuditModel) -> Self {
+        let parse = |json: Option<String>| json.and_then(|json| serde_json::from_str(&json).ok());
+        TaskHistoryEntry {
+            id: model.id,
+            op: model.op,
+            old: parse(model.old_json),
+            new: parse(model.new_json),
+            at: model.at,
         }
     }
 }
 
+/// Field names accepted by `?fields=`, matching `TaskResponse`.
+const TASK_FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "description",
+    "completed",
+    "status",
+    "assigned_user_id",
+    "tags",
+    "created_at",
+    "updated_at",
+    "completed_at",
+];
+
+/// Splits a `fields` parameter, rejecting names that aren't task fields.
+fn parse_fields(fields: &str) -> Result<Vec<&str>, String> {
+    let requested: Vec<&str> = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .collect();
+
+    match requested.iter().find(|field| !TASK_FIELDS.contains(field)) {
+        Some(unknown) => Err(format!("Unknown field: {}", unknown)),
+        None => Ok(requested),
+    }
+}
+
+/// Every `tag` parameter of the query string; `Query` keeps only one.
+fn tag_filter(uri: &axum::http::Uri) -> TaskFilter {
+    let tags = form_urlencoded::parse(uri.query().unwrap_or("").as_bytes())
+        .filter(|(key, _)| key == "tag")
+        .map(|(_, value)| normalize_text(&value).to_owned())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    TaskFilter {
+        tags,
+        ..Default::default()
+    }
+}
+
+/// Trims and validates request tags.
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>, (StatusCode, Json<ErrorResponse>)> {
+    let tags: Vec<&str> = tags.iter().map(|tag| normalize_text(tag)).collect();
+    validate_tags(&tags).map_err(validation_error)?;
+    Ok(tags.into_iter().map(str::to_owned).collect())
+}
+
+fn project(task: TaskResponse, fields: &[&str]) -> Map<String, Value> {
+    let Ok(Value::Object(mut object)) = serde_json::to_value(task) else {
+        return Map::new();
+    };
+    object.retain(|key, _| fields.contains(&key.as_str()));
+    object
+}
+
 /// List all tasks
 #[utoipa::path(
     get,
     path = "/api/tasks",
+    params(
+        ListTasksQuery,
+        ("tag" = Option<Vec<String>>, Query, description = "Only tasks carrying every given tag; repeat the parameter for several"),
+    ),
     responses(
-        (status = 200, description = "List of all tasks", body = Vec<TaskResponse>),
+        (status = 200, description = "List of all tasks; with `stream=true`, every task in id order, sent as it is read", body = Vec<TaskResponse>),
+        (status = 400, description = "Unknown field requested, non-positive limit, or `stream=true` with paging, fields or filters", body = ErrorResponse),
+        (status = 406, description = "Unsupported Accept type, or other than JSON with `stream=true`", body = ErrorResponse),
     ),
     tag = "tasks"
 )]
-pub async fn list_tasks<R: TaskRepository>(
+pub async fn list_tasks<R: TaskRepository + ?Sized>(
     State(repo): State<Arc<R>>,
-) -> Result<Json<Vec<TaskResponse>>, impl IntoResponse> {
-    match repo.list().await {
-        Ok(tasks) => Ok(Json(tasks.into_iter().map(TaskResponse::from).collect())),
+    Query(query): Query<ListTasksQuery>,
+    Extension(max_page_size): Extension<MaxPageSize>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Result<Response, impl IntoResponse> {
+    let Some(format) = negotiate(&headers) else {
+        return Err(not_acceptable());
+    };
+    if query.stream == Some(true) {
+        return stream_tasks(repo.as_ref(), &query, &uri, format);
+    }
+    let page = match capped_page(query.limit, query.offset, max_page_size) {
+        Ok(page) => page,
+        Err(error) => return Err(error),
+    };
+
+    let fields = match query.fields.as_deref().map(parse_fields).transpose() {
+        Ok(fields) => fields,
+        Err(error) => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new(error)))),
+    };
+
+    let filter = TaskFilter {
+        status: query.status,
+        ..tag_filter(&uri)
+    };
+    let result = match repo.list(page, &filter).await {
+        Ok(tasks) => repo.count(&filter).await.map(|total| (tasks, total)),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok((tasks, total)) => {
+            let tasks = tasks.into_iter().map(TaskResponse::from);
+            let mut response = match fields {
+                Some(fields) => Negotiated::new(
+                    format,
+                    "tasks",
+                    tasks.map(|task| project(task, &fields)).collect::<Vec<_>>(),
+                )
+                .item("task")
+                .into_response(),
+                None => Negotiated::new(format, "tasks", tasks.collect::<Vec<_>>())
+                    .item("task")
+                    .into_response(),
+            };
+
+            // Link a capped page even when no limit was asked for
+            let limit = page
+                .limit
+                .filter(|&limit| query.limit.is_some() || total > limit);
+            if let Some(link) = link_header(&uri, limit, query.offset, total) {
+                response.headers_mut().insert(header::LINK, link);
+            }
+            Ok(response)
+        }
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            Json(ErrorResponse::new(e.to_string())),
         )),
     }
 }
 
+/// Every task as a JSON array written from the row stream, so the response
+/// isn't held in memory. Paging and filtering would need their own streamed
+/// queries, so they're refused rather than silently ignored.
+fn stream_tasks<R: TaskRepository + ?Sized>(
+    repo: &R,
+    query: &ListTasksQuery,
+    uri: &Uri,
+    format: Format,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    if format != Format::Json {
+        return Err((
+            StatusCode::NOT_ACCEPTABLE,
+            Json(ErrorResponse::new(
+                "Streamed lists are only available as application/json",
+            )),
+        ));
+    }
+    if query.limit.is_some()
+        || query.offset.is_some()
+        || query.fields.is_some()
+        || query.status.is_some()
+        || !tag_filter(uri).tags.is_empty()
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "stream=true can't be combined with limit, offset, fields, status or tag",
+            )),
+        ));
+    }
+
+    let tasks = repo
+        .stream_all()
+        .map(|task| task.map(TaskResponse::from))
+        .boxed();
+    Ok(json_array(tasks))
+}
+
 /// Create a new task
 #[utoipa::path(
     post,
     path = "/api/tasks",
+    params(CreateTaskQuery),
     request_body = CreateTaskRequest,
     responses(
-        (status = 201, description = "Task created successfully", body = TaskResponse),
+        (status = 201, description = "Task created successfully; with `strict=false`, accepted problems are listed in `Warning` headers", body = TaskResponse),
+        (status = 400, description = "Invalid fields", body = ErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
     tag = "tasks"
 )]
-pub async fn create_task<R: TaskRepository>(
+pub async fn create_task<R: TaskRepository + ?Sized>(
     State(repo): State<Arc<R>>,
-    Json(payload): Json<CreateTaskRequest>,
+    Query(query): Query<CreateTaskQuery>,
+    JsonBody(payload): JsonBody<CreateTaskRequest>,
 ) -> Result<impl IntoResponse, impl IntoResponse> {
-    match repo.create(&payload.title, &payload.description).await {
-        Ok(task) => Ok((StatusCode::CREATED, Json(TaskResponse::from(task)))),
+    let title = normalize_text(&payload.title);
+    let description = normalize_text(&payload.description);
+
+    // Lenient mode still needs a usable title; only the description rules
+    // are downgraded to warnings.
+    let mut warnings = HeaderMap::new();
+    if query.strict == Some(false) {
+        validate_task_update(Some(title), None).map_err(validation_error)?;
+        if let Err(errors) = validate_task_update(None, Some(description)) {
+            for error in errors.0 {
+                let warning = format!("299 - \"{} {}\"", error.field, error.message);
+                if let Ok(value) = HeaderValue::from_str(&warning) {
+                    warnings.append(header::WARNING, value);
+                }
+            }
+        }
+    } else if let Err(errors) = validate_new_task(title, description) {
+        return Err(validation_error(errors));
+    }
+    let tags = normalize_tags(&payload.tags)?;
+
+    match repo.create_with_tags(None, title, description, &tags).await {
+        Ok(task) => Ok((
+            StatusCode::CREATED,
+            warnings,
+            Json(TaskResponse::from(task)),
+        )),
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            Json(ErrorResponse::new(e.to_string())),
         )),
     }
 }
 
+/// Check a task payload without saving it
+///
+/// Applies the rules of `POST /api/tasks` and reports every problem at
+/// once; the database is never touched.
+#[utoipa::path(
+    post,
+    path = "/api/tasks/validate",
+    request_body = CreateTaskRequest,
+    responses(
+        (status = 200, description = "Payload would be accepted", body = ValidationResponse),
+        (status = 400, description = "Invalid fields", body = ErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn validate_task(
+    JsonBody(payload): JsonBody<CreateTaskRequest>,
+) -> Result<Json<ValidationResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let tags: Vec<&str> = payload.tags.iter().map(|tag| normalize_text(tag)).collect();
+    validate_new_task_with_tags(
+        normalize_text(&payload.title),
+        normalize_text(&payload.description),
+        &tags,
+    )
+    .map_err(validation_error)?;
+
+    Ok(Json(ValidationResponse { valid: true }))
+}
+
+/// Create a task assigned to a user
+#[utoipa::path(
+    post,
+    path = "/api/users/{id}/tasks",
+    params(
+        ("id" = i64, Path, description = "User the task is assigned to")
+    ),
+    request_body = CreateTaskRequest,
+    responses(
+        (status = 201, description = "Task created and assigned", body = TaskResponse),
+        (status = 400, description = "Invalid fields or user id", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn create_user_task<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    IdPath(user_id): IdPath<i64>,
+    JsonBody(payload): JsonBody<CreateTaskRequest>,
+) -> Result<impl IntoResponse, impl IntoResponse> {
+    let title = normalize_text(&payload.title);
+    let description = normalize_text(&payload.description);
+    if let Err(errors) = validate_new_task(title, description) {
+        return Err(validation_error(errors));
+    }
+    let tags = normalize_tags(&payload.tags)?;
+
+    let result = repo
+        .create_with_tags(Some(user_id), title, description, &tags)
+        .await;
+
+    match result {
+        Ok(task) => Ok((StatusCode::CREATED, Json(TaskResponse::from(task)))),
+        Err(e) => {
+            let status = if e.is::<UnknownUser>() {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            Err((status, Json(ErrorResponse::new(e.to_string()))))
+        }
+    }
+}
+
 /// Get a task by ID
+///
+/// Responses carry an `ETag`; sending it back in `If-None-Match` gets
+/// `304 Not Modified` with no body while the task is unchanged.
 #[utoipa::path(
     get,
     path = "/api/tasks/{id}",
     params(
-        ("id" = i64, Path, description = "Task ID")
+        ("id" = i64, Path, description = "Task ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag from an earlier response"),
     ),
     responses(
         (status = 200, description = "Task found", body = TaskResponse),
+        (status = 304, description = "Task unchanged since the given ETag"),
+        (status = 400, description = "Id is not a positive integer", body = ErrorResponse),
         (status = 404, description = "Task not found", body = ErrorResponse),
+        (status = 406, description = "Unsupported Accept type", body = ErrorResponse),
     ),
     tag = "tasks"
 )]
-pub async fn get_task<R: TaskRepository>(
+pub async fn get_task<R: TaskRepository + ?Sized>(
     State(repo): State<Arc<R>>,
-    Path(id): Path<i64>,
-) -> Result<Json<TaskResponse>, impl IntoResponse> {
+    IdPath(id): IdPath<i64>,
+    headers: HeaderMap,
+) -> Result<Response, impl IntoResponse> {
+    let Some(format) = negotiate(&headers) else {
+        return Err(not_acceptable());
+    };
+
     match repo.get(id).await {
-        Ok(task) => Ok(Json(TaskResponse::from(task))),
+        Ok(task) => {
+            let task = TaskResponse::from(task);
+            let tag = etag(format, &task);
+            if if_none_match(&headers, &tag) {
+                return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, tag)]).into_response());
+            }
+            Ok(([(header::ETAG, tag)], Negotiated::new(format, "task", task)).into_response())
+        }
         Err(_) => Err((
             StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: format!("Task with id {} not found", id),
-            }),
+            Json(ErrorResponse::new(format!("Task with id {} not found", id))),
         )),
     }
 }
@@ -123,39 +473,51 @@ pub async fn get_task<R: TaskRepository>(
     request_body = UpdateTaskRequest,
     responses(
         (status = 200, description = "Task updated successfully", body = TaskResponse),
+        (status = 400, description = "Invalid fields or id, or `assigned_user_id` names no user", body = ErrorResponse),
         (status = 404, description = "Task not found", body = ErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
     tag = "tasks"
 )]
-pub async fn update_task<R: TaskRepository>(
+pub async fn update_task<R: TaskRepository + ?Sized>(
     State(repo): State<Arc<R>>,
-    Path(id): Path<i64>,
-    Json(payload): Json<UpdateTaskRequest>,
+    IdPath(id): IdPath<i64>,
+    JsonBody(payload): JsonBody<UpdateTaskRequest>,
 ) -> Result<Json<TaskResponse>, impl IntoResponse> {
-    match repo
-        .update(
-            id,
-            payload.title.as_deref(),
-            payload.description.as_deref(),
-            payload.completed,
-        )
-        .await
-    {
+    let title = payload.title.as_deref().map(normalize_text);
+    let description = payload.description.as_deref().map(normalize_text);
+    if let Err(errors) = validate_task_update(title, description) {
+        return Err(validation_error(errors));
+    }
+    let status = requested_status(payload.status, payload.completed).map_err(validation_error)?;
+    let tags = payload.tags.as_deref().map(normalize_tags).transpose()?;
+
+    let update = TaskUpdate {
+        title: title.map(String::from),
+        description: description.map(String::from),
+        status,
+        assigned_user_id: payload.assigned_user_id,
+        tags,
+        expected_updated_at: None,
+    };
+
+    match repo.update_with_tags(id, &update).await {
         Ok(task) => Ok(Json(TaskResponse::from(task))),
         Err(e) => {
             let error_msg = e.to_string();
             if error_msg.contains("no rows") {
                 Err((
                     StatusCode::NOT_FOUND,
-                    Json(ErrorResponse {
-                        error: format!("Task with id {} not found", id),
-                    }),
+                    Json(ErrorResponse::new(format!("Task with id {} not found", id))),
                 ))
+            } else if e.is::<UnknownUser>() {
+                Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new(error_msg))))
             } else {
                 Err((
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse { error: error_msg }),
+                    Json(ErrorResponse::new(error_msg)),
                 ))
             }
         }
@@ -163,40 +525,763 @@ pub async fn update_task<R: TaskRepository>(
 }
 
 /// Delete a task
+///
+/// With `?return=representation` the task is read and deleted in one
+/// transaction and sent back with `200`.
 #[utoipa::path(
     delete,
     path = "/api/tasks/{id}",
     params(
-        ("id" = i64, Path, description = "Task ID")
+        ("id" = i64, Path, description = "Task ID"),
+        DeleteTaskQuery,
     ),
     responses(
+        (status = 200, description = "Task deleted; `return=representation` was asked for", body = TaskResponse),
         (status = 204, description = "Task deleted successfully"),
+        (status = 400, description = "Id is not a positive integer", body = ErrorResponse),
+        (status = 404, description = "Task not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn delete_task<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    IdPath(id): IdPath<i64>,
+    Query(query): Query<DeleteTaskQuery>,
+) -> Result<Response, impl IntoResponse> {
+    let deleted = match query.return_preference.unwrap_or_default() {
+        ReturnPreference::Minimal => repo
+            .delete(id)
+            .await
+            .map(|deleted| deleted.then(|| StatusCode::NO_CONTENT.into_response())),
+        ReturnPreference::Representation => repo
+            .delete_returning(id)
+            .await
+            .map(|task| task.map(|task| Json(TaskResponse::from(task)).into_response())),
+    };
+
+    match deleted {
+        Ok(Some(response)) => Ok(response),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new(format!("Task with id {} not found", id))),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Toggle a task's completion
+#[utoipa::path(
+    post,
+    path = "/api/tasks/{id}/toggle",
+    params(
+        ("id" = i64, Path, description = "Task ID")
+    ),
+    responses(
+        (status = 200, description = "Task toggled successfully", body = TaskResponse),
+        (status = 400, description = "Id is not a positive integer", body = ErrorResponse),
         (status = 404, description = "Task not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
     tag = "tasks"
 )]
-pub async fn delete_task<R: TaskRepository>(
+pub async fn toggle_task<R: TaskRepository + ?Sized>(
     State(repo): State<Arc<R>>,
-    Path(id): Path<i64>,
-) -> Result<StatusCode, impl IntoResponse> {
-    match repo.delete(id).await {
-        Ok(deleted) => {
-            if deleted {
-                Ok(StatusCode::NO_CONTENT)
+    IdPath(id): IdPath<i64>,
+) -> Result<Json<TaskResponse>, impl IntoResponse> {
+    match repo.toggle(id).await {
+        Ok(task) => Ok(Json(TaskResponse::from(task))),
+        Err(e) => {
+            let error_msg = e.to_string();
+            if error_msg.contains("no rows") {
+                Err((
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse::new(format!("Task with id {} not found", id))),
+                ))
             } else {
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse::new(error_msg)),
+                ))
+            }
+        }
+    }
+}
+
+/// Move a task in the manual order
+#[utoipa::path(
+    post,
+    path = "/api/tasks/{id}/move",
+    params(
+        ("id" = i64, Path, description = "Task ID")
+    ),
+    request_body = MoveTaskRequest,
+    responses(
+        (status = 200, description = "Task moved", body = TaskResponse),
+        (status = 400, description = "`after_id` is the task itself or no task, or an id is not a positive integer", body = ErrorResponse),
+        (status = 404, description = "Task not found", body = ErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn move_task<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    IdPath(id): IdPath<i64>,
+    JsonBody(payload): JsonBody<MoveTaskRequest>,
+) -> Result<Json<TaskResponse>, impl IntoResponse> {
+    if payload.after_id == Some(id) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("A task can't be moved after itself")),
+        ));
+    }
+
+    match repo.move_after(id, payload.after_id).await {
+        Ok(task) => Ok(Json(TaskResponse::from(task))),
+        Err(e) if e.is::<UnknownTask>() => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+        Err(e) => {
+            let error_msg = e.to_string();
+            if error_msg.contains("no rows") {
                 Err((
                     StatusCode::NOT_FOUND,
-                    Json(ErrorResponse {
-                        error: format!("Task with id {} not found", id),
-                    }),
+                    Json(ErrorResponse::new(format!("Task with id {} not found", id))),
+                ))
+            } else {
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse::new(error_msg)),
                 ))
             }
         }
+    }
+}
+
+/// List every recorded change to a task, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/tasks/{id}/history",
+    params(
+        ("id" = i64, Path, description = "Task ID")
+    ),
+    responses(
+        (status = 200, description = "Changes to the task, oldest first", body = [TaskHistoryEntry]),
+        (status = 400, description = "Id is not a positive integer", body = ErrorResponse),
+        (status = 404, description = "Task not found and never recorded", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn task_history<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    IdPath(id): IdPath<i64>,
+) -> Result<Json<Vec<TaskHistoryEntry>>, impl IntoResponse> {
+    let entries = match repo.history(id).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::new(e.to_string())),
+            ))
+        }
+    };
+
+    // A task written before auditing began exists without any history
+    if entries.is_empty() {
+        if let Err(e) = repo.get(id).await {
+            let error_msg = e.to_string();
+            return Err(if error_msg.contains("no rows") {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse::new(format!("Task with id {} not found", id))),
+                )
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse::new(error_msg)),
+                )
+            });
+        }
+    }
+
+    Ok(Json(
+        entries.into_iter().map(TaskHistoryEntry::from).collect(),
+    ))
+}
+
+/// Reports the tasks a bulk write over `filter` would change, reading them
+/// instead of writing.
+async fn dry_run<R: TaskRepository + ?Sized>(
+    repo: &R,
+    filter: &TaskFilter,
+) -> Result<Json<AffectedResponse>, (StatusCode, Json<ErrorResponse>)> {
+    match repo.list(Page::default(), filter).await {
+        Ok(tasks) => {
+            let mut ids: Vec<i64> = tasks.iter().map(|task| task.id).collect();
+            ids.sort_unstable();
+            Ok(Json(AffectedResponse {
+                affected: ids.len() as u64,
+                ids: Some(ids),
+            }))
+        }
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            Json(ErrorResponse::new(e.to_string())),
         )),
     }
 }
+
+/// Header that must be `true` for `DELETE /api/tasks` to run.
+pub const CONFIRM_DELETE_ALL_HEADER: &str = "x-confirm-delete-all";
+
+/// Delete every task (requires `X-Confirm-Delete-All: true`)
+#[utoipa::path(
+    delete,
+    path = "/api/tasks",
+    params(
+        ("X-Confirm-Delete-All" = String, Header, description = "Must be `true`; not needed for a dry run"),
+        DryRunQuery
+    ),
+    responses(
+        (status = 200, description = "Number of tasks deleted, or that would be on a dry run", body = AffectedResponse),
+        (status = 400, description = "Confirmation header missing", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn delete_all_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    Query(query): Query<DryRunQuery>,
+    headers: HeaderMap,
+) -> Result<Json<AffectedResponse>, impl IntoResponse> {
+    if query.dry_run == Some(true) {
+        return dry_run(repo.as_ref(), &TaskFilter::default()).await;
+    }
+
+    let confirmed = headers
+        .get(CONFIRM_DELETE_ALL_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("true"));
+
+    if !confirmed {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "Deleting all tasks requires the X-Confirm-Delete-All: true header".to_string(),
+            )),
+        ));
+    }
+
+    match repo.delete_all().await {
+        Ok(affected) => Ok(Json(AffectedResponse {
+            affected,
+            ids: None,
+        })),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Get several tasks by ID
+#[utoipa::path(
+    post,
+    path = "/api/tasks/batch-get",
+    request_body = BatchGetTasksRequest,
+    responses(
+        (status = 200, description = "Tasks found, in id order", body = Vec<TaskResponse>),
+        (status = 400, description = "More distinct ids than the maximum page size", body = ErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn batch_get_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    Extension(max_page_size): Extension<MaxPageSize>,
+    JsonBody(payload): JsonBody<BatchGetTasksRequest>,
+) -> Result<Json<Vec<TaskResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let ids = capped_ids(&payload.ids, max_page_size)?;
+    match repo.get_many(&ids).await {
+        Ok(tasks) => Ok(Json(tasks.into_iter().map(TaskResponse::from).collect())),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// List tasks updated since a point in time
+///
+/// For incremental sync: pass the time of the previous sync to get only
+/// what changed, most recently updated first. Deletions aren't reported.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/recent",
+    params(RecentTasksQuery),
+    responses(
+        (status = 200, description = "Tasks with `updated_at >= since`", body = Vec<TaskResponse>),
+        (status = 400, description = "Missing or malformed `since`", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn recent_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    Query(query): Query<RecentTasksQuery>,
+) -> Result<Json<Vec<TaskResponse>>, impl IntoResponse> {
+    let Some(since) = query.since.as_deref().and_then(parse_rfc3339) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "since must be an RFC 3339 timestamp, e.g. 2025-01-02T03:04:05Z",
+            )),
+        ));
+    };
+
+    match repo.list_updated_since(&since).await {
+        Ok(tasks) => Ok(Json(tasks.into_iter().map(TaskResponse::from).collect())),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// List the newest tasks
+///
+/// For "recent activity" views: the last `n` tasks created, newest first,
+/// without the paging and counting of the general list.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/latest",
+    params(LatestTasksQuery),
+    responses(
+        (status = 200, description = "Up to `n` tasks, highest id first", body = Vec<TaskResponse>),
+        (status = 400, description = "Zero or negative `n`", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn latest_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    Query(query): Query<LatestTasksQuery>,
+    Extension(MaxPageSize(max)): Extension<MaxPageSize>,
+) -> Result<Json<Vec<TaskResponse>>, impl IntoResponse> {
+    let n = query.n.unwrap_or(DEFAULT_LATEST_TASKS);
+    if n <= 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(format!(
+                "n must be a positive integer, got {}",
+                n
+            ))),
+        ));
+    }
+
+    let filter = TaskFilter {
+        order: Some(TaskOrder {
+            key: TaskSortKey::Id,
+            descending: true,
+        }),
+        ..Default::default()
+    };
+    match repo.list(Page::new(Some(n.min(max)), None), &filter).await {
+        Ok(tasks) => Ok(Json(tasks.into_iter().map(TaskResponse::from).collect())),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// List tasks completed within a time range
+///
+/// For reporting what got done between two points in time, e.g. the past
+/// week. Both bounds are inclusive. A task reopened and completed again
+/// counts from its latest completion.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/completed",
+    params(CompletedTasksQuery),
+    responses(
+        (status = 200, description = "Tasks with `from <= completed_at <= to`, earliest first", body = Vec<TaskResponse>),
+        (status = 400, description = "Missing or malformed `from`/`to`, or `from` after `to`", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn completed_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    Query(query): Query<CompletedTasksQuery>,
+) -> Result<Json<Vec<TaskResponse>>, impl IntoResponse> {
+    let from = query.from.as_deref().and_then(parse_rfc3339);
+    let to = query.to.as_deref().and_then(parse_rfc3339);
+    let (Some(from), Some(to)) = (from, to) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "from and to must be RFC 3339 timestamps, e.g. 2025-01-02T03:04:05Z",
+            )),
+        ));
+    };
+    if from > to {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("from must not be after to")),
+        ));
+    }
+
+    match repo.list_completed_between(&from, &to).await {
+        Ok(tasks) => Ok(Json(tasks.into_iter().map(TaskResponse::from).collect())),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Export every task as newline-delimited JSON
+///
+/// Rows are streamed as they are read, so memory use doesn't grow with the
+/// table. A database error mid-export ends the response early.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/export.ndjson",
+    responses(
+        (status = 200, description = "One `TaskResponse` JSON object per line, in id order", content_type = "application/x-ndjson", body = TaskResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn export_tasks<R: TaskRepository + ?Sized>(State(repo): State<Arc<R>>) -> Response {
+    let lines = repo.stream_all().map(|task| {
+        task.map(|task| {
+            let mut line =
+                serde_json::to_vec(&TaskResponse::from(task)).expect("TaskResponse serializes");
+            line.push(b'\n');
+            line
+        })
+    });
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+/// Mark every open task completed
+///
+/// Open means `todo` or `in_progress`; cancelled tasks are left alone.
+#[utoipa::path(
+    post,
+    path = "/api/tasks/complete-all",
+    params(DryRunQuery),
+    responses(
+        (status = 200, description = "Number of tasks completed, or that would be on a dry run", body = AffectedResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn complete_all_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    Query(query): Query<DryRunQuery>,
+) -> Result<Json<AffectedResponse>, impl IntoResponse> {
+    if query.dry_run == Some(true) {
+        let open = TaskFilter {
+            open: true,
+            ..Default::default()
+        };
+        return dry_run(repo.as_ref(), &open).await;
+    }
+
+    match repo.complete_all().await {
+        Ok(affected) => Ok(Json(AffectedResponse {
+            affected,
+            ids: None,
+        })),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Move every completed task to the archive
+///
+/// Archived tasks leave every other task endpoint and are read back with
+/// `GET /api/tasks/archived`.
+#[utoipa::path(
+    post,
+    path = "/api/tasks/archive-completed",
+    responses(
+        (status = 200, description = "Number of tasks archived", body = AffectedResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn archive_completed_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+) -> Result<Json<AffectedResponse>, impl IntoResponse> {
+    match repo.archive_completed().await {
+        Ok(affected) => Ok(Json(AffectedResponse {
+            affected,
+            ids: None,
+        })),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// List archived tasks, most recently archived first
+#[utoipa::path(
+    get,
+    path = "/api/tasks/archived",
+    params(ArchivedTasksQuery),
+    responses(
+        (status = 200, description = "Archived tasks", body = Vec<ArchivedTaskResponse>),
+        (status = 400, description = "Non-positive limit", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn archived_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    Query(query): Query<ArchivedTasksQuery>,
+    Extension(max_page_size): Extension<MaxPageSize>,
+) -> Result<Json<Vec<ArchivedTaskResponse>>, impl IntoResponse> {
+    let page = match capped_page(query.limit, query.offset, max_page_size) {
+        Ok(page) => page,
+        Err(error) => return Err(error),
+    };
+
+    match repo.list_archived(page).await {
+        Ok(tasks) => Ok(Json(
+            tasks
+                .into_iter()
+                .map(|archived| ArchivedTaskResponse {
+                    task: TaskResponse::from(archived.task),
+                    archived_at: archived.archived_at,
+                })
+                .collect(),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// List tasks sharing a title
+///
+/// For data cleanup: groups of two or more tasks whose titles match once
+/// trimmed and lower-cased, ordered by that title.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/duplicates",
+    responses(
+        (status = 200, description = "Duplicate groups; empty when there are none", body = Vec<DuplicateTasksResponse>),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn duplicate_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+) -> Result<Json<Vec<DuplicateTasksResponse>>, impl IntoResponse> {
+    match repo.duplicates().await {
+        Ok(groups) => Ok(Json(
+            groups
+                .into_iter()
+                .map(|group| DuplicateTasksResponse {
+                    title: group.title,
+                    count: group.tasks.len(),
+                    tasks: group.tasks.into_iter().map(TaskResponse::from).collect(),
+                })
+                .collect(),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Count completed and pending tasks
+///
+/// A lighter summary than listing tasks, from one grouped query.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/count-by-status",
+    responses(
+        (status = 200, description = "Task counts", body = TaskCountsResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn count_tasks_by_status<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+) -> Result<Json<TaskCountsResponse>, impl IntoResponse> {
+    match repo.count_by_status().await {
+        Ok(counts) => Ok(Json(TaskCountsResponse {
+            completed: counts.completed(),
+            pending: counts.pending(),
+        })),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Fetch task changes since a sync token
+///
+/// For offline-first clients. Without a token, returns every task with
+/// `reset: true`; with one, only the tasks created, updated or deleted
+/// since. Either way the response carries the token for the next call.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/sync",
+    params(SyncTasksQuery),
+    responses(
+        (status = 200, description = "Changes since the token", body = TaskSyncResponse),
+        (status = 400, description = "Token not issued by this server", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn sync_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    Query(query): Query<SyncTasksQuery>,
+) -> Result<Json<TaskSyncResponse>, impl IntoResponse> {
+    let cursor = match query.token.as_deref() {
+        Some(token) => match sync_token::decode(token) {
+            Some(cursor) => Some(cursor),
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse::new("Invalid sync token")),
+                ))
+            }
+        },
+        None => None,
+    };
+
+    match repo.changes_since(cursor).await {
+        Ok(changes) => Ok(Json(TaskSyncResponse {
+            tasks: changes.tasks.into_iter().map(TaskResponse::from).collect(),
+            deleted: changes.deleted,
+            token: sync_token::encode(changes.cursor),
+            reset: changes.reset,
+        })),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Mark the open tasks matching a filter completed
+#[utoipa::path(
+    post,
+    path = "/api/tasks/bulk-complete",
+    params(DryRunQuery),
+    request_body = BulkCompleteRequest,
+    responses(
+        (status = 200, description = "Number of tasks completed, or that would be on a dry run", body = AffectedResponse),
+        (status = 400, description = "No filter given", body = ErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "tasks"
+)]
+pub async fn bulk_complete_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    Query(query): Query<DryRunQuery>,
+    JsonBody(payload): JsonBody<BulkCompleteRequest>,
+) -> Result<Json<AffectedResponse>, impl IntoResponse> {
+    let tag = payload
+        .tag
+        .as_deref()
+        .map(normalize_text)
+        .filter(|tag| !tag.is_empty());
+    let filter = TaskFilter {
+        assigned_user_id: payload.assigned_user_id,
+        tags: tag.into_iter().map(str::to_owned).collect(),
+        ..Default::default()
+    };
+    if filter.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "Give at least one of `assigned_user_id` or `tag`",
+            )),
+        ));
+    }
+
+    if query.dry_run == Some(true) {
+        let open = TaskFilter {
+            open: true,
+            ..filter
+        };
+        return dry_run(repo.as_ref(), &open).await;
+    }
+
+    match repo.complete_matching(&filter).await {
+        Ok(affected) => Ok(Json(AffectedResponse {
+            affected,
+            ids: None,
+        })),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Move every task of one user to another
+#[utoipa::path(
+    post,
+    path = "/api/users/{from}/reassign-tasks/{to}",
+    params(
+        ("from" = i64, Path, description = "User whose tasks are moved"),
+        ("to" = i64, Path, description = "User receiving the tasks")
+    ),
+    responses(
+        (status = 200, description = "Number of tasks moved", body = AffectedResponse),
+        (status = 400, description = "Target user not found, or an id is not a positive integer", body = ErrorResponse),
+        (status = 404, description = "Source user not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn reassign_tasks<R: TaskRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    IdPath((from, to)): IdPath<(i64, i64)>,
+) -> Result<Json<AffectedResponse>, impl IntoResponse> {
+    match repo.reassign(from, to).await {
+        Ok(affected) => Ok(Json(AffectedResponse {
+            affected,
+            ids: None,
+        })),
+        Err(e) => {
+            let status = match e.downcast_ref::<UnknownUser>() {
+                Some(unknown) if unknown.user_id == from => StatusCode::NOT_FOUND,
+                Some(_) => StatusCode::BAD_REQUEST,
+                None => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            Err((status, Json(ErrorResponse::new(e.to_string()))))
+        }
+    }
+}
@@ -1,30 +1,57 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{OriginalUri, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     routing::get,
-    Json, Router,
+    Extension, Json, Router,
 };
 
 use crate::db::UserModel;
-use crate::repository::UserRepository;
+use crate::repository::{
+    DeleteUserPolicy, EmailTaken, UserHasTasks, UserRepository, UserSummary, UserTaskCounts,
+};
+use crate::validation::{validate_new_user, validate_user_update};
 
-use super::{CreateUserRequest, ErrorResponse, UpdateUserRequest, UserResponse};
+use super::json::JsonBody;
+use super::pagination::{capped_page, link_header, MaxPageSize};
+use super::path::IdPath;
+use super::{
+    validation_error, CreateUserRequest, DeleteUserQuery, ErrorResponse, ListUsersQuery, OnTasks,
+    SearchUsersQuery, UpdateUserRequest, UserResponse, UserSummaryResponse, UserTaskCountsResponse,
+};
 
-pub fn user_routes<R: UserRepository + 'static>(repo: Arc<R>) -> Router {
+pub fn user_routes<R: UserRepository + ?Sized + 'static>(repo: Arc<R>) -> Router {
     Router::new()
-        .route("/users", get(list_users::<R>).post(create_user::<R>))
+        .route(
+            "/users",
+            get(list_users::<R>)
+                .post(create_user::<R>)
+                .put(upsert_user::<R>),
+        )
+        .route("/users/search", get(search_users::<R>))
+        .route("/users/task-counts", get(user_task_counts::<R>))
         .route(
             "/users/{id}",
             get(get_user::<R>)
                 .put(update_user::<R>)
                 .delete(delete_user::<R>),
         )
+        .route("/users/{id}/summary", get(user_summary::<R>))
         .with_state(repo)
 }
 
+impl From<OnTasks> for DeleteUserPolicy {
+    fn from(on_tasks: OnTasks) -> Self {
+        match on_tasks {
+            OnTasks::Restrict => DeleteUserPolicy::Restrict,
+            OnTasks::Nullify => DeleteUserPolicy::Nullify,
+            OnTasks::Delete => DeleteUserPolicy::Delete,
+        }
+    }
+}
+
 impl From<UserModel> for UserResponse {
     fn from(model: UserModel) -> Self {
         UserResponse {
@@ -35,25 +62,70 @@ impl From<UserModel> for UserResponse {
     }
 }
 
+impl From<UserSummary> for UserSummaryResponse {
+    fn from(summary: UserSummary) -> Self {
+        UserSummaryResponse {
+            user: UserResponse::from(summary.user),
+            task_total: summary.total,
+            task_completed: summary.completed,
+        }
+    }
+}
+
+impl From<UserTaskCounts> for UserTaskCountsResponse {
+    fn from(counts: UserTaskCounts) -> Self {
+        UserTaskCountsResponse {
+            user_id: counts.user_id,
+            name: counts.name,
+            total: counts.total,
+            completed: counts.completed,
+        }
+    }
+}
+
 /// List all users
 #[utoipa::path(
     get,
     path = "/api/users",
+    params(ListUsersQuery),
     responses(
         (status = 200, description = "List of all users", body = Vec<UserResponse>),
+        (status = 400, description = "Non-positive limit", body = ErrorResponse),
     ),
     tag = "users"
 )]
-pub async fn list_users<R: UserRepository>(
+pub async fn list_users<R: UserRepository + ?Sized>(
     State(repo): State<Arc<R>>,
-) -> Result<Json<Vec<UserResponse>>, impl IntoResponse> {
-    match repo.list().await {
-        Ok(users) => Ok(Json(users.into_iter().map(UserResponse::from).collect())),
+    Query(query): Query<ListUsersQuery>,
+    Extension(max_page_size): Extension<MaxPageSize>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response, impl IntoResponse> {
+    let page = match capped_page(query.limit, query.offset, max_page_size) {
+        Ok(page) => page,
+        Err(error) => return Err(error),
+    };
+    let name = query.name.as_deref();
+    let result = match repo.list(page, name).await {
+        Ok(users) => repo.count(name).await.map(|total| (users, total)),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok((users, total)) => {
+            let body: Vec<UserResponse> = users.into_iter().map(UserResponse::from).collect();
+            let mut response = Json(body).into_response();
+            // Link a capped page even when no limit was asked for
+            let limit = page
+                .limit
+                .filter(|&limit| query.limit.is_some() || total > limit);
+            if let Some(link) = link_header(&uri, limit, query.offset, total) {
+                response.headers_mut().insert(header::LINK, link);
+            }
+            Ok(response)
+        }
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            Json(ErrorResponse::new(e.to_string())),
         )),
     }
 }
@@ -65,21 +137,139 @@ pub async fn list_users<R: UserRepository>(
     request_body = CreateUserRequest,
     responses(
         (status = 201, description = "User created successfully", body = UserResponse),
+        (status = 400, description = "Invalid fields", body = ErrorResponse),
+        (status = 409, description = "Email already registered", body = ErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
     tag = "users"
 )]
-pub async fn create_user<R: UserRepository>(
+pub async fn create_user<R: UserRepository + ?Sized>(
     State(repo): State<Arc<R>>,
-    Json(payload): Json<CreateUserRequest>,
+    JsonBody(payload): JsonBody<CreateUserRequest>,
 ) -> Result<impl IntoResponse, impl IntoResponse> {
+    if let Err(errors) = validate_new_user(&payload.name, &payload.email) {
+        return Err(validation_error(errors));
+    }
+
     match repo.create(&payload.name, &payload.email).await {
         Ok(user) => Ok((StatusCode::CREATED, Json(UserResponse::from(user)))),
+        Err(e) if e.is::<EmailTaken>() => Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Create or update a user by email
+///
+/// For syncing from another system: creates the user, or renames the one
+/// already registered with `email` (ignoring case). 201 when created, 200
+/// when updated.
+#[utoipa::path(
+    put,
+    path = "/api/users",
+    request_body = CreateUserRequest,
+    responses(
+        (status = 200, description = "Existing user updated", body = UserResponse),
+        (status = 201, description = "User created", body = UserResponse),
+        (status = 400, description = "Invalid fields", body = ErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn upsert_user<R: UserRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    JsonBody(payload): JsonBody<CreateUserRequest>,
+) -> Result<impl IntoResponse, impl IntoResponse> {
+    if let Err(errors) = validate_new_user(&payload.name, &payload.email) {
+        return Err(validation_error(errors));
+    }
+
+    match repo.upsert_by_email(&payload.name, &payload.email).await {
+        Ok((user, inserted)) => {
+            let status = if inserted {
+                StatusCode::CREATED
+            } else {
+                StatusCode::OK
+            };
+            Ok((status, Json(UserResponse::from(user))))
+        }
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Search users by name
+///
+/// For autocomplete: users whose name contains `q`, ignoring case, sorted
+/// by name. `%` and `_` in `q` match themselves.
+#[utoipa::path(
+    get,
+    path = "/api/users/search",
+    params(SearchUsersQuery),
+    responses(
+        (status = 200, description = "Matching users; empty when none match", body = Vec<UserResponse>),
+        (status = 400, description = "Missing or blank `q`", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn search_users<R: UserRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    Query(query): Query<SearchUsersQuery>,
+) -> Result<Json<Vec<UserResponse>>, impl IntoResponse> {
+    let Some(q) = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("q must not be empty")),
+        ));
+    };
+
+    match repo.search(q).await {
+        Ok(users) => Ok(Json(users.into_iter().map(UserResponse::from).collect())),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
+    }
+}
+
+/// Count the tasks assigned to each user
+///
+/// Every user appears, with zero counts when nothing is assigned to them.
+/// Unassigned tasks are not counted.
+#[utoipa::path(
+    get,
+    path = "/api/users/task-counts",
+    responses(
+        (status = 200, description = "Task counts per user, in id order", body = Vec<UserTaskCountsResponse>),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn user_task_counts<R: UserRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+) -> Result<Json<Vec<UserTaskCountsResponse>>, impl IntoResponse> {
+    match repo.task_counts().await {
+        Ok(counts) => Ok(Json(
+            counts
+                .into_iter()
+                .map(UserTaskCountsResponse::from)
+                .collect(),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
         )),
     }
 }
@@ -93,21 +283,52 @@ pub async fn create_user<R: UserRepository>(
     ),
     responses(
         (status = 200, description = "User found", body = UserResponse),
+        (status = 400, description = "Id is not a positive integer", body = ErrorResponse),
         (status = 404, description = "User not found", body = ErrorResponse),
     ),
     tag = "users"
 )]
-pub async fn get_user<R: UserRepository>(
+pub async fn get_user<R: UserRepository + ?Sized>(
     State(repo): State<Arc<R>>,
-    Path(id): Path<i64>,
+    IdPath(id): IdPath<i64>,
 ) -> Result<Json<UserResponse>, impl IntoResponse> {
     match repo.get(id).await {
         Ok(user) => Ok(Json(UserResponse::from(user))),
         Err(_) => Err((
             StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: format!("User with id {} not found", id),
-            }),
+            Json(ErrorResponse::new(format!("User with id {} not found", id))),
+        )),
+    }
+}
+
+/// Get a user with counts of their tasks
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}/summary",
+    params(
+        ("id" = i64, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "User and task counts", body = UserSummaryResponse),
+        (status = 400, description = "Id is not a positive integer", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn user_summary<R: UserRepository + ?Sized>(
+    State(repo): State<Arc<R>>,
+    IdPath(id): IdPath<i64>,
+) -> Result<Json<UserSummaryResponse>, impl IntoResponse> {
+    match repo.summary(id).await {
+        Ok(summary) => Ok(Json(UserSummaryResponse::from(summary))),
+        Err(e) if e.to_string().contains("no rows") => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new(format!("User with id {} not found", id))),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(e.to_string())),
         )),
     }
 }
@@ -122,34 +343,44 @@ pub async fn get_user<R: UserRepository>(
     request_body = UpdateUserRequest,
     responses(
         (status = 200, description = "User updated successfully", body = UserResponse),
+        (status = 400, description = "Invalid fields or id", body = ErrorResponse),
         (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 409, description = "Email already registered", body = ErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
     tag = "users"
 )]
-pub async fn update_user<R: UserRepository>(
+pub async fn update_user<R: UserRepository + ?Sized>(
     State(repo): State<Arc<R>>,
-    Path(id): Path<i64>,
-    Json(payload): Json<UpdateUserRequest>,
+    IdPath(id): IdPath<i64>,
+    JsonBody(payload): JsonBody<UpdateUserRequest>,
 ) -> Result<Json<UserResponse>, impl IntoResponse> {
+    if let Err(errors) = validate_user_update(payload.name.as_deref(), payload.email.as_deref()) {
+        return Err(validation_error(errors));
+    }
+
     match repo
         .update(id, payload.name.as_deref(), payload.email.as_deref())
         .await
     {
         Ok(user) => Ok(Json(UserResponse::from(user))),
+        Err(e) if e.is::<EmailTaken>() => Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
         Err(e) => {
             let error_msg = e.to_string();
             if error_msg.contains("no rows") {
                 Err((
                     StatusCode::NOT_FOUND,
-                    Json(ErrorResponse {
-                        error: format!("User with id {} not found", id),
-                    }),
+                    Json(ErrorResponse::new(format!("User with id {} not found", id))),
                 ))
             } else {
                 Err((
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse { error: error_msg }),
+                    Json(ErrorResponse::new(error_msg)),
                 ))
             }
         }
@@ -161,36 +392,42 @@ pub async fn update_user<R: UserRepository>(
     delete,
     path = "/api/users/{id}",
     params(
-        ("id" = i64, Path, description = "User ID")
+        ("id" = i64, Path, description = "User ID"),
+        DeleteUserQuery
     ),
     responses(
         (status = 204, description = "User deleted successfully"),
+        (status = 400, description = "Id is not a positive integer", body = ErrorResponse),
         (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 409, description = "User still has assigned tasks", body = ErrorResponse),
     ),
     tag = "users"
 )]
-pub async fn delete_user<R: UserRepository>(
+pub async fn delete_user<R: UserRepository + ?Sized>(
     State(repo): State<Arc<R>>,
-    Path(id): Path<i64>,
+    IdPath(id): IdPath<i64>,
+    Query(query): Query<DeleteUserQuery>,
 ) -> Result<StatusCode, impl IntoResponse> {
-    match repo.delete(id).await {
+    let policy = query.on_tasks.unwrap_or_default().into();
+
+    match repo.delete(id, policy).await {
         Ok(deleted) => {
             if deleted {
                 Ok(StatusCode::NO_CONTENT)
             } else {
                 Err((
                     StatusCode::NOT_FOUND,
-                    Json(ErrorResponse {
-                        error: format!("User with id {} not found", id),
-                    }),
+                    Json(ErrorResponse::new(format!("User with id {} not found", id))),
                 ))
             }
         }
+        Err(e) if e.is::<UserHasTasks>() => Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse::new(e.to_string())),
+        )),
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            Json(ErrorResponse::new(e.to_string())),
         )),
     }
 }
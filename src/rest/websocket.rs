@@ -0,0 +1,225 @@
+//! Just enough of RFC 6455 to push text messages to a browser: the opening
+//! handshake plus unfragmented frames. Client messages other than ping and
+//! close are read and dropped.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper_util::rt::TokioIo;
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::ErrorResponse;
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest client frame we are willing to read; we only expect control frames.
+const MAX_CLIENT_FRAME: u64 = 64 * 1024;
+
+pub const OPCODE_TEXT: u8 = 0x1;
+pub const OPCODE_CLOSE: u8 = 0x8;
+pub const OPCODE_PING: u8 = 0x9;
+pub const OPCODE_PONG: u8 = 0xA;
+
+pub type Upgraded = TokioIo<hyper::upgrade::Upgraded>;
+
+/// The request lacked the headers of a version 13 WebSocket handshake.
+#[derive(Debug)]
+pub struct NotAnUpgrade;
+
+impl IntoResponse for NotAnUpgrade {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::BAD_REQUEST,
+            axum::Json(ErrorResponse::new("Expected a WebSocket upgrade request")),
+        )
+            .into_response()
+    }
+}
+
+/// Validates the upgrade request and answers `101 Switching Protocols`; the
+/// connection itself becomes available once this response has been sent,
+/// through the returned future.
+pub fn upgrade(
+    mut request: Request,
+) -> Result<
+    (
+        Response,
+        impl std::future::Future<Output = Option<Upgraded>>,
+    ),
+    NotAnUpgrade,
+> {
+    let key = handshake_key(request.headers()).ok_or(NotAnUpgrade)?;
+
+    let on_upgrade = hyper::upgrade::on(&mut request);
+    let upgraded = async move { on_upgrade.await.ok().map(TokioIo::new) };
+
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+    headers.insert(
+        header::SEC_WEBSOCKET_ACCEPT,
+        HeaderValue::from_str(&accept_key(&key)).expect("base64 is a valid header value"),
+    );
+
+    Ok((response, upgraded))
+}
+
+fn handshake_key(headers: &HeaderMap) -> Option<String> {
+    let has_token = |name: header::HeaderName, token: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| {
+                value
+                    .split(',')
+                    .any(|part| part.trim().eq_ignore_ascii_case(token))
+            })
+    };
+
+    if !has_token(header::UPGRADE, "websocket")
+        || !has_token(header::CONNECTION, "upgrade")
+        || !has_token(header::SEC_WEBSOCKET_VERSION, "13")
+    {
+        return None;
+    }
+
+    headers
+        .get(header::SEC_WEBSOCKET_KEY)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+/// `Sec-WebSocket-Accept` for a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    let mut sha1 = Sha1::new();
+    sha1.update(key.as_bytes());
+    sha1.update(ACCEPT_GUID.as_bytes());
+    STANDARD.encode(sha1.finalize())
+}
+
+/// Writes one unmasked, unfragmented server frame.
+pub async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    opcode: u8,
+    payload: &[u8],
+) -> std::io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+/// Reads one client frame and unmasks its payload. Returns the opcode and
+/// payload. Clients must mask every frame (RFC 6455 section 5.1), so an
+/// unmasked one fails with `InvalidData`.
+///
+/// Not cancel-safe: a frame dropped halfway loses the bytes already read, so
+/// callers read from one place rather than in a `select!` branch.
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<(u8, Vec<u8>)> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head).await?;
+    let opcode = head[0] & 0x0f;
+    if head[1] & 0x80 == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "WebSocket client frame is not masked",
+        ));
+    }
+
+    let len = match head[1] & 0x7f {
+        126 => reader.read_u16().await? as u64,
+        127 => reader.read_u64().await?,
+        len => len as u64,
+    };
+    if len > MAX_CLIENT_FRAME {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "WebSocket frame too large",
+        ));
+    }
+
+    let mut mask = [0u8; 4];
+    reader.read_exact(&mut mask).await?;
+
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload).await?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+
+    Ok((opcode, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_key_matches_rfc_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    /// A client frame: masked, as browsers send them.
+    fn masked_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x12, 0x34, 0x56, 0x78];
+        let mut frame = vec![0x80 | opcode];
+        if payload.len() < 126 {
+            frame.push(0x80 | payload.len() as u8);
+        } else {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(
+            payload
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte ^ mask[i % 4]),
+        );
+        frame
+    }
+
+    #[tokio::test]
+    async fn test_read_masked_client_frame() {
+        let payload = "x".repeat(300);
+        let frame = masked_frame(OPCODE_TEXT, payload.as_bytes());
+
+        let (opcode, read) = read_frame(&mut frame.as_slice()).await.unwrap();
+
+        assert_eq!(opcode, OPCODE_TEXT);
+        assert_eq!(read, payload.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unmasked_client_frame() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, OPCODE_PING, b"hi").await.unwrap();
+
+        let error = read_frame(&mut buffer.as_slice()).await.unwrap_err();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,81 @@
+//! The client's `grpc-timeout`, enforced inside the service methods.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::Instant;
+use tonic::{Request, Status};
+
+/// How much earlier than the client's deadline a method gives up. tonic's
+/// transport enforces the same header and answers `CANCELLED` when it fires,
+/// so the method has to finish first for the client to see
+/// `DEADLINE_EXCEEDED`.
+const HEADROOM: Duration = Duration::from_millis(5);
+
+/// When the call must be answered by, if the client set a deadline.
+pub(crate) struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// Reads the `grpc-timeout` metadata; a missing or malformed value means
+    /// no deadline.
+    pub(crate) fn of<T>(request: &Request<T>) -> Self {
+        let timeout = request
+            .metadata()
+            .get("grpc-timeout")
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_timeout);
+        Self(timeout.map(|timeout| Instant::now() + timeout.saturating_sub(HEADROOM)))
+    }
+
+    /// Runs `call`, dropping it with `DEADLINE_EXCEEDED` once the deadline
+    /// passes. Dropping the future abandons the repository query it awaits.
+    pub(crate) async fn run<T>(
+        self,
+        call: impl Future<Output = Result<T, Status>>,
+    ) -> Result<T, Status> {
+        match self.0 {
+            Some(deadline) => match tokio::time::timeout_at(deadline, call).await {
+                Ok(result) => result,
+                Err(_) => Err(Status::deadline_exceeded("Deadline exceeded")),
+            },
+            None => call.await,
+        }
+    }
+}
+
+/// `TimeoutValue TimeoutUnit` as in the gRPC over HTTP/2 spec: at most eight
+/// digits followed by one of `H`, `M`, `S`, `m`, `u` or `n`.
+fn parse_timeout(value: &str) -> Option<Duration> {
+    let (digits, unit) = value.split_at(value.len().checked_sub(1)?);
+    if digits.is_empty() || digits.len() > 8 {
+        return None;
+    }
+    let amount: u64 = digits.parse().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(amount * 60 * 60),
+        "M" => Duration::from_secs(amount * 60),
+        "S" => Duration::from_secs(amount),
+        "m" => Duration::from_millis(amount),
+        "u" => Duration::from_micros(amount),
+        "n" => Duration::from_nanos(amount),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(parse_timeout("2S"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_timeout("150m"), Some(Duration::from_millis(150)));
+        assert_eq!(parse_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_timeout("10u"), Some(Duration::from_micros(10)));
+        assert_eq!(parse_timeout("S"), None);
+        assert_eq!(parse_timeout(""), None);
+        assert_eq!(parse_timeout("123456789S"), None);
+        assert_eq!(parse_timeout("5x"), None);
+        assert_eq!(parse_timeout("-5S"), None);
+    }
+}
@@ -1,5 +1,37 @@
+mod deadline;
+mod onboarding;
 mod task_service;
 mod user_service;
 
+pub use onboarding::create_user_with_task;
 pub use task_service::TaskServiceImpl;
 pub use user_service::UserServiceImpl;
+
+use tonic::{Code, Status};
+
+use crate::grpc_server::error::{ErrorDetail, ErrorReason};
+use crate::validation::ValidationErrors;
+
+/// Whether `error` is the repository reporting a missing row.
+fn is_not_found(error: &anyhow::Error) -> bool {
+    error.to_string().contains("no rows")
+}
+
+/// `NotFound` carrying an [`ErrorDetail`] for the missing resource.
+fn not_found(resource_type: &str, id: i64) -> Status {
+    ErrorDetail::new(ErrorReason::NotFound, resource_type, id).into_status(
+        Code::NotFound,
+        format!("{} {} not found", resource_type, id),
+    )
+}
+
+/// `code` carrying a conflict [`ErrorDetail`] for the resource.
+fn conflict(code: Code, resource_type: &str, id: i64, message: impl Into<String>) -> Status {
+    ErrorDetail::new(ErrorReason::Conflict, resource_type, id).into_status(code, message)
+}
+
+/// `InvalidArgument` listing every violation, worded as the REST API words
+/// them.
+fn invalid_argument(errors: ValidationErrors) -> Status {
+    Status::invalid_argument(errors.to_string())
+}
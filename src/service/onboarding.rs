@@ -0,0 +1,58 @@
+use anyhow::Result;
+
+use crate::db::{TaskModel, UserModel};
+use crate::repository::SqliteUnitOfWork;
+
+/// Creates a user together with their first task. Both are committed at
+/// once: if the task can't be created, the user isn't either.
+pub async fn create_user_with_task(
+    units: &SqliteUnitOfWork,
+    name: &str,
+    email: &str,
+    title: &str,
+    description: &str,
+) -> Result<(UserModel, TaskModel)> {
+    let mut work = units.begin().await?;
+    let user = work.create_user(name, email).await?;
+    let task = work
+        .create_task_for_user(user.id, title, description)
+        .await?;
+    work.commit().await?;
+
+    Ok((user, task))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        EmailTaken, SqliteTaskRepository, SqliteUserRepository, TaskFilter, TaskRepository,
+        UserRepository,
+    };
+
+    #[tokio::test]
+    async fn test_create_user_with_task_commits_both() {
+        let pool = crate::db::init_db_with_url("sqlite::memory:")
+            .await
+            .unwrap();
+        let units = SqliteUnitOfWork::new(pool.clone());
+
+        let (user, task) =
+            create_user_with_task(&units, "Ada", "ada@example.com", "Onboarding", "")
+                .await
+                .unwrap();
+
+        assert_eq!(task.assigned_user_id, Some(user.id));
+        let tasks = SqliteTaskRepository::new(pool.clone());
+        assert_eq!(tasks.get(task.id).await.unwrap().title, "Onboarding");
+
+        // A taken email fails the first step and leaves no task behind
+        let err = create_user_with_task(&units, "Ada again", "ADA@example.com", "Dup", "")
+            .await
+            .unwrap_err();
+        assert!(err.is::<EmailTaken>());
+        let users = SqliteUserRepository::new(pool);
+        assert_eq!(users.count(None).await.unwrap(), 1);
+        assert_eq!(tasks.count(&TaskFilter::default()).await.unwrap(), 1);
+    }
+}
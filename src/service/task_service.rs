@@ -1,26 +1,64 @@
 use std::sync::Arc;
-use tonic::{Request, Response, Status};
+use tonic::{Code, Request, Response, Status, Streaming};
 
+use crate::config;
 use crate::db;
 use crate::grpc_server::task::{
     task_service_server::{TaskService, TaskServiceServer},
-    CreateTaskRequest, CreateTaskResponse, DeleteTaskRequest, DeleteTaskResponse, GetTaskRequest,
-    GetTaskResponse, ListTasksRequest, ListTasksResponse, Task, UpdateTaskRequest,
+    BatchGetTasksRequest, BatchGetTasksResponse, CompleteAllTasksRequest, CompleteAllTasksResponse,
+    CreateTaskRequest, CreateTaskResponse, CreateTasksResponse, DeleteTaskRequest,
+    DeleteTaskResponse, GetTaskRequest, GetTaskResponse, ListTasksRequest, ListTasksResponse, Task,
+    TaskOrderBy, TaskStatus, ToggleTaskRequest, ToggleTaskResponse, UpdateTaskRequest,
     UpdateTaskResponse,
 };
-use crate::repository::TaskRepository;
+use crate::repository::{
+    batch_ids, Page, TaskFilter, TaskOrder, TaskRepository, TaskSortKey, TaskUpdate,
+    VersionMismatch,
+};
+use crate::validation::{
+    normalize_text, requested_status, validate_new_task_with_tags, validate_tags,
+    validate_task_update,
+};
+
+use super::deadline::Deadline;
+use super::{conflict, invalid_argument, is_not_found, not_found};
 
 pub struct TaskServiceImpl {
     repository: Arc<dyn TaskRepository>,
+    max_page_size: i64,
+    max_message_size: Option<usize>,
 }
 
 impl TaskServiceImpl {
     pub fn new(repository: Arc<dyn TaskRepository>) -> Self {
-        Self { repository }
+        Self {
+            repository,
+            max_page_size: config::DEFAULT_MAX_PAGE_SIZE,
+            max_message_size: None,
+        }
+    }
+
+    /// Caps `limit` on list calls, and is the page size when none is given.
+    pub fn with_max_page_size(mut self, max_page_size: i64) -> Self {
+        self.max_page_size = max_page_size;
+        self
+    }
+
+    /// Limit on decoded and encoded messages, in place of tonic's 4 MiB.
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
     }
 
     pub fn into_service(self) -> TaskServiceServer<Self> {
-        TaskServiceServer::new(self)
+        let max_message_size = self.max_message_size;
+        let service = TaskServiceServer::new(self);
+        match max_message_size {
+            Some(bytes) => service
+                .max_decoding_message_size(bytes)
+                .max_encoding_message_size(bytes),
+            None => service,
+        }
     }
 }
 
@@ -30,94 +68,322 @@ fn model_to_proto(model: db::TaskModel) -> Task {
         title: model.title,
         description: model.description,
         completed: model.completed,
+        assigned_user_id: model.assigned_user_id,
+        created_at: model.created_at,
+        updated_at: model.updated_at,
+        tags: model.tags,
+        completed_at: model.completed_at,
+        status: status_to_proto(model.status).into(),
+    }
+}
+
+fn status_to_proto(status: db::TaskStatus) -> TaskStatus {
+    match status {
+        db::TaskStatus::Todo => TaskStatus::Todo,
+        db::TaskStatus::InProgress => TaskStatus::InProgress,
+        db::TaskStatus::Done => TaskStatus::Done,
+        db::TaskStatus::Cancelled => TaskStatus::Cancelled,
+    }
+}
+
+/// `None` when unset or `UNSPECIFIED`; the error is the message for an
+/// `InvalidArgument`.
+fn status_from_proto(status: Option<i32>) -> Result<Option<db::TaskStatus>, String> {
+    let Some(value) = status else {
+        return Ok(None);
+    };
+    match TaskStatus::try_from(value) {
+        Ok(TaskStatus::Unspecified) => Ok(None),
+        Ok(TaskStatus::Todo) => Ok(Some(db::TaskStatus::Todo)),
+        Ok(TaskStatus::InProgress) => Ok(Some(db::TaskStatus::InProgress)),
+        Ok(TaskStatus::Done) => Ok(Some(db::TaskStatus::Done)),
+        Ok(TaskStatus::Cancelled) => Ok(Some(db::TaskStatus::Cancelled)),
+        Err(_) => Err(format!("Unknown task status {}", value)),
     }
 }
 
+/// `None` keeps the repository's configured order.
+fn task_order(order_by: TaskOrderBy) -> Option<TaskOrder> {
+    let (key, descending) = match order_by {
+        TaskOrderBy::Default => return None,
+        TaskOrderBy::CreatedDesc => (TaskSortKey::CreatedAt, true),
+        TaskOrderBy::CreatedAsc => (TaskSortKey::CreatedAt, false),
+        TaskOrderBy::UpdatedDesc => (TaskSortKey::UpdatedAt, true),
+        TaskOrderBy::UpdatedAsc => (TaskSortKey::UpdatedAt, false),
+        TaskOrderBy::TitleAsc => (TaskSortKey::Title, false),
+        TaskOrderBy::TitleDesc => (TaskSortKey::Title, true),
+    };
+    Some(TaskOrder { key, descending })
+}
+
+/// `NotFound` with an error detail when task `id` is missing, `Internal`
+/// otherwise.
+fn task_error(id: i64, context: &str, e: anyhow::Error) -> Status {
+    if is_not_found(&e) {
+        not_found("task", id)
+    } else {
+        Status::internal(format!("{}: {}", context, e))
+    }
+}
+
+/// Trimmed tags, leaving out blank ones.
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    tags.iter()
+        .map(|tag| normalize_text(tag))
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
 #[tonic::async_trait]
 impl TaskService for TaskServiceImpl {
     async fn create_task(
         &self,
         request: Request<CreateTaskRequest>,
     ) -> Result<Response<CreateTaskResponse>, Status> {
-        let req = request.into_inner();
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+                let title = normalize_text(&req.title);
+                let description = normalize_text(&req.description);
+                let tags = normalize_tags(req.tags);
+                let tag_refs: Vec<&str> = tags.iter().map(String::as_str).collect();
+                validate_new_task_with_tags(title, description, &tag_refs)
+                    .map_err(invalid_argument)?;
 
-        let task = self
-            .repository
-            .create(&req.title, &req.description)
+                let task = self
+                    .repository
+                    .create_with_tags(None, title, description, &tags)
+                    .await
+                    .map_err(|e| Status::internal(format!("Failed to create task: {}", e)))?;
+
+                Ok(Response::new(CreateTaskResponse {
+                    task: Some(model_to_proto(task)),
+                }))
+            })
             .await
-            .map_err(|e| Status::internal(format!("Failed to create task: {}", e)))?;
+    }
+
+    async fn create_tasks(
+        &self,
+        request: Request<Streaming<CreateTaskRequest>>,
+    ) -> Result<Response<CreateTasksResponse>, Status> {
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let mut stream = request.into_inner();
+
+                // Buffer the whole stream first so a client error midway leaves
+                // nothing behind
+                let mut tasks = Vec::new();
+                while let Some(req) = stream.message().await? {
+                    let title = normalize_text(&req.title);
+                    let description = normalize_text(&req.description);
+                    validate_new_task_with_tags(title, description, &[]).map_err(|errors| {
+                        Status::invalid_argument(format!("Task {}: {}", tasks.len(), errors))
+                    })?;
+                    tasks.push((title.to_string(), description.to_string()));
+                }
+
+                let created = self
+                    .repository
+                    .create_many(&tasks)
+                    .await
+                    .map_err(|e| Status::internal(format!("Failed to create tasks: {}", e)))?;
 
-        Ok(Response::new(CreateTaskResponse {
-            task: Some(model_to_proto(task)),
-        }))
+                Ok(Response::new(CreateTasksResponse {
+                    created_count: created.len() as u64,
+                    ids: created.into_iter().map(|task| task.id).collect(),
+                }))
+            })
+            .await
     }
 
     async fn get_task(
         &self,
         request: Request<GetTaskRequest>,
     ) -> Result<Response<GetTaskResponse>, Status> {
-        let req = request.into_inner();
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
 
-        let task = self
-            .repository
-            .get(req.id)
-            .await
-            .map_err(|e| Status::not_found(format!("Task not found: {}", e)))?;
+                let task = self
+                    .repository
+                    .get(req.id)
+                    .await
+                    .map_err(|e| task_error(req.id, "Failed to get task", e))?;
 
-        Ok(Response::new(GetTaskResponse {
-            task: Some(model_to_proto(task)),
-        }))
+                Ok(Response::new(GetTaskResponse {
+                    task: Some(model_to_proto(task)),
+                }))
+            })
+            .await
     }
 
     async fn list_tasks(
         &self,
-        _request: Request<ListTasksRequest>,
+        request: Request<ListTasksRequest>,
     ) -> Result<Response<ListTasksResponse>, Status> {
-        let tasks = self
-            .repository
-            .list()
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+                let page = Page::capped(req.limit, req.offset, self.max_page_size)
+                    .map_err(Status::invalid_argument)?;
+                let status = status_from_proto(req.status).map_err(Status::invalid_argument)?;
+
+                let tasks = self
+                    .repository
+                    .list(
+                        page,
+                        &TaskFilter {
+                            order: task_order(req.order_by()),
+                            tags: normalize_tags(req.tags),
+                            status,
+                            ..Default::default()
+                        },
+                    )
+                    .await
+                    .map_err(|e| Status::internal(format!("Failed to list tasks: {}", e)))?;
+
+                let tasks = tasks.into_iter().map(model_to_proto).collect();
+
+                Ok(Response::new(ListTasksResponse { tasks }))
+            })
             .await
-            .map_err(|e| Status::internal(format!("Failed to list tasks: {}", e)))?;
+    }
 
-        let tasks = tasks.into_iter().map(model_to_proto).collect();
+    async fn batch_get_tasks(
+        &self,
+        request: Request<BatchGetTasksRequest>,
+    ) -> Result<Response<BatchGetTasksResponse>, Status> {
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+                let ids =
+                    batch_ids(&req.ids, self.max_page_size).map_err(Status::invalid_argument)?;
+
+                let tasks = self
+                    .repository
+                    .get_many(&ids)
+                    .await
+                    .map_err(|e| Status::internal(format!("Failed to get tasks: {}", e)))?;
+
+                let tasks = tasks.into_iter().map(model_to_proto).collect();
 
-        Ok(Response::new(ListTasksResponse { tasks }))
+                Ok(Response::new(BatchGetTasksResponse { tasks }))
+            })
+            .await
     }
 
     async fn update_task(
         &self,
         request: Request<UpdateTaskRequest>,
     ) -> Result<Response<UpdateTaskResponse>, Status> {
-        let req = request.into_inner();
-
-        let task = self
-            .repository
-            .update(
-                req.id,
-                req.title.as_deref(),
-                req.description.as_deref(),
-                req.completed,
-            )
-            .await
-            .map_err(|e| Status::internal(format!("Failed to update task: {}", e)))?;
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+                let title = req.title.as_deref().map(normalize_text);
+                let description = req.description.as_deref().map(normalize_text);
+                validate_task_update(title, description).map_err(invalid_argument)?;
+                let tags = req.tags.map(|list| normalize_tags(list.tags));
+                if let Some(tags) = &tags {
+                    let tag_refs: Vec<&str> = tags.iter().map(String::as_str).collect();
+                    validate_tags(&tag_refs).map_err(invalid_argument)?;
+                }
+                let status = requested_status(
+                    status_from_proto(req.status).map_err(Status::invalid_argument)?,
+                    req.completed,
+                )
+                .map_err(invalid_argument)?;
+
+                let update = TaskUpdate {
+                    title: title.map(String::from),
+                    description: description.map(String::from),
+                    status,
+                    tags,
+                    expected_updated_at: req.expected_version,
+                    ..TaskUpdate::default()
+                };
+                let task = self
+                    .repository
+                    .update_with_tags(req.id, &update)
+                    .await
+                    .map_err(|e| {
+                        if e.is::<VersionMismatch>() {
+                            conflict(Code::Aborted, "task", req.id, e.to_string())
+                        } else {
+                            task_error(req.id, "Failed to update task", e)
+                        }
+                    })?;
 
-        Ok(Response::new(UpdateTaskResponse {
-            task: Some(model_to_proto(task)),
-        }))
+                Ok(Response::new(UpdateTaskResponse {
+                    task: Some(model_to_proto(task)),
+                }))
+            })
+            .await
     }
 
     async fn delete_task(
         &self,
         request: Request<DeleteTaskRequest>,
     ) -> Result<Response<DeleteTaskResponse>, Status> {
-        let req = request.into_inner();
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+
+                let success = self
+                    .repository
+                    .delete(req.id)
+                    .await
+                    .map_err(|e| Status::internal(format!("Failed to delete task: {}", e)))?;
+
+                Ok(Response::new(DeleteTaskResponse { success }))
+            })
+            .await
+    }
+
+    async fn toggle_task(
+        &self,
+        request: Request<ToggleTaskRequest>,
+    ) -> Result<Response<ToggleTaskResponse>, Status> {
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+
+                let task = self
+                    .repository
+                    .toggle(req.id)
+                    .await
+                    .map_err(|e| task_error(req.id, "Failed to toggle task", e))?;
 
-        let success = self
-            .repository
-            .delete(req.id)
+                Ok(Response::new(ToggleTaskResponse {
+                    task: Some(model_to_proto(task)),
+                }))
+            })
             .await
-            .map_err(|e| Status::internal(format!("Failed to delete task: {}", e)))?;
+    }
 
-        Ok(Response::new(DeleteTaskResponse { success }))
+    async fn complete_all_tasks(
+        &self,
+        request: Request<CompleteAllTasksRequest>,
+    ) -> Result<Response<CompleteAllTasksResponse>, Status> {
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let count =
+                    self.repository.complete_all().await.map_err(|e| {
+                        Status::internal(format!("Failed to complete tasks: {}", e))
+                    })?;
+
+                Ok(Response::new(CompleteAllTasksResponse { count }))
+            })
+            .await
     }
 }
@@ -1,26 +1,58 @@
 use std::sync::Arc;
-use tonic::{Request, Response, Status};
+use tonic::{Code, Request, Response, Status};
 
+use crate::config;
 use crate::db;
 use crate::grpc_server::user::{
     user_service_server::{UserService, UserServiceServer},
-    CreateUserRequest, CreateUserResponse, DeleteUserRequest, DeleteUserResponse, GetUserRequest,
-    GetUserResponse, ListUsersRequest, ListUsersResponse, UpdateUserRequest, UpdateUserResponse,
-    User,
+    BatchGetUsersRequest, BatchGetUsersResponse, CreateUserRequest, CreateUserResponse,
+    DeleteUserRequest, DeleteUserResponse, GetUserRequest, GetUserResponse, ListUsersRequest,
+    ListUsersResponse, UpdateUserRequest, UpdateUserResponse, User,
 };
-use crate::repository::UserRepository;
+use crate::repository::{
+    batch_ids, DeleteUserPolicy, EmailTaken, Page, UserHasTasks, UserRepository,
+};
+use crate::validation::{validate_new_user, validate_user_update};
+
+use super::deadline::Deadline;
+use super::{conflict, invalid_argument, is_not_found, not_found};
 
 pub struct UserServiceImpl {
     repository: Arc<dyn UserRepository>,
+    max_page_size: i64,
+    max_message_size: Option<usize>,
 }
 
 impl UserServiceImpl {
     pub fn new(repository: Arc<dyn UserRepository>) -> Self {
-        Self { repository }
+        Self {
+            repository,
+            max_page_size: config::DEFAULT_MAX_PAGE_SIZE,
+            max_message_size: None,
+        }
+    }
+
+    /// Caps `limit` on list calls, and is the page size when none is given.
+    pub fn with_max_page_size(mut self, max_page_size: i64) -> Self {
+        self.max_page_size = max_page_size;
+        self
+    }
+
+    /// Limit on decoded and encoded messages, in place of tonic's 4 MiB.
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
     }
 
     pub fn into_service(self) -> UserServiceServer<Self> {
-        UserServiceServer::new(self)
+        let max_message_size = self.max_message_size;
+        let service = UserServiceServer::new(self);
+        match max_message_size {
+            Some(bytes) => service
+                .max_decoding_message_size(bytes)
+                .max_encoding_message_size(bytes),
+            None => service,
+        }
     }
 }
 
@@ -38,80 +70,158 @@ impl UserService for UserServiceImpl {
         &self,
         request: Request<CreateUserRequest>,
     ) -> Result<Response<CreateUserResponse>, Status> {
-        let req = request.into_inner();
-
-        let user = self
-            .repository
-            .create(&req.name, &req.email)
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+                validate_new_user(&req.name, &req.email).map_err(invalid_argument)?;
+
+                let user = self
+                    .repository
+                    .create(&req.name, &req.email)
+                    .await
+                    .map_err(|e| {
+                        if e.is::<EmailTaken>() {
+                            Status::already_exists(e.to_string())
+                        } else {
+                            Status::internal(format!("Failed to create user: {}", e))
+                        }
+                    })?;
+
+                Ok(Response::new(CreateUserResponse {
+                    user: Some(user_model_to_proto(user)),
+                }))
+            })
             .await
-            .map_err(|e| Status::internal(format!("Failed to create user: {}", e)))?;
-
-        Ok(Response::new(CreateUserResponse {
-            user: Some(user_model_to_proto(user)),
-        }))
     }
 
     async fn get_user(
         &self,
         request: Request<GetUserRequest>,
     ) -> Result<Response<GetUserResponse>, Status> {
-        let req = request.into_inner();
-
-        let user = self
-            .repository
-            .get(req.id)
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+
+                let user = self.repository.get(req.id).await.map_err(|e| {
+                    if is_not_found(&e) {
+                        not_found("user", req.id)
+                    } else {
+                        Status::internal(format!("Failed to get user: {}", e))
+                    }
+                })?;
+
+                Ok(Response::new(GetUserResponse {
+                    user: Some(user_model_to_proto(user)),
+                }))
+            })
             .await
-            .map_err(|e| Status::not_found(format!("User not found: {}", e)))?;
+    }
 
-        Ok(Response::new(GetUserResponse {
-            user: Some(user_model_to_proto(user)),
-        }))
+    async fn batch_get_users(
+        &self,
+        request: Request<BatchGetUsersRequest>,
+    ) -> Result<Response<BatchGetUsersResponse>, Status> {
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+                let ids =
+                    batch_ids(&req.ids, self.max_page_size).map_err(Status::invalid_argument)?;
+
+                let users = self
+                    .repository
+                    .get_many(&ids)
+                    .await
+                    .map_err(|e| Status::internal(format!("Failed to get users: {}", e)))?;
+
+                let users = users.into_iter().map(user_model_to_proto).collect();
+
+                Ok(Response::new(BatchGetUsersResponse { users }))
+            })
+            .await
     }
 
     async fn list_users(
         &self,
-        _request: Request<ListUsersRequest>,
+        request: Request<ListUsersRequest>,
     ) -> Result<Response<ListUsersResponse>, Status> {
-        let users = self
-            .repository
-            .list()
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+                let page = Page::capped(req.limit, req.offset, self.max_page_size)
+                    .map_err(Status::invalid_argument)?;
+
+                let users = self
+                    .repository
+                    .list(page, req.name.as_deref())
+                    .await
+                    .map_err(|e| Status::internal(format!("Failed to list users: {}", e)))?;
+
+                let users = users.into_iter().map(user_model_to_proto).collect();
+
+                Ok(Response::new(ListUsersResponse { users }))
+            })
             .await
-            .map_err(|e| Status::internal(format!("Failed to list users: {}", e)))?;
-
-        let users = users.into_iter().map(user_model_to_proto).collect();
-
-        Ok(Response::new(ListUsersResponse { users }))
     }
 
     async fn update_user(
         &self,
         request: Request<UpdateUserRequest>,
     ) -> Result<Response<UpdateUserResponse>, Status> {
-        let req = request.into_inner();
-
-        let user = self
-            .repository
-            .update(req.id, req.name.as_deref(), req.email.as_deref())
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+                validate_user_update(req.name.as_deref(), req.email.as_deref())
+                    .map_err(invalid_argument)?;
+
+                let user = self
+                    .repository
+                    .update(req.id, req.name.as_deref(), req.email.as_deref())
+                    .await
+                    .map_err(|e| {
+                        if is_not_found(&e) {
+                            not_found("user", req.id)
+                        } else if e.is::<EmailTaken>() {
+                            conflict(Code::AlreadyExists, "user", req.id, e.to_string())
+                        } else {
+                            Status::internal(format!("Failed to update user: {}", e))
+                        }
+                    })?;
+
+                Ok(Response::new(UpdateUserResponse {
+                    user: Some(user_model_to_proto(user)),
+                }))
+            })
             .await
-            .map_err(|e| Status::internal(format!("Failed to update user: {}", e)))?;
-
-        Ok(Response::new(UpdateUserResponse {
-            user: Some(user_model_to_proto(user)),
-        }))
     }
 
     async fn delete_user(
         &self,
         request: Request<DeleteUserRequest>,
     ) -> Result<Response<DeleteUserResponse>, Status> {
-        let req = request.into_inner();
-
-        let success = self
-            .repository
-            .delete(req.id)
+        let deadline = Deadline::of(&request);
+        deadline
+            .run(async move {
+                let req = request.into_inner();
+
+                let success = self
+                    .repository
+                    .delete(req.id, DeleteUserPolicy::Restrict)
+                    .await
+                    .map_err(|e| {
+                        if e.is::<UserHasTasks>() {
+                            conflict(Code::FailedPrecondition, "user", req.id, e.to_string())
+                        } else {
+                            Status::internal(format!("Failed to delete user: {}", e))
+                        }
+                    })?;
+
+                Ok(Response::new(DeleteUserResponse { success }))
+            })
             .await
-            .map_err(|e| Status::internal(format!("Failed to delete user: {}", e)))?;
-
-        Ok(Response::new(DeleteUserResponse { success }))
     }
 }
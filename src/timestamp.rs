@@ -0,0 +1,148 @@
+//! Client-supplied timestamps. The database stores UTC timestamps as
+//! `YYYY-MM-DDTHH:MM:SS.sssZ` text, so comparisons against a column only
+//! work once input is rewritten into exactly that form.
+
+/// Parses an RFC 3339 timestamp such as `2025-01-02T03:04:05Z` or
+/// `2025-01-02T05:04:05.5+02:00` into the stored UTC form. Returns `None`
+/// for anything else, including impossible dates.
+pub fn parse_rfc3339(value: &str) -> Option<String> {
+    let (date, rest) = value.split_once(['T', 't'])?;
+
+    let mut date_parts = date.split('-');
+    let year = number(date_parts.next()?, 4)?;
+    let month = number(date_parts.next()?, 2)?;
+    let day = number(date_parts.next()?, 2)?;
+    if date_parts.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+
+    let (time, offset_minutes) = match rest.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => {
+            let at = rest.rfind(['+', '-'])?;
+            let (time, offset) = rest.split_at(at);
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let (hours, minutes) = (number(hours, 2)?, number(minutes, 2)?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            (time, sign * (hours * 60 + minutes))
+        }
+    };
+
+    let (clock, fraction) = match time.split_once('.') {
+        Some((clock, fraction)) => (clock, Some(fraction)),
+        None => (time, None),
+    };
+    let mut clock_parts = clock.split(':');
+    let hour = number(clock_parts.next()?, 2)?;
+    let minute = number(clock_parts.next()?, 2)?;
+    let second = number(clock_parts.next()?, 2)?;
+    if clock_parts.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let millis = match fraction {
+        Some(fraction) if !fraction.is_empty() && fraction.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("{:0<3}", &fraction[..fraction.len().min(3)])
+        }
+        Some(_) => return None,
+        None => "000".to_string(),
+    };
+
+    let total_minutes = days * 1440 + hour * 60 + minute - offset_minutes;
+    let (year, month, day) = civil_from_days(total_minutes.div_euclid(1440));
+    if !(0..=9999).contains(&year) {
+        return None;
+    }
+    let minute_of_day = total_minutes.rem_euclid(1440);
+
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{}Z",
+        year,
+        month,
+        day,
+        minute_of_day / 60,
+        minute_of_day % 60,
+        second,
+        millis
+    ))
+}
+
+/// A field of exactly `width` ASCII digits.
+fn number(field: &str, width: usize) -> Option<i64> {
+    if field.len() != width || !field.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    field.parse().ok()
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rfc3339_normalizes_to_utc() {
+        assert_eq!(
+            parse_rfc3339("2025-01-02T03:04:05Z").as_deref(),
+            Some("2025-01-02T03:04:05.000Z")
+        );
+        assert_eq!(
+            parse_rfc3339("2025-01-02T03:04:05.5+02:00").as_deref(),
+            Some("2025-01-02T01:04:05.500Z")
+        );
+        assert_eq!(
+            parse_rfc3339("2024-12-31T23:30:00.123456-01:00").as_deref(),
+            Some("2025-01-01T00:30:00.123Z")
+        );
+    }
+
+    #[test]
+    fn test_parse_rfc3339_rejects_invalid_input() {
+        for bad in [
+            "",
+            "yesterday",
+            "2025-01-02",
+            "2025-01-02T03:04Z",
+            "2025-02-30T00:00:00Z",
+            "2025-01-02T24:00:00Z",
+            "2025-01-02T03:04:05",
+            "2025-01-02T03:04:05.Z",
+            "2025-01-02T03:04:05+0200",
+        ] {
+            assert_eq!(parse_rfc3339(bad), None, "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Input rules shared by the API layers. Each check collects every
+//! violation instead of stopping at the first, so forms can show them all.
+
+use crate::db::TaskStatus;
+
+pub const MAX_TITLE_LEN: usize = 200;
+pub const MAX_DESCRIPTION_LEN: usize = 2000;
+pub const MAX_TAG_LEN: usize = 50;
+
+/// One invalid field and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// All violations found in a payload. Never empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Validation failed: ")?;
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{} {}", error.field, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+#[derive(Default)]
+struct Collector(Vec<FieldError>);
+
+impl Collector {
+    fn check(&mut self, ok: bool, field: &'static str, message: impl Into<String>) {
+        if !ok {
+            self.0.push(FieldError {
+                field,
+                message: message.into(),
+            });
+        }
+    }
+
+    fn finish(self) -> Result<(), ValidationErrors> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(self.0))
+        }
+    }
+}
+
+fn title_rules(errors: &mut Collector, title: &str) {
+    errors.check(!title.trim().is_empty(), "title", "must not be empty");
+    errors.check(
+        title.chars().count() <= MAX_TITLE_LEN,
+        "title",
+        format!("must be at most {} characters", MAX_TITLE_LEN),
+    );
+}
+
+fn description_rules(errors: &mut Collector, description: &str) {
+    errors.check(
+        description.chars().count() <= MAX_DESCRIPTION_LEN,
+        "description",
+        format!("must be at most {} characters", MAX_DESCRIPTION_LEN),
+    );
+}
+
+fn tag_rules(errors: &mut Collector, tags: &[&str]) {
+    errors.check(
+        tags.iter().all(|tag| !tag.is_empty()),
+        "tags",
+        "must not contain empty tags",
+    );
+    errors.check(
+        tags.iter().all(|tag| tag.chars().count() <= MAX_TAG_LEN),
+        "tags",
+        format!("must each be at most {} characters", MAX_TAG_LEN),
+    );
+}
+
+fn email_rules(errors: &mut Collector, email: &str) {
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.chars().any(char::is_whitespace)
+        }
+        None => false,
+    };
+    errors.check(valid, "email", "must be a valid email address");
+}
+
+/// Normalizes a task title or description before it is validated and stored:
+/// surrounding whitespace is dropped, so a blank title fails validation.
+pub fn normalize_text(value: &str) -> &str {
+    value.trim()
+}
+
+pub fn validate_new_task(title: &str, description: &str) -> Result<(), ValidationErrors> {
+    let mut errors = Collector::default();
+    title_rules(&mut errors, title);
+    description_rules(&mut errors, description);
+    errors.finish()
+}
+
+/// Like [`validate_new_task`], skipping fields that aren't being changed.
+pub fn validate_task_update(
+    title: Option<&str>,
+    description: Option<&str>,
+) -> Result<(), ValidationErrors> {
+    let mut errors = Collector::default();
+    if let Some(title) = title {
+        title_rules(&mut errors, title);
+    }
+    if let Some(description) = description {
+        description_rules(&mut errors, description);
+    }
+    errors.finish()
+}
+
+/// Checks tags after [`normalize_text`]; duplicates are allowed and collapse
+/// when stored.
+pub fn validate_tags(tags: &[&str]) -> Result<(), ValidationErrors> {
+    let mut errors = Collector::default();
+    tag_rules(&mut errors, tags);
+    errors.finish()
+}
+
+/// Every check `POST /api/tasks` makes, tags included, reported together.
+pub fn validate_new_task_with_tags(
+    title: &str,
+    description: &str,
+    tags: &[&str],
+) -> Result<(), ValidationErrors> {
+    let mut errors = Collector::default();
+    title_rules(&mut errors, title);
+    description_rules(&mut errors, description);
+    tag_rules(&mut errors, tags);
+    errors.finish()
+}
+
+/// The status a task update sets: `status` when given, otherwise the one
+/// the older `completed` flag stands for. Sending both is fine as long as
+/// they agree.
+pub fn requested_status(
+    status: Option<TaskStatus>,
+    completed: Option<bool>,
+) -> Result<Option<TaskStatus>, ValidationErrors> {
+    let mut errors = Collector::default();
+    if let (Some(status), Some(completed)) = (status, completed) {
+        errors.check(
+            status.is_done() == completed,
+            "completed",
+            format!("must be {} when status is {}", status.is_done(), status),
+        );
+    }
+    errors.finish()?;
+    Ok(status.or(completed.map(TaskStatus::from_completed)))
+}
+
+pub fn validate_new_user(name: &str, email: &str) -> Result<(), ValidationErrors> {
+    let mut errors = Collector::default();
+    errors.check(!name.trim().is_empty(), "name", "must not be empty");
+    email_rules(&mut errors, email);
+    errors.finish()
+}
+
+pub fn validate_user_update(
+    name: Option<&str>,
+    email: Option<&str>,
+) -> Result<(), ValidationErrors> {
+    let mut errors = Collector::default();
+    if let Some(name) = name {
+        errors.check(!name.trim().is_empty(), "name", "must not be empty");
+    }
+    if let Some(email) = email {
+        email_rules(&mut errors, email);
+    }
+    errors.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_task_collects_all_errors() {
+        let long = "x".repeat(MAX_DESCRIPTION_LEN + 1);
+        let errors = validate_new_task("  ", &long).unwrap_err();
+
+        let fields: Vec<&str> = errors.0.iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["title", "description"]);
+    }
+
+    #[test]
+    fn test_valid_task() {
+        assert!(validate_new_task("Write docs", "").is_ok());
+        assert!(validate_task_update(None, None).is_ok());
+    }
+
+    #[test]
+    fn test_tag_rules() {
+        assert!(validate_tags(&["work", "work"]).is_ok());
+        assert!(validate_tags(&["work", ""]).is_err());
+        assert!(validate_tags(&[&"x".repeat(MAX_TAG_LEN + 1)]).is_err());
+
+        let errors = validate_new_task_with_tags("", "", &["work", ""]).unwrap_err();
+        let fields: Vec<_> = errors.0.iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["title", "tags"]);
+    }
+
+    #[test]
+    fn test_requested_status() {
+        assert_eq!(requested_status(None, None), Ok(None));
+        assert_eq!(
+            requested_status(None, Some(true)),
+            Ok(Some(TaskStatus::Done))
+        );
+        assert_eq!(
+            requested_status(Some(TaskStatus::InProgress), Some(false)),
+            Ok(Some(TaskStatus::InProgress))
+        );
+        let errors = requested_status(Some(TaskStatus::Cancelled), Some(true)).unwrap_err();
+        assert_eq!(errors.0[0].field, "completed");
+    }
+
+    #[test]
+    fn test_email_rules() {
+        assert!(validate_new_user("Ada", "ada@example.com").is_ok());
+        for bad in [
+            "",
+            "ada",
+            "@example.com",
+            "ada@",
+            "ada@example",
+            "a b@example.com",
+        ] {
+            assert!(validate_new_user("Ada", bad).is_err(), "{}", bad);
+        }
+    }
+}
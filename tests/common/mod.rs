@@ -1,36 +1,12 @@
+#![allow(dead_code)]
+
 use rust_grpc_sqlite::repository::{SqliteTaskRepository, SqliteUserRepository};
 use sqlx::SqlitePool;
 use std::sync::Arc;
 
 pub async fn setup_test_pool() -> SqlitePool {
     let pool = SqlitePool::connect(":memory:").await.unwrap();
-
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS tasks (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            title TEXT NOT NULL,
-            description TEXT NOT NULL,
-            completed BOOLEAN NOT NULL DEFAULT 0
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await
-    .unwrap();
-
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT NOT NULL,
-            email TEXT NOT NULL UNIQUE
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await
-    .unwrap();
+    rust_grpc_sqlite::db::MIGRATOR.run(&pool).await.unwrap();
 
     pool
 }
@@ -48,18 +24,20 @@ pub async fn setup_test_user_repository() -> Arc<SqliteUserRepository> {
 pub async fn setup_test_pool_with_data() -> SqlitePool {
     let pool = setup_test_pool().await;
 
-    sqlx::query("INSERT INTO tasks (title, description, completed) VALUES (?, ?, ?)")
+    sqlx::query("INSERT INTO tasks (title, description, completed, status) VALUES (?, ?, ?, ?)")
         .bind("Test Task 1")
         .bind("Description 1")
         .bind(false)
+        .bind("todo")
         .execute(&pool)
         .await
         .unwrap();
 
-    sqlx::query("INSERT INTO tasks (title, description, completed) VALUES (?, ?, ?)")
+    sqlx::query("INSERT INTO tasks (title, description, completed, status) VALUES (?, ?, ?, ?)")
         .bind("Test Task 2")
         .bind("Description 2")
         .bind(true)
+        .bind("done")
         .execute(&pool)
         .await
         .unwrap();
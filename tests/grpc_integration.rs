@@ -1,14 +1,19 @@
 mod common;
 
+use rust_grpc_sqlite::grpc_server::error::{ErrorDetail, ErrorReason};
 use rust_grpc_sqlite::grpc_server::task::{
-    task_service_client::TaskServiceClient, CreateTaskRequest, DeleteTaskRequest, GetTaskRequest,
-    ListTasksRequest, UpdateTaskRequest,
+    task_service_client::TaskServiceClient, CompleteAllTasksRequest, CreateTaskRequest,
+    DeleteTaskRequest, GetTaskRequest, ListTasksRequest, TagList, TaskOrderBy, TaskStatus,
+    ToggleTaskRequest, UpdateTaskRequest,
 };
 use rust_grpc_sqlite::grpc_server::user::{
-    user_service_client::UserServiceClient, CreateUserRequest, DeleteUserRequest, GetUserRequest,
-    ListUsersRequest, UpdateUserRequest,
+    user_service_client::UserServiceClient, BatchGetUsersRequest, CreateUserRequest,
+    DeleteUserRequest, GetUserRequest, ListUsersRequest, UpdateUserRequest,
 };
+use rust_grpc_sqlite::repository::MockTaskRepository;
 use rust_grpc_sqlite::service::{TaskServiceImpl, UserServiceImpl};
+use std::sync::Arc;
+use std::time::Duration;
 use tonic::transport::{Channel, Server};
 
 async fn setup_grpc_client() -> (TaskServiceClient<Channel>, tokio::task::JoinHandle<()>) {
@@ -71,6 +76,7 @@ async fn test_create_task_grpc() {
     let request = tonic::Request::new(CreateTaskRequest {
         title: "Test Task".to_string(),
         description: "Test Description".to_string(),
+        tags: vec![],
     });
 
     let response = client.create_task(request).await.unwrap();
@@ -82,6 +88,90 @@ async fn test_create_task_grpc() {
     assert!(task.id > 0);
 }
 
+#[tokio::test]
+async fn test_create_task_trims_input_grpc() {
+    let (mut client, _handle) = setup_grpc_client().await;
+
+    let request = tonic::Request::new(CreateTaskRequest {
+        title: "  Padded  ".to_string(),
+        description: "\tNotes\n".to_string(),
+        tags: vec![],
+    });
+
+    let task = client
+        .create_task(request)
+        .await
+        .unwrap()
+        .into_inner()
+        .task
+        .unwrap();
+
+    assert_eq!(task.title, "Padded");
+    assert_eq!(task.description, "Notes");
+}
+
+#[tokio::test]
+async fn test_task_tags_grpc() {
+    let (mut client, _handle) = setup_grpc_client().await;
+
+    let mut ids = Vec::new();
+    for (title, tags) in [("Both", vec!["work", "urgent"]), ("Work", vec!["work"])] {
+        let request = tonic::Request::new(CreateTaskRequest {
+            title: title.to_string(),
+            description: String::new(),
+            tags: tags.into_iter().map(String::from).collect(),
+        });
+        let task = client
+            .create_task(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .task
+            .unwrap();
+        ids.push(task.id);
+    }
+
+    let list = |tags: &[&str]| {
+        tonic::Request::new(ListTasksRequest {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        })
+    };
+    let tasks = client
+        .list_tasks(list(&["work", "urgent"]))
+        .await
+        .unwrap()
+        .into_inner()
+        .tasks;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].tags, vec!["urgent", "work"]);
+    let tasks = client
+        .list_tasks(list(&["work"]))
+        .await
+        .unwrap()
+        .into_inner()
+        .tasks;
+    assert_eq!(tasks.len(), 2);
+
+    let request = tonic::Request::new(UpdateTaskRequest {
+        id: ids[0],
+        title: None,
+        description: None,
+        completed: None,
+        tags: Some(TagList { tags: vec![] }),
+        status: None,
+        expected_version: None,
+    });
+    let task = client
+        .update_task(request)
+        .await
+        .unwrap()
+        .into_inner()
+        .task
+        .unwrap();
+    assert!(task.tags.is_empty());
+}
+
 #[tokio::test]
 async fn test_get_task_grpc() {
     let (mut client, _handle) = setup_grpc_client_with_data().await;
@@ -109,11 +199,69 @@ async fn test_get_task_not_found_grpc() {
     assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
 }
 
+#[tokio::test]
+async fn test_get_task_deadline_exceeded_grpc() {
+    let repository = MockTaskRepository::new()
+        .on_get(|_| anyhow::bail!("no rows returned"))
+        .with_latency(Duration::from_millis(300));
+    let service = TaskServiceImpl::new(Arc::new(repository)).into_service();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let _handle = tokio::spawn(async move {
+        Server::builder()
+            .add_service(service)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TaskServiceClient::new(channel);
+
+    let mut request = tonic::Request::new(GetTaskRequest { id: 1 });
+    request.set_timeout(Duration::from_millis(50));
+    let status = client.get_task(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+
+    // A deadline the repository can meet leaves the call alone
+    let mut request = tonic::Request::new(GetTaskRequest { id: 1 });
+    request.set_timeout(Duration::from_secs(5));
+    let status = client.get_task(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_update_task_not_found_carries_error_detail_grpc() {
+    let (mut client, _handle) = setup_grpc_client().await;
+
+    let request = tonic::Request::new(UpdateTaskRequest {
+        id: 999,
+        title: Some("Renamed".to_string()),
+        ..Default::default()
+    });
+
+    let status = client.update_task(request).await.unwrap_err();
+
+    assert_eq!(status.code(), tonic::Code::NotFound);
+    let detail = ErrorDetail::from_status(&status).unwrap();
+    assert_eq!(detail.reason(), ErrorReason::NotFound);
+    assert_eq!(detail.resource_type, "task");
+    assert_eq!(detail.resource_id, 999);
+}
+
 #[tokio::test]
 async fn test_list_tasks_grpc() {
     let (mut client, _handle) = setup_grpc_client_with_data().await;
 
-    let request = tonic::Request::new(ListTasksRequest {});
+    let request = tonic::Request::new(ListTasksRequest::default());
 
     let response = client.list_tasks(request).await.unwrap();
     let tasks = response.into_inner().tasks;
@@ -123,11 +271,26 @@ async fn test_list_tasks_grpc() {
     assert_eq!(tasks[1].id, 1);
 }
 
+#[tokio::test]
+async fn test_list_tasks_ascending_grpc() {
+    let (mut client, _handle) = setup_grpc_client_with_data().await;
+
+    let request = tonic::Request::new(ListTasksRequest {
+        order_by: TaskOrderBy::CreatedAsc.into(),
+        ..Default::default()
+    });
+
+    let tasks = client.list_tasks(request).await.unwrap().into_inner().tasks;
+
+    let ids: Vec<i64> = tasks.iter().map(|task| task.id).collect();
+    assert_eq!(ids, vec![1, 2]);
+}
+
 #[tokio::test]
 async fn test_list_tasks_empty_grpc() {
     let (mut client, _handle) = setup_grpc_client().await;
 
-    let request = tonic::Request::new(ListTasksRequest {});
+    let request = tonic::Request::new(ListTasksRequest::default());
 
     let response = client.list_tasks(request).await.unwrap();
     let tasks = response.into_inner().tasks;
@@ -135,6 +298,63 @@ async fn test_list_tasks_empty_grpc() {
     assert_eq!(tasks.len(), 0);
 }
 
+#[tokio::test]
+async fn test_list_tasks_limit_clamped_grpc() {
+    let repository = common::setup_test_repository_with_data().await;
+    let service = TaskServiceImpl::new(repository)
+        .with_max_page_size(1)
+        .into_service();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let _handle = tokio::spawn(async move {
+        Server::builder()
+            .add_service(service)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TaskServiceClient::new(channel);
+
+    let request = tonic::Request::new(ListTasksRequest {
+        limit: Some(1_000_000),
+        ..Default::default()
+    });
+    let tasks = client.list_tasks(request).await.unwrap().into_inner().tasks;
+    assert_eq!(tasks.len(), 1);
+}
+
+#[tokio::test]
+async fn test_list_rejects_non_positive_limit_grpc() {
+    let (mut client, _handle) = setup_grpc_client_with_data().await;
+
+    for limit in [0, -1] {
+        let request = tonic::Request::new(ListTasksRequest {
+            limit: Some(limit),
+            ..Default::default()
+        });
+        let status = client.list_tasks(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    let (mut client, _handle) = setup_user_grpc_client_with_data().await;
+    let request = tonic::Request::new(ListUsersRequest {
+        limit: Some(0),
+        ..Default::default()
+    });
+    let status = client.list_users(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
 #[tokio::test]
 async fn test_update_task_grpc() {
     let (mut client, _handle) = setup_grpc_client_with_data().await;
@@ -144,6 +364,9 @@ async fn test_update_task_grpc() {
         title: Some("Updated Task".to_string()),
         description: Some("Updated Description".to_string()),
         completed: Some(true),
+        tags: None,
+        status: None,
+        expected_version: None,
     });
 
     let response = client.update_task(request).await.unwrap();
@@ -164,6 +387,9 @@ async fn test_update_task_partial_grpc() {
         title: None,
         description: None,
         completed: Some(true),
+        tags: None,
+        status: None,
+        expected_version: None,
     });
 
     let response = client.update_task(request).await.unwrap();
@@ -175,6 +401,89 @@ async fn test_update_task_partial_grpc() {
     assert_eq!(task.completed, true);
 }
 
+#[tokio::test]
+async fn test_update_task_with_stale_version_grpc() {
+    let (mut client, _handle) = setup_grpc_client_with_data().await;
+
+    let current = client
+        .get_task(tonic::Request::new(GetTaskRequest { id: 1 }))
+        .await
+        .unwrap()
+        .into_inner()
+        .task
+        .unwrap();
+
+    let status = client
+        .update_task(tonic::Request::new(UpdateTaskRequest {
+            id: 1,
+            title: Some("Lost update".to_string()),
+            expected_version: Some("2000-01-01T00:00:00.000Z".to_string()),
+            ..Default::default()
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Aborted);
+    let detail = ErrorDetail::from_status(&status).unwrap();
+    assert_eq!(detail.reason(), ErrorReason::Conflict);
+
+    let task = client
+        .update_task(tonic::Request::new(UpdateTaskRequest {
+            id: 1,
+            title: Some("Renamed".to_string()),
+            expected_version: Some(current.updated_at),
+            ..Default::default()
+        }))
+        .await
+        .unwrap()
+        .into_inner()
+        .task
+        .unwrap();
+    assert_eq!(task.title, "Renamed");
+}
+
+#[tokio::test]
+async fn test_update_task_status_grpc() {
+    let (mut client, _handle) = setup_grpc_client_with_data().await;
+
+    let task = client
+        .update_task(tonic::Request::new(UpdateTaskRequest {
+            id: 1,
+            status: Some(TaskStatus::InProgress.into()),
+            ..Default::default()
+        }))
+        .await
+        .unwrap()
+        .into_inner()
+        .task
+        .unwrap();
+    assert_eq!(task.status(), TaskStatus::InProgress);
+    assert!(!task.completed);
+
+    let tasks = client
+        .list_tasks(tonic::Request::new(ListTasksRequest {
+            status: Some(TaskStatus::InProgress.into()),
+            ..Default::default()
+        }))
+        .await
+        .unwrap()
+        .into_inner()
+        .tasks;
+    let ids: Vec<i64> = tasks.iter().map(|task| task.id).collect();
+    assert_eq!(ids, vec![1]);
+
+    // completed has to agree with the status it's sent with
+    let status = client
+        .update_task(tonic::Request::new(UpdateTaskRequest {
+            id: 1,
+            completed: Some(true),
+            status: Some(TaskStatus::Cancelled.into()),
+            ..Default::default()
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
 #[tokio::test]
 async fn test_update_task_not_found_grpc() {
     let (mut client, _handle) = setup_grpc_client().await;
@@ -184,6 +493,9 @@ async fn test_update_task_not_found_grpc() {
         title: Some("Updated".to_string()),
         description: None,
         completed: None,
+        tags: None,
+        status: None,
+        expected_version: None,
     });
 
     let result = client.update_task(request).await;
@@ -191,6 +503,48 @@ async fn test_update_task_not_found_grpc() {
     assert!(result.is_err());
 }
 
+#[tokio::test]
+async fn test_create_task_empty_title_invalid_argument_grpc() {
+    let (mut client, _handle) = setup_grpc_client().await;
+
+    let status = client
+        .create_task(tonic::Request::new(CreateTaskRequest {
+            title: "   ".to_string(),
+            description: String::new(),
+            tags: vec![],
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        "Validation failed: title must not be empty"
+    );
+
+    let status = client
+        .update_task(tonic::Request::new(UpdateTaskRequest {
+            id: 999,
+            title: Some(String::new()),
+            description: None,
+            completed: None,
+            tags: None,
+            status: None,
+            expected_version: None,
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    // Nothing was written
+    let tasks = client
+        .list_tasks(tonic::Request::new(ListTasksRequest::default()))
+        .await
+        .unwrap()
+        .into_inner()
+        .tasks;
+    assert!(tasks.is_empty());
+}
+
 #[tokio::test]
 async fn test_delete_task_grpc() {
     let (mut client, _handle) = setup_grpc_client_with_data().await;
@@ -219,6 +573,80 @@ async fn test_delete_task_not_found_grpc() {
     assert_eq!(result.success, false);
 }
 
+#[tokio::test]
+async fn test_toggle_task_grpc() {
+    let (mut client, _handle) = setup_grpc_client_with_data().await;
+
+    let response = client
+        .toggle_task(tonic::Request::new(ToggleTaskRequest { id: 1 }))
+        .await
+        .unwrap();
+    assert!(response.into_inner().task.unwrap().completed);
+
+    let response = client
+        .toggle_task(tonic::Request::new(ToggleTaskRequest { id: 1 }))
+        .await
+        .unwrap();
+    assert!(!response.into_inner().task.unwrap().completed);
+}
+
+#[tokio::test]
+async fn test_toggle_task_not_found_grpc() {
+    let (mut client, _handle) = setup_grpc_client().await;
+
+    let result = client
+        .toggle_task(tonic::Request::new(ToggleTaskRequest { id: 999 }))
+        .await;
+
+    assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_complete_all_tasks_grpc() {
+    let (mut client, _handle) = setup_grpc_client_with_data().await;
+
+    let response = client
+        .complete_all_tasks(tonic::Request::new(CompleteAllTasksRequest {}))
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner().count, 1);
+
+    let response = client
+        .list_tasks(tonic::Request::new(ListTasksRequest::default()))
+        .await
+        .unwrap();
+    assert!(response
+        .into_inner()
+        .tasks
+        .iter()
+        .all(|task| task.completed));
+}
+
+#[tokio::test]
+async fn test_create_tasks_client_stream_grpc() {
+    let (mut client, _handle) = setup_grpc_client().await;
+
+    let requests = (1..=3).map(|i| CreateTaskRequest {
+        title: format!("Streamed {}", i),
+        description: String::new(),
+        tags: vec![],
+    });
+    let response = client
+        .create_tasks(tonic::Request::new(tokio_stream::iter(requests)))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response.created_count, 3);
+    assert_eq!(response.ids, vec![1, 2, 3]);
+
+    let response = client
+        .get_task(tonic::Request::new(GetTaskRequest { id: 2 }))
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner().task.unwrap().title, "Streamed 2");
+}
+
 // User gRPC tests
 
 async fn setup_user_grpc_client() -> (UserServiceClient<Channel>, tokio::task::JoinHandle<()>) {
@@ -317,11 +745,40 @@ async fn test_get_user_not_found_grpc() {
     assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
 }
 
+#[tokio::test]
+async fn test_batch_get_users_skips_missing_ids_grpc() {
+    let (mut client, _handle) = setup_user_grpc_client_with_data().await;
+
+    let request = tonic::Request::new(BatchGetUsersRequest {
+        ids: vec![2, 999, 1, 2],
+    });
+
+    let response = client.batch_get_users(request).await.unwrap();
+    let users = response.into_inner().users;
+
+    let ids: Vec<i64> = users.iter().map(|user| user.id).collect();
+    assert_eq!(ids, vec![1, 2]);
+    assert_eq!(users[1].name, "Jane Doe");
+}
+
+#[tokio::test]
+async fn test_batch_get_users_rejects_too_many_ids_grpc() {
+    let (mut client, _handle) = setup_user_grpc_client().await;
+
+    let request = tonic::Request::new(BatchGetUsersRequest {
+        ids: (1..=101).collect(),
+    });
+
+    let result = client.batch_get_users(request).await;
+
+    assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+}
+
 #[tokio::test]
 async fn test_list_users_grpc() {
     let (mut client, _handle) = setup_user_grpc_client_with_data().await;
 
-    let request = tonic::Request::new(ListUsersRequest {});
+    let request = tonic::Request::new(ListUsersRequest::default());
 
     let response = client.list_users(request).await.unwrap();
     let users = response.into_inner().users;
@@ -335,7 +792,7 @@ async fn test_list_users_grpc() {
 async fn test_list_users_empty_grpc() {
     let (mut client, _handle) = setup_user_grpc_client().await;
 
-    let request = tonic::Request::new(ListUsersRequest {});
+    let request = tonic::Request::new(ListUsersRequest::default());
 
     let response = client.list_users(request).await.unwrap();
     let users = response.into_inner().users;
@@ -394,6 +851,34 @@ async fn test_update_user_not_found_grpc() {
     assert!(result.is_err());
 }
 
+#[tokio::test]
+async fn test_create_user_bad_email_invalid_argument_grpc() {
+    let (mut client, _handle) = setup_user_grpc_client().await;
+
+    let status = client
+        .create_user(tonic::Request::new(CreateUserRequest {
+            name: "Ada".to_string(),
+            email: "not-an-email".to_string(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        "Validation failed: email must be a valid email address"
+    );
+
+    let status = client
+        .update_user(tonic::Request::new(UpdateUserRequest {
+            id: 1,
+            name: None,
+            email: Some("ada@".to_string()),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
 #[tokio::test]
 async fn test_delete_user_grpc() {
     let (mut client, _handle) = setup_user_grpc_client_with_data().await;
@@ -421,3 +906,33 @@ async fn test_delete_user_not_found_grpc() {
 
     assert_eq!(result.success, false);
 }
+
+#[tokio::test]
+async fn test_list_users_filtered_grpc() {
+    let (mut client, _handle) = setup_user_grpc_client_with_data().await;
+
+    let request = tonic::Request::new(ListUsersRequest {
+        name: Some("jane".to_string()),
+        ..Default::default()
+    });
+
+    let response = client.list_users(request).await.unwrap();
+    let users = response.into_inner().users;
+
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].name, "Jane Doe");
+}
+
+#[tokio::test]
+async fn test_list_users_offset_out_of_range_grpc() {
+    let (mut client, _handle) = setup_user_grpc_client_with_data().await;
+
+    let request = tonic::Request::new(ListUsersRequest {
+        offset: Some(10),
+        ..Default::default()
+    });
+
+    let response = client.list_users(request).await.unwrap();
+
+    assert!(response.into_inner().users.is_empty());
+}
@@ -0,0 +1,196 @@
+//! Runs the repositories against a real Postgres. Ignored by default; run
+//! with a throwaway database:
+//!
+//! ```text
+//! TEST_POSTGRES_URL=postgres://postgres@localhost/tasks_test \
+//!     cargo test --features postgres --test postgres_integration -- --ignored
+//! ```
+#![cfg(feature = "postgres")]
+
+use rust_grpc_sqlite::db::TaskStatus;
+use rust_grpc_sqlite::repository::postgres::{self, PgTaskRepository, PgUserRepository};
+use rust_grpc_sqlite::repository::{
+    DeleteUserPolicy, EmailTaken, Page, TaskFilter, TaskRepository, UnknownUser, UserHasTasks,
+    UserRepository,
+};
+use sqlx::PgPool;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// The tests share one database, so they take turns.
+static DATABASE: Mutex<()> = Mutex::const_new(());
+
+async fn setup_test_pool() -> (PgPool, MutexGuard<'static, ()>) {
+    let guard = DATABASE.lock().await;
+    let url = std::env::var("TEST_POSTGRES_URL").expect("TEST_POSTGRES_URL is not set");
+    let pool = postgres::init_db(&url).await.unwrap();
+    sqlx::query("TRUNCATE task_audit, task_tags, tasks, users RESTART IDENTITY")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    (pool, guard)
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_POSTGRES_URL"]
+async fn test_task_repository_postgres() {
+    let (pool, _guard) = setup_test_pool().await;
+    let tasks = PgTaskRepository::new(pool.clone());
+    let users = PgUserRepository::new(pool);
+
+    let first = tasks.create("First", "one").await.unwrap();
+    let second = tasks.create("Second", "two").await.unwrap();
+    assert!(!first.completed);
+    assert!(first.tags.is_empty());
+    assert!(first.created_at.ends_with('Z'));
+
+    let tagged = tasks
+        .set_tags(first.id, &["work".to_string(), "home".to_string()])
+        .await
+        .unwrap();
+    assert_eq!(tagged.tags, vec!["home", "work"]);
+
+    let listed = tasks
+        .list(Page::new(Some(1), None), &TaskFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, second.id);
+    let work = TaskFilter {
+        tags: vec!["work".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(tasks.count(&work).await.unwrap(), 1);
+    assert_eq!(tasks.count(&TaskFilter::default()).await.unwrap(), 2);
+
+    let many = tasks.get_many(&[second.id, 999, first.id]).await.unwrap();
+    assert_eq!(
+        many.iter().map(|task| task.id).collect::<Vec<_>>(),
+        vec![first.id, second.id]
+    );
+
+    let done = tasks.toggle(first.id).await.unwrap();
+    assert!(done.completed);
+    assert!(done.completed_at.is_some());
+    let history = tasks.history(first.id).await.unwrap();
+    assert_eq!(history[0].op, "create");
+    let toggled = history.last().unwrap();
+    assert_eq!(toggled.op, "update");
+    let old: serde_json::Value =
+        serde_json::from_str(toggled.old_json.as_deref().unwrap()).unwrap();
+    let new: serde_json::Value =
+        serde_json::from_str(toggled.new_json.as_deref().unwrap()).unwrap();
+    assert_eq!(old["completed"], false);
+    assert_eq!(new["completed"], true);
+    assert_eq!(new["status"], "done");
+    assert_eq!(done.status, TaskStatus::Done);
+    let err = tasks
+        .update(first.id, None, None, None, Some(Some(999)))
+        .await
+        .unwrap_err();
+    assert!(err.is::<UnknownUser>());
+
+    let user = users.create("Ada", "ada@example.com").await.unwrap();
+    let assigned = tasks
+        .create_for_user(user.id, "Assigned", "")
+        .await
+        .unwrap();
+    assert_eq!(assigned.assigned_user_id, Some(user.id));
+    assert!(tasks
+        .create_for_user(999, "Orphan", "")
+        .await
+        .unwrap_err()
+        .is::<UnknownUser>());
+
+    let by_user = TaskFilter {
+        assigned_user_id: Some(user.id),
+        ..Default::default()
+    };
+    assert_eq!(tasks.complete_matching(&by_user).await.unwrap(), 1);
+    assert_eq!(tasks.complete_all().await.unwrap(), 1);
+    assert!(tasks
+        .get(999)
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("no rows"));
+    assert!(tasks.delete(second.id).await.unwrap());
+    assert_eq!(tasks.delete_all().await.unwrap(), 2);
+    assert_eq!(tasks.create("Again", "").await.unwrap().id, 1);
+
+    let open = tasks.create("Open", "").await.unwrap();
+    tasks.toggle(1).await.unwrap();
+    let cursor = tasks.changes_since(None).await.unwrap().cursor;
+    assert_eq!(tasks.archive_completed().await.unwrap(), 1);
+    let changes = tasks.changes_since(Some(cursor)).await.unwrap();
+    assert!(changes.tasks.is_empty());
+    assert_eq!(changes.deleted, vec![1]);
+    assert_ne!(open.id, 1);
+    assert_eq!(tasks.count(&TaskFilter::default()).await.unwrap(), 1);
+    let archived = tasks.list_archived(Page::default()).await.unwrap();
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0].task.title, "Again");
+    assert!(archived[0].task.tags.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_POSTGRES_URL"]
+async fn test_user_repository_postgres() {
+    let (pool, _guard) = setup_test_pool().await;
+    let tasks = PgTaskRepository::new(pool.clone());
+    let users = PgUserRepository::new(pool);
+
+    let ada = users.create("Ada", "ada@example.com").await.unwrap();
+    users.create("Adam", "adam@example.com").await.unwrap();
+    users.create("Grace", "grace@example.com").await.unwrap();
+    assert!(users
+        .create("Imposter", "ADA@Example.com")
+        .await
+        .unwrap_err()
+        .is::<EmailTaken>());
+
+    assert_eq!(users.count(None).await.unwrap(), 3);
+    let found = users.get_many(&[ada.id, -1]).await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(users.count(Some("ADA")).await.unwrap(), 2);
+    let page = users.list(Page::new(Some(2), None), None).await.unwrap();
+    assert_eq!(page[0].name, "Grace");
+    assert_eq!(page.len(), 2);
+    let found = users.search("gr").await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(users.search("%").await.unwrap().is_empty());
+
+    let renamed = users.update(ada.id, Some("Ada L."), None).await.unwrap();
+    assert_eq!(renamed.name, "Ada L.");
+
+    let (grace, inserted) = users
+        .upsert_by_email("Grace H.", "GRACE@example.com")
+        .await
+        .unwrap();
+    assert!(!inserted);
+    assert_eq!(grace.name, "Grace H.");
+    let (_, inserted) = users
+        .upsert_by_email("Linus", "linus@example.com")
+        .await
+        .unwrap();
+    assert!(inserted);
+
+    let task = tasks.create_for_user(ada.id, "Task", "").await.unwrap();
+    tasks.toggle(task.id).await.unwrap();
+    let counts = users.task_counts().await.unwrap();
+    assert_eq!((counts[0].total, counts[0].completed), (1, 1));
+    assert_eq!((counts[1].total, counts[1].completed), (0, 0));
+    let summary = users.summary(ada.id).await.unwrap();
+    assert_eq!((summary.total, summary.completed), (1, 1));
+
+    assert!(users
+        .delete(ada.id, DeleteUserPolicy::Restrict)
+        .await
+        .unwrap_err()
+        .is::<UserHasTasks>());
+    assert!(users
+        .delete(ada.id, DeleteUserPolicy::Nullify)
+        .await
+        .unwrap());
+    assert_eq!(tasks.get(task.id).await.unwrap().assigned_user_id, None);
+}
@@ -0,0 +1,3248 @@
+mod common;
+
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use futures_util::stream::{BoxStream, StreamExt};
+use http_body_util::BodyExt;
+use rust_grpc_sqlite::config::Config;
+use rust_grpc_sqlite::db::{TaskAuditModel, TaskModel, TaskStatus};
+use rust_grpc_sqlite::repository::{
+    ArchivedTask, DuplicateTasks, MockTaskRepository, Page, SqliteMaintenanceRepository,
+    SqliteTaskRepository, SqliteUserRepository, TaskChanges, TaskFilter, TaskRepository,
+    TaskStatusCounts, TaskUpdate,
+};
+use rust_grpc_sqlite::rest::{
+    create_router, create_router_with_config, ErrorResponse, InflightRequestResponse,
+    PublishingTaskRepository, RateLimit, Readiness, RouterConfig, TaskEventKind, TaskEvents,
+    VacuumResponse, DEFAULT_MAX_BODY_BYTES, REQUEST_ID_HEADER,
+};
+use rust_grpc_sqlite::validation::MAX_DESCRIPTION_LEN;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+async fn setup_router() -> Router {
+    let pool = common::setup_test_pool_with_data().await;
+    create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    )
+}
+
+/// Task repository whose `list` never completes in test time.
+struct SlowTaskRepository;
+
+#[async_trait]
+impl TaskRepository for SlowTaskRepository {
+    async fn create(&self, _title: &str, _description: &str) -> anyhow::Result<TaskModel> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn create_for_user(
+        &self,
+        _user_id: i64,
+        _title: &str,
+        _description: &str,
+    ) -> anyhow::Result<TaskModel> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn create_with_tags(
+        &self,
+        _assigned_user_id: Option<i64>,
+        _title: &str,
+        _description: &str,
+        _tags: &[String],
+    ) -> anyhow::Result<TaskModel> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn create_returning_meta(
+        &self,
+        _title: &str,
+        _description: &str,
+    ) -> anyhow::Result<(TaskModel, i64)> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn create_many(&self, _tasks: &[(String, String)]) -> anyhow::Result<Vec<TaskModel>> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn get(&self, _id: i64) -> anyhow::Result<TaskModel> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn get_many(&self, _ids: &[i64]) -> anyhow::Result<Vec<TaskModel>> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn list(&self, _page: Page, _filter: &TaskFilter) -> anyhow::Result<Vec<TaskModel>> {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        Ok(vec![])
+    }
+
+    async fn list_updated_since(&self, _since: &str) -> anyhow::Result<Vec<TaskModel>> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn list_completed_between(
+        &self,
+        _from: &str,
+        _to: &str,
+    ) -> anyhow::Result<Vec<TaskModel>> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn count(&self, _filter: &TaskFilter) -> anyhow::Result<i64> {
+        Ok(0)
+    }
+
+    async fn history(&self, _id: i64) -> anyhow::Result<Vec<TaskAuditModel>> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn update(
+        &self,
+        _id: i64,
+        _title: Option<&str>,
+        _description: Option<&str>,
+        _status: Option<TaskStatus>,
+        _assigned_user_id: Option<Option<i64>>,
+    ) -> anyhow::Result<TaskModel> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn update_if_version(
+        &self,
+        _id: i64,
+        _expected_updated_at: &str,
+        _title: Option<&str>,
+        _description: Option<&str>,
+        _status: Option<TaskStatus>,
+    ) -> anyhow::Result<TaskModel> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn update_with_tags(&self, _id: i64, _update: &TaskUpdate) -> anyhow::Result<TaskModel> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn set_tags(&self, _id: i64, _tags: &[String]) -> anyhow::Result<TaskModel> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn move_after(&self, _id: i64, _after_id: Option<i64>) -> anyhow::Result<TaskModel> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn delete(&self, _id: i64) -> anyhow::Result<bool> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn delete_returning(&self, _id: i64) -> anyhow::Result<Option<TaskModel>> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn complete_all(&self) -> anyhow::Result<u64> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn complete_matching(&self, _filter: &TaskFilter) -> anyhow::Result<u64> {
+        anyhow::bail!("not implemented")
+    }
+
+    fn stream_all(&self) -> BoxStream<'static, anyhow::Result<TaskModel>> {
+        futures_util::stream::empty().boxed()
+    }
+
+    async fn reassign(&self, _from: i64, _to: i64) -> anyhow::Result<u64> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<u64> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn archive_completed(&self) -> anyhow::Result<u64> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn list_archived(&self, _page: Page) -> anyhow::Result<Vec<ArchivedTask>> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn duplicates(&self) -> anyhow::Result<Vec<DuplicateTasks>> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn count_by_status(&self) -> anyhow::Result<TaskStatusCounts> {
+        anyhow::bail!("not implemented")
+    }
+
+    async fn changes_since(&self, _cursor: Option<i64>) -> anyhow::Result<TaskChanges> {
+        anyhow::bail!("not implemented")
+    }
+}
+
+async fn body_json<T: serde::de::DeserializeOwned>(response: axum::response::Response) -> T {
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn test_response_has_generated_request_id() {
+    let app = setup_router().await;
+
+    let response = app
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let id = response.headers().get(REQUEST_ID_HEADER).unwrap();
+    assert_eq!(id.to_str().unwrap().len(), 36);
+}
+
+#[tokio::test]
+async fn test_client_request_id_is_preserved() {
+    let app = setup_router().await;
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks")
+                .header("X-Request-Id", "client-id-123")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.headers().get(REQUEST_ID_HEADER).unwrap(),
+        "client-id-123"
+    );
+}
+
+#[tokio::test]
+async fn test_cancel_inflight_request() {
+    let app = create_router_with_config(
+        Arc::new(SlowTaskRepository),
+        common::setup_test_user_repository().await,
+        RouterConfig {
+            admin_token: Some("s3cret".to_string()),
+            ..RouterConfig::default()
+        },
+    );
+
+    let slow = tokio::spawn(
+        app.clone().oneshot(
+            Request::get("/api/tasks")
+                .header("X-Request-Id", "slow-request")
+                .body(Body::empty())
+                .unwrap(),
+        ),
+    );
+
+    let mut listed = false;
+    for _ in 0..50 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/api/admin/inflight")
+                    .header("authorization", "Bearer s3cret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let inflight: Vec<InflightRequestResponse> = body_json(response).await;
+        if let Some(entry) = inflight.iter().find(|r| r.request_id == "slow-request") {
+            assert_eq!(entry.method, "GET");
+            assert_eq!(entry.path, "/api/tasks");
+            listed = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(listed);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/api/admin/inflight/slow-request/cancel")
+                .header("authorization", "Bearer s3cret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = tokio::time::timeout(Duration::from_secs(5), slow)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let response = app
+        .oneshot(
+            Request::get("/api/admin/inflight")
+                .header("authorization", "Bearer s3cret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let inflight: Vec<InflightRequestResponse> = body_json(response).await;
+    assert!(inflight.iter().all(|r| r.request_id != "slow-request"));
+}
+
+#[tokio::test]
+async fn test_cancel_unknown_request_not_found() {
+    let app = setup_vacuum_router(Some("s3cret")).await;
+
+    let response = app
+        .oneshot(
+            Request::post("/api/admin/inflight/missing/cancel")
+                .header("authorization", "Bearer s3cret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_list_users_query_params() {
+    let pool = common::setup_test_pool_with_user_data().await;
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/users?name=jane&limit=10")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let users: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0]["name"], "Jane Doe");
+
+    let response = app
+        .oneshot(
+            Request::get("/api/users?offset=5")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let users: Vec<serde_json::Value> = body_json(response).await;
+    assert!(users.is_empty());
+}
+
+#[tokio::test]
+async fn test_envelope_mode_wraps_get_and_list() {
+    let app = setup_router().await;
+    let get_json = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::get(uri)
+                        .header("x-request-id", "envelope-test")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            body_json::<serde_json::Value>(response).await
+        }
+    };
+
+    // Bare shape by default
+    let task = get_json("/api/tasks/1").await;
+    assert_eq!(task["id"], 1);
+    let tasks = get_json("/api/tasks").await;
+    assert!(tasks.is_array());
+
+    let wrapped = get_json("/api/tasks/1?envelope=true").await;
+    assert_eq!(wrapped["data"], task);
+    assert_eq!(wrapped["meta"]["request_id"], "envelope-test");
+    assert!(wrapped["meta"].get("count").is_none());
+
+    let wrapped = get_json("/api/tasks?envelope=true").await;
+    assert_eq!(wrapped["data"], tasks);
+    assert_eq!(wrapped["meta"]["count"], tasks.as_array().unwrap().len());
+
+    let users = get_json("/api/users?envelope=true").await;
+    assert!(users["data"].is_array());
+
+    // Errors keep the ErrorResponse shape
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks/999?envelope=true")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let error: ErrorResponse = body_json(response).await;
+    assert!(error.error.contains("999"));
+}
+
+#[tokio::test]
+async fn test_search_users() {
+    let pool = common::setup_test_pool_with_user_data().await;
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    );
+    let search = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let status = response.status();
+            (status, body_json::<serde_json::Value>(response).await)
+        }
+    };
+
+    let (status, users) = search("/api/users/search?q=JA").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(users.as_array().unwrap().len(), 1);
+    assert_eq!(users[0]["name"], "Jane Doe");
+
+    let (status, users) = search("/api/users/search?q=nobody").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(users, serde_json::json!([]));
+
+    let (status, _) = search("/api/users/search?q=%20").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_delete_user_with_tasks_policies() {
+    let pool = common::setup_test_pool_with_user_data().await;
+    sqlx::query("INSERT INTO tasks (title, description, assigned_user_id) VALUES ('Owned', '', 1)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    );
+
+    let response = app
+        .clone()
+        .oneshot(Request::delete("/api/users/1").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::delete("/api/users/1?on_tasks=nullify")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = app
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(tasks.len(), 1);
+    assert!(tasks[0]["assigned_user_id"].is_null());
+}
+
+#[tokio::test]
+async fn test_toggle_task_twice_restores_state() {
+    let app = setup_router().await;
+
+    for expected in [true, false] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/api/tasks/1/toggle")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let task: serde_json::Value = body_json(response).await;
+        assert_eq!(task["completed"], expected);
+    }
+}
+
+#[tokio::test]
+async fn test_update_task_status_sets_derived_completed() {
+    let app = setup_router().await;
+    let put = |body: &'static str| {
+        app.clone().oneshot(
+            Request::put("/api/tasks/1")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+    };
+
+    for (body, status, completed) in [
+        (r#"{"status":"in_progress"}"#, "in_progress", false),
+        (r#"{"status":"done"}"#, "done", true),
+        (r#"{"completed":false}"#, "todo", false),
+        (
+            r#"{"status":"cancelled","completed":false}"#,
+            "cancelled",
+            false,
+        ),
+    ] {
+        let response = put(body).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", body);
+        let task: serde_json::Value = body_json(response).await;
+        assert_eq!(task["status"], status);
+        assert_eq!(task["completed"], completed);
+    }
+
+    let response = put(r#"{"status":"todo","completed":true}"#).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/tasks?status=cancelled")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["id"], 1);
+}
+
+#[tokio::test]
+async fn test_toggle_task_not_found() {
+    let app = setup_router().await;
+
+    let response = app
+        .oneshot(
+            Request::post("/api/tasks/999/toggle")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_task_history_lists_changes_in_order() {
+    let app = setup_router().await;
+
+    app.clone()
+        .oneshot(
+            Request::post("/api/tasks/1/toggle")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/tasks/1/history")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let history: Vec<serde_json::Value> = body_json(response).await;
+    let ops: Vec<&str> = history
+        .iter()
+        .map(|entry| entry["op"].as_str().unwrap())
+        .collect();
+    assert_eq!(ops, vec!["create", "update"]);
+    assert_eq!(history[1]["old"]["completed"], false);
+    assert_eq!(history[1]["new"]["completed"], true);
+    assert!(history[0]["old"].is_null());
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks/999/history")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_ready_only_after_migrations() {
+    let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+    let readiness = Readiness::new();
+    let app = create_router_with_config(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool.clone())),
+        RouterConfig::default().with_readiness(readiness.clone()),
+    );
+    assert_eq!(
+        status_of(&app, "/ready").await,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    rust_grpc_sqlite::db::MIGRATOR.run(&pool).await.unwrap();
+    readiness.mark_ready();
+    assert_eq!(status_of(&app, "/ready").await, StatusCode::OK);
+    assert_eq!(status_of(&app, "/api/tasks").await, StatusCode::OK);
+
+    // Routers built without a flag have nothing to wait for
+    assert_eq!(
+        status_of(&setup_router().await, "/ready").await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_route_returns_json_404() {
+    let app = setup_router().await;
+
+    for uri in ["/api/foo", "/nowhere"] {
+        let response = app
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+        let body: serde_json::Value = body_json(response).await;
+        assert_eq!(body, serde_json::json!({ "error": "route not found" }));
+    }
+}
+
+#[tokio::test]
+async fn test_move_task_to_top_and_bottom() {
+    let app = setup_router().await;
+    let move_task = |id: i64, body: &'static str| {
+        Request::post(format!("/api/tasks/{}/move", id))
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    };
+    let listed_ids = |app: Router| async move {
+        let response = app
+            .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let tasks: Vec<serde_json::Value> = body_json(response).await;
+        tasks
+            .iter()
+            .map(|task| task["id"].as_i64().unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(listed_ids(app.clone()).await, vec![2, 1]);
+
+    let response = app.clone().oneshot(move_task(1, "{}")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let task: serde_json::Value = body_json(response).await;
+    assert_eq!(task["id"], 1);
+    assert_eq!(listed_ids(app.clone()).await, vec![1, 2]);
+
+    let response = app
+        .clone()
+        .oneshot(move_task(1, r#"{"after_id":2}"#))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(listed_ids(app.clone()).await, vec![2, 1]);
+
+    for (id, body, status) in [
+        (1, r#"{"after_id":1}"#, StatusCode::BAD_REQUEST),
+        (1, r#"{"after_id":999}"#, StatusCode::BAD_REQUEST),
+        (999, r#"{"after_id":1}"#, StatusCode::NOT_FOUND),
+    ] {
+        let response = app.clone().oneshot(move_task(id, body)).await.unwrap();
+        assert_eq!(response.status(), status, "{} {}", id, body);
+    }
+    assert_eq!(listed_ids(app).await, vec![2, 1]);
+}
+
+#[tokio::test]
+async fn test_complete_all_tasks() {
+    let app = setup_router().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/api/tasks/complete-all")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let result: serde_json::Value = body_json(response).await;
+    assert_eq!(result["affected"], 1);
+
+    let response = app
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(tasks.len(), 2);
+    assert!(tasks.iter().all(|task| task["completed"] == true));
+}
+
+#[tokio::test]
+async fn test_complete_all_leaves_cancelled_tasks() {
+    let pool = common::setup_test_pool().await;
+    let tasks = Arc::new(SqliteTaskRepository::new(pool.clone()));
+    let app = create_router(tasks.clone(), Arc::new(SqliteUserRepository::new(pool)));
+    let open = tasks.create("Open", "").await.unwrap();
+    let cancelled = tasks.create("Cancelled", "").await.unwrap();
+    let cancelled = tasks
+        .update(cancelled.id, None, None, Some(TaskStatus::Cancelled), None)
+        .await
+        .unwrap();
+
+    for uri in [
+        "/api/tasks/complete-all?dry_run=true",
+        "/api/tasks/complete-all",
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::post(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        let result: serde_json::Value = body_json(response).await;
+        assert_eq!(result["affected"], 1, "{}", uri);
+    }
+
+    assert_eq!(tasks.get(open.id).await.unwrap().status, TaskStatus::Done);
+    let after = tasks.get(cancelled.id).await.unwrap();
+    assert_eq!(after.status, TaskStatus::Cancelled);
+    assert_eq!(after.updated_at, cancelled.updated_at);
+}
+
+#[tokio::test]
+async fn test_bulk_complete_tasks_by_filter() {
+    let pool = common::setup_test_pool_with_user_data().await;
+    let tasks = Arc::new(SqliteTaskRepository::new(pool.clone()));
+    let app = create_router(tasks.clone(), Arc::new(SqliteUserRepository::new(pool)));
+    let assigned = tasks.create_for_user(1, "Assigned", "").await.unwrap();
+    let tagged = tasks.create("Tagged", "").await.unwrap();
+    tasks
+        .set_tags(tagged.id, &["work".to_string()])
+        .await
+        .unwrap();
+    let untouched = tasks.create("Untouched", "").await.unwrap();
+    let bulk_complete = |body: &'static str| {
+        Request::post("/api/tasks/bulk-complete")
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    for (body, task_id) in [
+        (r#"{"assigned_user_id":1}"#, assigned.id),
+        (r#"{"tag":" work "}"#, tagged.id),
+    ] {
+        let response = app.clone().oneshot(bulk_complete(body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", body);
+        let result: serde_json::Value = body_json(response).await;
+        assert_eq!(result["affected"], 1, "{}", body);
+        assert!(tasks.get(task_id).await.unwrap().completed, "{}", body);
+    }
+    assert!(!tasks.get(untouched.id).await.unwrap().completed);
+
+    for body in [r#"{}"#, r#"{"tag":"  "}"#] {
+        let response = app.clone().oneshot(bulk_complete(body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", body);
+        let error: ErrorResponse = body_json(response).await;
+        assert!(error.error.contains("at least one"), "{}", error.error);
+    }
+}
+
+#[tokio::test]
+async fn test_bulk_dry_run_reports_without_writing() {
+    let pool = common::setup_test_pool_with_user_data().await;
+    let tasks = Arc::new(SqliteTaskRepository::new(pool.clone()));
+    let app = create_router(tasks.clone(), Arc::new(SqliteUserRepository::new(pool)));
+    let first = tasks.create_for_user(1, "First", "").await.unwrap();
+    let second = tasks.create_for_user(1, "Second", "").await.unwrap();
+    tasks.toggle(second.id).await.unwrap();
+    let third = tasks.create("Unassigned", "").await.unwrap();
+    async fn snapshot(tasks: &SqliteTaskRepository) -> Vec<(i64, bool, String)> {
+        tasks
+            .list(Page::default(), &TaskFilter::default())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|task| (task.id, task.completed, task.updated_at))
+            .collect()
+    }
+    let before = snapshot(&tasks).await;
+
+    let requests = [
+        (
+            Request::post("/api/tasks/bulk-complete?dry_run=true")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"assigned_user_id":1}"#))
+                .unwrap(),
+            vec![first.id],
+        ),
+        (
+            Request::post("/api/tasks/complete-all?dry_run=true")
+                .body(Body::empty())
+                .unwrap(),
+            vec![first.id, third.id],
+        ),
+        (
+            Request::delete("/api/tasks?dry_run=true")
+                .body(Body::empty())
+                .unwrap(),
+            vec![first.id, second.id, third.id],
+        ),
+    ];
+    for (request, expected) in requests {
+        let uri = request.uri().to_string();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        let result: serde_json::Value = body_json(response).await;
+        assert_eq!(result["affected"], expected.len(), "{}", uri);
+        assert_eq!(result["ids"], serde_json::json!(expected), "{}", uri);
+    }
+
+    assert_eq!(snapshot(&tasks).await, before);
+    assert_eq!(tasks.history(first.id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_batch_get_tasks_skips_missing_ids() {
+    let app = setup_router().await;
+
+    let response = app
+        .oneshot(
+            Request::post("/api/tasks/batch-get")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"ids":[2,42,1,999]}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    let ids: Vec<i64> = tasks
+        .iter()
+        .map(|task| task["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn test_batch_get_tasks_caps_distinct_ids() {
+    let pool = common::setup_test_pool().await;
+    let app = create_router_with_config(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+        RouterConfig {
+            max_page_size: 2,
+            ..RouterConfig::default()
+        },
+    );
+
+    for (ids, expected) in [
+        ("[1,2,1,2,2]", StatusCode::OK),
+        ("[1,2,3]", StatusCode::BAD_REQUEST),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/api/tasks/batch-get")
+                    .header("content-type", "application/json")
+                    .body(Body::from(format!(r#"{{"ids":{}}}"#, ids)))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), expected, "{}", ids);
+    }
+}
+
+#[tokio::test]
+async fn test_list_tasks_repository_failure_returns_500() {
+    let pool = common::setup_test_pool().await;
+    let tasks =
+        Arc::new(MockTaskRepository::new().on_list(|_| anyhow::bail!("database is locked")));
+    let app = create_router(tasks.clone(), Arc::new(SqliteUserRepository::new(pool)));
+
+    let response = app
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body: ErrorResponse = body_json(response).await;
+    assert_eq!(body.error, "database is locked");
+    assert_eq!(tasks.calls(), 1);
+}
+
+#[tokio::test]
+async fn test_panicking_handler_returns_500() {
+    let pool = common::setup_test_pool().await;
+    let tasks = Arc::new(MockTaskRepository::new().on_list(|_| panic!("unexpected unwrap")));
+    let app = create_router(tasks, Arc::new(SqliteUserRepository::new(pool)));
+
+    let response = app
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body: ErrorResponse = body_json(response).await;
+    assert_eq!(body.error, "Internal server error");
+}
+
+#[tokio::test]
+async fn test_oversized_body_rejected() {
+    let app = setup_router().await;
+    let title = "a".repeat(DEFAULT_MAX_BODY_BYTES);
+    let body = serde_json::json!({ "title": title, "description": "" }).to_string();
+
+    for (method, uri) in [("POST", "/api/tasks"), ("PUT", "/api/tasks/1")] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.clone()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE, "{}", uri);
+    }
+
+    let response = app
+        .oneshot(
+            Request::post("/api/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"title":"Small","description":""}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+async fn gunzip(response: axum::response::Response) -> Vec<u8> {
+    use std::io::Read;
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(&bytes[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    decoded
+}
+
+#[tokio::test]
+async fn test_list_tasks_gzip() {
+    let app = setup_router().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/tasks")
+                .header("accept-encoding", "gzip")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("content-encoding").unwrap(), "gzip");
+    let tasks: Vec<serde_json::Value> = serde_json::from_slice(&gunzip(response).await).unwrap();
+    assert!(!tasks.is_empty());
+
+    let response = app
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert!(response.headers().get("content-encoding").is_none());
+}
+
+#[tokio::test]
+async fn test_swagger_ui_served_with_gzip() {
+    let app = setup_router().await;
+
+    let plain = app
+        .clone()
+        .oneshot(
+            Request::get("/swagger-ui/index.html")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(plain.status(), StatusCode::OK);
+    let plain = plain.into_body().collect().await.unwrap().to_bytes();
+
+    let response = app
+        .oneshot(
+            Request::get("/swagger-ui/index.html")
+                .header("accept-encoding", "gzip")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("content-encoding").unwrap(), "gzip");
+    assert_eq!(gunzip(response).await, plain.to_vec());
+}
+
+#[tokio::test]
+async fn test_slow_request_times_out() {
+    let pool = common::setup_test_pool().await;
+    let app = create_router_with_config(
+        Arc::new(SlowTaskRepository),
+        Arc::new(SqliteUserRepository::new(pool)),
+        RouterConfig {
+            request_timeout: Duration::from_millis(50),
+            ..RouterConfig::default()
+        },
+    );
+
+    let response = app
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    assert!(response.headers().get(REQUEST_ID_HEADER).is_some());
+}
+
+#[tokio::test]
+async fn test_router_against_in_memory_database() {
+    let pool = rust_grpc_sqlite::db::init_db_with_url("sqlite::memory:")
+        .await
+        .unwrap();
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/api/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"title":"Ephemeral","description":""}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["title"], "Ephemeral");
+}
+
+#[tokio::test]
+async fn test_list_tasks_sparse_fields() {
+    let app = setup_router().await;
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks?fields=id,title")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let tasks: Vec<serde_json::Map<String, serde_json::Value>> = body_json(response).await;
+    assert_eq!(tasks.len(), 2);
+    for task in tasks {
+        let mut keys: Vec<&str> = task.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["id", "title"]);
+    }
+}
+
+#[tokio::test]
+async fn test_list_tasks_unknown_field_rejected() {
+    let app = setup_router().await;
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks?fields=id,secret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: ErrorResponse = body_json(response).await;
+    assert_eq!(body.error, "Unknown field: secret");
+}
+
+#[tokio::test]
+async fn test_create_user_duplicate_email_conflict() {
+    let app = setup_router().await;
+    let create = |email: &str| {
+        Request::post("/api/users")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "name": "Ada", "email": email }).to_string(),
+            ))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(create("ada@example.com"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app.oneshot(create("Ada@Example.COM")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let body: ErrorResponse = body_json(response).await;
+    assert_eq!(body.error, "email already registered");
+}
+
+#[tokio::test]
+async fn test_upsert_user_creates_then_updates() {
+    let app = setup_router().await;
+    let upsert = |name: &str, email: &str| {
+        Request::put("/api/users")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "name": name, "email": email }).to_string(),
+            ))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(upsert("Ada", "ada@example.com"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: serde_json::Value = body_json(response).await;
+
+    let response = app
+        .oneshot(upsert("Ada Lovelace", "Ada@Example.com"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated: serde_json::Value = body_json(response).await;
+    assert_eq!(updated["id"], created["id"]);
+    assert_eq!(updated["name"], "Ada Lovelace");
+    assert_eq!(updated["email"], "ada@example.com");
+}
+
+async fn get_with_accept(uri: &str, accept: &str) -> axum::response::Response {
+    setup_router()
+        .await
+        .oneshot(
+            Request::get(uri)
+                .header("accept", accept)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_get_task_accept_json_and_xml() {
+    let response = get_with_accept("/api/tasks/1", "application/json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/json"
+    );
+    let task: serde_json::Value = body_json(response).await;
+    assert_eq!(task["title"], "Test Task 1");
+
+    let response = get_with_accept("/api/tasks/1", "application/xml").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/xml"
+    );
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let xml = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(xml.starts_with("<?xml"));
+    assert!(xml.contains("<task>"));
+    assert!(xml.contains("<id>1</id>"));
+    assert!(xml.contains("<title>Test Task 1</title>"));
+}
+
+#[tokio::test]
+async fn test_get_task_if_none_match_returns_304() {
+    let app = setup_router().await;
+    let get = |etag: Option<&str>| {
+        let mut request = Request::get("/api/tasks/1");
+        if let Some(etag) = etag {
+            request = request.header("if-none-match", etag);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    let response = get(None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+    let response = get(Some(&etag)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()["etag"], etag.as_str());
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(bytes.is_empty());
+
+    // Any change to the task invalidates the tag
+    let response = app
+        .clone()
+        .oneshot(
+            Request::put("/api/tasks/1")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"title":"Renamed"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = get(Some(&etag)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers()["etag"], etag.as_str());
+}
+
+#[tokio::test]
+async fn test_negotiated_responses_vary_on_accept() {
+    let app = setup_router().await;
+    let get = |uri: &str, accept: &str, etag: Option<&str>| {
+        let mut request = Request::get(uri).header("accept", accept);
+        if let Some(etag) = etag {
+            request = request.header("if-none-match", etag);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+    let varies = |response: &axum::response::Response| {
+        response
+            .headers()
+            .get_all("vary")
+            .iter()
+            .any(|value| value == "accept")
+    };
+
+    let json = get("/api/tasks/1", "application/json", None).await.unwrap();
+    assert!(varies(&json));
+    let json_etag = json.headers()["etag"].to_str().unwrap().to_string();
+
+    // The XML form has its own tag, so the JSON one doesn't revalidate it
+    let xml = get("/api/tasks/1", "application/xml", Some(&json_etag))
+        .await
+        .unwrap();
+    assert_eq!(xml.status(), StatusCode::OK);
+    assert!(varies(&xml));
+    assert_ne!(xml.headers()["etag"], json_etag.as_str());
+
+    let not_modified = get("/api/tasks/1", "application/json", Some(&json_etag))
+        .await
+        .unwrap();
+    assert_eq!(not_modified.status(), StatusCode::NOT_MODIFIED);
+    assert!(varies(&not_modified));
+
+    for uri in ["/api/tasks", "/api/tasks/1"] {
+        let response = get(uri, "text/csv", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE, "{}", uri);
+        assert!(varies(&response), "{}", uri);
+    }
+    assert!(varies(
+        &get("/api/tasks", "application/json", None).await.unwrap()
+    ));
+}
+
+#[tokio::test]
+async fn test_list_tasks_xml() {
+    let response = get_with_accept("/api/tasks", "application/xml").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let xml = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(xml.contains("<tasks><task>"));
+    assert_eq!(xml.matches("<task>").count(), 2);
+}
+
+#[tokio::test]
+async fn test_unsupported_accept_not_acceptable() {
+    for uri in ["/api/tasks", "/api/tasks/1"] {
+        let response = get_with_accept(uri, "text/csv").await;
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE, "{}", uri);
+    }
+}
+
+#[tokio::test]
+async fn test_list_tasks_link_header_middle_page() {
+    let pool = common::setup_test_pool().await;
+    let tasks = SqliteTaskRepository::new(pool.clone());
+    for i in 0..5 {
+        tasks.create(&format!("Task {}", i), "").await.unwrap();
+    }
+    let app = create_router(Arc::new(tasks), Arc::new(SqliteUserRepository::new(pool)));
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks?limit=2&offset=2")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let link = response
+        .headers()
+        .get("link")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(link.contains(r#"</api/tasks?limit=2&offset=4>; rel="next""#));
+    assert!(link.contains(r#"</api/tasks?limit=2&offset=0>; rel="prev""#));
+    assert!(link.contains(r#"</api/tasks?limit=2&offset=4>; rel="last""#));
+
+    let body: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(body.len(), 2);
+}
+
+#[tokio::test]
+async fn test_list_tasks_limit_clamped_to_max_page_size() {
+    let pool = common::setup_test_pool().await;
+    let tasks = SqliteTaskRepository::new(pool.clone());
+    for i in 0..5 {
+        tasks.create(&format!("Task {}", i), "").await.unwrap();
+    }
+    let app = create_router_with_config(
+        Arc::new(tasks),
+        Arc::new(SqliteUserRepository::new(pool)),
+        RouterConfig {
+            max_page_size: 2,
+            ..RouterConfig::default()
+        },
+    );
+
+    for uri in ["/api/tasks?limit=1000000", "/api/tasks"] {
+        let response = app
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        let link = response.headers().get("link").unwrap().to_str().unwrap();
+        assert!(link.contains(r#"</api/tasks?limit=2&offset=2>; rel="next""#));
+        let body: Vec<serde_json::Value> = body_json(response).await;
+        assert_eq!(body.len(), 2, "{}", uri);
+    }
+}
+
+#[tokio::test]
+async fn test_latest_tasks_newest_first_and_capped() {
+    let pool = common::setup_test_pool().await;
+    let tasks = SqliteTaskRepository::new(pool.clone());
+    for i in 0..15 {
+        tasks.create(&format!("Task {}", i), "").await.unwrap();
+    }
+    let app = create_router_with_config(
+        Arc::new(tasks),
+        Arc::new(SqliteUserRepository::new(pool)),
+        RouterConfig {
+            max_page_size: 12,
+            ..RouterConfig::default()
+        },
+    );
+    let latest = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            let body: Vec<serde_json::Value> = body_json(response).await;
+            body.iter()
+                .map(|task| task["id"].as_i64().unwrap())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(latest("/api/tasks/latest?n=3").await, vec![15, 14, 13]);
+    assert_eq!(latest("/api/tasks/latest").await.len(), 10);
+    let capped = latest("/api/tasks/latest?n=1000").await;
+    assert_eq!(capped.len(), 12);
+    assert_eq!(capped.first(), Some(&15));
+    assert_eq!(capped.last(), Some(&4));
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks/latest?n=0")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_list_rejects_non_positive_limit() {
+    let app = setup_router().await;
+
+    for uri in [
+        "/api/tasks?limit=0",
+        "/api/tasks?limit=-5",
+        "/api/users?limit=0",
+        "/api/users?limit=-1",
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        let body: ErrorResponse = body_json(response).await;
+        assert!(body.error.contains("limit"), "{}", uri);
+    }
+}
+
+#[tokio::test]
+async fn test_delete_all_tasks_requires_confirmation() {
+    let app = setup_router().await;
+
+    let response = app
+        .clone()
+        .oneshot(Request::delete("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(tasks.len(), 2);
+}
+
+#[tokio::test]
+async fn test_delete_all_tasks_confirmed() {
+    let app = setup_router().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::delete("/api/tasks")
+                .header("x-confirm-delete-all", "true")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = body_json(response).await;
+    assert_eq!(body["affected"], 2);
+
+    let response = app
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    assert!(tasks.is_empty());
+}
+
+#[tokio::test]
+async fn test_delete_task_return_representation() {
+    let app = setup_router().await;
+    let delete = |uri: &str| {
+        app.clone()
+            .oneshot(Request::delete(uri).body(Body::empty()).unwrap())
+    };
+
+    let response = delete("/api/tasks/1").await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = delete("/api/tasks/2?return=representation").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let task: serde_json::Value = body_json(response).await;
+    assert_eq!(task["id"], 2);
+    assert_eq!(task["title"], "Test Task 2");
+    assert_eq!(status_of(&app, "/api/tasks/2").await, StatusCode::NOT_FOUND);
+
+    for uri in ["/api/tasks/2", "/api/tasks/2?return=representation"] {
+        let response = delete(uri).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+    }
+}
+
+#[tokio::test]
+async fn test_not_found_increments_route_4xx_counter() {
+    let app = setup_router().await;
+
+    assert_eq!(status_of(&app, "/api/tasks/1").await, StatusCode::OK);
+    assert_eq!(
+        status_of(&app, "/api/tasks/999").await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        status_of(&app, "/api/tasks/998").await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        status_of(&app, "/no/such/path").await,
+        StatusCode::NOT_FOUND
+    );
+
+    let response = app
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+
+    assert!(text.contains(
+        "http_responses_total{method=\"GET\",route=\"/api/tasks/{id}\",status=\"4xx\"} 2\n"
+    ));
+    assert!(text.contains(
+        "http_responses_total{method=\"GET\",route=\"/api/tasks/{id}\",status=\"2xx\"} 1\n"
+    ));
+    assert!(text
+        .contains("http_responses_total{method=\"GET\",route=\"unmatched\",status=\"4xx\"} 1\n"));
+    assert!(!text.contains("/api/tasks/999"));
+}
+
+#[tokio::test]
+async fn test_archive_completed_tasks() {
+    let app = setup_router().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/api/tasks/archive-completed")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = body_json(response).await;
+    assert_eq!(body["affected"], 1);
+
+    // Only the pending task is left active
+    let response = app
+        .clone()
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["title"], "Test Task 1");
+    assert_eq!(status_of(&app, "/api/tasks/2").await, StatusCode::NOT_FOUND);
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks/archived")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let archived: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0]["task"]["id"], 2);
+    assert_eq!(archived[0]["task"]["title"], "Test Task 2");
+    assert_eq!(archived[0]["task"]["completed"], true);
+    assert!(archived[0]["archived_at"].is_string());
+}
+
+#[tokio::test]
+async fn test_rate_limit_throttles_each_client_ip_separately() {
+    let pool = common::setup_test_pool_with_data().await;
+    let app = create_router_with_config(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+        RouterConfig {
+            rate_limit: Some(RateLimit {
+                per_second: 1,
+                burst: 2,
+                trust_forwarded_for: false,
+            }),
+            ..RouterConfig::default()
+        },
+    );
+    let from =
+        |ip: [u8; 4]| {
+            let mut request = Request::get("/api/tasks").body(Body::empty()).unwrap();
+            request.extensions_mut().insert(axum::extract::ConnectInfo(
+                std::net::SocketAddr::from((ip, 40000)),
+            ));
+            request
+        };
+
+    for _ in 0..2 {
+        let response = app.clone().oneshot(from([10, 0, 0, 1])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app.clone().oneshot(from([10, 0, 0, 1])).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "1");
+
+    // Another client still has its whole burst
+    let response = app.oneshot(from([10, 0, 0, 2])).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_duplicate_tasks_groups_same_titles() {
+    let app = setup_router().await;
+    for title in ["Buy milk", "  buy MILK ", "Walk the dog"] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/api/tasks")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::json!({ "title": title, "description": "" }).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks/duplicates")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let groups: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["title"], "buy milk");
+    assert_eq!(groups[0]["count"], 2);
+    let titles: Vec<&str> = groups[0]["tasks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| task["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["Buy milk", "buy MILK"]);
+}
+
+async fn sync(app: &Router, token: Option<&str>) -> serde_json::Value {
+    let uri = match token {
+        Some(token) => format!("/api/tasks/sync?token={}", token),
+        None => "/api/tasks/sync".to_string(),
+    };
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    body_json(response).await
+}
+
+#[tokio::test]
+async fn test_sync_tasks_returns_changes_since_token() {
+    let app = setup_router().await;
+
+    let full = sync(&app, None).await;
+    assert_eq!(full["reset"], true);
+    assert_eq!(full["tasks"].as_array().unwrap().len(), 2);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/api/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"title":"Offline","description":""}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let created: serde_json::Value = body_json(response).await;
+
+    let first = sync(&app, full["token"].as_str()).await;
+    assert_eq!(first["reset"], false);
+    let tasks = first["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["id"], created["id"]);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::put(format!("/api/tasks/{}", created["id"]))
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"title":"Offline, edited"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let second = sync(&app, first["token"].as_str()).await;
+    assert_ne!(second["token"], first["token"]);
+    let tasks = second["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["id"], created["id"]);
+    assert_eq!(tasks[0]["title"], "Offline, edited");
+    assert_eq!(second["deleted"], serde_json::json!([]));
+
+    // Deletes come back as tombstones
+    let response = app
+        .clone()
+        .oneshot(Request::delete("/api/tasks/1").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let third = sync(&app, second["token"].as_str()).await;
+    assert_eq!(third["tasks"], serde_json::json!([]));
+    assert_eq!(third["deleted"], serde_json::json!([1]));
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks/sync?token=bogus")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Subscriber that keeps every event as `field=value` text, for asserting
+/// on what was logged.
+#[derive(Clone, Default)]
+struct CapturingSubscriber {
+    events: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl tracing::Subscriber for CapturingSubscriber {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        tracing::span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut line = String::new();
+        event.record(
+            &mut |field: &tracing::field::Field, value: &dyn std::fmt::Debug| {
+                line.push_str(&format!("{}={:?} ", field.name(), value));
+            },
+        );
+        self.events.lock().unwrap().push(line);
+    }
+
+    fn enter(&self, _: &tracing::span::Id) {}
+
+    fn exit(&self, _: &tracing::span::Id) {}
+}
+
+#[tokio::test]
+async fn test_debug_bodies_logs_create_request() {
+    let subscriber = CapturingSubscriber::default();
+    let _guard = tracing::subscriber::set_default(subscriber.clone());
+    let pool = common::setup_test_pool().await;
+    let app = create_router_with_config(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+        RouterConfig {
+            debug_bodies: true,
+            ..RouterConfig::default()
+        },
+    );
+
+    let response = app
+        .oneshot(
+            Request::post("/api/tasks")
+                .header("content-type", "application/json")
+                .header("authorization", "Bearer s3cret")
+                .body(Body::from(r#"{"title":"Logged","description":""}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let task: serde_json::Value = body_json(response).await;
+    assert_eq!(task["title"], "Logged");
+
+    let events = subscriber.events.lock().unwrap();
+    let request = events
+        .iter()
+        .find(|event| event.contains("request body"))
+        .expect("request body logged");
+    assert!(request.contains(r#"{"title":"Logged","description":""}"#));
+    assert!(request.contains("authorization: <redacted>"));
+    let response = events
+        .iter()
+        .find(|event| event.contains("response body"))
+        .expect("response body logged");
+    assert!(response.contains("status=201"));
+    assert!(response.contains("Logged"));
+    assert!(events.iter().all(|event| !event.contains("s3cret")));
+}
+
+#[tokio::test]
+async fn test_create_task_reports_every_invalid_field() {
+    let app = setup_router().await;
+    let body = serde_json::json!({ "title": "", "description": "x".repeat(2001) }).to_string();
+
+    let response = app
+        .oneshot(
+            Request::post("/api/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: ErrorResponse = body_json(response).await;
+    let fields: Vec<&str> = body.details.iter().map(|d| d.field.as_str()).collect();
+    assert_eq!(fields, vec!["title", "description"]);
+    assert_eq!(body.details[0].message, "must not be empty");
+}
+
+async fn setup_vacuum_router(admin_token: Option<&str>) -> Router {
+    let pool = rust_grpc_sqlite::db::init_db_with_url("sqlite::memory:")
+        .await
+        .unwrap();
+    let tasks = SqliteTaskRepository::new(pool.clone());
+    for i in 0..20 {
+        tasks
+            .create(&format!("Task {}", i), "padding")
+            .await
+            .unwrap();
+    }
+
+    create_router_with_config(
+        Arc::new(tasks),
+        Arc::new(SqliteUserRepository::new(pool.clone())),
+        RouterConfig {
+            admin_token: admin_token.map(String::from),
+            ..RouterConfig::default()
+        }
+        .with_maintenance(Arc::new(SqliteMaintenanceRepository::new(pool))),
+    )
+}
+
+#[tokio::test]
+async fn test_vacuum_populated_database() {
+    let app = setup_vacuum_router(Some("s3cret")).await;
+
+    let response = app
+        .oneshot(
+            Request::post("/api/admin/vacuum")
+                .header("authorization", "Bearer s3cret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: VacuumResponse = body_json(response).await;
+    assert!(body.size_after_bytes > 0);
+}
+
+#[tokio::test]
+async fn test_admin_routes_disabled_without_token() {
+    let app = setup_vacuum_router(None).await;
+
+    for request in [
+        Request::get("/api/admin/backup"),
+        Request::get("/api/admin/inflight"),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                request
+                    .header("authorization", "Bearer anything")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
+
+#[tokio::test]
+async fn test_vacuum_refused_without_admin_token() {
+    let app = setup_vacuum_router(None).await;
+
+    let response = app
+        .oneshot(
+            Request::post("/api/admin/vacuum")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_vacuum_requires_admin_token() {
+    let app = setup_vacuum_router(Some("s3cret")).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/api/admin/vacuum")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .oneshot(
+            Request::post("/api/admin/vacuum")
+                .header("authorization", "Bearer s3cret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_admin_pool_stats() {
+    let app = setup_vacuum_router(Some("s3cret")).await;
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/api/admin/pool").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .oneshot(
+            Request::get("/api/admin/pool")
+                .header("authorization", "Bearer s3cret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = body_json(response).await;
+    for field in ["size", "idle", "in_use", "max"] {
+        assert!(body[field].is_u64(), "{}", field);
+    }
+    assert_eq!(
+        body["max"],
+        rust_grpc_sqlite::db::max_connections("sqlite::memory:")
+    );
+    assert_eq!(
+        body["size"].as_u64(),
+        Some(body["idle"].as_u64().unwrap() + body["in_use"].as_u64().unwrap())
+    );
+}
+
+#[tokio::test]
+async fn test_backup_round_trip() {
+    let pool = common::setup_test_pool_with_user_data().await;
+    let tasks = SqliteTaskRepository::new(pool.clone());
+    let assigned = tasks
+        .create_for_user(1, "Assigned", "to John")
+        .await
+        .unwrap();
+    tasks
+        .set_tags(assigned.id, &["work".to_string(), "urgent".to_string()])
+        .await
+        .unwrap();
+    let moved = tasks.create("Moved", "to the top").await.unwrap();
+    tasks.move_after(moved.id, None).await.unwrap();
+    let app = create_router_with_config(
+        Arc::new(tasks),
+        Arc::new(SqliteUserRepository::new(pool.clone())),
+        RouterConfig {
+            admin_token: Some("s3cret".to_string()),
+            ..RouterConfig::default()
+        }
+        .with_maintenance(Arc::new(SqliteMaintenanceRepository::new(pool.clone()))),
+    );
+    let backup = |app: Router| async move {
+        let response = app
+            .oneshot(
+                Request::get("/api/admin/backup")
+                    .header("authorization", "Bearer s3cret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        body_json::<serde_json::Value>(response).await
+    };
+    let restore = |app: Router, body: &serde_json::Value| {
+        let request = Request::post("/api/admin/restore")
+            .header("authorization", "Bearer s3cret")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        async move { app.oneshot(request).await.unwrap().status() }
+    };
+
+    let saved = backup(app.clone()).await;
+    assert_eq!(saved["users"].as_array().unwrap().len(), 2);
+    assert_eq!(saved["tasks"][0]["assigned_user_id"], 1);
+    assert_eq!(
+        saved["tasks"][0]["tags"],
+        serde_json::json!(["urgent", "work"])
+    );
+    assert!(saved["tasks"][1]["position"].is_number());
+
+    let empty = serde_json::json!({ "tasks": [], "users": [] });
+    assert_eq!(restore(app.clone(), &empty).await, StatusCode::NO_CONTENT);
+    assert_eq!(backup(app.clone()).await, empty);
+
+    assert_eq!(restore(app.clone(), &saved).await, StatusCode::NO_CONTENT);
+    assert_eq!(backup(app.clone()).await, saved);
+
+    // A task assigned to a missing user is rejected and nothing changes
+    let mut broken = saved.clone();
+    broken["users"] = serde_json::json!([]);
+    assert_eq!(restore(app.clone(), &broken).await, StatusCode::BAD_REQUEST);
+    assert_eq!(backup(app.clone()).await, saved);
+
+    let response = app
+        .oneshot(
+            Request::get("/api/admin/backup")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_task_events_websocket_receives_created_event() {
+    use rust_grpc_sqlite::rest::websocket::{OPCODE_CLOSE, OPCODE_PING, OPCODE_PONG, OPCODE_TEXT};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A masked client frame with a short payload.
+    fn client_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x80 | opcode, 0x80 | payload.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(
+            payload
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte ^ mask[i % 4]),
+        );
+        frame
+    }
+
+    /// Reads one unmasked server frame.
+    async fn read_server_frame(socket: &mut tokio::net::TcpStream) -> (u8, Vec<u8>) {
+        let opcode = socket.read_u8().await.unwrap() & 0x0f;
+        let len = match socket.read_u8().await.unwrap() {
+            126 => socket.read_u16().await.unwrap() as usize,
+            127 => socket.read_u64().await.unwrap() as usize,
+            len => len as usize,
+        };
+        let mut payload = vec![0; len];
+        socket.read_exact(&mut payload).await.unwrap();
+        (opcode, payload)
+    }
+
+    let app = setup_router().await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn({
+        let app = app.clone();
+        async move { axum::serve(listener, app).await }
+    });
+
+    let mut socket = tokio::net::TcpStream::connect(addr).await.unwrap();
+    socket
+        .write_all(
+            b"GET /api/tasks/ws HTTP/1.1\r\n\
+              Host: localhost\r\n\
+              Upgrade: websocket\r\n\
+              Connection: Upgrade\r\n\
+              Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+              Sec-WebSocket-Version: 13\r\n\r\n",
+        )
+        .await
+        .unwrap();
+
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(socket.read_u8().await.unwrap());
+    }
+    let head = String::from_utf8(head).unwrap();
+    assert!(head.starts_with("HTTP/1.1 101"), "{}", head);
+    assert!(head.contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+    // An event pushed while a client frame is half read must not lose its
+    // bytes: the ping still gets its pong afterwards
+    let ping = client_frame(OPCODE_PING, b"hello");
+    socket.write_all(&ping[..3]).await.unwrap();
+    // Let the server start reading it
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let response = app
+        .oneshot(
+            Request::post("/api/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"title":"Live","description":""}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let (opcode, payload) =
+        tokio::time::timeout(Duration::from_secs(5), read_server_frame(&mut socket))
+            .await
+            .unwrap();
+    assert_eq!(opcode, OPCODE_TEXT);
+    let event: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+    assert_eq!(event["type"], "created");
+    assert_eq!(event["task"]["title"], "Live");
+
+    socket.write_all(&ping[3..]).await.unwrap();
+    let (opcode, payload) = read_server_frame(&mut socket).await;
+    assert_eq!(opcode, OPCODE_PONG);
+    assert_eq!(payload, b"hello");
+
+    // A close from the client is echoed before the server hangs up
+    socket
+        .write_all(&client_frame(OPCODE_CLOSE, &[]))
+        .await
+        .unwrap();
+    let (opcode, _) = read_server_frame(&mut socket).await;
+    assert_eq!(opcode, OPCODE_CLOSE);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_task_events_sse_receives_created_event() {
+    let app = setup_router().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/tasks/events")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut body = response.into_body();
+
+    let response = app
+        .oneshot(
+            Request::post("/api/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"title":"Streamed","description":""}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+    let data = text
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .unwrap();
+    let event: serde_json::Value = serde_json::from_str(data).unwrap();
+    assert_eq!(event["type"], "created");
+    assert_eq!(event["task"]["title"], "Streamed");
+}
+
+#[tokio::test]
+async fn test_task_events_include_writes_made_outside_rest() {
+    let pool = common::setup_test_pool().await;
+    let events = TaskEvents::new();
+    // Shared with the gRPC service in main
+    let tasks = Arc::new(PublishingTaskRepository::new(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        events.clone(),
+    ));
+    let app = create_router_with_config(
+        tasks.clone(),
+        Arc::new(SqliteUserRepository::new(pool)),
+        RouterConfig::default().with_events(events),
+    );
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks/events")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut body = response.into_body();
+
+    tasks.create("From gRPC", "").await.unwrap();
+
+    let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+    let data = text
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .unwrap();
+    let event: serde_json::Value = serde_json::from_str(data).unwrap();
+    assert_eq!(event["type"], "created");
+    assert_eq!(event["task"]["title"], "From gRPC");
+}
+
+#[tokio::test]
+async fn test_delete_event_carries_the_deleted_row() {
+    let pool = common::setup_test_pool().await;
+    let current = SqliteTaskRepository::new(pool)
+        .create("Current", "")
+        .await
+        .unwrap();
+    let stale = TaskModel {
+        title: "Stale".to_string(),
+        ..current.clone()
+    };
+    // A read separate from the delete would see the stale row
+    let events = TaskEvents::new();
+    let tasks = PublishingTaskRepository::new(
+        Arc::new(
+            MockTaskRepository::new()
+                .on_get(move |_| Ok(stale.clone()))
+                .on_delete_returning(move |_| Ok(Some(current.clone()))),
+        ),
+        events.clone(),
+    );
+    let mut subscriber = events.subscribe();
+
+    assert!(tasks.delete(1).await.unwrap());
+
+    let event = subscriber.try_recv().unwrap();
+    assert_eq!(event.kind, TaskEventKind::Deleted);
+    assert_eq!(event.task.title, "Current");
+}
+
+#[tokio::test]
+async fn test_export_tasks_ndjson_streams_every_row() {
+    let pool = common::setup_test_pool().await;
+    let tasks = SqliteTaskRepository::new(pool.clone());
+    let batch: Vec<(String, String)> = (0..500)
+        .map(|i| (format!("Export {}", i), "body".to_string()))
+        .collect();
+    tasks.create_many(&batch).await.unwrap();
+    let app = create_router(Arc::new(tasks), Arc::new(SqliteUserRepository::new(pool)));
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks/export.ndjson")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/x-ndjson"
+    );
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let lines: Vec<serde_json::Value> = body
+        .split(|&byte| byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 500);
+    assert_eq!(lines[0]["title"], "Export 0");
+}
+
+#[tokio::test]
+async fn test_list_tasks_streams_json_array() {
+    let pool = common::setup_test_pool().await;
+    let tasks = SqliteTaskRepository::new(pool.clone());
+    let batch: Vec<(String, String)> = (0..500)
+        .map(|i| (format!("Streamed {}", i), "body".to_string()))
+        .collect();
+    tasks.create_many(&batch).await.unwrap();
+    let app = create_router(Arc::new(tasks), Arc::new(SqliteUserRepository::new(pool)));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/tasks?stream=true")
+                .header("accept-encoding", "gzip")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/json"
+    );
+    // Compressing would buffer the whole array
+    assert!(response.headers().get("content-encoding").is_none());
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let tasks: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(tasks.len(), 500);
+    assert_eq!(tasks[0]["title"], "Streamed 0");
+    assert_eq!(tasks[499]["title"], "Streamed 499");
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks?stream=true&limit=5")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_validate_task_never_touches_the_database() {
+    let pool = common::setup_test_pool().await;
+    let tasks = Arc::new(MockTaskRepository::new());
+    let app = create_router(tasks.clone(), Arc::new(SqliteUserRepository::new(pool)));
+    let validate = |body: serde_json::Value| {
+        Request::post("/api/tasks/validate")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(validate(serde_json::json!({
+            "title": "Write docs",
+            "description": "",
+            "tags": ["work"],
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = body_json(response).await;
+    assert_eq!(body, serde_json::json!({ "valid": true }));
+
+    let response = app
+        .oneshot(validate(serde_json::json!({
+            "title": "  ",
+            "description": "x".repeat(MAX_DESCRIPTION_LEN + 1),
+            "tags": [""],
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: ErrorResponse = body_json(response).await;
+    let fields: Vec<&str> = body.details.iter().map(|d| d.field.as_str()).collect();
+    assert_eq!(fields, vec!["title", "description", "tags"]);
+
+    assert_eq!(tasks.calls(), 0);
+}
+
+#[tokio::test]
+async fn test_create_task_trims_title_and_description() {
+    let app = setup_router().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/api/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"title":"  Padded  ","description":" notes\n"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: serde_json::Value = body_json(response).await;
+
+    let response = app
+        .oneshot(
+            Request::get(format!("/api/tasks/{}", created["id"]))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let stored: serde_json::Value = body_json(response).await;
+    assert_eq!(stored["title"], "Padded");
+    assert_eq!(stored["description"], "notes");
+}
+
+#[tokio::test]
+async fn test_update_task_blank_title_after_trim_rejected() {
+    let app = setup_router().await;
+
+    let response = app
+        .oneshot(
+            Request::put("/api/tasks/1")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"title":"   "}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: ErrorResponse = body_json(response).await;
+    assert_eq!(body.details[0].field, "title");
+}
+
+async fn status_of(app: &Router, uri: &str) -> StatusCode {
+    app.clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn setup_swagger_router(swagger_path: Option<&str>) -> Router {
+    let pool = common::setup_test_pool().await;
+    create_router_with_config(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+        RouterConfig {
+            swagger_path: swagger_path.map(String::from),
+            ..RouterConfig::default()
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_swagger_path_and_disable_flag() {
+    let relocated = setup_swagger_router(Some("/docs")).await;
+    assert_eq!(
+        status_of(&relocated, "/docs/index.html").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status_of(&relocated, "/api-docs/openapi.json").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status_of(&relocated, "/swagger-ui/index.html").await,
+        StatusCode::NOT_FOUND
+    );
+
+    let disabled = setup_swagger_router(None).await;
+    assert_eq!(
+        status_of(&disabled, "/swagger-ui/index.html").await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        status_of(&disabled, "/api-docs/openapi.json").await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_openapi_spec_scoped_to_tag() {
+    let app = setup_swagger_router(Some("/swagger-ui")).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api-docs/openapi.json?tag=tasks")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let spec: serde_json::Value = body_json(response).await;
+
+    let paths = spec["paths"].as_object().unwrap();
+    assert!(paths.contains_key("/api/tasks"));
+    assert!(!paths.contains_key("/api/users"));
+    assert!(!paths.contains_key("/api/users/{id}"));
+    for item in paths.values() {
+        for operation in item.as_object().unwrap().values() {
+            assert_eq!(operation["tags"], serde_json::json!(["tasks"]));
+        }
+    }
+    let schemas = spec["components"]["schemas"].as_object().unwrap();
+    assert!(schemas.contains_key("TaskResponse"));
+    assert!(!schemas.contains_key("UserResponse"));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api-docs/openapi.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let spec: serde_json::Value = body_json(response).await;
+    assert!(spec["paths"]
+        .as_object()
+        .unwrap()
+        .contains_key("/api/users"));
+
+    assert_eq!(
+        status_of(&app, "/api-docs/openapi.json?tag=projects").await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_api_prefix_moves_routes_and_spec_together() {
+    let pool = common::setup_test_pool_with_data().await;
+    let app = create_router_with_config(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+        RouterConfig {
+            api_prefix: "/v1".to_string(),
+            ..RouterConfig::default()
+        },
+    );
+
+    assert_eq!(status_of(&app, "/v1/tasks").await, StatusCode::OK);
+    assert_eq!(status_of(&app, "/v1/tasks/1").await, StatusCode::OK);
+    assert_eq!(status_of(&app, "/api/tasks").await, StatusCode::NOT_FOUND);
+
+    let response = app
+        .oneshot(
+            Request::get("/api-docs/openapi.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let spec: serde_json::Value = body_json(response).await;
+    let paths = spec["paths"].as_object().unwrap();
+    assert!(paths.contains_key("/v1/tasks"));
+    assert!(paths.contains_key("/v1/tasks/{id}"));
+    assert!(
+        paths.keys().all(|path| path.starts_with("/v1/")),
+        "{:?}",
+        paths.keys()
+    );
+}
+
+async fn create_tagged(app: &Router, title: &str, tags: &[&str]) -> serde_json::Value {
+    let body = serde_json::json!({ "title": title, "description": "", "tags": tags });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/api/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    body_json(response).await
+}
+
+async fn titles_for(app: &Router, uri: &str) -> Vec<String> {
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    let mut titles: Vec<String> = tasks
+        .iter()
+        .map(|task| task["title"].as_str().unwrap().to_string())
+        .collect();
+    titles.sort();
+    titles
+}
+
+#[tokio::test]
+async fn test_list_tasks_filtered_by_tags() {
+    let app = setup_router().await;
+    let both = create_tagged(&app, "Both", &[" urgent ", "work", "work"]).await;
+    assert_eq!(both["tags"], serde_json::json!(["urgent", "work"]));
+    create_tagged(&app, "Work only", &["work"]).await;
+    create_tagged(&app, "Home", &["home"]).await;
+
+    assert_eq!(
+        titles_for(&app, "/api/tasks?tag=work").await,
+        vec!["Both", "Work only"]
+    );
+    assert_eq!(
+        titles_for(&app, "/api/tasks?tag=work&tag=urgent").await,
+        vec!["Both"]
+    );
+    assert!(titles_for(&app, "/api/tasks?tag=work&tag=home")
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_update_task_replaces_tags() {
+    let app = setup_router().await;
+    let task = create_tagged(&app, "Retag", &["old"]).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::put(format!("/api/tasks/{}", task["id"]))
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"tags":["new"]}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated: serde_json::Value = body_json(response).await;
+    assert_eq!(updated["tags"], serde_json::json!(["new"]));
+    assert!(titles_for(&app, "/api/tasks?tag=old").await.is_empty());
+
+    let response = app
+        .oneshot(
+            Request::put(format!("/api/tasks/{}", task["id"]))
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"tags":["  "]}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: ErrorResponse = body_json(response).await;
+    assert_eq!(body.details[0].field, "tags");
+}
+
+#[tokio::test]
+async fn test_recent_tasks_since() {
+    let pool = common::setup_test_pool().await;
+    for (title, updated_at) in [
+        ("Stale", "2024-12-31T23:59:59.999Z"),
+        ("Fresh", "2025-01-01T00:30:00.000Z"),
+        ("Newest", "2025-02-01T00:00:00.000Z"),
+    ] {
+        sqlx::query("INSERT INTO tasks (title, description, updated_at) VALUES (?, '', ?)")
+            .bind(title)
+            .bind(updated_at)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    );
+
+    // 01:00 at +01:00 is midnight UTC
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/tasks/recent?since=2025-01-01T01:00:00%2B01:00")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    let titles: Vec<&str> = tasks
+        .iter()
+        .map(|task| task["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["Newest", "Fresh"]);
+
+    for uri in ["/api/tasks/recent", "/api/tasks/recent?since=yesterday"] {
+        assert_eq!(
+            status_of(&app, uri).await,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            uri
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_completed_tasks_in_range() {
+    let pool = common::setup_test_pool().await;
+    for (title, completed_at) in [
+        ("Last week", "2025-01-05T18:00:00.000Z"),
+        ("This week", "2025-01-07T09:00:00.000Z"),
+    ] {
+        sqlx::query(
+            "INSERT INTO tasks (title, description, completed, completed_at) VALUES (?, '', 1, ?)",
+        )
+        .bind(title)
+        .bind(completed_at)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/tasks/completed?from=2025-01-06T00:00:00Z&to=2025-01-12T23:59:59Z")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["title"], "This week");
+    assert_eq!(tasks[0]["completed_at"], "2025-01-07T09:00:00.000Z");
+
+    for uri in [
+        "/api/tasks/completed?from=2025-01-06T00:00:00Z",
+        "/api/tasks/completed?from=monday&to=2025-01-12T00:00:00Z",
+        "/api/tasks/completed?from=2025-01-12T00:00:00Z&to=2025-01-06T00:00:00Z",
+    ] {
+        assert_eq!(
+            status_of(&app, uri).await,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            uri
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_create_task_lenient_mode_warns() {
+    let app = setup_router().await;
+    let body = serde_json::json!({
+        "title": "Long notes",
+        "description": "x".repeat(MAX_DESCRIPTION_LEN + 1),
+    })
+    .to_string();
+    let create = |uri: &'static str| {
+        Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.clone()))
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(create("/api/tasks")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.headers().get("warning").is_none());
+
+    let response = app
+        .oneshot(create("/api/tasks?strict=false"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let warning = response.headers().get("warning").unwrap().to_str().unwrap();
+    assert!(warning.starts_with("299 - \"description"), "{}", warning);
+    let created: serde_json::Value = body_json(response).await;
+    assert_eq!(
+        created["description"].as_str().unwrap().len(),
+        MAX_DESCRIPTION_LEN + 1
+    );
+}
+
+async fn post_status_and_body(app: &Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(Request::post(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    (response.status(), body_json(response).await)
+}
+
+#[tokio::test]
+async fn test_reassign_tasks() {
+    let pool = common::setup_test_pool().await;
+    sqlx::query("INSERT INTO users (id, name, email) VALUES (1, 'Leaving', 'a@example.com'), (2, 'Staying', 'b@example.com'), (3, 'Idle', 'c@example.com')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO tasks (title, description, assigned_user_id) VALUES ('One', '', 1), ('Two', '', 1), ('Other', '', 2)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool.clone())),
+    );
+
+    let (status, body) = post_status_and_body(&app, "/api/users/1/reassign-tasks/2").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["affected"], 2);
+    let (owned,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tasks WHERE assigned_user_id = 2")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(owned, 3);
+
+    let (status, body) = post_status_and_body(&app, "/api/users/3/reassign-tasks/2").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["affected"], 0);
+
+    let (status, _) = post_status_and_body(&app, "/api/users/2/reassign-tasks/99").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (owned,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tasks WHERE assigned_user_id = 2")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(owned, 3);
+
+    let (status, _) = post_status_and_body(&app, "/api/users/99/reassign-tasks/2").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_user_task_counts_include_users_without_tasks() {
+    let pool = common::setup_test_pool().await;
+    sqlx::query("INSERT INTO users (id, name, email) VALUES (1, 'Busy', 'busy@example.com'), (2, 'Free', 'free@example.com')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO tasks (title, description, completed, assigned_user_id) VALUES ('One', '', 1, 1), ('Two', '', 0, 1), ('Loose', '', 1, NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    );
+
+    let response = app
+        .oneshot(
+            Request::get("/api/users/task-counts")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let counts: serde_json::Value = body_json(response).await;
+    assert_eq!(
+        counts,
+        serde_json::json!([
+            {"user_id": 1, "name": "Busy", "total": 2, "completed": 1},
+            {"user_id": 2, "name": "Free", "total": 0, "completed": 0},
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_user_summary_counts_mixed_tasks() {
+    let pool = common::setup_test_pool().await;
+    sqlx::query("INSERT INTO users (id, name, email) VALUES (1, 'Busy', 'busy@example.com'), (2, 'Other', 'other@example.com')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO tasks (title, description, completed, assigned_user_id) VALUES ('One', '', 1, 1), ('Two', '', 0, 1), ('Three', '', 0, 1), ('Theirs', '', 1, 2), ('Loose', '', 1, NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/users/1/summary")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let summary: serde_json::Value = body_json(response).await;
+    assert_eq!(
+        summary,
+        serde_json::json!({
+            "user": {"id": 1, "name": "Busy", "email": "busy@example.com"},
+            "task_total": 3,
+            "task_completed": 1,
+        })
+    );
+
+    assert_eq!(
+        status_of(&app, "/api/users/99/summary").await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_update_task_assignee_set_unchanged_and_cleared() {
+    let pool = common::setup_test_pool().await;
+    sqlx::query("INSERT INTO users (id, name, email) VALUES (1, 'Owner', 'owner@example.com')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO tasks (id, title, description) VALUES (1, 'Task', '')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    );
+    let put = |body: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::put("/api/tasks/1")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = response.status();
+            (status, body_json::<serde_json::Value>(response).await)
+        }
+    };
+
+    let (status, task) = put(r#"{"assigned_user_id": 1}"#).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(task["assigned_user_id"], 1);
+
+    // Leaving the field out keeps the assignee
+    let (status, task) = put(r#"{"title": "Renamed"}"#).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(task["title"], "Renamed");
+    assert_eq!(task["assigned_user_id"], 1);
+
+    let (status, task) = put(r#"{"assigned_user_id": null}"#).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(task["assigned_user_id"].is_null());
+
+    let (status, _) = put(r#"{"assigned_user_id": 99}"#).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_malformed_json_body_returns_422() {
+    let app = setup_router().await;
+
+    for (body, mentions) in [
+        (r#"{"title": "Trunc"#, "EOF"),
+        (r#"{"description": "no title"}"#, "title"),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/api/tasks")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}",
+            body
+        );
+        let error: ErrorResponse = body_json(response).await;
+        assert!(error.error.contains(mentions), "{}", error.error);
+    }
+}
+
+#[tokio::test]
+async fn test_non_json_content_type_returns_415() {
+    let app = setup_router().await;
+    let body = r#"{"title":"Typed","description":""}"#;
+
+    for content_type in [Some("text/plain"), None] {
+        for request in [Request::post("/api/tasks"), Request::put("/api/tasks/1")] {
+            let request = match content_type {
+                Some(value) => request.header("content-type", value),
+                None => request,
+            };
+            let response = app
+                .clone()
+                .oneshot(request.body(Body::from(body)).unwrap())
+                .await
+                .unwrap();
+
+            assert_eq!(
+                response.status(),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "{:?}",
+                content_type
+            );
+            let error: ErrorResponse = body_json(response).await;
+            assert_eq!(error.error, "Content-Type must be application/json");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_path_ids_return_400() {
+    let app = setup_router().await;
+
+    for (uri, mentions) in [
+        ("/api/tasks/abc", "Invalid id"),
+        ("/api/tasks/99999999999999999999", "Invalid id"),
+        ("/api/tasks/-1", "positive"),
+        ("/api/tasks/0", "positive"),
+        ("/api/users/abc", "Invalid id"),
+        ("/api/users/abc/reassign-tasks/2", "Invalid id"),
+    ] {
+        let method = if uri.contains("reassign") {
+            "POST"
+        } else {
+            "GET"
+        };
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        let error: ErrorResponse = body_json(response).await;
+        assert!(error.error.contains(mentions), "{}: {}", uri, error.error);
+    }
+}
+
+#[tokio::test]
+async fn test_create_task_for_user() {
+    let pool = common::setup_test_pool_with_user_data().await;
+    let app = create_router(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+    );
+    let create = |uri: &str| {
+        Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"title":"Onboarding","description":""}"#))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(create("/api/users/1/tasks"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let task: serde_json::Value = body_json(response).await;
+    assert_eq!(task["assigned_user_id"], 1);
+    assert_eq!(task["title"], "Onboarding");
+
+    let response = app
+        .clone()
+        .oneshot(create("/api/users/999/tasks"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = app
+        .oneshot(Request::get("/api/tasks").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let tasks: Vec<serde_json::Value> = body_json(response).await;
+    assert_eq!(tasks.len(), 1);
+}
+
+#[tokio::test]
+async fn test_admin_config_redacts_admin_token() {
+    let pool = common::setup_test_pool().await;
+    let router_config = RouterConfig {
+        admin_token: Some("s3cret-token".to_string()),
+        ..RouterConfig::default()
+    };
+    let effective = Config::from_env(&router_config).unwrap();
+    let app = create_router_with_config(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+        router_config.with_effective_config(effective),
+    );
+
+    let response = app
+        .oneshot(
+            Request::get("/api/admin/config")
+                .header("authorization", "Bearer s3cret-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(!text.contains("s3cret-token"), "{}", text);
+    let config: Config = serde_json::from_str(&text).unwrap();
+    assert!(config.admin_token_set);
+    assert_eq!(config.max_body_bytes, DEFAULT_MAX_BODY_BYTES);
+}
+
+#[tokio::test]
+async fn test_require_https_rejects_plaintext_behind_proxy() {
+    let pool = common::setup_test_pool_with_data().await;
+    let app = create_router_with_config(
+        Arc::new(SqliteTaskRepository::new(pool.clone())),
+        Arc::new(SqliteUserRepository::new(pool)),
+        RouterConfig {
+            require_https: true,
+            ..RouterConfig::default()
+        },
+    );
+    let send = |method: &str, proto: Option<&str>| {
+        let mut request = Request::builder()
+            .method(method)
+            .uri("/api/tasks?limit=1")
+            .header("host", "tasks.example.com")
+            .header("content-type", "application/json");
+        if let Some(proto) = proto {
+            request = request.header("x-forwarded-proto", proto);
+        }
+        let body = if method == "POST" {
+            Body::from(r#"{"title":"Plain","description":""}"#)
+        } else {
+            Body::empty()
+        };
+        app.clone().oneshot(request.body(body).unwrap())
+    };
+
+    let response = send("POST", Some("http")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = send("GET", Some("http")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(
+        response.headers()["location"],
+        "https://tasks.example.com/api/tasks?limit=1"
+    );
+
+    for proto in [Some("https"), None] {
+        let response = send("GET", proto).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn test_count_tasks_by_status() {
+    let app = setup_router().await;
+
+    let response = app
+        .oneshot(
+            Request::get("/api/tasks/count-by-status")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let counts: serde_json::Value = body_json(response).await;
+    assert_eq!(counts, serde_json::json!({"completed": 1, "pending": 1}));
+}